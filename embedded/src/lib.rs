@@ -0,0 +1,540 @@
+#![no_std]
+
+// The tokenizer, plain values and running statistics on `core` and `alloc` alone, for
+// targets without a file system that feed byte slices. coala builds its readers on top of it,
+// the items hidden from the docs are the parts they share rather than a stable API
+
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+/// Separator, quote and escape characters of a CSV file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dialect {
+    pub separator: char,
+    pub quote: char,
+    pub escape: Option<char>,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self {
+            separator: ',',
+            quote: '"',
+            escape: None,
+        }
+    }
+}
+
+/// Why a record couldn't be read, lines and fields counting from 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    UnclosedQuote {
+        line: usize,
+        field: usize,
+    },
+    TextAfterQuote {
+        line: usize,
+        field: usize,
+        found: char,
+    },
+    InvalidUtf8 {
+        line: usize,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnclosedQuote { line, field } => {
+                write!(
+                    f,
+                    "Quoted field {field} starting at line {line} is never closed"
+                )
+            }
+            ParseError::TextAfterQuote { line, field, found } => write!(
+                f,
+                "Unexpected `{found}` after the closing quote of field {field} at line {line}"
+            ),
+            ParseError::InvalidUtf8 { line } => write!(f, "Line {line} isn't valid UTF-8"),
+        }
+    }
+}
+
+impl core::error::Error for ParseError {}
+
+/// Line break convention of a file, `Mixed` when its lines don't agree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`, as on Unix
+    Lf,
+    /// `\r\n`, as on Windows
+    CrLf,
+    /// A lone `\r`, as on classic Mac OS
+    Cr,
+    Mixed,
+}
+
+impl LineEnding {
+    // The line break `line` ends with, `None` for the last line of a file without one
+    #[doc(hidden)]
+    pub fn of(line: &[u8]) -> Option<Self> {
+        if line.ends_with(b"\r\n") {
+            Some(LineEnding::CrLf)
+        } else if line.ends_with(b"\n") {
+            Some(LineEnding::Lf)
+        } else if line.ends_with(b"\r") {
+            Some(LineEnding::Cr)
+        } else {
+            None
+        }
+    }
+    // Convention of lines seen as `a` followed by lines seen as `b`
+    #[doc(hidden)]
+    pub fn merge(a: Option<Self>, b: Option<Self>) -> Option<Self> {
+        match (a, b) {
+            (Some(a), Some(b)) if a != b => Some(LineEnding::Mixed),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+// Length of the first line of `bytes` with its line break, which is `\n`, `\r\n` or a lone `\r`
+#[doc(hidden)]
+pub fn line_len(bytes: &[u8]) -> usize {
+    match bytes.iter().position(|&b| b == b'\n' || b == b'\r') {
+        Some(i) if bytes[i] == b'\r' && bytes.get(i + 1) == Some(&b'\n') => i + 2,
+        Some(i) => i + 1,
+        None => bytes.len(),
+    }
+}
+
+// Fields left out of `projection` are scanned for separators and quotes but never stored
+#[doc(hidden)]
+pub struct RecordState<'p, F> {
+    pub fields: Vec<F>,
+    field: String,
+    pub index: usize,
+    started: bool,
+    pub in_quotes: bool,
+    quoted: bool,
+    // The field's closing quote was read, only a separator or the line end may follow
+    closed: bool,
+    // Line being parsed, for the position of problems
+    pub line: usize,
+    // The first problem of the record. Parsing goes on past it, so a lenient caller still
+    // gets fields
+    pub problem: Option<ParseError>,
+    pub projection: Option<&'p [bool]>,
+    pub skipped_bytes: usize,
+}
+
+impl<'p, F> RecordState<'p, F> {
+    pub fn new(projection: Option<&'p [bool]>) -> Self {
+        Self {
+            fields: Vec::new(),
+            field: String::new(),
+            index: 0,
+            started: false,
+            in_quotes: false,
+            quoted: false,
+            closed: false,
+            line: 0,
+            problem: None,
+            projection,
+            skipped_bytes: 0,
+        }
+    }
+}
+
+impl<F: for<'a> From<&'a str>> RecordState<'_, F> {
+    fn keeps_field(&self) -> bool {
+        self.projection
+            .is_none_or(|keep| keep.get(self.index).copied().unwrap_or(false))
+    }
+    fn push(&mut self, c: char) {
+        self.started = true;
+        if self.keeps_field() {
+            self.field.push(c);
+        } else {
+            self.skipped_bytes += c.len_utf8();
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.index == 0 && !self.started
+    }
+    pub fn end_field(&mut self) {
+        if self.keeps_field() {
+            self.fields.push(F::from(self.field.as_str()));
+        }
+        self.field.clear();
+        self.index += 1;
+        self.started = false;
+        self.quoted = false;
+        self.closed = false;
+    }
+}
+
+// Returns whether the record ended on this line
+#[doc(hidden)]
+pub fn parse_line<F: for<'a> From<&'a str>>(
+    line: &str,
+    dialect: Dialect,
+    state: &mut RecordState<F>,
+) -> bool {
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if Some(c) == dialect.escape {
+            match chars.next() {
+                // An escaped line break continues the record on the next line
+                Some('\n') => {
+                    state.push('\n');
+                    return false;
+                }
+                Some('\r') if matches!(chars.peek(), Some('\n') | None) => {
+                    state.push('\r');
+                    if chars.next().is_some() {
+                        state.push('\n');
+                    }
+                    return false;
+                }
+                Some(next) => state.push(next),
+                None => state.push(c),
+            }
+            continue;
+        }
+        if state.in_quotes {
+            if c == dialect.quote {
+                if chars.peek() == Some(&dialect.quote) {
+                    chars.next();
+                    state.push(c);
+                } else {
+                    state.in_quotes = false;
+                    state.closed = true;
+                }
+            } else {
+                state.push(c);
+            }
+            continue;
+        }
+        if state.closed && c != dialect.separator && !matches!(c, '\n' | '\r') {
+            state.problem.get_or_insert(ParseError::TextAfterQuote {
+                line: state.line,
+                field: state.index + 1,
+                found: c,
+            });
+        }
+        match c {
+            c if c == dialect.quote && !state.started && !state.quoted => {
+                state.in_quotes = true;
+                state.quoted = true;
+            }
+            c if c == dialect.separator => state.end_field(),
+            '\r' if matches!(chars.peek(), Some('\n') | None) => {}
+            '\n' => {
+                state.end_field();
+                return true;
+            }
+            c => state.push(c),
+        }
+    }
+    if state.in_quotes {
+        return false;
+    }
+    state.end_field();
+    true
+}
+
+/// The records of CSV text already in memory, e.g. a buffer filled by a serial port or a
+/// network stack. Quoted fields may span lines and blank lines are skipped. Lines may end in
+/// `\n`, `\r\n` or a lone `\r`, the last one with no line break at all
+pub struct ByteRecords<'a> {
+    bytes: &'a [u8],
+    dialect: Dialect,
+    line_no: usize,
+    line_ending: Option<LineEnding>,
+}
+
+impl<'a> ByteRecords<'a> {
+    pub fn new(bytes: &'a [u8], dialect: Dialect) -> Self {
+        Self {
+            bytes,
+            dialect,
+            line_no: 0,
+            line_ending: None,
+        }
+    }
+    /// Bytes not read yet, e.g. to keep a partial record until more data comes in
+    pub fn remaining(&self) -> &'a [u8] {
+        self.bytes
+    }
+    /// Line break convention of the lines read so far, `None` before the first line break
+    pub fn line_ending(&self) -> Option<LineEnding> {
+        self.line_ending
+    }
+    // The next line with its line break, `None` at the end
+    fn next_line(&mut self) -> Option<&'a [u8]> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        let (line, rest) = self.bytes.split_at(line_len(self.bytes));
+        self.bytes = rest;
+        self.line_no += 1;
+        self.line_ending = LineEnding::merge(self.line_ending, LineEnding::of(line));
+        Some(line)
+    }
+}
+
+impl Iterator for ByteRecords<'_> {
+    type Item = Result<Vec<String>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut state = RecordState::new(None);
+        let start_line = self.line_no + 1;
+        loop {
+            let Some(line) = self.next_line() else {
+                if state.in_quotes {
+                    return Some(Err(ParseError::UnclosedQuote {
+                        line: start_line,
+                        field: state.index + 1,
+                    }));
+                }
+                if state.is_empty() {
+                    return None;
+                }
+                state.end_field();
+                break;
+            };
+            let Ok(line) = core::str::from_utf8(line) else {
+                return Some(Err(ParseError::InvalidUtf8 { line: self.line_no }));
+            };
+            if !state.in_quotes && state.is_empty() && line.trim_end().is_empty() {
+                continue;
+            }
+            state.line = self.line_no;
+            if parse_line(line, self.dialect, &mut state) {
+                break;
+            }
+        }
+        Some(match state.problem {
+            Some(problem) => Err(problem),
+            None => Ok(state.fields),
+        })
+    }
+}
+
+/// A field's value, typed the way inference types a column: integers, then floats, with
+/// empty fields as nulls and the rest as text
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scalar {
+    Null,
+    Integer(i64),
+    Float(f64),
+    String(String),
+}
+
+impl Scalar {
+    pub fn parse(field: &str) -> Self {
+        let trimmed = field.trim();
+        if trimmed.is_empty() {
+            Scalar::Null
+        } else if let Ok(value) = trimmed.parse() {
+            Scalar::Integer(value)
+        } else if let Ok(value) = trimmed.parse() {
+            Scalar::Float(value)
+        } else {
+            Scalar::String(String::from(field))
+        }
+    }
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Scalar::Integer(value) => Some(*value as f64),
+            Scalar::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scalar::Null => write!(f, "null"),
+            Scalar::Integer(value) => write!(f, "{value}"),
+            Scalar::Float(value) => write!(f, "{value}"),
+            Scalar::String(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+// Neumaier's variant of Kahan summation, also exact when an addend is larger than the total
+#[derive(Debug, Clone, Copy, Default)]
+#[doc(hidden)]
+pub struct CompensatedSum {
+    sum: f64,
+    compensation: f64,
+}
+
+impl CompensatedSum {
+    pub fn add(&mut self, value: f64) {
+        let total = self.sum + value;
+        if self.sum.abs() >= value.abs() {
+            self.compensation += (self.sum - total) + value;
+        } else {
+            self.compensation += (value - total) + self.sum;
+        }
+        self.sum = total;
+    }
+    pub fn merge(&mut self, other: &CompensatedSum) {
+        self.add(other.sum);
+        self.compensation += other.compensation;
+    }
+    pub fn value(&self) -> f64 {
+        self.sum + self.compensation
+    }
+}
+
+// Welford's online algorithm, so variance comes out of the same single pass as the sum.
+// The standard deviation needs a square root, which `core` lacks, so it comes with `std`
+#[derive(Debug, Clone, Default)]
+pub struct RunningStats {
+    count: usize,
+    sum: CompensatedSum,
+    welford_mean: f64,
+    m2: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl RunningStats {
+    pub fn push(&mut self, value: f64) {
+        self.count += 1;
+        self.sum.add(value);
+        let delta = value - self.welford_mean;
+        self.welford_mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.welford_mean);
+        self.min = Some(self.min.map_or(value, |m| m.min(value)));
+        self.max = Some(self.max.map_or(value, |m| m.max(value)));
+    }
+    pub fn merge(&mut self, other: &RunningStats) {
+        let count = self.count + other.count;
+        if count > 0 {
+            let delta = other.welford_mean - self.welford_mean;
+            let weight = self.count as f64 * other.count as f64 / count as f64;
+            self.welford_mean += delta * other.count as f64 / count as f64;
+            self.m2 += other.m2 + delta * delta * weight;
+        }
+        self.count = count;
+        self.sum.merge(&other.sum);
+        self.min = match (self.min, other.min) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.max = match (self.max, other.max) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+    }
+    pub fn count(&self) -> usize {
+        self.count
+    }
+    pub fn sum(&self) -> f64 {
+        self.sum.value()
+    }
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum.value() / self.count as f64)
+    }
+    pub fn variance(&self) -> Option<f64> {
+        (self.count > 1).then(|| self.m2 / (self.count - 1) as f64)
+    }
+    pub fn min(&self) -> Option<f64> {
+        self.min
+    }
+    pub fn max(&self) -> Option<f64> {
+        self.max
+    }
+}
+
+// The one place `std` is linked, so the rest of the crate can't reach it even with the feature
+#[cfg(feature = "std")]
+mod with_std {
+    extern crate std;
+
+    impl super::RunningStats {
+        pub fn stddev(&self) -> Option<f64> {
+            self.variance().map(f64::sqrt)
+        }
+    }
+}
+
+impl Extend<f64> for RunningStats {
+    fn extend<I: IntoIterator<Item = f64>>(&mut self, values: I) {
+        values.into_iter().for_each(|v| self.push(v));
+    }
+}
+
+impl FromIterator<f64> for RunningStats {
+    fn from_iter<I: IntoIterator<Item = f64>>(values: I) -> Self {
+        let mut stats = Self::default();
+        stats.extend(values);
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec, vec::Vec};
+
+    use super::*;
+
+    fn records(text: &str) -> Vec<Vec<String>> {
+        ByteRecords::new(text.as_bytes(), Dialect::default())
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn quoted_fields_span_lines() {
+        assert_eq!(
+            records("a,b\n1,\"x\ny\"\n"),
+            vec![vec!["a", "b"], vec!["1", "x\ny"]]
+        );
+    }
+
+    #[test]
+    fn lone_cr_ends_lines() {
+        let mut parsed = ByteRecords::new(b"a,b\r1,2\r", Dialect::default());
+        assert_eq!(parsed.by_ref().count(), 2);
+        assert_eq!(parsed.line_ending(), Some(LineEnding::Cr));
+    }
+
+    #[test]
+    fn unclosed_quote_is_an_error() {
+        let mut parsed = ByteRecords::new(b"a\n\"b\n", Dialect::default());
+        parsed.next();
+        assert_eq!(
+            parsed.next(),
+            Some(Err(ParseError::UnclosedQuote { line: 2, field: 1 }))
+        );
+    }
+
+    #[test]
+    fn scalars_infer_like_columns() {
+        assert_eq!(Scalar::parse(" "), Scalar::Null);
+        assert_eq!(Scalar::parse("3"), Scalar::Integer(3));
+        assert_eq!(Scalar::parse("3.5"), Scalar::Float(3.5));
+        assert_eq!(Scalar::parse("x"), Scalar::String(String::from("x")));
+    }
+
+    #[test]
+    fn merged_stats_match_one_pass() {
+        let mut left: RunningStats = [1.0, 2.0, 3.0].into_iter().collect();
+        let right: RunningStats = [4.0, 5.0].into_iter().collect();
+        left.merge(&right);
+        let whole: RunningStats = [1.0, 2.0, 3.0, 4.0, 5.0].into_iter().collect();
+        assert_eq!(left.count(), 5);
+        assert_eq!(left.mean(), whole.mean());
+        assert!((left.variance().unwrap() - whole.variance().unwrap()).abs() < 1e-12);
+        assert_eq!((left.min(), left.max()), (Some(1.0), Some(5.0)));
+    }
+}
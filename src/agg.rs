@@ -0,0 +1,349 @@
+use std::collections::HashSet;
+
+use miette::{miette, Error};
+
+use crate::{
+    bootstrap::interpolate,
+    col_parser::DataValue,
+    csv_parser::Csv,
+    error::CoalaError,
+    expr::Expr,
+    mask::Mask,
+    sketch::{HyperLogLog, TDigest},
+    statistics::RunningStats,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Agg {
+    /// Values that aren't null
+    Count,
+    Sum,
+    Mean,
+    Min,
+    Max,
+    Std,
+    Var,
+    ApproxQuantile(f64),
+    ApproxNUnique,
+    /// Exact, holds the values until the end
+    Median,
+    /// Exact with linear interpolation, holds the values until the end
+    Quantile(f64),
+    /// The value of the first row, in row order
+    First,
+    /// The value of the last row, in row order
+    Last,
+    /// The value of the `k`-th row counting from 0, null when there are fewer rows
+    Nth(usize),
+    /// Exact number of distinct values, holds every distinct value until the end. Nulls
+    /// aren't counted, here and in `ApproxNUnique`
+    NUnique,
+    /// The values as text joined into one string, nulls left out
+    StrJoin(Join),
+}
+
+/// How `Agg::StrJoin` joins values: in row order and with repeats unless asked otherwise
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Join {
+    separator: &'static str,
+    distinct: bool,
+    sorted: bool,
+}
+
+impl Join {
+    pub fn new(separator: &'static str) -> Self {
+        Self {
+            separator,
+            distinct: false,
+            sorted: false,
+        }
+    }
+    /// Keeps only the first occurrence of each value
+    pub fn distinct(mut self) -> Self {
+        self.distinct = true;
+        self
+    }
+    /// Sorts the values as strings before joining
+    pub fn sorted(mut self) -> Self {
+        self.sorted = true;
+        self
+    }
+}
+
+impl Agg {
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        match self {
+            Agg::ApproxQuantile(q) | Agg::Quantile(q) if !(0.0..=1.0).contains(q) => {
+                Err(CoalaError::InvalidQuantile { value: *q }.into())
+            }
+            _ => Ok(()),
+        }
+    }
+    pub(crate) fn is_numeric(&self) -> bool {
+        !matches!(
+            self,
+            Agg::Count
+                | Agg::ApproxNUnique
+                | Agg::NUnique
+                | Agg::First
+                | Agg::Last
+                | Agg::Nth(_)
+                | Agg::StrJoin(_)
+        )
+    }
+    // Suffix of the column an aggregation produces, e.g. `price_mean`
+    pub(crate) fn label(&self) -> String {
+        match self {
+            Agg::Count => "count".to_string(),
+            Agg::Sum => "sum".to_string(),
+            Agg::Mean => "mean".to_string(),
+            Agg::Min => "min".to_string(),
+            Agg::Max => "max".to_string(),
+            Agg::Std => "std".to_string(),
+            Agg::Var => "var".to_string(),
+            Agg::ApproxQuantile(q) => format!("approx_quantile_{q}"),
+            Agg::ApproxNUnique => "approx_n_unique".to_string(),
+            Agg::Median => "median".to_string(),
+            Agg::Quantile(q) => format!("quantile_{q}"),
+            Agg::First => "first".to_string(),
+            Agg::Last => "last".to_string(),
+            Agg::Nth(k) => format!("nth_{k}"),
+            Agg::NUnique => "n_unique".to_string(),
+            Agg::StrJoin(_) => "str_join".to_string(),
+        }
+    }
+}
+
+/// An aggregation of one column, as passed to `GroupBy::agg`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggExpr {
+    pub(crate) col: String,
+    pub(crate) agg: Agg,
+    alias: Option<String>,
+}
+
+impl Agg {
+    /// This aggregation applied to `col`
+    pub fn of(self, col: &str) -> AggExpr {
+        AggExpr {
+            col: col.to_string(),
+            agg: self,
+            alias: None,
+        }
+    }
+}
+
+impl AggExpr {
+    /// Names the output column, `{col}_{agg}` otherwise
+    pub fn alias(mut self, name: &str) -> Self {
+        self.alias = Some(name.to_string());
+        self
+    }
+    pub fn output_name(&self) -> String {
+        self.alias
+            .clone()
+            .unwrap_or_else(|| format!("{}_{}", self.col, self.agg.label()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Accumulator {
+    agg: Agg,
+    count: usize,
+    stats: RunningStats,
+    digest: Option<TDigest>,
+    distinct: Option<HyperLogLog>,
+    values: Option<Vec<f64>>,
+    // The first `k + 1` values for `Nth(k)` and `First`, the latest one for `Last`
+    picked: Vec<DataValue>,
+    unique: Option<HashSet<String>>,
+    texts: Option<Vec<String>>,
+}
+
+impl Accumulator {
+    pub(crate) fn new(agg: Agg) -> Self {
+        Self {
+            agg,
+            count: 0,
+            stats: RunningStats::default(),
+            digest: matches!(agg, Agg::ApproxQuantile(_)).then(TDigest::default),
+            distinct: matches!(agg, Agg::ApproxNUnique).then(HyperLogLog::default),
+            values: matches!(agg, Agg::Median | Agg::Quantile(_)).then(Vec::new),
+            picked: Vec::new(),
+            unique: matches!(agg, Agg::NUnique).then(HashSet::new),
+            texts: matches!(agg, Agg::StrJoin(_)).then(Vec::new),
+        }
+    }
+    fn pick(&mut self, value: impl FnOnce() -> DataValue) {
+        match self.agg {
+            Agg::First if self.picked.is_empty() => self.picked.push(value()),
+            Agg::Nth(k) if self.picked.len() <= k => self.picked.push(value()),
+            Agg::Last => self.picked = vec![value()],
+            _ => {}
+        }
+    }
+    /// From raw text, positional aggregations then yield the text
+    pub(crate) fn push(&mut self, raw: &str, value: Option<f64>) {
+        self.pick(|| DataValue::String(raw.to_string()));
+        self.fold(Some(raw), value);
+    }
+    /// From a typed value, positional aggregations then yield it unchanged
+    pub(crate) fn push_value(&mut self, value: &DataValue) {
+        self.pick(|| value.clone());
+        let raw = (!matches!(value, DataValue::Null)).then(|| value.to_string());
+        self.fold(raw.as_deref(), value.as_f64());
+    }
+    // `raw` is `None` for a null
+    fn fold(&mut self, raw: Option<&str>, value: Option<f64>) {
+        if let Some(raw) = raw {
+            self.count += 1;
+            if let Some(distinct) = &mut self.distinct {
+                distinct.push(raw);
+            }
+            if let Some(unique) = &mut self.unique
+                && !unique.contains(raw)
+            {
+                unique.insert(raw.to_string());
+            }
+            if let Some(texts) = &mut self.texts {
+                texts.push(raw.to_string());
+            }
+        }
+        if let Some(value) = value {
+            self.stats.push(value);
+            if let Some(digest) = &mut self.digest {
+                digest.push(value);
+            }
+            if let Some(values) = &mut self.values {
+                values.push(value);
+            }
+        }
+    }
+    // Fails when the sketches of the two can't be merged, e.g. of different precisions
+    pub(crate) fn merge(&mut self, other: &Accumulator) -> Result<(), Error> {
+        self.count += other.count;
+        self.stats.merge(&other.stats);
+        if let (Some(digest), Some(other)) = (&mut self.digest, &other.digest) {
+            digest.merge(other);
+        }
+        if let (Some(values), Some(other)) = (&mut self.values, &other.values) {
+            values.extend_from_slice(other);
+        }
+        for value in &other.picked {
+            self.pick(|| value.clone());
+        }
+        if let (Some(unique), Some(other)) = (&mut self.unique, &other.unique) {
+            unique.extend(other.iter().cloned());
+        }
+        if let (Some(texts), Some(other)) = (&mut self.texts, &other.texts) {
+            texts.extend_from_slice(other);
+        }
+        if let (Some(distinct), Some(other)) = (&mut self.distinct, &other.distinct) {
+            distinct.merge(other)?;
+        }
+        Ok(())
+    }
+    fn exact_quantile(&mut self, quantile: f64) -> Option<f64> {
+        let values = self.values.as_mut().filter(|values| !values.is_empty())?;
+        values.sort_unstable_by(f64::total_cmp);
+        Some(interpolate(values, quantile))
+    }
+    fn joined(&mut self, join: Join) -> String {
+        let mut texts = self.texts.take().unwrap_or_default();
+        if join.sorted {
+            texts.sort_unstable();
+        }
+        if join.distinct {
+            let mut seen = HashSet::new();
+            texts.retain(|text| seen.insert(text.clone()));
+        }
+        let joined = texts.join(join.separator);
+        self.texts = Some(texts);
+        joined
+    }
+    pub(crate) fn finish(&mut self) -> DataValue {
+        let float = |v: Option<f64>| v.map_or(DataValue::Null, DataValue::Float);
+        match self.agg {
+            Agg::Count => DataValue::Integer(self.count as i64),
+            Agg::Sum => DataValue::Float(self.stats.sum()),
+            Agg::Mean => float(self.stats.mean()),
+            Agg::Min => float(self.stats.min()),
+            Agg::Max => float(self.stats.max()),
+            Agg::Std => float(self.stats.stddev()),
+            Agg::Var => float(self.stats.variance()),
+            Agg::ApproxQuantile(q) => float(self.digest.as_mut().and_then(|d| d.quantile(q))),
+            Agg::ApproxNUnique => self.distinct.as_ref().map_or(DataValue::Null, |d| {
+                DataValue::Integer(d.estimate().round() as i64)
+            }),
+            Agg::Median => float(self.exact_quantile(0.5)),
+            Agg::Quantile(q) => float(self.exact_quantile(q)),
+            Agg::First | Agg::Last => self.picked.first().cloned().unwrap_or(DataValue::Null),
+            Agg::Nth(k) => self.picked.get(k).cloned().unwrap_or(DataValue::Null),
+            Agg::NUnique => self.unique.as_ref().map_or(DataValue::Null, |unique| {
+                DataValue::Integer(unique.len() as i64)
+            }),
+            Agg::StrJoin(join) => DataValue::String(self.joined(join)),
+        }
+    }
+}
+
+impl Csv {
+    /// Aggregates `name` over the rows where `condition` holds, in one pass and without
+    /// building the filtered frame
+    pub fn agg_where(&self, name: &str, condition: &Expr, agg: Agg) -> Result<DataValue, Error> {
+        let mask = self.eval_mask(condition)?;
+        self.agg_masked(name, &mask, agg)
+    }
+    /// Aggregates `name` over the rows where `mask` is set
+    pub fn agg_masked(&self, name: &str, mask: &Mask, agg: Agg) -> Result<DataValue, Error> {
+        agg.validate()?;
+        let col = self.get_col(name)?;
+        if agg.is_numeric() && !col.is_numeric() {
+            return Err(CoalaError::InvalidType {
+                col: name.to_string(),
+            }
+            .into());
+        }
+        if mask.len() != col.len() {
+            return Err(miette!(
+                "Mask has {} entries, the Dataframe has {} rows",
+                mask.len(),
+                col.len()
+            ));
+        }
+        let mut accumulator = Accumulator::new(agg);
+        for (value, keep) in col.iter().zip(mask.iter()) {
+            if keep {
+                accumulator.push_value(&value);
+            }
+        }
+        Ok(accumulator.finish())
+    }
+    pub fn mean_where(&self, name: &str, condition: &Expr) -> Result<DataValue, Error> {
+        self.agg_where(name, condition, Agg::Mean)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merged_partials_count_distinct_values_once() {
+        let mut total = Accumulator::new(Agg::ApproxNUnique);
+        for chunk in [["a", "b"], ["b", "c"]] {
+            let mut partial = Accumulator::new(Agg::ApproxNUnique);
+            chunk.iter().for_each(|raw| partial.push(raw, None));
+            total.merge(&partial).unwrap();
+        }
+        assert_eq!(total.finish().as_f64().map(f64::round), Some(3.0));
+    }
+
+    #[test]
+    fn merging_sketches_of_other_precisions_fails() {
+        let mut total = Accumulator::new(Agg::ApproxNUnique);
+        let mut partial = Accumulator::new(Agg::ApproxNUnique);
+        partial.distinct = Some(HyperLogLog::new(4));
+        assert!(total.merge(&partial).is_err());
+    }
+}
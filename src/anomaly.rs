@@ -0,0 +1,42 @@
+use miette::Error;
+
+use crate::{col_parser::DataValue, csv_parser::Csv, error::CoalaError, statistics::RunningStats};
+
+impl Csv {
+    /// Adds `{name}_anomaly`, 1 where the value of `name` is more than `threshold` standard
+    /// deviations from the mean of the `window` rows before it and 0 elsewhere. The row itself
+    /// is left out of its window so a spike can't hide itself. Null where the value is missing
+    /// or the window holds fewer than two values
+    pub fn flag_anomalies(
+        &mut self,
+        name: &str,
+        window: usize,
+        threshold: f64,
+    ) -> Result<(), Error> {
+        if window < 2 || threshold.is_nan() || threshold < 0.0 {
+            return Err(CoalaError::OutOfRange.into());
+        }
+        let values = self.get_col(name)?.finite_values()?;
+        let flags = (0..values.len())
+            .map(|row| {
+                let stats: RunningStats = values[row.saturating_sub(window)..row]
+                    .iter()
+                    .flatten()
+                    .copied()
+                    .collect();
+                let (Some(value), Some(mean), Some(stddev)) =
+                    (values[row], stats.mean(), stats.stddev())
+                else {
+                    return DataValue::Null;
+                };
+                // A flat window makes any departure from it an anomaly
+                let anomalous = match stddev {
+                    0.0 => value != mean,
+                    _ => ((value - mean) / stddev).abs() > threshold,
+                };
+                DataValue::Integer(anomalous as i64)
+            })
+            .collect();
+        self.add_col(&format!("{name}_anomaly"), flags)
+    }
+}
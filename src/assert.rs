@@ -0,0 +1,133 @@
+use std::cmp::Ordering;
+
+use crate::{
+    col_parser::DataValue,
+    csv_parser::{ColViewer, Csv},
+};
+
+// Nulls match each other and so do NaNs, everything else must compare equal
+fn same(a: &DataValue, b: &DataValue) -> bool {
+    match (a, b) {
+        (DataValue::Null, DataValue::Null) => true,
+        (DataValue::Float(a), DataValue::Float(b)) if a.is_nan() && b.is_nan() => true,
+        _ => a.compare(b).is_some_and(Ordering::is_eq),
+    }
+}
+
+/// What tells the names and types of the columns of `left` and `right` apart, `None` when
+/// they match in order
+pub fn schema_diff(left: &Csv, right: &Csv) -> Option<String> {
+    let n_cols = left.header.len().max(right.header.len());
+    for i in 0..n_cols {
+        let describe = |csv: &Csv| {
+            csv.header.get(i).map_or("nothing".to_string(), |name| {
+                format!("`{name}` {}", csv.cols[i].dtype().name())
+            })
+        };
+        let (l, r) = (describe(left), describe(right));
+        if l != r {
+            return Some(format!("column {i}: left has {l}, right has {r}"));
+        }
+    }
+    None
+}
+
+/// The first cell where `left` and `right` differ and how many do, `None` when they hold the
+/// same values with the same type
+pub fn column_diff(left: &ColViewer, right: &ColViewer) -> Option<String> {
+    if left.dtype() != right.dtype() {
+        return Some(format!(
+            "left is {}, right is {}",
+            left.dtype().name(),
+            right.dtype().name()
+        ));
+    }
+    if left.len() != right.len() {
+        return Some(format!(
+            "left has {} rows, right has {}",
+            left.len(),
+            right.len()
+        ));
+    }
+    let mut first = None;
+    let mut differing = 0;
+    for (row, (l, r)) in left.iter().zip(right.iter()).enumerate() {
+        if !same(&l, &r) {
+            differing += 1;
+            first.get_or_insert((row, l, r));
+        }
+    }
+    let (row, l, r) = first?;
+    Some(format!(
+        "row {row}: left {l}, right {r} ({differing} of {} cells differ)",
+        left.len()
+    ))
+}
+
+/// The schema difference, or the first differing cell of the first column that has one.
+/// `None` when the frames are equal
+pub fn frame_diff(left: &Csv, right: &Csv) -> Option<String> {
+    if let Some(diff) = schema_diff(left, right) {
+        return Some(format!("schemas differ, {diff}"));
+    }
+    if left.n_rows != right.n_rows {
+        return Some(format!(
+            "left has {} rows, right has {}",
+            left.n_rows, right.n_rows
+        ));
+    }
+    left.header.iter().find_map(|name| {
+        let (l, r) = (left.get_col(name).ok()?, right.get_col(name).ok()?);
+        column_diff(&l, &r).map(|diff| format!("column `{name}`, {diff}"))
+    })
+}
+
+#[track_caller]
+pub fn assert_schema_eq(left: &Csv, right: &Csv) {
+    if let Some(diff) = schema_diff(left, right) {
+        panic!("assertion failed: schemas differ, {diff}");
+    }
+}
+
+#[track_caller]
+pub fn assert_column_eq(left: &ColViewer, right: &ColViewer) {
+    if let Some(diff) = column_diff(left, right) {
+        panic!(
+            "assertion failed: columns `{}` and `{}` differ, {diff}",
+            left.name(),
+            right.name()
+        );
+    }
+}
+
+#[track_caller]
+pub fn assert_frame_eq(left: &Csv, right: &Csv) {
+    if let Some(diff) = frame_diff(left, right) {
+        panic!("assertion failed: frames differ, {diff}");
+    }
+}
+
+/// Panics with the first differing cell unless both frames have the same columns, types
+/// and values
+#[macro_export]
+macro_rules! assert_frame_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::assert::assert_frame_eq(&$left, &$right)
+    };
+}
+
+/// Panics unless both frames have the same column names and types, in the same order
+#[macro_export]
+macro_rules! assert_schema_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::assert::assert_schema_eq(&$left, &$right)
+    };
+}
+
+/// Panics with the first differing cell unless both columns hold the same values
+#[macro_export]
+macro_rules! assert_column_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::assert::assert_column_eq(&$left, &$right)
+    };
+}
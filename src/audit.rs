@@ -0,0 +1,78 @@
+use datetime::datetime::Datetime;
+
+use crate::{
+    col_parser::{ColType, DType},
+    csv_parser::Csv,
+};
+
+const MAX_OFFENDERS: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct TypeAudit {
+    pub column: String,
+    pub n_values: usize,
+    pub integer_fraction: f64,
+    pub float_fraction: f64,
+    pub datetime_fraction: f64,
+    pub candidate: Option<DType>,
+    pub offenders: Vec<String>,
+}
+
+impl Csv {
+    pub fn type_audit(&self) -> Vec<TypeAudit> {
+        self.cols
+            .iter()
+            .filter_map(|col| {
+                col.with_plain(|col| match col {
+                    ColType::String(col) => {
+                        Some(audit_values(&col.col_name, &col.iter().collect::<Vec<_>>()))
+                    }
+                    _ => None,
+                })
+            })
+            .collect()
+    }
+}
+
+fn audit_values(name: &str, values: &[&str]) -> TypeAudit {
+    let n_values = values.len();
+    let count = |f: &dyn Fn(&str) -> bool| values.iter().filter(|v| f(v)).count();
+    let integers = count(&|v| v.parse::<i64>().is_ok());
+    let floats = count(&|v| v.parse::<f64>().is_ok());
+    let datetimes = count(&|v| Datetime::try_guess(v).is_some());
+    let fraction = |n: usize| {
+        if n_values == 0 {
+            0.0
+        } else {
+            n as f64 / n_values as f64
+        }
+    };
+    // Integers also parse as floats, so only prefer Float when it covers strictly more values
+    let candidate = [
+        (DType::Integer, integers),
+        (DType::Float, floats),
+        (DType::Datetime, datetimes),
+    ]
+    .into_iter()
+    .filter(|(_, n)| *n > 0)
+    .reduce(|best, next| if next.1 > best.1 { next } else { best })
+    .map(|(dtype, _)| dtype);
+    let offenders = match candidate {
+        Some(dtype) => values
+            .iter()
+            .filter(|v| dtype.parse::<&str>(v, &[]).is_err())
+            .take(MAX_OFFENDERS)
+            .map(|v| v.to_string())
+            .collect(),
+        None => Vec::new(),
+    };
+    TypeAudit {
+        column: name.to_string(),
+        n_values,
+        integer_fraction: fraction(integers),
+        float_fraction: fraction(floats),
+        datetime_fraction: fraction(datetimes),
+        candidate,
+        offenders,
+    }
+}
@@ -0,0 +1,80 @@
+use miette::Error;
+
+use crate::{
+    col_parser::{ColType, CsvCol},
+    csv_parser::{ColViewer, Csv},
+    error::CoalaError,
+};
+
+// A `lag` column from 0 to `max_lag` next to the coefficients
+fn lag_frame(operation: String, coefficients: Vec<f64>) -> Csv {
+    let n_rows = coefficients.len();
+    let lags = (0..n_rows as i64).collect();
+    let cols = vec![
+        ColType::Integer(CsvCol::new("lag".to_string(), lags)),
+        ColType::Float(CsvCol::new("coefficient".to_string(), coefficients)),
+    ];
+    let header = vec!["lag".to_string(), "coefficient".to_string()];
+    let mut csv = Csv::from_cols(cols, header, n_rows);
+    csv.lineage.record(operation);
+    csv
+}
+
+impl ColViewer<'_> {
+    // Autocorrelations from lag 0 to `max_lag`. Pairs with a missing value are skipped, every
+    // lag is still scaled by the number of present values so the estimate stays a valid one
+    fn autocorrelations(&self, max_lag: usize) -> Result<Vec<f64>, Error> {
+        let values = self.finite_values()?;
+        if max_lag >= values.len() {
+            return Err(CoalaError::OutOfRange.into());
+        }
+        let present: Vec<f64> = values.iter().flatten().copied().collect();
+        if present.is_empty() {
+            return Err(CoalaError::EmptyColumn.into());
+        }
+        let mean = present.iter().sum::<f64>() / present.len() as f64;
+        let centered: Vec<Option<f64>> = values.iter().map(|v| v.map(|x| x - mean)).collect();
+        let covariance = |lag: usize| {
+            centered
+                .iter()
+                .zip(&centered[lag..])
+                .filter_map(|(a, b)| Some((*a)? * (*b)?))
+                .sum::<f64>()
+        };
+        // A constant column has no defined correlation, which shows up as NaN
+        let variance = covariance(0);
+        Ok((0..=max_lag)
+            .map(|lag| covariance(lag) / variance)
+            .collect())
+    }
+    /// Correlation of the column with itself shifted by each lag from 0 to `max_lag`, as a
+    /// frame of `lag` and `coefficient`. Peaks at a lag hint at a season of that length.
+    /// Values that aren't finite are treated as missing
+    pub fn acf(&self, max_lag: usize) -> Result<Csv, Error> {
+        Ok(lag_frame(
+            format!("acf({}, {max_lag})", self.name()),
+            self.autocorrelations(max_lag)?,
+        ))
+    }
+    /// Correlation at each lag from 0 to `max_lag` once the shorter lags are accounted for,
+    /// as a frame of `lag` and `coefficient`. Where it cuts off suggests the order of an
+    /// autoregressive model
+    pub fn pacf(&self, max_lag: usize) -> Result<Csv, Error> {
+        let acf = self.autocorrelations(max_lag)?;
+        let mut pacf = vec![1.0];
+        // Durbin-Levinson, `phi` holds the coefficients of the order reached so far
+        let mut phi: Vec<f64> = Vec::with_capacity(max_lag);
+        for k in 1..=max_lag {
+            let numerator = acf[k] - (1..k).map(|j| phi[j - 1] * acf[k - j]).sum::<f64>();
+            let denominator = 1.0 - (1..k).map(|j| phi[j - 1] * acf[j]).sum::<f64>();
+            let last = numerator / denominator;
+            let next: Vec<f64> = (1..k)
+                .map(|j| phi[j - 1] - last * phi[k - j - 1])
+                .chain(std::iter::once(last))
+                .collect();
+            phi = next;
+            pacf.push(last);
+        }
+        Ok(lag_frame(format!("pacf({}, {max_lag})", self.name()), pacf))
+    }
+}
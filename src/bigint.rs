@@ -0,0 +1,68 @@
+use miette::Error;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+use crate::{
+    col_parser::{CsvCol, DataValue},
+    error::CoalaError,
+    statistics::{mad_of, stddev_of, trimmed_std_of, Statistics},
+};
+
+impl CsvCol<BigInt> {
+    fn exact_sum(&self) -> BigInt {
+        self.valid_values().iter().sum()
+    }
+}
+
+impl Statistics for CsvCol<BigInt> {
+    fn sum(&self) -> Result<DataValue, Error> {
+        Ok(DataValue::BigInt(self.exact_sum()))
+    }
+    fn mean(&self) -> Result<DataValue, Error> {
+        let n_valid = self.n_valid();
+        if n_valid == 0 {
+            return Err(CoalaError::EmptyColumn.into());
+        }
+        // Dividing the exact sum keeps the result finite even when the sum itself doesn't fit an f64
+        let n = BigInt::from(n_valid);
+        let sum = self.exact_sum();
+        let (whole, remainder) = (&sum / &n, &sum % &n);
+        let mean = whole.to_f64().unwrap_or(f64::NAN)
+            + remainder.to_f64().unwrap_or_default() / n_valid as f64;
+        Ok(DataValue::Float(mean))
+    }
+    fn median(&self) -> Result<DataValue, Error> {
+        if self.n_valid() == 0 {
+            return Err(CoalaError::EmptyColumn.into());
+        }
+        let col = self.get_sorted();
+        let mid = col.len() / 2;
+        if !col.len().is_multiple_of(2) {
+            return Ok(DataValue::BigInt(col[mid].clone()));
+        }
+        Ok(DataValue::BigInt((&col[mid - 1] + &col[mid]) / 2))
+    }
+    fn quantile(&self, quantile: f64) -> Result<DataValue, Error> {
+        if !(0.0..=1.0).contains(&quantile) {
+            return Err(CoalaError::InvalidQuantile { value: quantile }.into());
+        }
+        if self.n_valid() == 0 {
+            return Err(CoalaError::EmptyColumn.into());
+        }
+        let col = self.get_sorted();
+        let index = ((quantile * col.len() as f64).ceil() as usize).clamp(1, col.len()) - 1;
+        Ok(DataValue::BigInt(col[index].clone()))
+    }
+    fn stddev(&self) -> Result<DataValue, Error> {
+        stddev_of(self.valid_values().iter().filter_map(|v| v.to_f64()))
+    }
+    fn median_absolute_deviation(&self) -> Result<DataValue, Error> {
+        mad_of(self.valid_values().iter().filter_map(|v| v.to_f64()))
+    }
+    fn trimmed_std(&self, proportion: f64) -> Result<DataValue, Error> {
+        trimmed_std_of(
+            self.valid_values().iter().filter_map(|v| v.to_f64()),
+            proportion,
+        )
+    }
+}
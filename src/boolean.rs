@@ -0,0 +1,54 @@
+use miette::Error;
+
+use crate::{col_parser::DataValue, csv_parser::ColViewer, error::CoalaError};
+
+// Truth value of a cell of a boolean column: 0 and 1, or `true` and `false` in any case
+pub(crate) fn truth(value: &DataValue) -> Option<bool> {
+    match value {
+        DataValue::Integer(0) => Some(false),
+        DataValue::Integer(1) => Some(true),
+        DataValue::Unsigned(0) => Some(false),
+        DataValue::Unsigned(1) => Some(true),
+        DataValue::Float(f) if *f == 0.0 => Some(false),
+        DataValue::Float(f) if *f == 1.0 => Some(true),
+        DataValue::String(s) if s.trim().eq_ignore_ascii_case("false") => Some(false),
+        DataValue::String(s) if s.trim().eq_ignore_ascii_case("true") => Some(true),
+        _ => None,
+    }
+}
+
+// Aggregations of boolean columns, nulls left out. Any other value fails
+impl ColViewer<'_> {
+    fn truths(&self) -> Result<Vec<bool>, Error> {
+        self.iter()
+            .filter(|v| !matches!(v, DataValue::Null))
+            .map(|v| {
+                truth(&v).ok_or_else(|| {
+                    CoalaError::InvalidType {
+                        col: self.name().to_string(),
+                    }
+                    .into()
+                })
+            })
+            .collect()
+    }
+    pub fn any(&self) -> Result<bool, Error> {
+        Ok(self.truths()?.into_iter().any(|t| t))
+    }
+    /// `true` when the column holds no values
+    pub fn all(&self) -> Result<bool, Error> {
+        Ok(self.truths()?.into_iter().all(|t| t))
+    }
+    pub fn count_true(&self) -> Result<usize, Error> {
+        Ok(self.truths()?.into_iter().filter(|&t| t).count())
+    }
+    /// Share of the non-null values that are true, the rate of a 0/1 outcome
+    pub fn proportion_true(&self) -> Result<f64, Error> {
+        let truths = self.truths()?;
+        if truths.is_empty() {
+            return Err(CoalaError::EmptyColumn.into());
+        }
+        let count = truths.iter().filter(|&&t| t).count();
+        Ok(count as f64 / truths.len() as f64)
+    }
+}
@@ -0,0 +1,91 @@
+use miette::Error;
+
+use crate::{csv_parser::ColViewer, error::CoalaError, rng::Rng, statistics::RunningStats};
+
+/// A statistic that can be recomputed on resamples of a numeric column
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stat {
+    Mean,
+    Median,
+    Std,
+    Quantile(f64),
+}
+
+// Linear interpolation between the two closest ranks
+pub(crate) fn interpolate(sorted: &[f64], quantile: f64) -> f64 {
+    let position = quantile * (sorted.len() - 1) as f64;
+    let (below, above) = (position.floor() as usize, position.ceil() as usize);
+    sorted[below] + (sorted[above] - sorted[below]) * (position - below as f64)
+}
+
+impl Stat {
+    fn validate(&self) -> Result<(), Error> {
+        match self {
+            Stat::Quantile(q) if !(0.0..=1.0).contains(q) => {
+                Err(CoalaError::InvalidQuantile { value: *q }.into())
+            }
+            _ => Ok(()),
+        }
+    }
+    // Never called on an empty sample. May reorder `values`
+    fn compute(&self, values: &mut [f64]) -> f64 {
+        match self {
+            Stat::Mean | Stat::Std => {
+                let stats: RunningStats = values.iter().copied().collect();
+                match self {
+                    Stat::Mean => stats.mean(),
+                    _ => stats.stddev(),
+                }
+                .unwrap_or(f64::NAN)
+            }
+            Stat::Median => Stat::Quantile(0.5).compute(values),
+            Stat::Quantile(q) => {
+                values.sort_unstable_by(f64::total_cmp);
+                interpolate(values, *q)
+            }
+        }
+    }
+}
+
+impl ColViewer<'_> {
+    /// Percentile bootstrap interval for `stat`: the column is resampled with replacement
+    /// `n_resamples` times and the central `confidence` share of the estimates is kept
+    pub fn bootstrap_ci(
+        &self,
+        stat: Stat,
+        n_resamples: usize,
+        confidence: f64,
+        seed: u64,
+    ) -> Result<(f64, f64), Error> {
+        stat.validate()?;
+        if !(confidence > 0.0 && confidence < 1.0) || n_resamples == 0 {
+            return Err(CoalaError::OutOfRange.into());
+        }
+        if !self.is_numeric() {
+            return Err(CoalaError::InvalidType {
+                col: self.name().to_string(),
+            }
+            .into());
+        }
+        let values: Vec<f64> = self.iter().filter_map(|v| v.as_f64()).collect();
+        if values.is_empty() {
+            return Err(CoalaError::EmptyColumn.into());
+        }
+        let mut rng = Rng::new(seed);
+        let mut resample = vec![0.0; values.len()];
+        let mut estimates: Vec<f64> = (0..n_resamples)
+            .map(|_| {
+                for slot in resample.iter_mut() {
+                    *slot = values[rng.below(values.len())];
+                }
+                stat.compute(&mut resample)
+            })
+            .collect();
+        estimates.sort_unstable_by(f64::total_cmp);
+        let tail = (1.0 - confidence) / 2.0;
+        Ok((
+            interpolate(&estimates, tail),
+            interpolate(&estimates, 1.0 - tail),
+        ))
+    }
+}
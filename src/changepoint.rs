@@ -0,0 +1,76 @@
+use miette::Error;
+
+use crate::{csv_parser::ColViewer, error::CoalaError};
+
+// Cost of a segment, its sum of squared deviations from its own mean, in O(1) from prefix
+// sums of the values and their squares
+struct SegmentCost {
+    sums: Vec<f64>,
+    squares: Vec<f64>,
+}
+
+impl SegmentCost {
+    fn new(values: &[f64]) -> Self {
+        let mut sums = vec![0.0; values.len() + 1];
+        let mut squares = vec![0.0; values.len() + 1];
+        for (i, v) in values.iter().enumerate() {
+            sums[i + 1] = sums[i] + v;
+            squares[i + 1] = squares[i] + v * v;
+        }
+        Self { sums, squares }
+    }
+    // Of `values[start..end]`
+    fn cost(&self, start: usize, end: usize) -> f64 {
+        let sum = self.sums[end] - self.sums[start];
+        let squares = self.squares[end] - self.squares[start];
+        (squares - sum * sum / (end - start) as f64).max(0.0)
+    }
+}
+
+impl ColViewer<'_> {
+    /// Rows where the mean of the column shifts, each the first row of a new level, found by
+    /// PELT. Every extra segment must lower the squared error by more than `penalty`, so higher
+    /// values find fewer changes. `2 * variance * ln(n)` is a common start. Values that aren't
+    /// finite are skipped
+    pub fn changepoints(&self, penalty: f64) -> Result<Vec<usize>, Error> {
+        if penalty.is_nan() || penalty < 0.0 {
+            return Err(CoalaError::OutOfRange.into());
+        }
+        let (rows, values): (Vec<usize>, Vec<f64>) = self
+            .finite_values()?
+            .into_iter()
+            .enumerate()
+            .filter_map(|(row, value)| Some((row, value?)))
+            .unzip();
+        let n = values.len();
+        let segments = SegmentCost::new(&values);
+        // `best[t]` is the lowest penalized cost of `values[..t]`, reached with a last segment
+        // starting at `last_start[t]`
+        let mut best = vec![-penalty; n + 1];
+        let mut last_start = vec![0; n + 1];
+        let mut candidates = vec![0];
+        for end in 1..=n {
+            let (cost, start) = candidates
+                .iter()
+                .map(|&start| (best[start] + segments.cost(start, end) + penalty, start))
+                .min_by(|a, b| a.0.total_cmp(&b.0))
+                .unwrap_or((0.0, 0));
+            best[end] = cost;
+            last_start[end] = start;
+            // Starts that can't beat the best even before paying for another segment never will
+            candidates.retain(|&start| best[start] + segments.cost(start, end) <= cost);
+            candidates.push(end);
+        }
+        let mut changes = Vec::new();
+        let mut end = n;
+        while end > 0 {
+            let start = last_start[end];
+            if start > 0 {
+                changes.push(rows[start]);
+            }
+            end = start;
+        }
+        changes.reverse();
+        Ok(changes)
+    }
+}
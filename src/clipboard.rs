@@ -0,0 +1,43 @@
+use arboard::Clipboard;
+use miette::{Error, IntoDiagnostic};
+
+use crate::{csv_parser::Csv, display::markdown_table};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardFormat {
+    Tsv,
+    Markdown,
+}
+
+impl Csv {
+    pub fn to_clipboard(&self, format: ClipboardFormat) -> Result<(), Error> {
+        let csv = self.in_column_order();
+        let cols = csv.cells()?;
+        let text = match format {
+            ClipboardFormat::Tsv => to_tsv(&csv.header, &cols, csv.n_rows),
+            ClipboardFormat::Markdown => markdown_table(&csv.header, &cols, csv.n_rows),
+        };
+        let mut clipboard = Clipboard::new().into_diagnostic()?;
+        clipboard.set_text(text).into_diagnostic()
+    }
+}
+
+fn to_tsv(header: &[String], cols: &[Vec<String>], n_rows: usize) -> String {
+    let escape = |s: &str| s.replace(['\t', '\n', '\r'], " ");
+    let mut result = header
+        .iter()
+        .map(|h| escape(h))
+        .collect::<Vec<_>>()
+        .join("\t");
+    result.push('\n');
+    for row in 0..n_rows {
+        let line = cols
+            .iter()
+            .map(|col| escape(&col[row]))
+            .collect::<Vec<_>>()
+            .join("\t");
+        result.push_str(&line);
+        result.push('\n');
+    }
+    result
+}
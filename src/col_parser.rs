@@ -1,40 +1,112 @@
+use bitvec::vec::BitVec;
 use datetime::datetime::Datetime;
-use log::info;
-use miette::{miette, Diagnostic, Error};
+use miette::{miette, Error};
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
+#[cfg(feature = "complex")]
+use num_complex::Complex64;
+#[cfg(feature = "decimal")]
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::{
-    cell::RefCell,
+    borrow::Cow,
+    cmp::Ordering,
     fmt::{self, Display},
     str::FromStr,
+    sync::{PoisonError, RwLock},
 };
-use thiserror::Error;
 
-use crate::statistics::{Statistics, StatisticsError};
+use crate::{
+    compress::CompressedCol,
+    embedded::Scalar,
+    epoch::EpochUnit,
+    equality::float_bits,
+    error::CoalaError,
+    kind::{ErasedColumn, SharedKind},
+    nested::StructCol,
+    perf,
+    sketch::{BloomFilter, HyperLogLog},
+    sort::SortKey,
+    statistics::{Memo, Statistics},
+    strings::StringCol,
+    trace::{info, span},
+    view::ColView,
+};
 
 #[derive(Debug)]
 pub(crate) struct CsvCol<T> {
     pub(crate) col_name: String,
     pub(crate) values: Vec<T>,
     pub(crate) n_elements: usize,
-    pub(crate) sorted_values: RefCell<Option<(Vec<T>, usize)>>,
+    pub(crate) sorted_values: RwLock<Option<(Vec<T>, usize)>>,
+    // Boxed, columns are moved around a lot and rarely asked for statistics
+    pub(crate) memo: Box<Memo>,
+    pub(crate) filter: Option<BloomFilter>,
+    // Unset where the row is null, `None` when no row is. A null row keeps a placeholder in
+    // `values`
+    pub(crate) validity: Option<BitVec>,
 }
 #[derive(Debug)]
 pub(crate) enum ColType {
     Float(CsvCol<f64>),
     Integer(CsvCol<i64>),
-    String(CsvCol<String>),
+    String(StringCol),
     Datetime(CsvCol<Datetime>),
+    #[cfg(feature = "decimal")]
+    Decimal(CsvCol<Decimal>),
+    #[cfg(feature = "complex")]
+    Complex(CsvCol<Complex64>),
+    #[cfg(feature = "bigint")]
+    BigInt(CsvCol<BigInt>),
+    List(CsvCol<Vec<DataValue>>),
+    Struct(StructCol),
+    Custom(Box<dyn ErasedColumn>),
+    Compressed(CompressedCol),
+    View(ColView),
 }
-#[derive(Debug, Error, Diagnostic)]
-enum ColParseError {
-    #[error("Error in collumn named `{name}`. Invalid data type, couldn't match with any.")]
-    InvalidColType { name: String },
-    #[error("Index out of range for column")]
-    OutOfRange,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DType {
+    Float,
+    Integer,
+    String,
+    Datetime,
+    #[cfg(feature = "decimal")]
+    Decimal,
+    #[cfg(feature = "complex")]
+    Complex,
+    #[cfg(feature = "bigint")]
+    BigInt,
+    List,
+    Struct,
+    Custom(&'static str),
 }
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Copy)]
 pub(crate) struct ColConfig<'a> {
-    pub(crate) date_format: Option<&'a str>,
+    pub(crate) date_formats: &'a [&'a str],
+    pub(crate) fallback_date_formats: &'a [&'a str],
     pub(crate) as_date: bool,
+    pub(crate) epoch_unit: Option<EpochUnit>,
+    pub(crate) detect_epochs: bool,
+    pub(crate) dtype: Option<DType>,
+    pub(crate) strict: bool,
+    #[cfg(feature = "decimal")]
+    pub(crate) infer_decimals: bool,
+}
+
+// Distinct values that keep a column from being numeric, up to three, when most of its values
+// are numbers
+fn numeric_outliers<S: AsRef<str>>(elements: &[S]) -> Option<Vec<String>> {
+    let mut outliers: Vec<String> = Vec::new();
+    let mut numbers = 0;
+    for element in elements {
+        let element = element.as_ref();
+        if element.trim().parse::<f64>().is_ok() {
+            numbers += 1;
+        } else if outliers.len() < 3 && !outliers.iter().any(|o| o == element) {
+            outliers.push(element.to_string());
+        }
+    }
+    (numbers * 2 > elements.len() && !outliers.is_empty()).then_some(outliers)
 }
 
 impl fmt::Display for ColType {
@@ -43,45 +115,116 @@ impl fmt::Display for ColType {
             Self::Float(col) => {
                 writeln!(f, "{}", col.col_name)?;
                 for idx in 0..col.n_elements {
-                    writeln!(f, "{}", col.values[idx])?;
+                    writeln!(f, "{}", col.display_at(idx))?;
                 }
                 Ok(())
             }
             Self::Integer(col) => {
                 writeln!(f, "{}", col.col_name)?;
                 for idx in 0..col.n_elements {
-                    writeln!(f, "{}", col.values[idx])?;
+                    writeln!(f, "{}", col.display_at(idx))?;
                 }
                 Ok(())
             }
             Self::String(col) => {
                 writeln!(f, "{}", col.col_name)?;
-                for idx in 0..col.n_elements {
-                    writeln!(f, "{}", col.values[idx])?;
+                for row in 0..col.len() {
+                    writeln!(f, "{}", col.display_at(row))?;
                 }
                 Ok(())
             }
             Self::Datetime(col) => {
                 writeln!(f, "{}", col.col_name)?;
                 for idx in 0..col.n_elements {
-                    writeln!(f, "{}", col.values[idx])?;
+                    writeln!(f, "{}", col.display_at(idx))?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "decimal")]
+            Self::Decimal(col) => {
+                writeln!(f, "{}", col.col_name)?;
+                for idx in 0..col.n_elements {
+                    writeln!(f, "{}", col.display_at(idx))?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "complex")]
+            Self::Complex(col) => {
+                writeln!(f, "{}", col.col_name)?;
+                for idx in 0..col.n_elements {
+                    writeln!(f, "{}", col.display_at(idx))?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "bigint")]
+            Self::BigInt(col) => {
+                writeln!(f, "{}", col.col_name)?;
+                for idx in 0..col.n_elements {
+                    writeln!(f, "{}", col.display_at(idx))?;
+                }
+                Ok(())
+            }
+            Self::List(col) => {
+                writeln!(f, "{}", col.col_name)?;
+                for row in 0..col.n_elements {
+                    writeln!(f, "{}", self.data_as_value(row).map_err(|_| fmt::Error)?)?;
+                }
+                Ok(())
+            }
+            Self::Struct(col) => {
+                writeln!(f, "{}", col.col_name)?;
+                for row in 0..col.len() {
+                    writeln!(f, "{}", col.get(row).map_err(|_| fmt::Error)?)?;
+                }
+                Ok(())
+            }
+            Self::Custom(col) => {
+                writeln!(f, "{}", col.name())?;
+                for idx in 0..col.len() {
+                    writeln!(f, "{}", col.display(idx).unwrap_or_default())?;
                 }
                 Ok(())
             }
+            Self::Compressed(col) => col.decode().fmt(f),
+            Self::View(col) => col.materialize().fmt(f),
         }
     }
 }
 
 impl ColType {
-    pub(crate) fn from_values(
-        elements: &[String],
+    pub(crate) fn from_values<S: AsRef<str>>(
+        elements: &[S],
         name: String,
         config: Option<ColConfig>,
+        kinds: &[SharedKind],
     ) -> Result<Self, Error> {
-        if let Some(config) = config
-           && let Some(col) = Self::as_date(elements, &name, config) {
+        let _span = span!("infer", column = name.as_str(), rows = elements.len());
+        if let Some(config) = config {
+            if let Some(col) = Self::as_date(elements, &name, config) {
                 let col = col?;
                 return Ok(Self::Datetime(col));
+            }
+            if let Some(unit) = config.epoch_unit {
+                let col = CsvCol::<i64>::from_str_list(elements, &name)?;
+                return Ok(Self::Datetime(col.to_datetime(unit)));
+            }
+            if config.detect_epochs
+                && let Ok(col) = CsvCol::<i64>::from_str_list(elements, &name)
+                && let Some(unit) = EpochUnit::detect(&col.values)
+            {
+                info!("Column {name} detected as epoch {unit:?}");
+                return Ok(Self::Datetime(col.to_datetime(unit)));
+            }
+            if let Some(dtype) = config.dtype {
+                match Self::from_values_as(elements, &name, dtype, config) {
+                    Ok(col) => return Ok(col),
+                    Err(e) if config.strict => return Err(e),
+                    Err(e) => info!(
+                        "Column {} couldn't be parsed as the requested type '{:?}'. Reason: {}",
+                        &name, dtype, e
+                    ),
+                }
+            }
         }
         macro_rules! try_type {
             ($t:ty, $p:expr,  $n:expr, $en:ident) => {
@@ -97,33 +240,296 @@ impl ColType {
             };
         }
         try_type!(i64, elements, &name, Integer);
+        #[cfg(feature = "decimal")]
+        if config.is_some_and(|c| c.infer_decimals) && is_fixed_scale(elements) {
+            try_type!(Decimal, elements, &name, Decimal);
+        }
         try_type!(f64, elements, &name, Float);
-        try_type!(String, elements, &name, String);
-        Err(ColParseError::InvalidColType { name }.into())
+        #[cfg(feature = "complex")]
+        try_type!(Complex64, elements, &name, Complex);
+        // Kinds take owned strings, only built when some are registered
+        let owned = (!kinds.is_empty()).then(|| {
+            elements
+                .iter()
+                .map(|e| e.as_ref().to_string())
+                .collect::<Vec<_>>()
+        });
+        for kind in kinds {
+            match kind.build(owned.as_deref().unwrap_or_default(), &name) {
+                Ok(col) => return Ok(ColType::Custom(col)),
+                Err(e) => info!(
+                    "Column {} couldn't be parsed as kind '{}'. Reason: {}",
+                    &name,
+                    kind.kind_name(),
+                    e
+                ),
+            }
+        }
+        if config.is_some_and(|c| c.strict)
+            && let Some(outliers) = numeric_outliers(elements)
+        {
+            let samples: Vec<String> = outliers.iter().map(|o| format!("`{o}`")).collect();
+            return Err(CoalaError::NumericFallback {
+                col: name,
+                samples: samples.join(", "),
+            }
+            .into());
+        }
+        Ok(ColType::String(StringCol::new(name, elements)))
+    }
+    pub(crate) fn from_data_values(values: Vec<DataValue>, name: String) -> Result<Self, Error> {
+        if values.iter().any(|v| matches!(v, DataValue::Null)) {
+            return Self::from_nullable_values(values, name);
+        }
+        let all = |f: fn(&DataValue) -> bool| values.iter().all(f);
+        if all(|v| matches!(v, DataValue::Integer(_))) {
+            let values = values.iter().filter_map(DataValue::as_i64).collect();
+            return Ok(Self::Integer(CsvCol::new(name, values)));
+        }
+        #[cfg(feature = "bigint")]
+        if all(|v| matches!(v, DataValue::BigInt(_))) {
+            let values = values
+                .into_iter()
+                .filter_map(|v| match v {
+                    DataValue::BigInt(b) => Some(b),
+                    _ => None,
+                })
+                .collect();
+            return Ok(Self::BigInt(CsvCol::new(name, values)));
+        }
+        #[cfg(feature = "complex")]
+        if all(|v| matches!(v, DataValue::Complex(_))) {
+            let values = values
+                .iter()
+                .filter_map(|v| match v {
+                    DataValue::Complex(c) => Some(*c),
+                    _ => None,
+                })
+                .collect();
+            return Ok(Self::Complex(CsvCol::new(name, values)));
+        }
+        #[cfg(feature = "decimal")]
+        if all(|v| matches!(v, DataValue::Decimal(_))) {
+            let values = values
+                .iter()
+                .filter_map(|v| match v {
+                    DataValue::Decimal(d) => Some(*d),
+                    _ => None,
+                })
+                .collect();
+            return Ok(Self::Decimal(CsvCol::new(name, values)));
+        }
+        if all(|v| v.as_f64().is_some()) {
+            let values = values.iter().filter_map(DataValue::as_f64).collect();
+            return Ok(Self::Float(CsvCol::new(name, values)));
+        }
+        if all(|v| matches!(v, DataValue::DateTime(_))) {
+            let values = values
+                .into_iter()
+                .filter_map(|v| match v {
+                    DataValue::DateTime(d) => Some(d),
+                    _ => None,
+                })
+                .collect();
+            return Ok(Self::Datetime(CsvCol::new(name, values)));
+        }
+        if all(|v| matches!(v, DataValue::List(_))) {
+            let values = values
+                .into_iter()
+                .filter_map(|v| match v {
+                    DataValue::List(list) => Some(list),
+                    _ => None,
+                })
+                .collect();
+            return Ok(Self::List(CsvCol::new(name, values)));
+        }
+        let values = values.iter().map(|v| v.to_string());
+        Ok(Self::String(StringCol::new(name, values)))
+    }
+    /// `from_data_values` keeping type `dtype` instead of inferring one, errors when the
+    /// values don't fit it. Values of every null are typeless like on load
+    pub(crate) fn from_data_values_as(
+        values: Vec<DataValue>,
+        name: String,
+        dtype: DType,
+    ) -> Result<Self, Error> {
+        let col = match dtype {
+            DType::Struct => Self::Struct(StructCol::from_values(name, values)?),
+            _ => Self::from_data_values(values, name)?,
+        };
+        if col.dtype() != dtype && col.null_count() < col.len() {
+            return Err(CoalaError::TypeMismatch {
+                col: col.name().to_string(),
+                expected: dtype.name().to_string(),
+                found: col.dtype().name().to_string(),
+            }
+            .into());
+        }
+        Ok(col)
     }
-    pub(crate) fn as_date(
-        elements: &[String],
+    /// A column of the same name and type holding `values`, a custom column keeping its kind
+    pub(crate) fn with_values(&self, values: Vec<DataValue>) -> Result<Self, Error> {
+        match self {
+            Self::Custom(col) => Ok(Self::Custom(col.with_values(&values)?)),
+            Self::Compressed(col) => col.decode().with_values(values),
+            Self::View(col) => col.materialize().with_values(values),
+            col => Self::from_data_values_as(values, col.name().to_string(), col.dtype()),
+        }
+    }
+    pub(crate) fn from_values_as<S: AsRef<str>>(
+        elements: &[S],
+        name: &str,
+        dtype: DType,
+        config: ColConfig,
+    ) -> Result<Self, Error> {
+        Ok(match dtype {
+            DType::Float => Self::Float(CsvCol::from_str_list(elements, name)?),
+            DType::Integer => Self::Integer(CsvCol::from_str_list(elements, name)?),
+            DType::String => Self::String(StringCol::new(name.to_string(), elements)),
+            DType::Datetime => Self::Datetime(CsvCol::as_datetime(
+                elements,
+                name,
+                config.date_formats,
+                config.fallback_date_formats,
+            )?),
+            #[cfg(feature = "decimal")]
+            DType::Decimal => Self::Decimal(CsvCol::from_str_list(elements, name)?),
+            #[cfg(feature = "complex")]
+            DType::Complex => Self::Complex(CsvCol::from_str_list(elements, name)?),
+            #[cfg(feature = "bigint")]
+            DType::BigInt => Self::BigInt(CsvCol::from_str_list(elements, name)?),
+            DType::List | DType::Struct | DType::Custom(_) => {
+                return Err(CoalaError::InvalidColType {
+                    name: name.to_string(),
+                }
+                .into())
+            }
+        })
+    }
+    pub(crate) fn as_date<S: AsRef<str>>(
+        elements: &[S],
         name: &str,
         config: ColConfig,
     ) -> Option<Result<CsvCol<Datetime>, Error>> {
         match config {
             ColConfig {
-                date_format,
+                date_formats,
+                fallback_date_formats,
                 as_date: true,
-            } => Some(CsvCol::as_datetime(elements, name, date_format)),
+                ..
+            } => Some(CsvCol::as_datetime(
+                elements,
+                name,
+                date_formats,
+                fallback_date_formats,
+            )),
             _ => None,
         }
     }
-    pub(crate) fn print_range_lines(
-        &self,
-        beg: usize,
-        end: usize,
-    ) -> Result<(Vec<String>, usize), Error> {
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Self::Float(col) => col.n_elements,
+            Self::Integer(col) => col.n_elements,
+            Self::String(col) => col.len(),
+            Self::Datetime(col) => col.n_elements,
+            #[cfg(feature = "decimal")]
+            Self::Decimal(col) => col.n_elements,
+            #[cfg(feature = "complex")]
+            Self::Complex(col) => col.n_elements,
+            #[cfg(feature = "bigint")]
+            Self::BigInt(col) => col.n_elements,
+            Self::List(col) => col.n_elements,
+            Self::Struct(col) => col.len(),
+            Self::Custom(col) => col.len(),
+            Self::Compressed(col) => col.len(),
+            Self::View(col) => col.len(),
+        }
+    }
+    // Looks through struct fields too, so nested columns are reachable by their dotted path
+    pub(crate) fn find(&self, name: &str) -> Option<&ColType> {
+        if self.name() == name {
+            return Some(self);
+        }
+        match self {
+            Self::Struct(col) => col.fields.iter().find_map(|field| field.find(name)),
+            _ => None,
+        }
+    }
+    // Builds a new column out of the rows at `indices`, keeping the column type
+    pub(crate) fn take(&self, indices: &[usize]) -> Self {
+        match self {
+            Self::Float(col) => Self::Float(col.take(indices)),
+            Self::Integer(col) => Self::Integer(col.take(indices)),
+            Self::String(col) => Self::String(col.take(indices)),
+            Self::Datetime(col) => Self::Datetime(col.take(indices)),
+            #[cfg(feature = "decimal")]
+            Self::Decimal(col) => Self::Decimal(col.take(indices)),
+            #[cfg(feature = "complex")]
+            Self::Complex(col) => Self::Complex(col.take(indices)),
+            #[cfg(feature = "bigint")]
+            Self::BigInt(col) => Self::BigInt(col.take(indices)),
+            Self::List(col) => Self::List(col.take(indices)),
+            Self::Struct(col) => Self::Struct(col.take(indices)),
+            Self::Custom(col) => Self::Custom(col.take(indices)),
+            Self::Compressed(col) => col.decode().take(indices),
+            Self::View(col) => col.take(indices),
+        }
+    }
+    pub(crate) fn print_range_lines(&self, beg: usize, end: usize) -> Result<Vec<String>, Error> {
         match self {
             Self::Float(col) => col.get_range_as_strings(beg, end),
             Self::Integer(col) => col.get_range_as_strings(beg, end),
             Self::Datetime(col) => col.get_range_as_strings(beg, end),
             Self::String(col) => col.get_range_as_strings(beg, end),
+            #[cfg(feature = "decimal")]
+            Self::Decimal(col) => col.get_range_as_strings(beg, end),
+            #[cfg(feature = "complex")]
+            Self::Complex(col) => col.get_range_as_strings(beg, end),
+            #[cfg(feature = "bigint")]
+            Self::BigInt(col) => col.get_range_as_strings(beg, end),
+            Self::List(col) => {
+                if end > col.n_elements || beg > end {
+                    return Err(CoalaError::OutOfRange.into());
+                }
+                (beg..end)
+                    .map(|row| self.data_as_value(row).map(|v| v.to_string()))
+                    .collect()
+            }
+            Self::Struct(col) => {
+                if end > col.len() || beg > end {
+                    return Err(CoalaError::OutOfRange.into());
+                }
+                (beg..end)
+                    .map(|row| col.get(row).map(|v| v.to_string()))
+                    .collect()
+            }
+            Self::Custom(col) => {
+                if end > col.len() || beg > end {
+                    return Err(CoalaError::OutOfRange.into());
+                }
+                Ok((beg..end).filter_map(|i| col.display(i)).collect())
+            }
+            Self::Compressed(col) => col.decode().print_range_lines(beg, end),
+            Self::View(col) => col.print_range_lines(beg, end),
+        }
+    }
+    pub(crate) fn dtype(&self) -> DType {
+        match self {
+            ColType::Float(_) => DType::Float,
+            ColType::Integer(_) => DType::Integer,
+            ColType::String(_) => DType::String,
+            ColType::Datetime(_) => DType::Datetime,
+            #[cfg(feature = "decimal")]
+            ColType::Decimal(_) => DType::Decimal,
+            #[cfg(feature = "complex")]
+            ColType::Complex(_) => DType::Complex,
+            #[cfg(feature = "bigint")]
+            ColType::BigInt(_) => DType::BigInt,
+            ColType::List(_) => DType::List,
+            ColType::Struct(_) => DType::Struct,
+            ColType::Custom(col) => DType::Custom(col.kind_name()),
+            ColType::Compressed(col) => col.dtype(),
+            ColType::View(col) => col.dtype(),
         }
     }
     pub(crate) fn name(&self) -> &str {
@@ -132,14 +538,66 @@ impl ColType {
             ColType::Integer(csv_col) => &csv_col.col_name,
             ColType::String(csv_col) => &csv_col.col_name,
             ColType::Datetime(csv_col) => &csv_col.col_name,
+            #[cfg(feature = "decimal")]
+            ColType::Decimal(csv_col) => &csv_col.col_name,
+            #[cfg(feature = "complex")]
+            ColType::Complex(csv_col) => &csv_col.col_name,
+            #[cfg(feature = "bigint")]
+            ColType::BigInt(csv_col) => &csv_col.col_name,
+            ColType::List(csv_col) => &csv_col.col_name,
+            ColType::Struct(col) => &col.col_name,
+            ColType::Custom(col) => col.name(),
+            ColType::Compressed(col) => &col.col_name,
+            ColType::View(col) => col.name(),
         }
     }
 
+    pub(crate) fn integers(&self) -> Result<&CsvCol<i64>, Error> {
+        match self {
+            Self::Integer(col) => Ok(col),
+            col => Err(CoalaError::InvalidType {
+                col: col.name().to_string(),
+            }
+            .into()),
+        }
+    }
+    pub(crate) fn sum(&self) -> Result<DataValue, Error> {
+        match self {
+            Self::Float(col) => col.sum(),
+            Self::Integer(col) => col.sum(),
+            #[cfg(feature = "decimal")]
+            Self::Decimal(col) => col.sum(),
+            #[cfg(feature = "bigint")]
+            Self::BigInt(col) => col.sum(),
+            #[cfg(feature = "complex")]
+            Self::Complex(col) => col.sum(),
+            Self::Custom(col) if let Some(values) = col.to_f64_values() => {
+                CsvCol::new(col.name().to_string(), values).sum()
+            }
+            Self::Compressed(col) => col.decode().sum(),
+            Self::View(col) => col.materialize().sum(),
+            col => Err(CoalaError::InvalidType {
+                col: col.name().to_string(),
+            }
+            .into()),
+        }
+    }
     pub(crate) fn mean(&self) -> Result<DataValue, Error> {
         match self {
             Self::Float(col) => col.mean(),
             Self::Integer(col) => col.mean(),
-            col => Err(StatisticsError::InvalidType {
+            #[cfg(feature = "decimal")]
+            Self::Decimal(col) => col.mean(),
+            #[cfg(feature = "bigint")]
+            Self::BigInt(col) => col.mean(),
+            #[cfg(feature = "complex")]
+            Self::Complex(col) => col.mean(),
+            Self::Custom(col) if let Some(values) = col.to_f64_values() => {
+                CsvCol::new(col.name().to_string(), values).mean()
+            }
+            Self::Compressed(col) => col.decode().mean(),
+            Self::View(col) => col.materialize().mean(),
+            col => Err(CoalaError::InvalidType {
                 col: col.name().to_string(),
             }
             .into()),
@@ -149,17 +607,92 @@ impl ColType {
         match self {
             Self::Float(col) => col.median(),
             Self::Integer(col) => col.median(),
-            col => Err(StatisticsError::InvalidType {
+            #[cfg(feature = "decimal")]
+            Self::Decimal(col) => col.median(),
+            #[cfg(feature = "bigint")]
+            Self::BigInt(col) => col.median(),
+            Self::Custom(col) if let Some(values) = col.to_f64_values() => {
+                CsvCol::new(col.name().to_string(), values).median()
+            }
+            Self::Compressed(col) => col.decode().median(),
+            Self::View(col) => col.materialize().median(),
+            col => Err(CoalaError::InvalidType {
                 col: col.name().to_string(),
             }
             .into()),
         }
     }
+    pub(crate) fn min(&self) -> Result<DataValue, Error> {
+        self.extreme(Ordering::Less)
+    }
+    pub(crate) fn max(&self) -> Result<DataValue, Error> {
+        self.extreme(Ordering::Greater)
+    }
+    fn extreme(&self, wanted: Ordering) -> Result<DataValue, Error> {
+        let index = match self {
+            Self::Float(col) => col.extreme_index(wanted),
+            Self::Integer(col) => col.extreme_index(wanted),
+            Self::String(col) => col.extreme_index(wanted),
+            Self::Datetime(col) => col.extreme_index(wanted),
+            #[cfg(feature = "decimal")]
+            Self::Decimal(col) => col.extreme_index(wanted),
+            #[cfg(feature = "bigint")]
+            Self::BigInt(col) => col.extreme_index(wanted),
+            #[cfg(feature = "complex")]
+            Self::Complex(col) => {
+                return Err(CoalaError::InvalidType {
+                    col: col.col_name.clone(),
+                }
+                .into())
+            }
+            Self::List(CsvCol { col_name, .. }) | Self::Struct(StructCol { col_name, .. }) => {
+                return Err(CoalaError::InvalidType {
+                    col: col_name.clone(),
+                }
+                .into())
+            }
+            Self::Custom(col) => col.extreme_index(wanted),
+            Self::Compressed(col) => return col.decode().extreme(wanted),
+            Self::View(col) => return col.materialize().extreme(wanted),
+        };
+        match index {
+            Some(index) => self.data_as_value(index),
+            None => Err(CoalaError::EmptyColumn.into()),
+        }
+    }
     pub(crate) fn quantile(&self, quantile: f64) -> Result<DataValue, Error> {
         match self {
             Self::Float(col) => col.quantile(quantile),
             Self::Integer(col) => col.quantile(quantile),
-            col => Err(StatisticsError::InvalidType {
+            #[cfg(feature = "decimal")]
+            Self::Decimal(col) => col.quantile(quantile),
+            #[cfg(feature = "bigint")]
+            Self::BigInt(col) => col.quantile(quantile),
+            Self::Custom(col) if let Some(values) = col.to_f64_values() => {
+                CsvCol::new(col.name().to_string(), values).quantile(quantile)
+            }
+            Self::Compressed(col) => col.decode().quantile(quantile),
+            Self::View(col) => col.materialize().quantile(quantile),
+            col => Err(CoalaError::InvalidType {
+                col: col.name().to_string(),
+            }
+            .into()),
+        }
+    }
+    pub(crate) fn quantiles(&self, quantiles: &[f64]) -> Result<Vec<DataValue>, Error> {
+        match self {
+            Self::Float(col) => col.quantiles(quantiles),
+            Self::Integer(col) => col.quantiles(quantiles),
+            #[cfg(feature = "decimal")]
+            Self::Decimal(col) => col.quantiles(quantiles),
+            #[cfg(feature = "bigint")]
+            Self::BigInt(col) => col.quantiles(quantiles),
+            Self::Custom(col) if let Some(values) = col.to_f64_values() => {
+                CsvCol::new(col.name().to_string(), values).quantiles(quantiles)
+            }
+            Self::Compressed(col) => col.decode().quantiles(quantiles),
+            Self::View(col) => col.materialize().quantiles(quantiles),
+            col => Err(CoalaError::InvalidType {
                 col: col.name().to_string(),
             }
             .into()),
@@ -169,76 +702,457 @@ impl ColType {
         match self {
             Self::Float(col) => col.stddev(),
             Self::Integer(col) => col.stddev(),
-            col => Err(StatisticsError::InvalidType {
+            #[cfg(feature = "decimal")]
+            Self::Decimal(col) => col.stddev(),
+            #[cfg(feature = "bigint")]
+            Self::BigInt(col) => col.stddev(),
+            #[cfg(feature = "complex")]
+            Self::Complex(col) => col.stddev(),
+            Self::Custom(col) if let Some(values) = col.to_f64_values() => {
+                CsvCol::new(col.name().to_string(), values).stddev()
+            }
+            Self::Compressed(col) => col.decode().stddev(),
+            Self::View(col) => col.materialize().stddev(),
+            col => Err(CoalaError::InvalidType {
+                col: col.name().to_string(),
+            }
+            .into()),
+        }
+    }
+    pub(crate) fn median_absolute_deviation(&self) -> Result<DataValue, Error> {
+        match self {
+            Self::Float(col) => col.median_absolute_deviation(),
+            Self::Integer(col) => col.median_absolute_deviation(),
+            #[cfg(feature = "decimal")]
+            Self::Decimal(col) => col.median_absolute_deviation(),
+            #[cfg(feature = "bigint")]
+            Self::BigInt(col) => col.median_absolute_deviation(),
+            Self::Custom(col) if let Some(values) = col.to_f64_values() => {
+                CsvCol::new(col.name().to_string(), values).median_absolute_deviation()
+            }
+            Self::Compressed(col) => col.decode().median_absolute_deviation(),
+            Self::View(col) => col.materialize().median_absolute_deviation(),
+            col => Err(CoalaError::InvalidType {
+                col: col.name().to_string(),
+            }
+            .into()),
+        }
+    }
+    pub(crate) fn trimmed_std(&self, proportion: f64) -> Result<DataValue, Error> {
+        match self {
+            Self::Float(col) => col.trimmed_std(proportion),
+            Self::Integer(col) => col.trimmed_std(proportion),
+            #[cfg(feature = "decimal")]
+            Self::Decimal(col) => col.trimmed_std(proportion),
+            #[cfg(feature = "bigint")]
+            Self::BigInt(col) => col.trimmed_std(proportion),
+            Self::Custom(col) if let Some(values) = col.to_f64_values() => {
+                CsvCol::new(col.name().to_string(), values).trimmed_std(proportion)
+            }
+            Self::Compressed(col) => col.decode().trimmed_std(proportion),
+            Self::View(col) => col.materialize().trimmed_std(proportion),
+            col => Err(CoalaError::InvalidType {
                 col: col.name().to_string(),
             }
             .into()),
         }
     }
+    pub(crate) fn distinct_sketch(&self) -> HyperLogLog {
+        let mut sketch = HyperLogLog::default();
+        match self {
+            ColType::Float(csv_col) => csv_col
+                .iter_valid()
+                .for_each(|v| sketch.push(&float_bits(*v))),
+            ColType::Integer(csv_col) => csv_col.iter_valid().for_each(|v| sketch.push(v)),
+            ColType::String(col) => (0..col.len())
+                .filter(|&i| col.is_valid(i))
+                .filter_map(|i| col.get(i))
+                .for_each(|v| sketch.push(v)),
+            ColType::Datetime(csv_col) => csv_col
+                .iter_valid()
+                .for_each(|v| sketch.push(&v.to_string())),
+            #[cfg(feature = "decimal")]
+            ColType::Decimal(csv_col) => csv_col.iter_valid().for_each(|v| sketch.push(v)),
+            #[cfg(feature = "bigint")]
+            ColType::BigInt(csv_col) => csv_col.iter_valid().for_each(|v| sketch.push(v)),
+            #[cfg(feature = "complex")]
+            ColType::Complex(csv_col) => csv_col
+                .iter_valid()
+                .for_each(|v| sketch.push(&(float_bits(v.re), float_bits(v.im)))),
+            ColType::List(csv_col) => csv_col
+                .iter_valid()
+                .for_each(|v| sketch.push(&DataValue::List(v.clone()).to_string())),
+            ColType::Struct(col) => (0..col.len())
+                .filter_map(|row| col.get(row).ok())
+                .filter(|v| !matches!(v, DataValue::Null))
+                .for_each(|v| sketch.push(&v.to_string())),
+            ColType::Custom(col) => (0..col.len())
+                .filter_map(|i| col.display(i))
+                .for_each(|v| sketch.push(&v)),
+            ColType::Compressed(col) => return col.decode().distinct_sketch(),
+            ColType::View(col) => return col.materialize().distinct_sketch(),
+        }
+        sketch
+    }
+    pub(crate) fn build_filter(&mut self) {
+        match self {
+            ColType::Float(csv_col) => {
+                let mut filter = BloomFilter::with_capacity(csv_col.n_elements);
+                csv_col
+                    .values
+                    .iter()
+                    .for_each(|v| filter.insert(&float_bits(*v)));
+                csv_col.filter = Some(filter);
+            }
+            ColType::Integer(csv_col) => {
+                let mut filter = BloomFilter::with_capacity(csv_col.n_elements);
+                csv_col.values.iter().for_each(|v| filter.insert(v));
+                csv_col.filter = Some(filter);
+            }
+            ColType::String(col) => {
+                let mut filter = BloomFilter::with_capacity(col.len());
+                col.iter().for_each(|v| filter.insert(v));
+                col.filter = Some(filter);
+            }
+            ColType::Datetime(csv_col) => {
+                let mut filter = BloomFilter::with_capacity(csv_col.n_elements);
+                csv_col
+                    .values
+                    .iter()
+                    .for_each(|v| filter.insert(&v.to_string()));
+                csv_col.filter = Some(filter);
+            }
+            #[cfg(feature = "decimal")]
+            ColType::Decimal(csv_col) => {
+                let mut filter = BloomFilter::with_capacity(csv_col.n_elements);
+                csv_col.values.iter().for_each(|v| filter.insert(v));
+                csv_col.filter = Some(filter);
+            }
+            #[cfg(feature = "complex")]
+            ColType::Complex(csv_col) => {
+                let mut filter = BloomFilter::with_capacity(csv_col.n_elements);
+                csv_col
+                    .values
+                    .iter()
+                    .for_each(|v| filter.insert(&(float_bits(v.re), float_bits(v.im))));
+                csv_col.filter = Some(filter);
+            }
+            #[cfg(feature = "bigint")]
+            ColType::BigInt(csv_col) => {
+                let mut filter = BloomFilter::with_capacity(csv_col.n_elements);
+                csv_col.values.iter().for_each(|v| filter.insert(v));
+                csv_col.filter = Some(filter);
+            }
+            ColType::Struct(col) => col.fields.iter_mut().for_each(ColType::build_filter),
+            ColType::List(_) | ColType::Custom(_) | ColType::Compressed(_) | ColType::View(_) => {}
+        }
+    }
+    pub(crate) fn might_contain(&self, value: &DataValue) -> bool {
+        match (self, value) {
+            (ColType::Float(csv_col), value) => match (&csv_col.filter, value.as_f64()) {
+                (Some(filter), Some(f)) => filter.contains(&float_bits(f)),
+                (None, Some(_)) => true,
+                (_, None) => false,
+            },
+            (ColType::Integer(csv_col), DataValue::Integer(i)) => {
+                csv_col.filter.as_ref().is_none_or(|f| f.contains(i))
+            }
+            (ColType::Integer(csv_col), DataValue::Float(f)) if f.fract() == 0.0 => csv_col
+                .filter
+                .as_ref()
+                .is_none_or(|filter| filter.contains(&(*f as i64))),
+            (ColType::String(csv_col), DataValue::String(s)) => csv_col
+                .filter
+                .as_ref()
+                .is_none_or(|f| f.contains(s.as_str())),
+            (ColType::Datetime(csv_col), DataValue::DateTime(d)) => csv_col
+                .filter
+                .as_ref()
+                .is_none_or(|f| f.contains(&d.to_string())),
+            #[cfg(feature = "decimal")]
+            (ColType::Decimal(csv_col), value) => match (&csv_col.filter, value.as_decimal()) {
+                (Some(filter), Some(d)) => filter.contains(&d.normalize()),
+                (None, Some(_)) => true,
+                (_, None) => false,
+            },
+            #[cfg(feature = "complex")]
+            (ColType::Complex(csv_col), value) => match (&csv_col.filter, value.as_complex()) {
+                (Some(filter), Some(c)) => filter.contains(&(float_bits(c.re), float_bits(c.im))),
+                (None, Some(_)) => true,
+                (_, None) => false,
+            },
+            #[cfg(feature = "bigint")]
+            (ColType::BigInt(csv_col), value) => match (&csv_col.filter, value.as_bigint()) {
+                (Some(filter), Some(b)) => filter.contains(&b),
+                (None, Some(_)) => true,
+                (_, None) => false,
+            },
+            (
+                ColType::List(_)
+                | ColType::Struct(_)
+                | ColType::Custom(_)
+                | ColType::Compressed(_)
+                | ColType::View(_),
+                _,
+            ) => true,
+            _ => false,
+        }
+    }
     pub(crate) fn data_as_value(&self, index: usize) -> Result<DataValue, Error> {
+        if self
+            .validity()
+            .is_some_and(|validity| validity.get(index).is_some_and(|valid| !*valid))
+        {
+            return Ok(DataValue::Null);
+        }
         match self {
             ColType::Float(csv_col) => csv_col
                 .values
                 .get(index)
                 .map(|f| DataValue::Float(*f))
-                .ok_or(ColParseError::OutOfRange.into()),
+                .ok_or(CoalaError::OutOfRange.into()),
             ColType::Integer(csv_col) => csv_col
                 .values
                 .get(index)
                 .map(|f| DataValue::Integer(*f))
-                .ok_or(ColParseError::OutOfRange.into()),
-            ColType::String(csv_col) => csv_col
-                .values
+                .ok_or(CoalaError::OutOfRange.into()),
+            ColType::String(col) => col
                 .get(index)
-                .map(|f| DataValue::String(f.clone()))
-                .ok_or(ColParseError::OutOfRange.into()),
+                .map(|s| DataValue::String(s.to_string()))
+                .ok_or(CoalaError::OutOfRange.into()),
             ColType::Datetime(csv_col) => csv_col
                 .values
                 .get(index)
                 .map(|f| DataValue::DateTime(*f))
-                .ok_or(ColParseError::OutOfRange.into()),
+                .ok_or(CoalaError::OutOfRange.into()),
+            #[cfg(feature = "decimal")]
+            ColType::Decimal(csv_col) => csv_col
+                .values
+                .get(index)
+                .map(|d| DataValue::Decimal(*d))
+                .ok_or(CoalaError::OutOfRange.into()),
+            #[cfg(feature = "complex")]
+            ColType::Complex(csv_col) => csv_col
+                .values
+                .get(index)
+                .map(|c| DataValue::Complex(*c))
+                .ok_or(CoalaError::OutOfRange.into()),
+            #[cfg(feature = "bigint")]
+            ColType::BigInt(csv_col) => csv_col
+                .values
+                .get(index)
+                .map(|b| DataValue::BigInt(b.clone()))
+                .ok_or(CoalaError::OutOfRange.into()),
+            ColType::List(csv_col) => csv_col
+                .values
+                .get(index)
+                .map(|list| DataValue::List(list.clone()))
+                .ok_or(CoalaError::OutOfRange.into()),
+            ColType::Struct(col) => col.get(index),
+            ColType::Custom(col) => col.get(index).ok_or(CoalaError::OutOfRange.into()),
+            ColType::Compressed(col) => col.get(index).ok_or(CoalaError::OutOfRange.into()),
+            ColType::View(col) => col.get(index).ok_or(CoalaError::OutOfRange.into()),
         }
     }
 }
 
-impl<T: Display> CsvCol<T> {
-    fn get_range_as_strings(&self, beg: usize, end: usize) -> Result<(Vec<String>, usize), Error> {
-        if end > self.n_elements || beg > end {
-            return Err(miette!("n is greater than number of lines in col"));
+impl DType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DType::Float => "Float",
+            DType::Integer => "Integer",
+            DType::String => "String",
+            DType::Datetime => "Datetime",
+            #[cfg(feature = "decimal")]
+            DType::Decimal => "Decimal",
+            #[cfg(feature = "complex")]
+            DType::Complex => "Complex",
+            #[cfg(feature = "bigint")]
+            DType::BigInt => "BigInt",
+            DType::List => "List",
+            DType::Struct => "Struct",
+            DType::Custom(name) => name,
+        }
+    }
+    pub fn is_numeric(&self) -> bool {
+        match self {
+            DType::Float | DType::Integer => true,
+            #[cfg(feature = "decimal")]
+            DType::Decimal => true,
+            #[cfg(feature = "complex")]
+            DType::Complex => true,
+            #[cfg(feature = "bigint")]
+            DType::BigInt => true,
+            _ => false,
+        }
+    }
+    pub(crate) fn parse<S: AsRef<str>>(
+        &self,
+        raw: &str,
+        date_formats: &[S],
+    ) -> Result<DataValue, Error> {
+        let invalid =
+            || miette!("Error parsing value `{raw}`. String couldn't be converted safely.");
+        match self {
+            DType::Float => raw.parse().map(DataValue::Float).map_err(|_| invalid()),
+            DType::Integer => raw.parse().map(DataValue::Integer).map_err(|_| invalid()),
+            DType::String | DType::List | DType::Struct | DType::Custom(_) => {
+                Ok(DataValue::String(raw.to_string()))
+            }
+            #[cfg(feature = "decimal")]
+            DType::Decimal => raw.parse().map(DataValue::Decimal).map_err(|_| invalid()),
+            #[cfg(feature = "complex")]
+            DType::Complex => raw.parse().map(DataValue::Complex).map_err(|_| invalid()),
+            #[cfg(feature = "bigint")]
+            DType::BigInt => raw.parse().map(DataValue::BigInt).map_err(|_| invalid()),
+            DType::Datetime => parse_datetime::<S>(raw, date_formats, &[])
+                .map(DataValue::DateTime)
+                .ok_or_else(invalid),
+        }
+    }
+}
+
+#[cfg(feature = "decimal")]
+fn is_fixed_scale<S: AsRef<str>>(elements: &[S]) -> bool {
+    let mut scales = elements
+        .iter()
+        .map(|e| e.as_ref().parse::<Decimal>().map(|d| d.scale()));
+    match scales.next() {
+        Some(Ok(scale)) if scale > 0 => scales.all(|s| s.is_ok_and(|s| s == scale)),
+        _ => false,
+    }
+}
+
+impl<T> CsvCol<T> {
+    pub(crate) fn new(col_name: String, values: Vec<T>) -> Self {
+        Self {
+            col_name,
+            n_elements: values.len(),
+            values,
+            sorted_values: RwLock::default(),
+            memo: Box::default(),
+            filter: None,
+            validity: None,
         }
-        let mut max_width = 0;
-        let mut strings = Vec::with_capacity(end - beg);
-        for i in beg..end {
-            let s = self.values[i].to_string();
-            if s.len() > max_width {
-                max_width = s.len();
+    }
+    pub(crate) fn is_valid(&self, index: usize) -> bool {
+        is_valid(self.validity.as_ref(), index)
+    }
+    /// Rows that aren't null
+    pub(crate) fn n_valid(&self) -> usize {
+        self.n_elements - self.validity.as_ref().map_or(0, |v| v.count_zeros())
+    }
+    /// The values of the rows that aren't null, without copying them
+    pub(crate) fn iter_valid(&self) -> impl Iterator<Item = &T> + '_ {
+        self.values
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.is_valid(*i))
+            .map(|(_, v)| v)
+    }
+}
+
+impl<T: Clone> CsvCol<T> {
+    pub(crate) fn take(&self, indices: &[usize]) -> Self {
+        let values = indices.iter().map(|&i| self.values[i].clone()).collect();
+        let mut col = Self::new(self.col_name.clone(), values);
+        col.validity = take_validity(self.validity.as_ref(), indices);
+        col
+    }
+    /// The values of the rows that aren't null
+    pub(crate) fn valid_values(&self) -> Cow<'_, [T]> {
+        match &self.validity {
+            None => Cow::Borrowed(&self.values),
+            Some(validity) => {
+                let mut values = Vec::with_capacity(self.n_valid());
+                values.extend(
+                    self.values
+                        .iter()
+                        .zip(validity.iter())
+                        .filter(|(_, valid)| **valid)
+                        .map(|(v, _)| v.clone()),
+                );
+                Cow::Owned(values)
             }
-            strings.push(s);
         }
-        Ok((strings, max_width))
     }
 }
 
+pub(crate) fn is_valid(validity: Option<&BitVec>, index: usize) -> bool {
+    validity.is_none_or(|validity| validity.get(index).is_none_or(|valid| *valid))
+}
+
+// Validity of the rows at `indices`, `None` once none of them is null
+pub(crate) fn take_validity(validity: Option<&BitVec>, indices: &[usize]) -> Option<BitVec> {
+    let validity = validity?;
+    let taken: BitVec = indices.iter().map(|&i| validity[i]).collect();
+    (!taken.all()).then_some(taken)
+}
+
+impl Serialize for DType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for DType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        match name.as_str() {
+            "Float" => Ok(DType::Float),
+            "Integer" => Ok(DType::Integer),
+            "String" => Ok(DType::String),
+            "Datetime" => Ok(DType::Datetime),
+            #[cfg(feature = "decimal")]
+            "Decimal" => Ok(DType::Decimal),
+            #[cfg(feature = "complex")]
+            "Complex" => Ok(DType::Complex),
+            #[cfg(feature = "bigint")]
+            "BigInt" => Ok(DType::BigInt),
+            "List" => Ok(DType::List),
+            "Struct" => Ok(DType::Struct),
+            other => Err(de::Error::custom(format!("unknown dtype `{other}`"))),
+        }
+    }
+}
+
+impl<T: Display> CsvCol<T> {
+    fn display_at(&self, index: usize) -> String {
+        if self.is_valid(index) {
+            self.values[index].to_string()
+        } else {
+            DataValue::Null.to_string()
+        }
+    }
+    fn get_range_as_strings(&self, beg: usize, end: usize) -> Result<Vec<String>, Error> {
+        if end > self.n_elements || beg > end {
+            return Err(CoalaError::OutOfRange.into());
+        }
+        Ok((beg..end).map(|i| self.display_at(i)).collect())
+    }
+}
+
+pub(crate) fn parse_error(row: usize, col: &str, value: &str) -> Error {
+    CoalaError::Parse {
+        row,
+        col: col.to_string(),
+        value: value.to_string(),
+    }
+    .into()
+}
+
 impl<T: FromStr> CsvCol<T> {
-    fn from_str_list(elements: &[String], name: &str) -> Result<Self, Error> {
+    fn from_str_list<S: AsRef<str>>(elements: &[S], name: &str) -> Result<Self, Error> {
         let mut values: Vec<T> = Vec::new();
-        for line in elements {
+        for (row, line) in elements.iter().enumerate() {
+            let line = line.as_ref();
             let t = match line.parse::<T>() {
                 Ok(t) => t,
-                Err(_) => {
-                    return Err(miette!(
-                        "Error parsing value `{line}`. String couldn't be converted safely."
-                    ))
-                }
+                Err(_) => return Err(parse_error(row, name, line)),
             };
             values.push(t);
         }
-        Ok(Self {
-            col_name: name.to_string(),
-            n_elements: values.len(),
-            values,
-            sorted_values: RefCell::default(),
-        })
+        Ok(CsvCol::new(name.to_string(), values))
     }
 }
 
@@ -256,55 +1170,98 @@ impl<T: FromStr> CsvCol<T> {
 //     }
 // }
 
-impl<T: PartialOrd + Clone> CsvCol<T> {
+impl<T: PartialOrd> CsvCol<T> {
+    pub(crate) fn extreme_index(&self, wanted: Ordering) -> Option<usize> {
+        let mut best: Option<usize> = None;
+        for (i, value) in self.values.iter().enumerate() {
+            if !self.is_valid(i) {
+                continue;
+            }
+            match best {
+                Some(b) if value.partial_cmp(&self.values[b]) != Some(wanted) => {}
+                _ => best = Some(i),
+            }
+        }
+        best
+    }
+}
+
+impl<T: SortKey> CsvCol<T> {
     pub(crate) fn get_sorted(&self) -> Vec<T> {
-        if let Some((cached, len)) = &*self.sorted_values.borrow() 
-            && *len == self.n_elements {
-                return cached.clone();
-            }
-        
-        let mut sorted = self.values.clone();
-        sorted.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-        *self.sorted_values.borrow_mut() = Some((sorted.clone(), sorted.len()));
+        if let Ok(cached) = self.sorted_values.read()
+            && let Some((cached, len)) = &*cached
+            && *len == self.n_elements
+        {
+            return cached.clone();
+        }
+        // Threads asking at the same time sort once, the others wait for the write lock and
+        // find the order there
+        let mut cached = self
+            .sorted_values
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some((sorted, len)) = &*cached
+            && *len == self.n_elements
+        {
+            return sorted.clone();
+        }
+        let mut sorted = self.valid_values().into_owned();
+        perf::slow_path("Order statistic", || {
+            format!(
+                "sorting all {} values of `{}`, later ones reuse the order",
+                sorted.len(),
+                self.col_name
+            )
+        });
+        T::sort(&mut sorted);
+        *cached = Some((sorted.clone(), self.n_elements));
         sorted
     }
 }
 
+impl CsvCol<i64> {
+    fn to_datetime(&self, unit: EpochUnit) -> CsvCol<Datetime> {
+        let values = self.values.iter().map(|&v| unit.to_datetime(v)).collect();
+        CsvCol::new(self.col_name.clone(), values)
+    }
+}
+
+/// Tries each format in order, column formats first. Without any format the value is guessed
+pub(crate) fn parse_datetime<S: AsRef<str>>(
+    raw: &str,
+    formats: &[S],
+    fallback: &[S],
+) -> Option<Datetime> {
+    if formats.is_empty() && fallback.is_empty() {
+        return Datetime::try_guess(raw);
+    }
+    formats
+        .iter()
+        .chain(fallback)
+        .find_map(|format| Datetime::from_str(raw, format.as_ref()).ok())
+}
+
 impl CsvCol<Datetime> {
-    fn as_datetime(elements: &[String], name: &str, format: Option<&str>) -> Result<Self, Error> {
+    fn as_datetime<S: AsRef<str>>(
+        elements: &[S],
+        name: &str,
+        formats: &[&str],
+        fallback: &[&str],
+    ) -> Result<Self, Error> {
         let mut values = Vec::new();
-        for line in elements {
-            let t: Datetime;
-            if let Some(format) = format {
-                t = match Datetime::from_str(line, format) {
-                    Ok(t) => t,
-                    Err(e) => {
-                        return Err(miette!(
-                            "Error parsing value `{line}`. String couldn't be converted safely. {e}"
-                        ))
-                    }
-                };
-            } else {
-                t = match Datetime::try_guess(line) {
-                    Some(t) => t,
-                    None => {
-                        return Err(miette!(
-                            "Error parsing value `{line}`. String couldn't be converted safely."
-                        ))
-                    }
-                };
+        for (row, line) in elements.iter().enumerate() {
+            let line = line.as_ref();
+            match parse_datetime(line, formats, fallback) {
+                Some(t) => values.push(t),
+                None => return Err(parse_error(row, name, line)),
             }
-            values.push(t);
         }
-        Ok(CsvCol {
-            col_name: name.to_string(),
-            n_elements: values.len(),
-            values,
-            sorted_values: RefCell::default(),
-        })
+        Ok(CsvCol::new(name.to_string(), values))
     }
 }
 
+/// A cell's value. `Null`, `Integer`, `Float` and `String` are the values of
+/// `embedded::Scalar`, which converts to and from them
 #[derive(Debug, Clone)]
 pub enum DataValue {
     Float(f64),
@@ -312,5 +1269,216 @@ pub enum DataValue {
     Unsigned(u64),
     String(String),
     DateTime(Datetime),
+    #[cfg(feature = "decimal")]
+    Decimal(Decimal),
+    #[cfg(feature = "complex")]
+    Complex(Complex64),
+    #[cfg(feature = "bigint")]
+    BigInt(BigInt),
+    List(Vec<DataValue>),
+    Struct(Vec<(String, DataValue)>),
     Null,
 }
+
+impl From<i64> for DataValue {
+    fn from(value: i64) -> Self {
+        DataValue::Integer(value)
+    }
+}
+
+impl From<f64> for DataValue {
+    fn from(value: f64) -> Self {
+        DataValue::Float(value)
+    }
+}
+
+impl From<&str> for DataValue {
+    fn from(value: &str) -> Self {
+        DataValue::String(value.to_string())
+    }
+}
+
+impl From<String> for DataValue {
+    fn from(value: String) -> Self {
+        DataValue::String(value)
+    }
+}
+
+impl From<Datetime> for DataValue {
+    fn from(value: Datetime) -> Self {
+        DataValue::DateTime(value)
+    }
+}
+
+impl From<Scalar> for DataValue {
+    fn from(value: Scalar) -> Self {
+        match value {
+            Scalar::Null => DataValue::Null,
+            Scalar::Integer(value) => DataValue::Integer(value),
+            Scalar::Float(value) => DataValue::Float(value),
+            Scalar::String(value) => DataValue::String(value),
+        }
+    }
+}
+
+impl TryFrom<DataValue> for Scalar {
+    type Error = DataValue;
+
+    fn try_from(value: DataValue) -> Result<Self, DataValue> {
+        match value {
+            DataValue::Null => Ok(Scalar::Null),
+            DataValue::Integer(value) => Ok(Scalar::Integer(value)),
+            DataValue::Float(value) => Ok(Scalar::Float(value)),
+            DataValue::String(value) => Ok(Scalar::String(value)),
+            other => Err(other),
+        }
+    }
+}
+
+impl DataValue {
+    pub fn as_list(&self) -> Option<&[DataValue]> {
+        match self {
+            DataValue::List(values) => Some(values),
+            _ => None,
+        }
+    }
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            DataValue::Integer(i) => Some(*i),
+            DataValue::Unsigned(u) => i64::try_from(*u).ok(),
+            _ => None,
+        }
+    }
+    // `None` when either side is null or the values can't be ordered against each other
+    pub(crate) fn compare(&self, other: &DataValue) -> Option<Ordering> {
+        match (self, other) {
+            (DataValue::Null, _) | (_, DataValue::Null) => None,
+            (DataValue::Integer(a), DataValue::Integer(b)) => Some(a.cmp(b)),
+            (DataValue::String(a), DataValue::String(b)) => Some(a.cmp(b)),
+            (DataValue::DateTime(a), DataValue::DateTime(b)) => a.partial_cmp(b),
+            #[cfg(feature = "decimal")]
+            (DataValue::Decimal(_), _) | (_, DataValue::Decimal(_)) => {
+                self.as_decimal()?.partial_cmp(&other.as_decimal()?)
+            }
+            #[cfg(feature = "bigint")]
+            (DataValue::BigInt(_), _) | (_, DataValue::BigInt(_)) => {
+                self.as_bigint()?.partial_cmp(&other.as_bigint()?)
+            }
+            (a, b) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        }
+    }
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            DataValue::Float(f) => Some(*f),
+            DataValue::Integer(i) => Some(*i as f64),
+            DataValue::Unsigned(u) => Some(*u as f64),
+            #[cfg(feature = "decimal")]
+            DataValue::Decimal(d) => d.to_f64(),
+            #[cfg(feature = "bigint")]
+            DataValue::BigInt(b) => num_traits::ToPrimitive::to_f64(b),
+            _ => None,
+        }
+    }
+    #[cfg(feature = "bigint")]
+    pub fn as_bigint(&self) -> Option<BigInt> {
+        match self {
+            DataValue::BigInt(b) => Some(b.clone()),
+            DataValue::Integer(i) => Some(BigInt::from(*i)),
+            DataValue::Unsigned(u) => Some(BigInt::from(*u)),
+            _ => None,
+        }
+    }
+    #[cfg(feature = "complex")]
+    pub fn as_complex(&self) -> Option<Complex64> {
+        match self {
+            DataValue::Complex(c) => Some(*c),
+            value => value.as_f64().map(|re| Complex64::new(re, 0.0)),
+        }
+    }
+    #[cfg(feature = "decimal")]
+    pub fn as_decimal(&self) -> Option<Decimal> {
+        match self {
+            DataValue::Decimal(d) => Some(*d),
+            DataValue::Integer(i) => Some(Decimal::from(*i)),
+            DataValue::Unsigned(u) => Some(Decimal::from(*u)),
+            DataValue::Float(f) => Decimal::try_from(*f).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DataValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataValue::Float(v) => write!(f, "{v}"),
+            DataValue::Integer(v) => write!(f, "{v}"),
+            DataValue::Unsigned(v) => write!(f, "{v}"),
+            DataValue::String(v) => write!(f, "{v}"),
+            DataValue::DateTime(v) => write!(f, "{v}"),
+            #[cfg(feature = "decimal")]
+            DataValue::Decimal(v) => write!(f, "{v}"),
+            #[cfg(feature = "complex")]
+            DataValue::Complex(v) => write!(f, "{v}"),
+            #[cfg(feature = "bigint")]
+            DataValue::BigInt(v) => write!(f, "{v}"),
+            DataValue::List(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }
+            DataValue::Struct(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{key}: {value}")?;
+                }
+                write!(f, "}}")
+            }
+            DataValue::Null => write!(f, "null"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn float_filter_matches_both_zeros_and_nan() {
+        let mut col = ColType::Float(CsvCol::new("x".to_string(), vec![0.0, f64::NAN]));
+        col.build_filter();
+        assert!(col.might_contain(&DataValue::Float(-0.0)));
+        assert!(col.might_contain(&DataValue::Float(0.0)));
+        assert!(col.might_contain(&DataValue::Float(-f64::NAN)));
+    }
+
+    #[test]
+    fn scalars_round_trip() {
+        for field in ["", "12", "1.5", "text"] {
+            let scalar = Scalar::parse(field);
+            let value = DataValue::from(scalar.clone());
+            assert_eq!(Scalar::try_from(value).unwrap(), scalar);
+        }
+        assert!(Scalar::try_from(DataValue::Unsigned(1)).is_err());
+    }
+
+    #[test]
+    fn distinct_sketch_skips_nulls_and_merges_zeros() {
+        let mut floats = CsvCol::new("x".to_string(), vec![0.0, -0.0, 5.0, 7.0, f64::NAN]);
+        floats.validity = Some([true, true, true, false, true].into_iter().collect());
+        let sketch = ColType::Float(floats).distinct_sketch();
+        assert_eq!(sketch.estimate().round(), 3.0);
+
+        let mut integers = CsvCol::new("n".to_string(), vec![1, 2, 3, 4]);
+        integers.validity = Some([true, false, false, true].into_iter().collect());
+        let sketch = ColType::Integer(integers).distinct_sketch();
+        assert_eq!(sketch.estimate().round(), 2.0);
+    }
+}
@@ -0,0 +1,105 @@
+use std::borrow::Cow;
+
+use miette::Error;
+
+use crate::{col_parser::DType, csv_parser::Csv, error::CoalaError};
+
+/// An order of the columns of a frame, set with `sort_columns`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColumnOrder {
+    /// Alphabetical by name
+    ByName,
+    /// Grouped by type in the order of the type names, alphabetical by name within a type
+    ByDtype,
+    /// The listed columns first in that order, the others after them as they were
+    Custom(Vec<String>),
+}
+
+impl ColumnOrder {
+    // Index of each column of `header` in this order. Names of a custom order the header
+    // lacks are skipped, frames selected from the sorted one needn't have them all
+    fn permutation(&self, header: &[String], dtypes: &[DType]) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..header.len()).collect();
+        match self {
+            ColumnOrder::ByName => indices.sort_by(|&a, &b| header[a].cmp(&header[b])),
+            ColumnOrder::ByDtype => indices.sort_by(|&a, &b| {
+                (dtypes[a].name(), &header[a]).cmp(&(dtypes[b].name(), &header[b]))
+            }),
+            ColumnOrder::Custom(order) => {
+                let rank = |i: &usize| {
+                    order
+                        .iter()
+                        .position(|name| *name == header[*i])
+                        .unwrap_or(order.len())
+                };
+                indices.sort_by_key(rank);
+            }
+        }
+        indices
+    }
+    fn name(&self) -> String {
+        match self {
+            ColumnOrder::ByName => "by_name".to_string(),
+            ColumnOrder::ByDtype => "by_dtype".to_string(),
+            ColumnOrder::Custom(order) => order.join(", "),
+        }
+    }
+}
+
+impl Csv {
+    /// Puts the columns in `order` and keeps them so: frames selected or concatenated from
+    /// this one, columns added later and exports all follow it, so written files don't depend
+    /// on the order the columns happened to come in
+    pub fn sort_columns(&mut self, order: ColumnOrder) -> Result<(), Error> {
+        if let ColumnOrder::Custom(names) = &order {
+            for name in names {
+                self.col_index(name)?;
+            }
+            if let Some(name) = names
+                .iter()
+                .enumerate()
+                .find_map(|(i, name)| names[..i].contains(name).then_some(name))
+            {
+                return Err(CoalaError::DuplicateColumn { name: name.clone() }.into());
+            }
+        }
+        self.lineage
+            .record(format!("sort_columns({})", order.name()));
+        self.column_order = Some(order);
+        self.apply_column_order();
+        Ok(())
+    }
+    pub fn column_order(&self) -> Option<&ColumnOrder> {
+        self.column_order.as_ref()
+    }
+    // Reorders the columns by the order set with `sort_columns`, if any
+    pub(crate) fn apply_column_order(&mut self) {
+        let Some(order) = &self.column_order else {
+            return;
+        };
+        let dtypes: Vec<DType> = self.cols.iter().map(|col| col.dtype()).collect();
+        let permutation = order.permutation(&self.header, &dtypes);
+        if permutation.iter().enumerate().all(|(i, &j)| i == j) {
+            return;
+        }
+        self.cols = permutation.iter().map(|&i| self.cols[i].clone()).collect();
+        self.header = permutation
+            .iter()
+            .map(|&i| self.header[i].clone())
+            .collect();
+        if let Some(units) = &self.units {
+            self.units = Some(permutation.iter().map(|&i| units[i].clone()).collect());
+        }
+    }
+    // The frame with its columns in the order set with `sort_columns`, for the writers
+    pub(crate) fn in_column_order(&self) -> Cow<'_, Csv> {
+        match self.column_order {
+            Some(_) => {
+                let mut csv = self.clone();
+                csv.apply_column_order();
+                Cow::Owned(csv)
+            }
+            None => Cow::Borrowed(self),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use miette::{Error, IntoDiagnostic};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    agg::{Accumulator, Agg},
+    col_parser::{DType, DataValue},
+    csv_parser::Csv,
+};
+
+/// The cached statistics of a column as plain values, for storing summaries and comparing them
+/// across versions of a dataset. Statistics the column's type has no answer for are `None`,
+/// extremes are kept as text since they needn't be numbers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnStats {
+    pub name: String,
+    pub dtype: DType,
+    pub count: usize,
+    pub null_count: usize,
+    pub mean: Option<f64>,
+    pub median: Option<f64>,
+    pub stddev: Option<f64>,
+    pub min: Option<String>,
+    pub max: Option<String>,
+}
+
+impl ColumnStats {
+    /// The statistics as a JSON object, for monitoring jobs to store or ship
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self).into_diagnostic()
+    }
+}
+
+/// A row of `Csv::profile`: how many values a column holds, how many are null and distinct,
+/// and its extremes as text when it has an order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnProfile {
+    pub name: String,
+    pub dtype: DType,
+    pub count: usize,
+    pub null_count: usize,
+    pub n_unique: usize,
+    pub min: Option<String>,
+    pub max: Option<String>,
+}
+
+/// The profile of every column of a frame, as `Csv::profile` prints it, in a form that
+/// serializes for data-quality artifacts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileReport {
+    pub n_rows: usize,
+    pub columns: Vec<ColumnProfile>,
+}
+
+impl ProfileReport {
+    /// The report as a JSON object, for monitoring jobs to store or ship
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self).into_diagnostic()
+    }
+}
+
+impl Csv {
+    /// The profile of every column, `profile` without building a frame
+    pub fn profile_report(&self) -> Result<ProfileReport, Error> {
+        let text = |value: Result<DataValue, Error>| match value {
+            Ok(DataValue::Null) | Err(_) => None,
+            Ok(value) => Some(value.to_string()),
+        };
+        let columns = self
+            .header
+            .iter()
+            .map(|name| {
+                let col = self.get_col(name)?;
+                let mut distinct = Accumulator::new(Agg::NUnique);
+                for value in col.iter() {
+                    distinct.push_value(&value);
+                }
+                let n_unique = match distinct.finish() {
+                    DataValue::Integer(n) => n as usize,
+                    _ => 0,
+                };
+                let null_count = col.null_count();
+                Ok(ColumnProfile {
+                    name: name.clone(),
+                    dtype: col.dtype(),
+                    count: col.len() - null_count,
+                    null_count,
+                    n_unique,
+                    min: text(col.min()),
+                    max: text(col.max()),
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(ProfileReport {
+            n_rows: self.n_rows,
+            columns,
+        })
+    }
+    /// Every statistic the frame caches for `name`, computing and caching the missing ones
+    pub fn stats(&mut self, name: &str) -> Result<ColumnStats, Error> {
+        let index = self.col_index(name)?;
+        let number = |value: Result<DataValue, Error>| value.ok().and_then(|v| v.as_f64());
+        let text = |value: Result<DataValue, Error>| value.ok().map(|v| v.to_string());
+        let null_count = self.cols[index].null_count();
+        Ok(ColumnStats {
+            name: name.to_string(),
+            dtype: self.cols[index].dtype(),
+            count: self.n_rows - null_count,
+            null_count,
+            mean: number(self.mean(name)),
+            median: number(self.median(name)),
+            stddev: number(self.stddev(name)),
+            min: text(self.min(name)),
+            max: text(self.max(name)),
+        })
+    }
+}
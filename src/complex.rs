@@ -0,0 +1,75 @@
+use miette::Error;
+use num_complex::Complex64;
+
+use crate::{
+    col_parser::{ColType, CsvCol, DataValue},
+    csv_parser::ColViewer,
+    error::CoalaError,
+    statistics::Statistics,
+};
+
+impl Statistics for CsvCol<Complex64> {
+    fn sum(&self) -> Result<DataValue, Error> {
+        Ok(DataValue::Complex(self.valid_values().iter().sum()))
+    }
+    fn mean(&self) -> Result<DataValue, Error> {
+        let values = self.valid_values();
+        if values.is_empty() {
+            return Err(CoalaError::EmptyColumn.into());
+        }
+        let sum: Complex64 = values.iter().sum();
+        Ok(DataValue::Complex(sum / values.len() as f64))
+    }
+    // Complex numbers have no total order, so order statistics aren't defined
+    fn median(&self) -> Result<DataValue, Error> {
+        Err(CoalaError::InvalidType {
+            col: self.col_name.clone(),
+        }
+        .into())
+    }
+    fn quantile(&self, _quantile: f64) -> Result<DataValue, Error> {
+        self.median()
+    }
+    fn stddev(&self) -> Result<DataValue, Error> {
+        let values = self.valid_values();
+        match values.len() {
+            0 => return Err(CoalaError::EmptyColumn.into()),
+            1 => return Ok(DataValue::Null),
+            _ => {}
+        }
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<Complex64>() / n;
+        let variance = values.iter().map(|v| (v - mean).norm_sqr()).sum::<f64>() / (n - 1.0);
+        Ok(DataValue::Float(variance.sqrt()))
+    }
+    fn median_absolute_deviation(&self) -> Result<DataValue, Error> {
+        self.median()
+    }
+    fn trimmed_std(&self, _proportion: f64) -> Result<DataValue, Error> {
+        self.median()
+    }
+}
+
+impl ColViewer<'_> {
+    pub fn magnitudes(&self) -> Result<Vec<f64>, Error> {
+        self.complex_map(Complex64::norm)
+    }
+    pub fn phases(&self) -> Result<Vec<f64>, Error> {
+        self.complex_map(Complex64::arg)
+    }
+    fn complex_map(&self, f: fn(Complex64) -> f64) -> Result<Vec<f64>, Error> {
+        match self.inner {
+            // Nulls come out as NaN
+            ColType::Complex(col) => Ok(col
+                .values
+                .iter()
+                .enumerate()
+                .map(|(row, v)| if col.is_valid(row) { f(*v) } else { f64::NAN })
+                .collect()),
+            col => Err(CoalaError::InvalidType {
+                col: col.name().to_string(),
+            }
+            .into()),
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use std::sync::Arc;
+
+use datetime::datetime::Datetime;
+
+use crate::{
+    col_parser::{ColType, CsvCol, DType, DataValue},
+    csv_parser::{ColViewer, Csv},
+};
+
+// Rows between absolute values of a delta encoded column, bounding the gaps summed per lookup
+const BLOCK: usize = 64;
+
+// A column kept encoded in memory. Reads decode on the fly, so every accessor still sees the
+// original values, only slower
+#[derive(Debug)]
+pub(crate) struct CompressedCol {
+    pub(crate) col_name: String,
+    len: usize,
+    encoding: Encoding,
+}
+
+#[derive(Debug)]
+enum Encoding {
+    // `values` holds one value per run, run `i` ending before row `ends[i]`
+    RunLength {
+        values: Box<ColType>,
+        ends: Vec<usize>,
+    },
+    // Non-decreasing integers or timestamps as their gap to the previous row, with the value at
+    // the start of every block kept whole
+    Delta {
+        dtype: DType,
+        anchors: Vec<i64>,
+        gaps: Vec<u32>,
+    },
+}
+
+fn run_starts(col: &ColType) -> Vec<usize> {
+    let mut starts = Vec::new();
+    let mut previous = None;
+    for row in 0..col.len() {
+        // A null doesn't continue a run of the text "null"
+        let value = col
+            .data_as_value(row)
+            .ok()
+            .map(|v| (matches!(v, DataValue::Null), v.to_string()));
+        if row == 0 || value != previous {
+            starts.push(row);
+        }
+        previous = value;
+    }
+    starts
+}
+
+// Integers, or timestamps that survive the round trip through whole seconds. Gaps have no
+// room for nulls
+fn as_i64s(col: &ColType) -> Option<Vec<i64>> {
+    if col.validity().is_some() {
+        return None;
+    }
+    match col {
+        ColType::Integer(col) => Some(col.values.clone()),
+        ColType::Datetime(col) => col
+            .values
+            .iter()
+            .map(|dt| {
+                let seconds = dt.timestamp();
+                (Datetime::from_timestamp(seconds) == *dt).then_some(seconds)
+            })
+            .collect(),
+        _ => None,
+    }
+}
+
+fn delta_encode(values: &[i64]) -> Option<(Vec<i64>, Vec<u32>)> {
+    let mut gaps = Vec::with_capacity(values.len());
+    let mut previous = *values.first()?;
+    for &value in values {
+        gaps.push(u32::try_from(value.checked_sub(previous)?).ok()?);
+        previous = value;
+    }
+    let anchors = values.iter().step_by(BLOCK).copied().collect();
+    Some((anchors, gaps))
+}
+
+impl CompressedCol {
+    /// Run-length encoding when it at least halves the values held, otherwise delta encoding
+    /// for sorted integer and datetime columns. `None` when neither applies
+    pub(crate) fn encode(col: &ColType) -> Option<Self> {
+        if !matches!(
+            col,
+            ColType::Integer(_) | ColType::String(_) | ColType::Datetime(_)
+        ) {
+            return None;
+        }
+        let len = col.len();
+        if len == 0 {
+            return None;
+        }
+        let starts = run_starts(col);
+        let encoding = if starts.len() * 2 <= len {
+            let mut ends: Vec<usize> = starts[1..].to_vec();
+            ends.push(len);
+            Encoding::RunLength {
+                values: Box::new(col.take(&starts)),
+                ends,
+            }
+        } else {
+            let (anchors, gaps) = delta_encode(&as_i64s(col)?)?;
+            Encoding::Delta {
+                dtype: col.dtype(),
+                anchors,
+                gaps,
+            }
+        };
+        Some(Self {
+            col_name: col.name().to_string(),
+            len,
+            encoding,
+        })
+    }
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+    pub(crate) fn dtype(&self) -> DType {
+        match &self.encoding {
+            Encoding::RunLength { values, .. } => values.dtype(),
+            Encoding::Delta { dtype, .. } => *dtype,
+        }
+    }
+    pub(crate) fn get(&self, index: usize) -> Option<DataValue> {
+        if index >= self.len {
+            return None;
+        }
+        match &self.encoding {
+            Encoding::RunLength { values, ends } => {
+                let run = ends.partition_point(|&end| end <= index);
+                values.data_as_value(run).ok()
+            }
+            Encoding::Delta {
+                dtype,
+                anchors,
+                gaps,
+            } => {
+                let block = index / BLOCK;
+                let value = gaps[block * BLOCK + 1..=index]
+                    .iter()
+                    .fold(anchors[block], |value, &gap| value + i64::from(gap));
+                Some(match dtype {
+                    DType::Datetime => DataValue::DateTime(Datetime::from_timestamp(value)),
+                    _ => DataValue::Integer(value),
+                })
+            }
+        }
+    }
+    /// The plain column the encoding stands for
+    pub(crate) fn decode(&self) -> ColType {
+        match &self.encoding {
+            Encoding::RunLength { values, ends } => {
+                let mut runs = Vec::with_capacity(self.len);
+                let mut start = 0;
+                for (run, &end) in ends.iter().enumerate() {
+                    runs.extend(std::iter::repeat_n(run, end - start));
+                    start = end;
+                }
+                values.take(&runs)
+            }
+            Encoding::Delta {
+                dtype,
+                anchors,
+                gaps,
+            } => {
+                // The first gap is zero
+                let mut value = anchors[0];
+                let values = gaps.iter().map(|&gap| {
+                    value += i64::from(gap);
+                    value
+                });
+                let name = self.col_name.clone();
+                match dtype {
+                    DType::Datetime => ColType::Datetime(CsvCol::new(
+                        name,
+                        values.map(Datetime::from_timestamp).collect(),
+                    )),
+                    _ => ColType::Integer(CsvCol::new(name, values.collect())),
+                }
+            }
+        }
+    }
+}
+
+impl ColType {
+    // Runs `f` on the plain column, decoding it first if it's compressed or copying its rows
+    // if it's a view. For code that reads the typed storage directly
+    pub(crate) fn with_plain<R>(&self, f: impl FnOnce(&ColType) -> R) -> R {
+        match self {
+            ColType::Compressed(col) => f(&col.decode()),
+            ColType::View(col) => f(&col.materialize()),
+            col => f(col),
+        }
+    }
+}
+
+impl ColViewer<'_> {
+    pub fn is_compressed(&self) -> bool {
+        matches!(self.inner, ColType::Compressed(_))
+    }
+}
+
+impl Csv {
+    /// Encodes every column that gets smaller for it: run-length encoding for repetitive
+    /// columns, delta encoding for sorted integer and datetime ones. Returns how many columns were
+    pub fn compress(&mut self) -> usize {
+        let mut compressed = 0;
+        for col in self.cols.iter_mut() {
+            if matches!(**col, ColType::Compressed(_)) {
+                continue;
+            }
+            if let Some(encoded) = CompressedCol::encode(col) {
+                *col = Arc::new(ColType::Compressed(encoded));
+                compressed += 1;
+            }
+        }
+        compressed
+    }
+    /// Turns compressed columns back into plain ones, for the fastest reads
+    pub fn decompress(&mut self) {
+        for col in self.cols.iter_mut() {
+            if let ColType::Compressed(encoded) = &**col {
+                *col = Arc::new(encoded.decode());
+            }
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use miette::{miette, Error};
+
+use crate::{
+    col_parser::{ColType, DataValue},
+    csv_parser::{ColViewer, Csv, Statistics},
+    error::CoalaError,
+    promote::{convert, promote_with, StringPromotion},
+};
+
+/// What `union_by_name` does with a column some of the frames lack
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MissingColumns {
+    /// The frames without it hold nulls in it
+    #[default]
+    FillNull,
+    Error,
+}
+
+fn extreme(a: &Option<DataValue>, b: &Option<DataValue>, keep: Ordering) -> Option<DataValue> {
+    let (a, b) = (a.as_ref()?, b.as_ref()?);
+    let ordering = a.compare(b)?;
+    Some(if ordering == keep { a } else { b }.clone())
+}
+
+// Sum of squared deviations from the mean, recovered from a cached sample standard deviation
+fn m2(stddev: &Option<DataValue>, rows: f64) -> Option<f64> {
+    match stddev.as_ref()? {
+        DataValue::Null => Some(0.0),
+        stddev => Some(stddev.as_f64()?.powi(2) * (rows - 1.0)),
+    }
+}
+
+// The parts of a column one after the other, a missing part standing for as many nulls as its
+// frame has rows. A part of nulls only has no type of its own yet, the column takes the type
+// `promote_with` gives the others
+fn stack(
+    name: &str,
+    parts: &[(Option<&ColType>, usize)],
+    strings: StringPromotion,
+) -> Result<ColType, Error> {
+    let mut dtype = None;
+    for col in parts.iter().filter_map(|(col, _)| *col) {
+        if col.null_count() == col.len() {
+            continue;
+        }
+        dtype = Some(match dtype {
+            Some(dtype) => promote_with(dtype, col.dtype(), strings)?,
+            None => col.dtype(),
+        });
+    }
+    let Some(dtype) = dtype.or_else(|| parts.iter().find_map(|(col, _)| col.map(ColType::dtype)))
+    else {
+        return Err(CoalaError::MissingColumn {
+            name: name.to_string(),
+        }
+        .into());
+    };
+    let mut values = Vec::with_capacity(parts.iter().map(|(_, rows)| rows).sum());
+    for &(col, rows) in parts {
+        let Some(col) = col else {
+            values.extend(std::iter::repeat_n(DataValue::Null, rows));
+            continue;
+        };
+        for value in ColViewer::new(col).iter() {
+            let found = value.to_string();
+            values.push(convert(value, dtype).ok_or_else(|| {
+                miette!(
+                    "Value `{found}` of column `{name}` can't become {}",
+                    dtype.name()
+                )
+            })?);
+        }
+    }
+    ColType::from_data_values(values, name.to_string())
+}
+
+impl Statistics {
+    // Statistics of two sets of rows put together, from the cached statistics of each. The
+    // median can't be combined and is left to be recomputed. A set without rows adds nothing,
+    // and when neither has any nothing is cached
+    fn combine(&self, rows: usize, other: &Statistics, other_rows: usize) -> Statistics {
+        match (rows, other_rows) {
+            (0, 0) => return Statistics::default(),
+            (0, _) => return other.clone(),
+            (_, 0) => return self.clone(),
+            _ => {}
+        }
+        let (n_a, n_b) = (rows as f64, other_rows as f64);
+        let n = n_a + n_b;
+        let means = self
+            .mean
+            .as_ref()
+            .and_then(DataValue::as_f64)
+            .zip(other.mean.as_ref().and_then(DataValue::as_f64));
+        let stddev = means
+            .zip(m2(&self.stddev, n_a).zip(m2(&other.stddev, n_b)))
+            .map(|((a, b), (m2_a, m2_b))| {
+                // Chan et al.'s pairwise update of Welford's M2
+                let delta = b - a;
+                let m2 = m2_a + m2_b + delta * delta * n_a * n_b / n;
+                if n > 1.0 {
+                    DataValue::Float((m2 / (n - 1.0)).sqrt())
+                } else {
+                    DataValue::Null
+                }
+            });
+        Statistics {
+            mean: means.map(|(a, b)| DataValue::Float((a * n_a + b * n_b) / n)),
+            median: None,
+            stddev,
+            min: extreme(&self.min, &other.min, Ordering::Less),
+            max: extreme(&self.max, &other.max, Ordering::Greater),
+        }
+    }
+}
+
+impl Csv {
+    // Index in `other` of each column of `self`, both frames needing the same columns
+    pub(crate) fn matching_cols(&self, other: &Csv) -> Result<Vec<usize>, Error> {
+        if let Some(name) = other.header.iter().find(|h| !self.header.contains(h)) {
+            return Err(CoalaError::MissingColumn { name: name.clone() }.into());
+        }
+        self.header
+            .iter()
+            .map(|name| other.col_index(name))
+            .collect()
+    }
+    // Cached statistics of the rows of both frames, for the columns both have them for and
+    // whose type the rows agree on
+    pub(crate) fn combined_cache(&self, other: &Csv) -> HashMap<String, Statistics> {
+        self.cache
+            .iter()
+            .filter_map(|(name, stats)| {
+                let other_stats = other.cache.get(name)?;
+                let (i, j) = (self.col_index(name).ok()?, other.col_index(name).ok()?);
+                if self.cols[i].dtype() != other.cols[j].dtype() {
+                    return None;
+                }
+                // Nulls take no part in the statistics
+                let rows = self.n_rows - self.cols[i].null_count();
+                let other_rows = other.n_rows - other.cols[j].null_count();
+                let combined = stats.combine(rows, other_stats, other_rows);
+                Some((name.clone(), combined))
+            })
+            .collect()
+    }
+    /// The rows of `self` followed by those of `other`, matching columns by name. Columns of
+    /// different types take the type `promote` gives them. Statistics already computed on
+    /// both frames are combined rather than recomputed
+    pub fn concat(&self, other: &Csv) -> Result<Csv, Error> {
+        self.concat_with(other, StringPromotion::Error)
+    }
+    /// `concat` with the policy for stacking strings on columns of other types
+    pub fn concat_with(&self, other: &Csv, strings: StringPromotion) -> Result<Csv, Error> {
+        let other_cols = self.matching_cols(other)?;
+        let cols = self
+            .cols
+            .iter()
+            .zip(other_cols)
+            .map(|(col, j)| {
+                let parts = [
+                    (Some(&**col), self.n_rows),
+                    (Some(&*other.cols[j]), other.n_rows),
+                ];
+                stack(col.name(), &parts, strings)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let mut csv = Csv::from_cols(cols, self.header.clone(), self.n_rows + other.n_rows);
+        csv.row_ids = Csv::stacked_row_ids([self, other]);
+        csv.units = self.units.clone();
+        csv.meta = self.meta.clone();
+        csv.cache = self.combined_cache(other);
+        csv.lineage = self.lineage.clone();
+        csv.lineage.record("concat".to_string());
+        csv.column_order = self.column_order.clone();
+        Ok(csv)
+    }
+    /// The rows of every frame in turn, matching columns by name, e.g. to merge exports whose
+    /// columns changed over time. Columns come in order of first appearance and take the
+    /// type `promote` gives them. Metadata of earlier frames wins
+    pub fn union_by_name(frames: &[Csv], missing: MissingColumns) -> Result<Csv, Error> {
+        let Some(first) = frames.first() else {
+            return Err(miette!("A union needs at least one frame"));
+        };
+        let mut header: Vec<String> = Vec::new();
+        for name in frames.iter().flat_map(|frame| &frame.header) {
+            if !header.contains(name) {
+                header.push(name.clone());
+            }
+        }
+        let cols = header
+            .iter()
+            .map(|name| {
+                let parts = frames
+                    .iter()
+                    .map(|frame| {
+                        let col = frame.col_index(name).ok().map(|i| &*frame.cols[i]);
+                        if col.is_none() && missing == MissingColumns::Error {
+                            return Err(CoalaError::MissingColumn { name: name.clone() }.into());
+                        }
+                        Ok((col, frame.n_rows))
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                stack(name, &parts, StringPromotion::Error)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let n_rows = frames.iter().map(|frame| frame.n_rows).sum();
+        let mut csv = Csv::from_cols(cols, header, n_rows);
+        csv.row_ids = Csv::stacked_row_ids(frames);
+        for frame in frames.iter().rev() {
+            csv.meta.extend(frame.meta.clone());
+        }
+        csv.lineage = first.lineage.clone();
+        csv.lineage
+            .record(format!("union_by_name({} frames)", frames.len()));
+        csv.column_order = first.column_order.clone();
+        csv.apply_column_order();
+        Ok(csv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MissingColumns;
+    use crate::col_parser::{DType, DataValue};
+    use crate::csv_parser::{Csv, Statistics};
+
+    fn floats(values: &[Option<f64>]) -> Csv {
+        let records = values
+            .iter()
+            .map(|value| vec![value.map_or(DataValue::Null, DataValue::Float)]);
+        Csv::from_records(records, &[("x", DType::Float)]).unwrap()
+    }
+
+    // Fills the cache the way calls to the statistics do, some of them failing on no values
+    fn cached(mut csv: Csv) -> Csv {
+        let _ = csv.mean("x");
+        let _ = csv.stddev("x");
+        let _ = csv.min("x");
+        let _ = csv.max("x");
+        csv
+    }
+
+    fn as_f64(value: DataValue) -> f64 {
+        value.as_f64().unwrap()
+    }
+
+    #[test]
+    fn combined_cache_matches_recomputed() {
+        let left = cached(floats(&[Some(1.0), Some(2.0), None]));
+        let right = cached(floats(&[Some(4.0), Some(8.0)]));
+        let mut stacked = left.concat(&right).unwrap();
+        let mut fresh = floats(&[Some(1.0), Some(2.0), None, Some(4.0), Some(8.0)]);
+        assert_eq!(stacked.mean("x").unwrap(), fresh.mean("x").unwrap());
+        let (combined, recomputed) = (stacked.stddev("x").unwrap(), fresh.stddev("x").unwrap());
+        assert!((as_f64(combined) - as_f64(recomputed)).abs() < 1e-12);
+        assert_eq!(as_f64(stacked.min("x").unwrap()), 1.0);
+        assert_eq!(as_f64(stacked.max("x").unwrap()), 8.0);
+    }
+
+    #[test]
+    fn all_null_side_adds_nothing() {
+        let left = cached(floats(&[None, None]));
+        let right = cached(floats(&[Some(3.0), Some(5.0)]));
+        let mut stacked = left.concat(&right).unwrap();
+        assert_eq!(as_f64(stacked.mean("x").unwrap()), 4.0);
+        assert_eq!(as_f64(stacked.min("x").unwrap()), 3.0);
+    }
+
+    #[test]
+    fn combining_without_rows_gives_no_nan() {
+        let stats = Statistics {
+            mean: Some(DataValue::Float(2.0)),
+            stddev: Some(DataValue::Null),
+            min: Some(DataValue::Float(2.0)),
+            max: Some(DataValue::Float(2.0)),
+            median: None,
+        };
+        let empty = stats.combine(0, &stats, 0);
+        assert!(empty.mean.is_none() && empty.stddev.is_none() && empty.min.is_none());
+        let one_side = stats.combine(0, &stats, 1);
+        assert_eq!(one_side.mean.and_then(|mean| mean.as_f64()), Some(2.0));
+        assert!(matches!(one_side.stddev, Some(DataValue::Null)));
+    }
+
+    #[test]
+    fn union_fills_missing_columns_with_nulls() {
+        let left = Csv::from_records([vec![DataValue::Integer(1)]], &[("a", DType::Integer)]);
+        let right = Csv::from_records(
+            [vec![DataValue::from("x"), DataValue::Float(2.5)]],
+            &[("b", DType::String), ("a", DType::Float)],
+        );
+        let frames = [left.unwrap(), right.unwrap()];
+        let union = Csv::union_by_name(&frames, MissingColumns::FillNull).unwrap();
+        assert_eq!(union.header, ["a", "b"]);
+        let a: Vec<DataValue> = union.get_col("a").unwrap().iter().collect();
+        assert_eq!(a, [DataValue::Float(1.0), DataValue::Float(2.5)]);
+        let b: Vec<DataValue> = union.get_col("b").unwrap().iter().collect();
+        assert!(matches!(b[0], DataValue::Null));
+        assert!(Csv::union_by_name(&frames, MissingColumns::Error).is_err());
+        assert!(Csv::union_by_name(&[], MissingColumns::FillNull).is_err());
+    }
+}
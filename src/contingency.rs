@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+
+use miette::{miette, Error};
+
+use crate::{col_parser::DataValue, csv_parser::Csv, special::chi_square_survival};
+
+/// Pearson's chi-square test of independence over the crosstab of two columns
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChiSquare {
+    pub statistic: f64,
+    pub dof: usize,
+    pub p_value: f64,
+    /// Rows counted, those with a null in either column are left out
+    pub n: usize,
+    /// Categories of each column, the crosstab's shape
+    pub shape: (usize, usize),
+}
+
+impl Csv {
+    /// Chi-square test of independence between the categories of `a` and of `b`, each distinct
+    /// value a category. A small p-value means the columns are unlikely to be independent
+    pub fn chi_square(&self, a: &str, b: &str) -> Result<ChiSquare, Error> {
+        let mut table: HashMap<(String, String), usize> = HashMap::new();
+        let mut rows: HashMap<String, usize> = HashMap::new();
+        let mut cols: HashMap<String, usize> = HashMap::new();
+        for (a, b) in self.zip_cols(a, b)? {
+            if matches!(a, DataValue::Null) || matches!(b, DataValue::Null) {
+                continue;
+            }
+            let (a, b) = (a.to_string(), b.to_string());
+            *rows.entry(a.clone()).or_default() += 1;
+            *cols.entry(b.clone()).or_default() += 1;
+            *table.entry((a, b)).or_default() += 1;
+        }
+        if rows.len() < 2 || cols.len() < 2 {
+            return Err(miette!(
+                "Chi-square needs at least two categories in each column"
+            ));
+        }
+        let n: usize = rows.values().sum();
+        let mut statistic = 0.0;
+        for (a, row_total) in &rows {
+            for (b, col_total) in &cols {
+                let expected = (row_total * col_total) as f64 / n as f64;
+                let observed = table.get(&(a.clone(), b.clone())).copied().unwrap_or(0) as f64;
+                statistic += (observed - expected).powi(2) / expected;
+            }
+        }
+        let dof = (rows.len() - 1) * (cols.len() - 1);
+        Ok(ChiSquare {
+            statistic,
+            dof,
+            p_value: chi_square_survival(statistic, dof as f64),
+            n,
+            shape: (rows.len(), cols.len()),
+        })
+    }
+    /// Cramér's V between `a` and `b`, the strength of their association from 0 for
+    /// independent columns to 1 when one determines the other
+    pub fn cramers_v(&self, a: &str, b: &str) -> Result<f64, Error> {
+        let test = self.chi_square(a, b)?;
+        let smaller_side = test.shape.0.min(test.shape.1);
+        Ok((test.statistic / (test.n as f64 * (smaller_side - 1) as f64)).sqrt())
+    }
+}
@@ -1,37 +1,87 @@
 use std::{
-    collections::HashMap,
-    fs::File,
-    io::{BufRead, BufReader},
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io::BufReader,
+    ops::Range,
+    sync::Arc,
 };
 
-use miette::{miette, Diagnostic, Error, IntoDiagnostic};
-use thiserror::Error;
+use miette::{miette, Error};
 
-use crate::col_parser::{ColConfig, ColType, DataValue};
+use crate::{
+    agg::{Accumulator, Agg},
+    col_parser::{ColConfig, ColType, DType, DataValue},
+    column_order::ColumnOrder,
+    display,
+    embedded::LineEnding,
+    epoch::EpochUnit,
+    error::CoalaError,
+    events::{ChangeEvent, Observers},
+    kind::{ColumnKind, SharedKind},
+    lineage::Lineage,
+    metrics::ScanMetrics,
+    nulls::DEFAULT_NULL_VALUES,
+    perf::Timer,
+    query_cache::QueryCache,
+    row_index::RowIndex,
+    runtime::Runtime,
+    sidecar::Sidecar,
+    sketch::HyperLogLog,
+    tokenizer::{self, ColumnBuilder, Dialect, Records},
+    trace::{span, SpanGuard},
+};
 
-#[derive(Debug)]
+/// Named columns of the same length, each behind an `Arc` that frames share copy-on-write.
+/// Edits like `set` or `push_row` build a new column and swap it into the edited frame's own
+/// `Arc`, so the frames sharing the old one keep seeing it unchanged. Cloning, `share`,
+/// `select`, `slice`, `filter` and `iter_chunks` share the column buffers, while `deep_copy`,
+/// `materialize` and operations reordering rows copy them
+#[derive(Debug, Clone)]
 pub struct Csv {
-    cols: Vec<ColType>,
-    n_cols: usize,
-    n_rows: usize,
-    header: Vec<String>,
-    cache: HashMap<String, Statistics>,
+    pub(crate) cols: Vec<Arc<ColType>>,
+    pub(crate) n_cols: usize,
+    pub(crate) n_rows: usize,
+    pub(crate) header: Vec<String>,
+    pub(crate) units: Option<Vec<String>>,
+    pub(crate) cache: HashMap<String, Statistics>,
+    pub(crate) queries: QueryCache,
+    pub(crate) meta: HashMap<String, BTreeMap<String, String>>,
+    pub(crate) lineage: Lineage,
+    pub(crate) metrics: ScanMetrics,
+    display_width: Option<usize>,
+    show_dtypes: bool,
+    show_row_index: bool,
+    null_placeholder: String,
+    missing_placeholder: String,
+    // Rows printed as they are whatever the display format of their column, like the counts
+    // of `describe`
+    pub(crate) unformatted_rows: Vec<usize>,
+    pub(crate) observers: Observers,
+    pub(crate) column_order: Option<ColumnOrder>,
+    // Number of each row where it came from, see `track_row_ids`
+    pub(crate) row_ids: Option<Arc<[usize]>>,
 }
 
-#[derive(Debug, Default)]
-struct Statistics {
-    mean: Option<DataValue>,
-    median: Option<DataValue>,
-    std_dev: Option<DataValue>,
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Statistics {
+    pub(crate) mean: Option<DataValue>,
+    pub(crate) median: Option<DataValue>,
+    pub(crate) stddev: Option<DataValue>,
+    pub(crate) min: Option<DataValue>,
+    pub(crate) max: Option<DataValue>,
 }
 
 pub struct ColViewer<'a> {
     pub(crate) inner: &'a ColType,
+    meta: Option<&'a BTreeMap<String, String>>,
 }
 
 impl<'a> ColViewer<'a> {
     pub(crate) fn new(col: &'a ColType) -> Self {
-        Self { inner: col }
+        Self {
+            inner: col,
+            meta: None,
+        }
     }
     pub(crate) fn get_ref(&self) -> &Self {
         self
@@ -39,55 +89,439 @@ impl<'a> ColViewer<'a> {
     pub fn name(&self) -> &str {
         self.inner.name()
     }
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn dtype(&self) -> DType {
+        self.inner.dtype()
+    }
+    pub fn is_numeric(&self) -> bool {
+        self.dtype().is_numeric()
+    }
+    pub fn iter(&self) -> ColIter<'a> {
+        ColIter {
+            inner: self.inner,
+            index: 0,
+        }
+    }
+    // Around the computation of one statistic, carrying the column and its length
+    fn statistic_span(&self, statistic: &'static str) -> SpanGuard {
+        span!(
+            "statistic",
+            statistic = statistic,
+            column = self.name(),
+            rows = self.len(),
+        )
+    }
+    pub fn meta(&self, key: &str) -> Option<&str> {
+        self.meta?.get(key).map(String::as_str)
+    }
     pub fn mean(&self) -> Result<DataValue, Error> {
+        let _span = self.statistic_span("mean");
         self.inner.mean()
     }
     pub fn get(&self, index: usize) -> Result<DataValue, Error> {
         self.inner.data_as_value(index)
     }
     pub fn quantile(&self, quantile: f64) -> Result<DataValue, Error> {
+        let _span = self.statistic_span("quantile");
         self.inner.quantile(quantile)
     }
+    /// `quantile` of each of `quantiles`, in order, from a single sort of the column
+    pub fn quantiles(&self, quantiles: &[f64]) -> Result<Vec<DataValue>, Error> {
+        let _span = self.statistic_span("quantiles");
+        self.inner.quantiles(quantiles)
+    }
     pub fn median(&self) -> Result<DataValue, Error> {
+        let _span = self.statistic_span("median");
         self.inner.median()
     }
-    pub fn mean_unchecked(&self) -> DataValue {
-        self.inner.mean().unwrap()
+    pub fn stddev(&self) -> Result<DataValue, Error> {
+        let _span = self.statistic_span("stddev");
+        self.inner.stddev()
+    }
+    pub fn median_absolute_deviation(&self) -> Result<DataValue, Error> {
+        let _span = self.statistic_span("median_absolute_deviation");
+        self.inner.median_absolute_deviation()
+    }
+    pub fn trimmed_std(&self, proportion: f64) -> Result<DataValue, Error> {
+        let _span = self.statistic_span("trimmed_std");
+        self.inner.trimmed_std(proportion)
+    }
+    pub fn sum(&self) -> Result<DataValue, Error> {
+        let _span = self.statistic_span("sum");
+        self.inner.sum()
     }
-    pub fn get_unchecked(&self, index: usize) -> DataValue {
-        self.inner.data_as_value(index).unwrap()
+    pub fn sum_checked(&self) -> Result<i64, Error> {
+        self.inner.with_plain(|col| col.integers()?.sum_checked())
     }
-    pub fn quantile_unchecked(&self, quantile: f64) -> DataValue {
-        self.inner.quantile(quantile).unwrap()
+    pub fn sum_saturating(&self) -> Result<i64, Error> {
+        self.inner
+            .with_plain(|col| Ok(col.integers()?.sum_saturating()))
+    }
+    pub fn sum_as_f64(&self) -> Result<f64, Error> {
+        self.inner
+            .with_plain(|col| Ok(col.integers()?.sum_as_f64()))
+    }
+    pub fn might_contain(&self, value: &DataValue) -> bool {
+        self.inner.might_contain(value)
+    }
+    pub fn approx_n_unique(&self) -> usize {
+        self.distinct_sketch().estimate().round() as usize
+    }
+    pub fn distinct_sketch(&self) -> HyperLogLog {
+        self.inner.distinct_sketch()
+    }
+    pub fn min(&self) -> Result<DataValue, Error> {
+        let _span = self.statistic_span("min");
+        self.inner.min()
+    }
+    pub fn max(&self) -> Result<DataValue, Error> {
+        let _span = self.statistic_span("max");
+        self.inner.max()
+    }
+}
+
+impl<'a> IntoIterator for &ColViewer<'a> {
+    type Item = DataValue;
+    type IntoIter = ColIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct ColIter<'a> {
+    inner: &'a ColType,
+    index: usize,
+}
+
+impl Iterator for ColIter<'_> {
+    type Item = DataValue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = self.inner.data_as_value(self.index).ok()?;
+        self.index += 1;
+        Some(value)
     }
-    pub fn median_unchecked(&self) -> DataValue {
-        self.inner.median().unwrap()
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.inner.len().saturating_sub(self.index);
+        (remaining, Some(remaining))
     }
 }
 
+impl ExactSizeIterator for ColIter<'_> {}
+
+#[derive(Clone)]
 pub struct CsvConfig<'a> {
     pub separator: char,
     pub header: Option<usize>,
-    pub parser_as_date: Option<HashMap<String, Option<&'a str>>>,
-}
-#[derive(Debug, Diagnostic, Error)]
-enum ColParserError {
-    #[error("Csv unexpectely ended")]
-    UnexpectedEOF,
-    #[error(
-        "Number of lines to print (`{}`) is greater than DataFrame len (`{}`)",
-        n,
-        len
-    )]
-    OutOfLines { n: usize, len: usize },
-    #[error("Column `{}` not found in Dataframe", name)]
-    MissingCol { name: String },
-    #[error(
-        "Column `{}` doesn't have a datatype where `{}` can be calculated",
-        name,
-        metric
-    )]
-    InvalidMetric { name: String, metric: String },
+    /// New names for columns by the name the file gives them, applied before anything else
+    /// so the rest of the config can use the new names
+    pub rename_map: HashMap<String, String>,
+    /// Columns parsed as dates, each with formats tried in order. An empty list guesses the format
+    pub parser_as_date: Option<HashMap<String, Vec<&'a str>>>,
+    /// Formats tried for every date column after its own ones
+    pub fallback_date_formats: Vec<&'a str>,
+    /// Integer columns holding Unix timestamps, read as dates
+    pub epoch_units: HashMap<String, EpochUnit>,
+    /// Reads integer columns as dates when every value is a plausible timestamp
+    pub detect_epochs: bool,
+    /// Threads tokenizing the file, above one the whole file is read into memory first
+    pub threads: usize,
+    /// Soft cap in bytes on the file held in memory while loading, see `Runtime`
+    pub memory_budget: Option<usize>,
+    /// Stops reading after this many records, types are inferred on those alone
+    pub n_rows: Option<usize>,
+    /// Data records skipped after the header. They're only scanned for where they end, none
+    /// of their fields is stored, or skipped at once with the file's `RowIndex`
+    pub row_offset: usize,
+    /// Only these columns are kept, the other fields are skipped while scanning
+    pub use_columns: Option<Vec<String>>,
+    pub build_filters: bool,
+    pub use_sidecar: bool,
+    /// Row holding units, counted from the first line after the header
+    pub units_row: Option<usize>,
+    pub escape_char: Option<char>,
+    /// Encloses fields holding separators, line breaks or itself, doubled inside them
+    pub quote_char: char,
+    /// Cells read as null, matched against the whole cell. Custom parsers and kinds given for
+    /// a column see these cells as written
+    pub null_values: Vec<String>,
+    pub custom_parsers: HashMap<String, CustomParser>,
+    pub kinds: Vec<SharedKind>,
+    pub column_kinds: HashMap<String, SharedKind>,
+    pub dtypes: HashMap<String, DType>,
+    /// Errors instead of reading as text a column given a type or mostly holding numbers
+    pub strict: bool,
+    /// Text columns keep their values in a pool shared by every frame, so a string repeated
+    /// across columns and files, like a country code, is stored once. Pays off when values
+    /// repeat a lot, see `intern::release_unused`
+    pub intern_strings: bool,
+    /// Numbers each row by its data row in the file, kept through filters and sorts, see
+    /// `Csv::track_row_ids`
+    pub row_ids: bool,
+    /// Columns whose values all share the same scale are read as `Decimal` instead of floats
+    #[cfg(feature = "decimal")]
+    pub infer_decimals: bool,
+}
+
+pub type CustomParser = Arc<dyn Fn(&str) -> DataValue + Send + Sync>;
+
+impl Default for CsvConfig<'_> {
+    fn default() -> Self {
+        Self {
+            separator: ',',
+            header: None,
+            rename_map: HashMap::new(),
+            parser_as_date: None,
+            fallback_date_formats: Vec::new(),
+            epoch_units: HashMap::new(),
+            detect_epochs: false,
+            threads: 1,
+            memory_budget: None,
+            n_rows: None,
+            row_offset: 0,
+            use_columns: None,
+            build_filters: false,
+            use_sidecar: false,
+            units_row: None,
+            escape_char: None,
+            quote_char: '"',
+            null_values: DEFAULT_NULL_VALUES.map(String::from).to_vec(),
+            custom_parsers: HashMap::new(),
+            kinds: Vec::new(),
+            column_kinds: HashMap::new(),
+            dtypes: HashMap::new(),
+            strict: false,
+            intern_strings: false,
+            row_ids: false,
+            #[cfg(feature = "decimal")]
+            infer_decimals: false,
+        }
+    }
+}
+
+impl CsvConfig<'_> {
+    pub fn custom_parser<F>(mut self, col: &str, parser: F) -> Self
+    where
+        F: Fn(&str) -> DataValue + Send + Sync + 'static,
+    {
+        self.custom_parsers
+            .insert(col.to_string(), Arc::new(parser));
+        self
+    }
+    pub fn register_kind<K: ColumnKind>(mut self, kind: K) -> Self {
+        self.kinds.push(Arc::new(kind));
+        self
+    }
+    pub fn column_kind<K: ColumnKind>(mut self, col: &str, kind: K) -> Self {
+        self.column_kinds.insert(col.to_string(), Arc::new(kind));
+        self
+    }
+    pub fn dtype(mut self, col: &str, dtype: DType) -> Self {
+        self.dtypes.insert(col.to_string(), dtype);
+        self
+    }
+    /// Replaces the cells read as null, an empty list reads every cell as a value
+    pub fn null_values(mut self, values: &[&str]) -> Self {
+        self.null_values = values.iter().map(|v| v.to_string()).collect();
+        self
+    }
+    /// Reads the column the file names `raw` as `name`
+    pub fn rename(mut self, raw: &str, name: &str) -> Self {
+        self.rename_map.insert(raw.to_string(), name.to_string());
+        self
+    }
+}
+
+// Gives the columns of a header as read from a file their names from `rename_map`
+pub(crate) fn rename_header(
+    rename_map: &HashMap<String, String>,
+    header: &mut [String],
+) -> Result<(), Error> {
+    if let Some(raw) = rename_map.keys().find(|raw| !header.contains(raw)) {
+        return Err(CoalaError::MissingColumn { name: raw.clone() }.into());
+    }
+    for name in header.iter_mut() {
+        if let Some(new) = rename_map.get(name) {
+            name.clone_from(new);
+        }
+    }
+    // Only the new names are checked, files may repeat a name of their own
+    match rename_map
+        .values()
+        .find(|new| header.iter().filter(|name| name == new).count() > 1)
+    {
+        Some(name) => Err(CoalaError::DuplicateColumn { name: name.clone() }.into()),
+        None => Ok(()),
+    }
+}
+
+// Separators tried when a file reads as a single column
+const SEPARATOR_CANDIDATES: [char; 4] = [',', ';', '\t', '|'];
+// Data lines looked at for other separators
+const SEPARATOR_SAMPLE: usize = 10;
+// Rows `page` renders at a time
+const PAGE_BLOCK_ROWS: usize = 1000;
+
+// Printed cells by column, and whether each one is a placeholder
+type TableCells = (Vec<Vec<String>>, Vec<Vec<bool>>);
+
+// Candidate separators every one of `lines` holds the same number of times, most frequent
+// first. A file of one column holding none of them is left alone
+fn separator_candidates(separator: char, lines: &[&str]) -> Vec<(char, usize)> {
+    let mut candidates: Vec<(char, usize)> = SEPARATOR_CANDIDATES
+        .into_iter()
+        .filter(|&c| c != separator)
+        .filter_map(|c| {
+            let counts: Vec<usize> = lines.iter().map(|line| line.matches(c).count()).collect();
+            let first = *counts.first()?;
+            (first > 0 && counts.iter().all(|&count| count == first)).then_some((c, first))
+        })
+        .collect();
+    candidates.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    candidates
+}
+
+/// Fluent front end over `CsvConfig`, started with `Csv::reader()`
+#[derive(Default)]
+pub struct CsvBuilder<'a> {
+    pub(crate) config: CsvConfig<'a>,
+}
+
+impl<'a> CsvBuilder<'a> {
+    pub fn separator(mut self, separator: char) -> Self {
+        self.config.separator = separator;
+        self
+    }
+    pub fn escape_char(mut self, escape_char: char) -> Self {
+        self.config.escape_char = Some(escape_char);
+        self
+    }
+    pub fn quote_char(mut self, quote_char: char) -> Self {
+        self.config.quote_char = quote_char;
+        self
+    }
+    /// Number of lines skipped before the header
+    pub fn skip_rows(mut self, n: usize) -> Self {
+        self.config.header = Some(n);
+        self
+    }
+    pub fn units_row(mut self, row: usize) -> Self {
+        self.config.units_row = Some(row);
+        self
+    }
+    /// Parses the columns as dates, guessing their format
+    pub fn dates(mut self, cols: &[&str]) -> Self {
+        let dates = self.config.parser_as_date.get_or_insert_default();
+        for col in cols {
+            dates.entry(col.to_string()).or_default();
+        }
+        self
+    }
+    /// Adds a candidate format for the column, tried after the ones already given
+    pub fn date_format(mut self, col: &str, format: &'a str) -> Self {
+        let dates = self.config.parser_as_date.get_or_insert_default();
+        dates.entry(col.to_string()).or_default().push(format);
+        self
+    }
+    pub fn fallback_date_formats(mut self, formats: &[&'a str]) -> Self {
+        self.config.fallback_date_formats.extend_from_slice(formats);
+        self
+    }
+    pub fn epoch(mut self, col: &str, unit: EpochUnit) -> Self {
+        self.config.epoch_units.insert(col.to_string(), unit);
+        self
+    }
+    pub fn detect_epochs(mut self, detect_epochs: bool) -> Self {
+        self.config.detect_epochs = detect_epochs;
+        self
+    }
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.config.threads = threads;
+        self
+    }
+    /// Loads with the threads of `runtime`. The multi-threaded loader holds the whole file in
+    /// memory, so it's skipped for files over the memory budget
+    pub fn runtime(mut self, runtime: &Runtime) -> Self {
+        self.config.threads = runtime.threads;
+        self.config.memory_budget = runtime.memory_budget;
+        self
+    }
+    pub fn n_rows(mut self, n_rows: usize) -> Self {
+        self.config.n_rows = Some(n_rows);
+        self
+    }
+    pub fn row_offset(mut self, row_offset: usize) -> Self {
+        self.config.row_offset = row_offset;
+        self
+    }
+    pub fn columns(mut self, names: &[&str]) -> Self {
+        self.config.use_columns = Some(names.iter().map(|name| name.to_string()).collect());
+        self
+    }
+    pub fn dtype(mut self, col: &str, dtype: DType) -> Self {
+        self.config = self.config.dtype(col, dtype);
+        self
+    }
+    pub fn rename(mut self, raw: &str, name: &str) -> Self {
+        self.config = self.config.rename(raw, name);
+        self
+    }
+    pub fn null_values(mut self, values: &[&str]) -> Self {
+        self.config = self.config.null_values(values);
+        self
+    }
+    pub fn custom_parser<F>(mut self, col: &str, parser: F) -> Self
+    where
+        F: Fn(&str) -> DataValue + Send + Sync + 'static,
+    {
+        self.config = self.config.custom_parser(col, parser);
+        self
+    }
+    pub fn register_kind<K: ColumnKind>(mut self, kind: K) -> Self {
+        self.config = self.config.register_kind(kind);
+        self
+    }
+    pub fn column_kind<K: ColumnKind>(mut self, col: &str, kind: K) -> Self {
+        self.config = self.config.column_kind(col, kind);
+        self
+    }
+    pub fn build_filters(mut self, build_filters: bool) -> Self {
+        self.config.build_filters = build_filters;
+        self
+    }
+    pub fn use_sidecar(mut self, use_sidecar: bool) -> Self {
+        self.config.use_sidecar = use_sidecar;
+        self
+    }
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.config.strict = strict;
+        self
+    }
+    pub fn intern_strings(mut self, intern_strings: bool) -> Self {
+        self.config.intern_strings = intern_strings;
+        self
+    }
+    pub fn row_ids(mut self, row_ids: bool) -> Self {
+        self.config.row_ids = row_ids;
+        self
+    }
+    #[cfg(feature = "decimal")]
+    pub fn infer_decimals(mut self, infer_decimals: bool) -> Self {
+        self.config.infer_decimals = infer_decimals;
+        self
+    }
+    pub fn config(&self) -> &CsvConfig<'a> {
+        &self.config
+    }
+    pub fn finish(self, path: &str) -> Result<Csv, Error> {
+        Csv::new(path, self.config)
+    }
 }
 
 macro_rules! statistics {
@@ -108,123 +542,326 @@ macro_rules! statistics {
         )*)
     }
 impl Csv {
+    pub fn reader<'a>() -> CsvBuilder<'a> {
+        CsvBuilder::default()
+    }
+    /// Reads only the first `n` records, so peeking at a huge file doesn't load all of it
+    pub fn preview(path: &str, n: usize) -> Result<Self, Error> {
+        Self::reader().n_rows(n).finish(path)
+    }
+    /// Rows `rows` of the file, counting data rows from 0. The rows before them are scanned
+    /// but not stored, so a viewer can load page 500 of a huge file without holding the
+    /// pages before it. Types are inferred on the rows read
+    pub fn read_range(
+        path: &str,
+        rows: Range<usize>,
+        mut config: CsvConfig,
+    ) -> Result<Self, Error> {
+        config.row_offset = rows.start;
+        config.n_rows = Some(rows.len());
+        let mut csv = Self::new(path, config)?;
+        csv.lineage
+            .record(format!("rows({}..{})", rows.start, rows.start + csv.n_rows));
+        Ok(csv)
+    }
     pub fn new(path: &str, config: CsvConfig) -> Result<Self, Error> {
-        let csv = File::open(path).into_diagnostic()?;
-        let reader = BufReader::new(csv);
-        let mut lines = if let Some(header) = config.header {
-            let mut lines = reader.lines();
-            for _ in 0..header {
-                lines.next();
-            }
-            lines
-        } else {
-            reader.lines()
+        let _timer = Timer::start(|| format!("Loading `{path}`"));
+        let _span = span!("load", path = path);
+        let dialect = Dialect::from(&config);
+        let csv = File::open(path).map_err(CoalaError::from)?;
+        let mut records = Records::new(BufReader::new(csv), dialect);
+        for _ in 0..config.header.unwrap_or(0) {
+            records.next().transpose()?;
+        }
+        let mut header: Vec<String> = match records.next() {
+            Some(header) => header?.into_iter().map(String::from).collect(),
+            None => return Err(CoalaError::UnexpectedEof.into()),
         };
-        let header: Vec<String> = match lines.next() {
-            Some(header) => {
-                let header = header.into_diagnostic()?;
-                header
-                    .split(config.separator)
-                    .map(|s| s.to_string())
-                    .collect()
+        rename_header(&config.rename_map, &mut header)?;
+        let projection = match &config.use_columns {
+            Some(names) => {
+                if let Some(name) = names.iter().find(|name| !header.contains(name)) {
+                    return Err(CoalaError::MissingColumn { name: name.clone() }.into());
+                }
+                let keep: Vec<bool> = header.iter().map(|h| names.contains(h)).collect();
+                header.retain(|h| names.contains(h));
+                records.project(keep.clone());
+                Some(keep)
             }
-            None => return Err(ColParserError::UnexpectedEOF.into()),
+            None => None,
         };
+        if config.row_offset > 0 {
+            // The units row would be among the skipped records
+            if config.units_row.is_some() {
+                return Err(miette!("A row offset can't be combined with a units row"));
+            }
+            match RowIndex::read(path)?.filter(|index| index.matches(&config)) {
+                // Line numbers in errors count from the first row read then
+                Some(index) => records.seek(index.offset(config.row_offset), 0)?,
+                None => {
+                    records.skip_records(config.row_offset)?;
+                }
+            }
+        }
         let n_cols = header.len();
-        let values: Vec<Vec<_>> = lines
-            .map(|l| {
-                let l = l.into_diagnostic();
-                match l {
-                    Ok(l) => Ok(l
-                        .split(config.separator)
-                        .map(|l| l.to_string())
-                        .collect::<Vec<_>>()),
-                    Err(e) => Err(miette!(e)),
+        let mut metrics = ScanMetrics::default();
+        let mut builder = ColumnBuilder::new(n_cols, config.units_row);
+        let fits_budget = config
+            .memory_budget
+            .is_none_or(|budget| fs::metadata(path).is_ok_and(|meta| meta.len() <= budget as u64));
+        if config.threads > 1 && config.n_rows.is_none() && fits_budget {
+            // The header was read on its own, the threads only see the data after it
+            let text = fs::read_to_string(path).map_err(CoalaError::from)?;
+            let data = text.get(records.bytes_read()..).unwrap_or_default();
+            let parsed =
+                tokenizer::parse_parallel(data, dialect, config.threads, projection.as_deref())?;
+            for record in parsed.chunks.into_iter().flatten() {
+                builder.push(record)?;
+            }
+            metrics.bytes_read = text.len();
+            metrics.bytes_skipped = parsed.skipped_bytes;
+            metrics.line_ending = LineEnding::merge(records.line_ending(), parsed.line_ending);
+        } else {
+            // The units row isn't data, so it doesn't count towards the limit
+            let limit = config.n_rows.map_or(usize::MAX, |n| {
+                n.saturating_add(config.units_row.map_or(0, |_| 1))
+            });
+            for record in records.by_ref().take(limit) {
+                builder.push(record?)?;
+            }
+            metrics.bytes_read = records.bytes_read();
+            metrics.bytes_skipped = records.skipped_bytes();
+            metrics.line_ending = records.line_ending();
+        }
+        metrics.records = builder.records();
+        let n_rows = builder.n_rows();
+        let (columns, units) = builder.finish()?;
+        if let ([name], [values], None) = (&header[..], &columns[..], &config.use_columns) {
+            let lines: Vec<&str> = std::iter::once(name.as_str())
+                .chain(values.iter().take(SEPARATOR_SAMPLE).map(|v| v.as_ref()))
+                .collect();
+            let candidates = separator_candidates(config.separator, &lines);
+            if !candidates.is_empty() {
+                let candidates: Vec<String> = candidates
+                    .iter()
+                    .map(|(c, count)| format!("`{}` ({count} per line)", c.escape_debug()))
+                    .collect();
+                return Err(CoalaError::WrongSeparator {
+                    separator: config.separator.escape_debug().to_string(),
+                    candidates: candidates.join(", "),
                 }
-            })
-            .collect::<Result<Vec<_>, _>>()?;
-        let mut row_iters = values.into_iter().map(Vec::into_iter).collect::<Vec<_>>();
-        let transposed: Vec<Vec<String>> = (0..n_cols)
-            .map(|_| {
-                row_iters
-                    .iter_mut()
-                    .map(|it| it.next().expect("Must exist from previous construction"))
-                    .collect()
-            })
-            .collect();
-        let n_rows = transposed[0].len();
+                .into());
+            }
+        }
+        let units = units.map(|units| units.into_iter().map(String::from).collect());
+        let build_filters = config.build_filters;
+        let intern_strings = config.intern_strings;
+        let custom_parsers = &config.custom_parsers;
+        let (kinds, column_kinds) = (&config.kinds, &config.column_kinds);
+        let null_values = &config.null_values;
+        let sidecar = if config.use_sidecar {
+            Sidecar::read(path)?.filter(|sidecar| sidecar.matches(&header, n_rows))
+        } else {
+            None
+        };
         let mut cols: Vec<ColType> = Vec::with_capacity(n_cols);
-        for (i, col_data) in transposed.into_iter().enumerate() {
+        for (i, col_data) in columns.into_iter().enumerate() {
             let col_name = header
                 .get(i)
                 .cloned()
                 .unwrap_or_else(|| format!("Unnamed: {i}"));
-            let config = if let Some(ref cols_as_date) = config.parser_as_date {
-                if cols_as_date.contains_key(&col_name) {
-                    Some(ColConfig {
-                        as_date: true,
-                        date_format: if let Some(&date_format) = cols_as_date.get(&col_name) {
-                            date_format
-                        } else {
-                            None
-                        },
-                    })
-                } else {
-                    None
-                }
+            let date_formats = config
+                .parser_as_date
+                .as_ref()
+                .and_then(|cols_as_date| cols_as_date.get(&col_name));
+            let dtype = config
+                .dtypes
+                .get(&col_name)
+                .copied()
+                .or_else(|| sidecar.as_ref().map(|sidecar| sidecar.columns[i].dtype));
+            let config = Some(ColConfig {
+                as_date: date_formats.is_some(),
+                date_formats: date_formats.map_or(&[], Vec::as_slice),
+                fallback_date_formats: &config.fallback_date_formats,
+                epoch_unit: config.epoch_units.get(&col_name).copied(),
+                detect_epochs: config.detect_epochs,
+                dtype,
+                strict: config.strict,
+                #[cfg(feature = "decimal")]
+                infer_decimals: config.infer_decimals,
+            });
+            let mut col = if let Some(parser) = custom_parsers.get(&col_name) {
+                ColType::from_data_values(
+                    col_data.iter().map(|raw| parser(raw)).collect(),
+                    col_name,
+                )?
+            } else if let Some(kind) = column_kinds.get(&col_name) {
+                let owned: Vec<String> = col_data.into_iter().map(String::from).collect();
+                ColType::Custom(kind.build(&owned, &col_name)?)
             } else {
-                None
+                ColType::from_values_with_nulls(&col_data, col_name, config, kinds, null_values)?
             };
-            cols.push(ColType::from_values(&col_data, col_name, config)?);
+            if build_filters {
+                col.build_filter();
+            }
+            if let (ColType::String(strings), true) = (&mut col, intern_strings) {
+                strings.intern();
+            }
+            cols.push(col);
         }
-        Ok(Self {
+        let mut csv = Self::from_cols(cols, header, n_rows);
+        csv.units = units;
+        if config.row_ids {
+            csv.row_ids = Some((config.row_offset..config.row_offset + n_rows).collect());
+        }
+        csv.lineage = Lineage::from_source(path);
+        csv.metrics = metrics;
+        if let Some(sidecar) = sidecar {
+            sidecar.populate_cache(&mut csv);
+        }
+        Ok(csv)
+    }
+    pub(crate) fn from_cols(cols: Vec<ColType>, header: Vec<String>, n_rows: usize) -> Self {
+        Self::from_shared(cols.into_iter().map(Arc::new).collect(), header, n_rows)
+    }
+    pub(crate) fn from_shared(cols: Vec<Arc<ColType>>, header: Vec<String>, n_rows: usize) -> Self {
+        Self {
+            n_cols: cols.len(),
             cols,
-            n_cols,
             n_rows,
             header,
+            units: None,
             cache: Default::default(),
+            queries: QueryCache::default(),
+            meta: HashMap::new(),
+            lineage: Lineage::default(),
+            metrics: ScanMetrics::default(),
+            display_width: None,
+            show_dtypes: true,
+            show_row_index: true,
+            null_placeholder: "null".to_string(),
+            missing_placeholder: "-".to_string(),
+            unformatted_rows: Vec::new(),
+            observers: Observers::default(),
+            column_order: None,
+            row_ids: None,
+        }
+    }
+    pub(crate) fn col_index(&self, name: &str) -> Result<usize, Error> {
+        self.header.iter().position(|h| h == name).ok_or_else(|| {
+            CoalaError::MissingColumn {
+                name: name.to_string(),
+            }
+            .into()
         })
     }
-    fn print_n_lines(&self, beg: usize, end: usize) -> Result<String, Error> {
-        if beg > self.n_rows {
-            return Err(miette!(
-                "Number of rows to print is greater than len of dataset"
-            ));
-        }
-        let cols = self
-            .cols
+    // Keeps units, metadata, lineage and row ids, the caller records its own operation
+    pub(crate) fn take_rows(&self, indices: &[usize]) -> Self {
+        let cols = self.cols.iter().map(|col| col.take(indices)).collect();
+        let mut csv = Self::from_cols(cols, self.header.clone(), indices.len());
+        csv.row_ids = self.row_ids_at(indices.iter().copied());
+        csv.units = self.units.clone();
+        csv.meta = self.meta.clone();
+        csv.lineage = self.lineage.clone();
+        csv
+    }
+    pub(crate) fn cells(&self) -> Result<Vec<Vec<String>>, Error> {
+        self.cols
             .iter()
-            .map(|col| col.print_range_lines(beg, end))
-            .collect::<Result<Vec<_>, _>>()?;
-        let (cols, mut max_widths): (Vec<_>, Vec<_>) = cols.into_iter().unzip();
-        dbg!(&cols);
-        let mut result = String::new();
-        for (i, header) in self.header.iter().enumerate() {
-            max_widths[i] = max_widths[i].max(self.header[i].len());
-            result.push_str(&format!("{:<width$}", header, width = max_widths[i]));
-            if i < self.n_cols - 1 {
-                result.push_str(", ");
+            .map(|col| col.print_range_lines(0, self.n_rows))
+            .collect()
+    }
+    fn print_n_lines(&self, beg: usize, end: usize) -> Result<String, Error> {
+        let (cols, faint) = self.table_cells(beg, end)?;
+        let dtypes = self.dtype_labels();
+        let rows = self.show_row_index.then_some(beg..end);
+        let width = self.display_width.unwrap_or_else(display::terminal_width);
+        Ok(display::render_table(
+            &self.header,
+            dtypes.as_deref(),
+            rows,
+            &cols,
+            display::styled().then_some(faint.as_slice()),
+            width,
+        ))
+    }
+    // Cells of rows `beg..end` as printed
+    fn table_cells(&self, beg: usize, end: usize) -> Result<TableCells, Error> {
+        if beg > end || end > self.n_rows {
+            return Err(CoalaError::OutOfLines {
+                n: end,
+                len: self.n_rows,
             }
+            .into());
         }
-        result.push('\n');
-        for row in cols.iter().take(beg - end) {
-            for col_idx in 0..self.n_cols {
-                let value = &row[col_idx];
-                result.push_str(&format!("{:<width$}", value, width = max_widths[col_idx]));
-                if col_idx < self.n_cols - 1 {
-                    result.push_str(", ");
+        let mut cols = Vec::with_capacity(self.cols.len());
+        let mut faint = Vec::with_capacity(self.cols.len());
+        for (col, name) in self.cols.iter().zip(&self.header) {
+            // Rows past the end of a shorter column get the missing placeholder
+            let held = end.min(col.len()).max(beg);
+            let mut cells = match self.display_format(name) {
+                Some(format) => (beg..held)
+                    .map(|row| {
+                        let value = col.data_as_value(row)?;
+                        Ok(if self.unformatted_rows.contains(&row) {
+                            value.to_string()
+                        } else {
+                            format.render(&value)
+                        })
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?,
+                None => col.print_range_lines(beg, held)?,
+            };
+            let mut placeholders = Vec::with_capacity(end - beg);
+            for (cell, row) in cells.iter_mut().zip(beg..held) {
+                let null = col.is_null_at(row);
+                if null {
+                    cell.clone_from(&self.null_placeholder);
                 }
+                placeholders.push(null);
             }
-            result.push('\n')
+            cells.resize(end - beg, self.missing_placeholder.clone());
+            placeholders.resize(end - beg, true);
+            cols.push(cells);
+            faint.push(placeholders);
         }
-        Ok(result)
+        Ok((cols, faint))
+    }
+    fn dtype_labels(&self) -> Option<Vec<String>> {
+        self.show_dtypes.then(|| {
+            self.cols
+                .iter()
+                .map(|col| display::dtype_label(col.dtype()))
+                .collect()
+        })
+    }
+    pub fn set_display_width(&mut self, width: Option<usize>) {
+        self.display_width = width;
+    }
+    /// Whether printed tables have a row of column types under the header, on by default
+    pub fn set_show_dtypes(&mut self, show: bool) {
+        self.show_dtypes = show;
+    }
+    /// Whether printed tables number the rows in a gutter on the left, on by default
+    pub fn set_show_row_index(&mut self, show: bool) {
+        self.show_row_index = show;
+    }
+    /// Text printed for a null in tables, `null` by default. Like the placeholder of missing
+    /// cells it's printed faint on a terminal, so it stands apart from the values
+    pub fn set_null_placeholder(&mut self, placeholder: &str) {
+        self.null_placeholder = placeholder.to_string();
+    }
+    /// Text printed for the cells past the end of a column shorter than the frame, `-` by
+    /// default
+    pub fn set_missing_placeholder(&mut self, placeholder: &str) {
+        self.missing_placeholder = placeholder.to_string();
     }
     pub fn head(&self) -> Result<(), Error> {
         self.head_n(5)
     }
     pub fn head_n(&self, n_lines: usize) -> Result<(), Error> {
         if self.n_rows < n_lines {
-            return Err(ColParserError::OutOfLines {
+            return Err(CoalaError::OutOfLines {
                 n: n_lines,
                 len: self.n_rows,
             }
@@ -234,21 +871,202 @@ impl Csv {
         println!("{result}");
         Ok(())
     }
+    pub fn units(&self) -> Option<&[String]> {
+        self.units.as_deref()
+    }
+    pub fn unit(&self, name: &str) -> Option<&str> {
+        let from_row = || {
+            let i = self.header.iter().position(|h| h == name)?;
+            self.units.as_ref()?.get(i).map(String::as_str)
+        };
+        from_row().or_else(|| self.column_meta(name)?.get("unit").map(String::as_str))
+    }
+    pub fn set_meta(&mut self, col: &str, key: &str, value: &str) -> Result<(), Error> {
+        self.get_col(col)?;
+        self.meta
+            .entry(col.to_string())
+            .or_default()
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+    pub fn column_meta(&self, col: &str) -> Option<&BTreeMap<String, String>> {
+        self.meta.get(col)
+    }
+    /// Shows every row in `$PAGER`, rendering blocks of rows only as the pager reads them.
+    /// Column widths come from the first block, wider cells further down are elided
+    pub fn page(&self) -> Result<(), Error> {
+        let first = self.n_rows.min(PAGE_BLOCK_ROWS);
+        let (cols, faint) = self.table_cells(0, first)?;
+        let dtypes = self.dtype_labels();
+        let numbered = |rows: Range<usize>| self.show_row_index.then_some(rows);
+        let layout = display::TableLayout::new(
+            &self.header,
+            dtypes.as_deref(),
+            numbered(0..self.n_rows).as_ref(),
+            &cols,
+            self.display_width.unwrap_or_else(display::terminal_width),
+        );
+        let styled = display::styled();
+        let header = layout.render_header(&self.header, dtypes.as_deref());
+        let first_rows = layout.render_rows(
+            numbered(0..first),
+            &cols,
+            styled.then_some(faint.as_slice()),
+        );
+        let rest = (first..self.n_rows).step_by(PAGE_BLOCK_ROWS).map(|beg| {
+            let end = (beg + PAGE_BLOCK_ROWS).min(self.n_rows);
+            let (cols, faint) = self.table_cells(beg, end)?;
+            Ok(layout.render_rows(
+                numbered(beg..end),
+                &cols,
+                styled.then_some(faint.as_slice()),
+            ))
+        });
+        display::page([Ok(header), Ok(first_rows)].into_iter().chain(rest))
+    }
     pub fn get_col(&self, name: &str) -> Result<ColViewer<'_>, Error> {
         self.cols
             .iter()
-            .find(|c| c.name() == name)
-            .map(ColViewer::new)
+            .find_map(|c| c.find(name))
+            .map(|col| ColViewer {
+                meta: self.meta.get(name),
+                ..ColViewer::new(col)
+            })
             .ok_or(
-                ColParserError::MissingCol {
+                CoalaError::MissingColumn {
                     name: name.to_string(),
                 }
                 .into(),
             )
     }
+    pub fn zip_cols(
+        &self,
+        left: &str,
+        right: &str,
+    ) -> Result<impl Iterator<Item = (DataValue, DataValue)> + '_, Error> {
+        let (left, right) = (self.get_col(left)?, self.get_col(right)?);
+        let (left_len, right_len) = (left.iter().len(), right.iter().len());
+        if left_len != right_len {
+            return Err(CoalaError::LengthMismatch {
+                left: left.name().to_string(),
+                left_len,
+                right: right.name().to_string(),
+                right_len,
+            }
+            .into());
+        }
+        Ok(left.iter().zip(right.iter()))
+    }
+    /// How each value of `left` orders against the one of `right` on its row: -1 when it's
+    /// less, 0 when equal and 1 when greater, null when either is null or they don't compare.
+    /// E.g. expected against actual values in a reconciliation report
+    pub fn compare_cols(&self, left: &str, right: &str) -> Result<Vec<DataValue>, Error> {
+        Ok(self
+            .zip_cols(left, right)?
+            .map(|(a, b)| match (&a, &b) {
+                (DataValue::Null, _) | (_, DataValue::Null) => DataValue::Null,
+                _ => a.compare(&b).map_or(DataValue::Null, |ordering| {
+                    DataValue::Integer(ordering as i64)
+                }),
+            })
+            .collect())
+    }
+    pub fn add_col(&mut self, name: &str, values: Vec<DataValue>) -> Result<(), Error> {
+        let col = ColType::from_data_values(values, name.to_string())?;
+        self.push_col(col, &format!("add_col({name})"))
+    }
+    // Appends a built column, recording `operation` in the lineage
+    pub(crate) fn push_col(&mut self, col: ColType, operation: &str) -> Result<(), Error> {
+        let name = col.name().to_string();
+        if self.header.contains(&name) {
+            return Err(CoalaError::DuplicateColumn { name }.into());
+        }
+        if col.len() != self.n_rows {
+            return Err(miette!(
+                "Column `{name}` has {} values, the Dataframe has {} rows",
+                col.len(),
+                self.n_rows
+            ));
+        }
+        self.cols.push(Arc::new(col));
+        self.header.push(name.clone());
+        self.n_cols += 1;
+        if let Some(units) = &mut self.units {
+            units.push(String::new());
+        }
+        self.lineage.record(operation.to_string());
+        self.apply_column_order();
+        self.notify(ChangeEvent::ColumnAdded { name });
+        Ok(())
+    }
+    /// Aggregates across `cols` within each row, yielding one value per row
+    pub fn row_stats(&self, cols: &[&str], agg: Agg) -> Result<Vec<DataValue>, Error> {
+        agg.validate()?;
+        let cols = cols
+            .iter()
+            .map(|name| self.get_col(name))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut accumulators = vec![Accumulator::new(agg); self.n_rows];
+        for col in &cols {
+            for (value, acc) in col.iter().zip(accumulators.iter_mut()) {
+                // Nulls are skipped by the accumulator like in column statistics
+                if agg.is_numeric() && !matches!(value, DataValue::Null) && value.as_f64().is_none()
+                {
+                    return Err(CoalaError::InvalidType {
+                        col: col.name().to_string(),
+                    }
+                    .into());
+                }
+                acc.push_value(&value);
+            }
+        }
+        Ok(accumulators.iter_mut().map(Accumulator::finish).collect())
+    }
 
-    statistics! {mean median}
+    statistics! {mean median min max stddev}
     pub fn quantile(&self, name: &str, quantile: f64) -> Result<DataValue, Error> {
         self.get_col(name)?.quantile(quantile)
     }
+    pub fn quantiles(&self, name: &str, quantiles: &[f64]) -> Result<Vec<DataValue>, Error> {
+        self.get_col(name)?.quantiles(quantiles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_leave_shared_columns_alone() {
+        let records = [vec![DataValue::Integer(1)], vec![DataValue::Integer(2)]];
+        let original = Csv::from_records(records, &[("a", DType::Integer)]).unwrap();
+        let mut edited = original.clone();
+        assert!(Arc::ptr_eq(&original.cols[0], &edited.cols[0]));
+        edited.set(0, "a", DataValue::Integer(10)).unwrap();
+        assert!(!Arc::ptr_eq(&original.cols[0], &edited.cols[0]));
+        let first = |csv: &Csv| csv.get_col("a").unwrap().iter().next();
+        assert_eq!(first(&original), Some(DataValue::Integer(1)));
+        assert_eq!(first(&edited), Some(DataValue::Integer(10)));
+    }
+
+    #[test]
+    fn row_stats_skip_nulls() {
+        let records = [
+            vec![DataValue::Integer(1), DataValue::Null],
+            vec![DataValue::Integer(3), DataValue::Integer(5)],
+        ];
+        let schema = [("a", DType::Integer), ("b", DType::Integer)];
+        let csv = Csv::from_records(records, &schema).unwrap();
+        let sums = csv.row_stats(&["a", "b"], Agg::Sum).unwrap();
+        assert_eq!(sums[0].as_f64(), Some(1.0));
+        assert_eq!(sums[1].as_f64(), Some(8.0));
+    }
+
+    #[test]
+    fn row_stats_reject_text() {
+        let records = [vec![DataValue::Integer(1), DataValue::from("x")]];
+        let schema = [("a", DType::Integer), ("b", DType::String)];
+        let csv = Csv::from_records(records, &schema).unwrap();
+        assert!(csv.row_stats(&["a", "b"], Agg::Mean).is_err());
+    }
 }
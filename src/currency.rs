@@ -0,0 +1,76 @@
+use std::collections::{BTreeSet, HashMap};
+
+use miette::Error;
+
+use crate::{col_parser::DataValue, csv_parser::Csv, error::CoalaError};
+
+impl Csv {
+    /// The values of `amount` converted to currency `to`, each from the currency named on its
+    /// row in `currency`. `rates` has a row per pair: columns `from`, `to` and `rate`, what
+    /// one unit of `from` is worth in `to`. A pair only listed the other way round is used
+    /// inverted, amounts already in `to` are kept. Rows with a null amount or currency give
+    /// null. Fails listing every currency with no rate to `to`
+    pub fn convert_currency(
+        &self,
+        amount: &str,
+        currency: &str,
+        rates: &Csv,
+        to: &str,
+    ) -> Result<Vec<DataValue>, Error> {
+        let mut factors: HashMap<String, f64> = HashMap::new();
+        let pairs = rates
+            .get_col("from")?
+            .iter()
+            .zip(rates.get_col("to")?.iter());
+        for ((from, target), rate) in pairs.zip(rates.get_col("rate")?.iter()) {
+            let Some(rate) = rate.as_f64().filter(|rate| *rate != 0.0) else {
+                continue;
+            };
+            let (from, target) = (from.to_string(), target.to_string());
+            if target == to {
+                factors.insert(from, rate);
+            } else if from == to {
+                factors.entry(target).or_insert(1.0 / rate);
+            }
+        }
+        factors.insert(to.to_string(), 1.0);
+        let amounts = self.get_col(amount)?;
+        if !amounts.is_numeric() {
+            return Err(CoalaError::InvalidType {
+                col: amount.to_string(),
+            }
+            .into());
+        }
+        let mut unmatched = BTreeSet::new();
+        let values = amounts
+            .iter()
+            .zip(self.get_col(currency)?.iter())
+            .map(|(amount, code)| {
+                let (Some(amount), false) = (amount.as_f64(), matches!(code, DataValue::Null))
+                else {
+                    return DataValue::Null;
+                };
+                let code = code.to_string();
+                match factors.get(&code) {
+                    Some(factor) => DataValue::Float(amount * factor),
+                    None => {
+                        unmatched.insert(code);
+                        DataValue::Null
+                    }
+                }
+            })
+            .collect();
+        if !unmatched.is_empty() {
+            let codes: Vec<String> = unmatched
+                .into_iter()
+                .map(|code| format!("`{code}`"))
+                .collect();
+            return Err(CoalaError::MissingRates {
+                to: to.to_string(),
+                codes: codes.join(", "),
+            }
+            .into());
+        }
+        Ok(values)
+    }
+}
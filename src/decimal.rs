@@ -0,0 +1,69 @@
+use miette::Error;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+
+use crate::{
+    col_parser::{CsvCol, DataValue},
+    error::CoalaError,
+    statistics::{mad_of, stddev_of, trimmed_std_of, Statistics},
+};
+
+impl CsvCol<Decimal> {
+    fn checked_sum(&self) -> Result<Decimal, Error> {
+        self.valid_values()
+            .iter()
+            .try_fold(Decimal::ZERO, |acc, &x| acc.checked_add(x))
+            .ok_or_else(|| {
+                CoalaError::Overflow {
+                    col: self.col_name.clone(),
+                }
+                .into()
+            })
+    }
+}
+
+impl Statistics for CsvCol<Decimal> {
+    fn sum(&self) -> Result<DataValue, Error> {
+        self.checked_sum().map(DataValue::Decimal)
+    }
+    fn mean(&self) -> Result<DataValue, Error> {
+        if self.n_valid() == 0 {
+            return Err(CoalaError::EmptyColumn.into());
+        }
+        let sum = self.checked_sum()?;
+        Ok(DataValue::Decimal(sum / Decimal::from(self.n_valid())))
+    }
+    fn median(&self) -> Result<DataValue, Error> {
+        self.quantile(0.5)
+    }
+    fn quantile(&self, quantile: f64) -> Result<DataValue, Error> {
+        if !(0.0..=1.0).contains(&quantile) {
+            return Err(CoalaError::InvalidQuantile { value: quantile }.into());
+        }
+        if self.n_valid() == 0 {
+            return Err(CoalaError::EmptyColumn.into());
+        }
+        let col = self.get_sorted();
+        // The position is kept exact so halfway points between two values don't pick up float noise
+        let position =
+            Decimal::try_from(quantile).unwrap_or_default() * Decimal::from(col.len() - 1);
+        let lower_idx = position.floor().to_usize().unwrap_or(0);
+        let fraction = position - position.floor();
+        let value = match col.get(lower_idx + 1) {
+            Some(upper) => col[lower_idx] + (upper - col[lower_idx]) * fraction,
+            None => col[lower_idx],
+        };
+        Ok(DataValue::Decimal(value.normalize()))
+    }
+    fn stddev(&self) -> Result<DataValue, Error> {
+        stddev_of(self.valid_values().iter().filter_map(|v| v.to_f64()))
+    }
+    fn median_absolute_deviation(&self) -> Result<DataValue, Error> {
+        mad_of(self.valid_values().iter().filter_map(|v| v.to_f64()))
+    }
+    fn trimmed_std(&self, proportion: f64) -> Result<DataValue, Error> {
+        trimmed_std_of(
+            self.valid_values().iter().filter_map(|v| v.to_f64()),
+            proportion,
+        )
+    }
+}
@@ -0,0 +1,93 @@
+use miette::Error;
+
+use crate::{
+    col_parser::{ColType, CsvCol, DataValue},
+    csv_parser::{ColViewer, Csv},
+    error::CoalaError,
+};
+
+// Centered moving average over one period, `None` near the ends and wherever the window
+// has a missing value. An even period takes half of each end value so the window stays centered
+fn centered_average(values: &[Option<f64>], period: usize) -> Vec<Option<f64>> {
+    let half = period / 2;
+    let even = period.is_multiple_of(2);
+    let weight = |offset: usize| {
+        if even && (offset == 0 || offset == period) {
+            0.5
+        } else {
+            1.0
+        }
+    };
+    let width = if period.is_multiple_of(2) {
+        period + 1
+    } else {
+        period
+    };
+    (0..values.len())
+        .map(|t| {
+            if t < half || t + half >= values.len() {
+                return None;
+            }
+            let window = &values[t - half..t - half + width];
+            let mut sum = 0.0;
+            for (offset, value) in window.iter().enumerate() {
+                sum += weight(offset) * (*value)?;
+            }
+            Some(sum / period as f64)
+        })
+        .collect()
+}
+
+fn nullable(name: &str, values: Vec<Option<f64>>) -> Result<ColType, Error> {
+    let values = values
+        .into_iter()
+        .map(|v| v.map_or(DataValue::Null, DataValue::Float))
+        .collect();
+    ColType::from_data_values(values, name.to_string())
+}
+
+impl ColViewer<'_> {
+    /// Additive decomposition into `trend`, `seasonal` and `residual` columns that add up to
+    /// the values. The trend is a centered moving average over `period` rows and is null for
+    /// the half period at each end, the seasonal part is the average deviation from it at
+    /// each position in the period
+    pub fn decompose(&self, period: usize) -> Result<Csv, Error> {
+        let values = self.finite_values()?;
+        if period < 2 || values.len() < 2 * period {
+            return Err(CoalaError::OutOfRange.into());
+        }
+        let trend = centered_average(&values, period);
+        let mut sums = vec![(0.0, 0usize); period];
+        for (t, (value, trend)) in values.iter().zip(&trend).enumerate() {
+            if let (Some(value), Some(trend)) = (value, trend) {
+                sums[t % period].0 += value - trend;
+                sums[t % period].1 += 1;
+            }
+        }
+        let mut season: Vec<f64> = sums
+            .iter()
+            .map(|&(sum, n)| if n > 0 { sum / n as f64 } else { 0.0 })
+            .collect();
+        // Centered so the seasonal part doesn't move the level, which belongs to the trend
+        let level = season.iter().sum::<f64>() / period as f64;
+        season.iter_mut().for_each(|s| *s -= level);
+        let seasonal: Vec<f64> = (0..values.len()).map(|t| season[t % period]).collect();
+        let residual = values
+            .iter()
+            .zip(&trend)
+            .zip(&seasonal)
+            .map(|((value, trend), seasonal)| Some((*value)? - (*trend)? - seasonal))
+            .collect();
+        let n_rows = values.len();
+        let cols = vec![
+            nullable("trend", trend)?,
+            ColType::Float(CsvCol::new("seasonal".to_string(), seasonal)),
+            nullable("residual", residual)?,
+        ];
+        let header = ["trend", "seasonal", "residual"].map(String::from).to_vec();
+        let mut csv = Csv::from_cols(cols, header, n_rows);
+        csv.lineage
+            .record(format!("decompose({}, {period})", self.name()));
+        Ok(csv)
+    }
+}
@@ -0,0 +1,114 @@
+use std::{collections::HashMap, hash::Hash};
+
+use crate::{
+    col_parser::{ColType, DataValue},
+    csv_parser::ColViewer,
+    perf,
+};
+
+/// A column as integer codes into its distinct values, kept in order of first appearance.
+/// Per-value work can then index plain arrays by code instead of hashing every cell
+#[derive(Debug, Clone)]
+pub struct Dictionary {
+    pub codes: Vec<u32>,
+    pub values: Vec<DataValue>,
+}
+
+impl Dictionary {
+    fn encode<K, I, F>(keys: I, value_of: F) -> Self
+    where
+        K: Hash + Eq,
+        I: Iterator<Item = K>,
+        F: Fn(usize) -> DataValue,
+    {
+        let mut lookup: HashMap<K, u32> = HashMap::new();
+        let mut values = Vec::new();
+        let codes = keys
+            .enumerate()
+            .map(|(row, key)| {
+                *lookup.entry(key).or_insert_with(|| {
+                    values.push(value_of(row));
+                    (values.len() - 1) as u32
+                })
+            })
+            .collect();
+        Self { codes, values }
+    }
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+    /// Rows holding each value, indexed by code
+    pub fn counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.values.len()];
+        for &code in &self.codes {
+            counts[code as usize] += 1;
+        }
+        counts
+    }
+    /// Row where each value first appears, indexed by code
+    pub fn first_indices(&self) -> Vec<usize> {
+        // Codes are handed out in order of first appearance, so a new value's code is always
+        // the number of values seen before it
+        let mut first = Vec::with_capacity(self.values.len());
+        for (row, &code) in self.codes.iter().enumerate() {
+            if code as usize == first.len() {
+                first.push(row);
+            }
+        }
+        first
+    }
+}
+
+impl ColViewer<'_> {
+    pub fn dictionary_encode(&self) -> Dictionary {
+        let value_of = |row| self.get(row).unwrap_or(DataValue::Null);
+        // Nulls get a code of their own rather than the one of their placeholder
+        self.inner.with_plain(|col| match col {
+            ColType::String(col) => {
+                perf::slow_path("Dictionary encoding", || {
+                    format!("`{}` holds plain strings, each one is hashed", self.name())
+                });
+                Dictionary::encode(
+                    col.iter()
+                        .enumerate()
+                        .map(|(row, s)| col.is_valid(row).then_some(s)),
+                    value_of,
+                )
+            }
+            ColType::Integer(col) => Dictionary::encode(
+                col.values
+                    .iter()
+                    .enumerate()
+                    .map(|(row, i)| col.is_valid(row).then_some(i)),
+                value_of,
+            ),
+            // Zeroes share a code whatever their sign
+            ColType::Float(col) => Dictionary::encode(
+                col.values.iter().enumerate().map(|(row, &f)| {
+                    col.is_valid(row)
+                        .then_some(if f == 0.0 { 0 } else { f.to_bits() })
+                }),
+                value_of,
+            ),
+            col => {
+                perf::slow_path("Dictionary encoding", || {
+                    format!(
+                        "`{}` is {}, each value is hashed by its text",
+                        self.name(),
+                        col.dtype().name()
+                    )
+                });
+                Dictionary::encode(self.iter().map(|v| v.to_string()), value_of)
+            }
+        })
+    }
+    /// Distinct values in order of first appearance, along with the row each first appears at
+    pub fn unique_with_indices(&self) -> (Vec<DataValue>, Vec<usize>) {
+        let dictionary = self.dictionary_encode();
+        let first = dictionary.first_indices();
+        (dictionary.values, first)
+    }
+}
@@ -0,0 +1,373 @@
+use std::{
+    io::{ErrorKind, IsTerminal, Write},
+    ops::Range,
+    process::{Command, Stdio},
+};
+
+use log::warn;
+use miette::Error;
+use terminal_size::{terminal_size, Width};
+
+use crate::{col_parser::DType, error::CoalaError};
+
+const SEPARATOR: &str = ", ";
+const DEFAULT_WIDTH: usize = 80;
+const MAX_CELL_WIDTH: usize = 32;
+const ELLIPSIS: char = '…';
+const DEFAULT_PAGER: &str = "less -S";
+// Escape sequences printing text faint and back to normal
+const FAINT: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+pub(crate) fn terminal_width() -> usize {
+    if let Some((Width(width), _)) = terminal_size() {
+        return width as usize;
+    }
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+// Whether printed tables may use escape sequences: only on a terminal, unless `NO_COLOR` is set
+pub(crate) fn styled() -> bool {
+    std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+// Writes the chunks to `$PAGER` as they're rendered, to stdout when it isn't a terminal. Once
+// the reader goes away the chunks left aren't rendered at all
+pub(crate) fn page(chunks: impl IntoIterator<Item = Result<String, Error>>) -> Result<(), Error> {
+    let chunks = chunks.into_iter();
+    if !std::io::stdout().is_terminal() {
+        return write_chunks(&mut std::io::stdout().lock(), chunks);
+    }
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| DEFAULT_PAGER.to_string());
+    let mut args = pager.split_whitespace();
+    let Some(program) = args.next() else {
+        return write_chunks(&mut std::io::stdout().lock(), chunks);
+    };
+    let mut child = match Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Couldn't spawn pager `{pager}`, writing to stdout instead. Reason: {e}");
+            return write_chunks(&mut std::io::stdout().lock(), chunks);
+        }
+    };
+    // The pager's stdin is closed before waiting, so it sees the end of the table
+    let written = match child.stdin.take() {
+        Some(mut stdin) => write_chunks(&mut stdin, chunks),
+        None => Ok(()),
+    };
+    child.wait().map_err(CoalaError::from)?;
+    written
+}
+
+// Stops at the first chunk the reader doesn't take because it has gone, e.g. a pager quit
+// before the end of the table
+fn write_chunks(
+    out: &mut impl Write,
+    chunks: impl Iterator<Item = Result<String, Error>>,
+) -> Result<(), Error> {
+    for chunk in chunks {
+        match out.write_all(chunk?.as_bytes()) {
+            Err(e) if e.kind() == ErrorKind::BrokenPipe => return Ok(()),
+            written => written.map_err(CoalaError::from)?,
+        }
+    }
+    match out.flush() {
+        Err(e) if e.kind() != ErrorKind::BrokenPipe => Err(CoalaError::from(e).into()),
+        _ => Ok(()),
+    }
+}
+
+// Short type name shown under a column's name, like `[f64]`
+pub(crate) fn dtype_label(dtype: DType) -> String {
+    let name = match dtype {
+        DType::Float => "f64",
+        DType::Integer => "i64",
+        DType::String => "str",
+        DType::Datetime => "datetime",
+        #[cfg(feature = "decimal")]
+        DType::Decimal => "decimal",
+        #[cfg(feature = "complex")]
+        DType::Complex => "complex",
+        #[cfg(feature = "bigint")]
+        DType::BigInt => "bigint",
+        DType::List => "list",
+        DType::Struct => "struct",
+        DType::Custom(name) => name,
+    };
+    format!("[{name}]")
+}
+
+/// Renders `cols` under `header`, with a row of `dtypes` below it when given and the numbers
+/// of `rows` in a gutter on the left. The gutter is always shown, other columns only as many
+/// as fit in `max_width`. The cells set in `faint`, placeholders rather than values, are
+/// printed faint when given
+pub(crate) fn render_table(
+    header: &[String],
+    dtypes: Option<&[String]>,
+    rows: Option<Range<usize>>,
+    cols: &[Vec<String>],
+    faint: Option<&[Vec<bool>]>,
+    max_width: usize,
+) -> String {
+    let layout = TableLayout::new(header, dtypes, rows.as_ref(), cols, max_width);
+    let mut result = layout.render_header(header, dtypes);
+    result.push_str(&layout.render_rows(rows, cols, faint));
+    result
+}
+
+/// Column widths and the columns that fit, worked out once so a long table can be rendered a
+/// block of rows at a time
+pub(crate) struct TableLayout {
+    widths: Vec<usize>,
+    n_shown: usize,
+    gutter: bool,
+    indicator: Option<String>,
+}
+
+impl TableLayout {
+    /// Widths fitting `cols` and a gutter for the numbers of `rows` when given. Cells of rows
+    /// rendered later that are wider than their column get elided
+    pub(crate) fn new(
+        header: &[String],
+        dtypes: Option<&[String]>,
+        rows: Option<&Range<usize>>,
+        cols: &[Vec<String>],
+        max_width: usize,
+    ) -> Self {
+        let gutter = rows.map(|rows| {
+            rows.clone()
+                .next_back()
+                .map_or(0, |last| last.to_string().len())
+                .min(MAX_CELL_WIDTH)
+        });
+        let widths: Vec<usize> = gutter
+            .into_iter()
+            .chain(header.iter().enumerate().zip(cols).map(|((i, name), col)| {
+                col.iter()
+                    .map(|s| s.chars().count())
+                    .chain(std::iter::once(name.chars().count()))
+                    .chain(dtypes.iter().map(|dtypes| dtypes[i].chars().count()))
+                    .max()
+                    .unwrap_or(0)
+                    .min(MAX_CELL_WIDTH)
+            }))
+            .collect();
+        let fixed = usize::from(gutter.is_some());
+        let gutter_width = widths[..fixed]
+            .iter()
+            .map(|width| width + SEPARATOR.len())
+            .sum::<usize>();
+        let n_shown =
+            fixed + fitting_cols(&widths[fixed..], max_width.saturating_sub(gutter_width));
+        let hidden = widths.len() - n_shown;
+        Self {
+            indicator: (hidden > 0).then(|| hidden_indicator(hidden)),
+            widths,
+            n_shown,
+            gutter: gutter.is_some(),
+        }
+    }
+    fn shown(&self) -> &[usize] {
+        &self.widths[..self.n_shown]
+    }
+    fn trailer(&self) -> Option<&str> {
+        self.indicator.as_ref().map(|_| "…")
+    }
+    /// The header line, with the line of `dtypes` under it when given
+    pub(crate) fn render_header(&self, header: &[String], dtypes: Option<&[String]>) -> String {
+        let blank = self.gutter.then_some("");
+        let mut result = String::new();
+        let header = blank.into_iter().chain(header.iter().map(String::as_str));
+        push_row(
+            &mut result,
+            header.map(|cell| (cell, false)),
+            self.shown(),
+            self.indicator.as_deref(),
+        );
+        if let Some(dtypes) = dtypes {
+            let dtypes = blank.into_iter().chain(dtypes.iter().map(String::as_str));
+            push_row(
+                &mut result,
+                dtypes.map(|cell| (cell, false)),
+                self.shown(),
+                self.trailer(),
+            );
+        }
+        result
+    }
+    /// The lines of `cols`, numbered with `rows` when the layout has a gutter
+    pub(crate) fn render_rows(
+        &self,
+        rows: Option<Range<usize>>,
+        cols: &[Vec<String>],
+        faint: Option<&[Vec<bool>]>,
+    ) -> String {
+        let gutter: Option<Vec<String>> = rows
+            .filter(|_| self.gutter)
+            .map(|rows| rows.map(|row| row.to_string()).collect());
+        let fixed = usize::from(gutter.is_some());
+        let cols: Vec<&Vec<String>> = gutter.iter().chain(cols).collect();
+        let n_rows = cols.iter().map(|col| col.len()).max().unwrap_or(0);
+        let mut result = String::new();
+        for row in 0..n_rows {
+            // The gutter is never faint
+            let is_faint = |i: usize| {
+                i.checked_sub(fixed)
+                    .and_then(|i| faint?.get(i)?.get(row).copied())
+                    .unwrap_or(false)
+            };
+            push_row(
+                &mut result,
+                cols.iter().enumerate().map(|(i, col)| {
+                    let cell = col.get(row).map(String::as_str).unwrap_or("");
+                    (cell, is_faint(i))
+                }),
+                self.shown(),
+                self.trailer(),
+            );
+        }
+        result
+    }
+}
+
+fn hidden_indicator(hidden: usize) -> String {
+    format!("{ELLIPSIS} +{hidden} cols")
+}
+
+fn fitting_cols(widths: &[usize], max_width: usize) -> usize {
+    let mut used = 0;
+    for (i, width) in widths.iter().enumerate() {
+        let needed = used + if i > 0 { SEPARATOR.len() } else { 0 } + width;
+        let hidden = widths.len() - i - 1;
+        let reserved = if hidden > 0 {
+            SEPARATOR.len() + hidden_indicator(hidden).chars().count()
+        } else {
+            0
+        };
+        if needed + reserved > max_width {
+            return i.max(1);
+        }
+        used = needed;
+    }
+    widths.len()
+}
+
+// Cells come with whether they're printed faint
+fn push_row<'a>(
+    result: &mut String,
+    cells: impl Iterator<Item = (&'a str, bool)>,
+    widths: &[usize],
+    trailer: Option<&str>,
+) {
+    for (i, ((cell, faint), &width)) in cells.zip(widths).enumerate() {
+        if i > 0 {
+            result.push_str(SEPARATOR);
+        }
+        // Padded first, the escape sequences take no room on screen
+        let padded = format!("{:<width$}", elide(cell, width));
+        if faint {
+            result.push_str(&format!("{FAINT}{padded}{RESET}"));
+        } else {
+            result.push_str(&padded);
+        }
+    }
+    if let Some(trailer) = trailer {
+        result.push_str(SEPARATOR);
+        result.push_str(trailer);
+    }
+    result.push('\n');
+}
+
+fn elide(cell: &str, width: usize) -> String {
+    if cell.chars().count() <= width {
+        return cell.to_string();
+    }
+    let mut elided: String = cell.chars().take(width.saturating_sub(1)).collect();
+    elided.push(ELLIPSIS);
+    elided
+}
+
+// A GitHub flavored Markdown table, pipes in cells escaped
+pub(crate) fn markdown_table(header: &[String], cols: &[Vec<String>], n_rows: usize) -> String {
+    let escape = |s: &str| s.replace('|', "\\|").replace(['\n', '\r'], " ");
+    let mut result = format!(
+        "| {} |\n",
+        header
+            .iter()
+            .map(|h| escape(h))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    );
+    result.push_str(&format!("|{}\n", " --- |".repeat(header.len())));
+    for row in 0..n_rows {
+        let line = cols
+            .iter()
+            .map(|col| escape(&col[row]))
+            .collect::<Vec<_>>()
+            .join(" | ");
+        result.push_str(&format!("| {line} |\n"));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        io::{self, ErrorKind, Write},
+    };
+
+    use super::*;
+
+    // Takes one write, then behaves like a pipe whose reader has quit
+    struct QuitAfterOne(usize);
+
+    impl Write for QuitAfterOne {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += 1;
+            if self.0 > 1 {
+                return Err(ErrorKind::BrokenPipe.into());
+            }
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn chunks_stop_once_the_reader_is_gone() {
+        let rendered = Cell::new(0);
+        let chunks = (0..10).map(|i| {
+            rendered.set(rendered.get() + 1);
+            Ok(format!("{i}\n"))
+        });
+        write_chunks(&mut QuitAfterOne(0), chunks).unwrap();
+        assert_eq!(rendered.get(), 2);
+    }
+
+    #[test]
+    fn blocks_line_up_with_the_whole_table() {
+        let header = vec!["a".to_string(), "b".to_string()];
+        let cols: Vec<Vec<String>> = vec![
+            (0..12).map(|i| i.to_string()).collect(),
+            (0..12).map(|i| "x".repeat(i)).collect(),
+        ];
+        let whole = render_table(&header, None, Some(0..12), &cols, None, 80);
+        let layout = TableLayout::new(&header, None, Some(&(0..12)), &cols, 80);
+        let mut blocks = layout.render_header(&header, None);
+        for rows in [0..5, 5..12] {
+            let block: Vec<Vec<String>> =
+                cols.iter().map(|col| col[rows.clone()].to_vec()).collect();
+            blocks.push_str(&layout.render_rows(Some(rows), &block, None));
+        }
+        assert_eq!(blocks, whole);
+    }
+}
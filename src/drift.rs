@@ -0,0 +1,128 @@
+use miette::Error;
+
+use crate::{col_parser::DataValue, csv_parser::Csv, error::CoalaError, histogram::Bins};
+
+// Share given to empty bins, whose logarithm would otherwise be infinite
+const EMPTY_BIN_SHARE: f64 = 1e-4;
+
+// Share of the values in each bin of `edges`
+fn bin_shares(values: &[f64], bins: &Bins, edges: &[f64]) -> Result<Vec<f64>, Error> {
+    let mut counts = vec![0usize; edges.len() - 1];
+    for &value in values {
+        if let Some(bin) = bins.bin_of(edges, value) {
+            counts[bin] += 1;
+        }
+    }
+    let total: usize = counts.iter().sum();
+    if total == 0 {
+        return Err(CoalaError::EmptyColumn.into());
+    }
+    Ok(counts
+        .iter()
+        .map(|&c| (c as f64 / total as f64).max(EMPTY_BIN_SHARE))
+        .collect())
+}
+
+// Statistics `stats_drift` compares, in the order of `profile`
+const PROFILED: [&str; 4] = ["mean", "median", "null_percent", "unique"];
+
+// Mean, median, percentage of nulls and distinct values of column `name`. `None` where the
+// frame lacks the column or its type has no mean or median
+fn profile(csv: &Csv, name: &str) -> [Option<f64>; 4] {
+    let Ok(col) = csv.get_col(name) else {
+        return [None; 4];
+    };
+    let number = |value: Result<DataValue, Error>| value.ok().and_then(|v| v.as_f64());
+    let null_percent =
+        (csv.n_rows > 0).then(|| 100.0 * col.null_count() as f64 / csv.n_rows as f64);
+    let unique = col
+        .dictionary_encode()
+        .values
+        .iter()
+        .filter(|v| !matches!(v, DataValue::Null))
+        .count();
+    [
+        number(col.mean()),
+        number(col.median()),
+        null_percent,
+        Some(unique as f64),
+    ]
+}
+
+impl Csv {
+    /// Population stability index of `name` from this frame, the reference, to `other`, e.g.
+    /// training data to production data. Bins come from this frame's values, the extreme ones
+    /// open ended unless the edges are explicit. Below 0.1 is usually read as stable, above
+    /// 0.25 as a significant shift
+    pub fn psi(&self, other: &Csv, name: &str, bins: &Bins) -> Result<f64, Error> {
+        let expected: Vec<f64> = self
+            .get_col(name)?
+            .finite_values()?
+            .into_iter()
+            .flatten()
+            .collect();
+        let actual: Vec<f64> = other
+            .get_col(name)?
+            .finite_values()?
+            .into_iter()
+            .flatten()
+            .collect();
+        let mut sorted = expected.clone();
+        if sorted.is_empty() {
+            return Err(CoalaError::EmptyColumn.into());
+        }
+        sorted.sort_unstable_by(f64::total_cmp);
+        let edges = bins.edges(&sorted)?;
+        let expected = bin_shares(&expected, bins, &edges)?;
+        let actual = bin_shares(&actual, bins, &edges)?;
+        Ok(expected
+            .iter()
+            .zip(&actual)
+            .map(|(e, a)| (a - e) * (a / e).ln())
+            .sum())
+    }
+    /// How each column's mean, median, percentage of nulls and number of distinct values
+    /// changed from this frame to `other`, e.g. yesterday's export to today's. One row per
+    /// column and statistic, with the value `before`, `after` and the `change`. Columns only
+    /// one frame has get nulls on the other side
+    pub fn stats_drift(&self, other: &Csv) -> Result<Csv, Error> {
+        let mut names: Vec<&String> = self.header.iter().collect();
+        names.extend(
+            other
+                .header
+                .iter()
+                .filter(|name| !self.header.contains(name)),
+        );
+        let mut rows: [Vec<DataValue>; 5] = Default::default();
+        for name in names {
+            let before = profile(self, name);
+            let after = profile(other, name);
+            for ((statistic, before), after) in PROFILED.iter().zip(before).zip(after) {
+                let float = |v: Option<f64>| v.map_or(DataValue::Null, DataValue::Float);
+                let row = [
+                    DataValue::String(name.clone()),
+                    DataValue::String(statistic.to_string()),
+                    float(before),
+                    float(after),
+                    float(before.zip(after).map(|(b, a)| a - b)),
+                ];
+                for (values, value) in rows.iter_mut().zip(row) {
+                    values.push(value);
+                }
+            }
+        }
+        let n_rows = rows[0].len();
+        let [column, statistic, before, after, change] = rows;
+        self.summary_frame(
+            "stats_drift",
+            vec![
+                ("column", column),
+                ("statistic", statistic),
+                ("before", before),
+                ("after", after),
+                ("change", change),
+            ],
+            n_rows,
+        )
+    }
+}
@@ -0,0 +1,388 @@
+use std::{borrow::Cow, collections::HashSet};
+
+use bitvec::vec::BitVec;
+use datetime::datetime::Datetime;
+use miette::Error;
+
+use crate::{
+    col_parser::{self, ColType, DataValue},
+    csv_parser::ColViewer,
+    error::CoalaError,
+    mask::Mask,
+};
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+// Dates are derived in UTC from the timestamp, using the civil calendar algorithms from
+// http://howardhinnant.github.io/date_algorithms.html
+pub(crate) fn days(dt: &Datetime) -> i64 {
+    dt.timestamp().div_euclid(SECONDS_PER_DAY)
+}
+
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (i64::from(month) + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// ISO weekday, Monday is 1 and Sunday is 7
+pub(crate) fn weekday(days: i64) -> u32 {
+    (days + 3).rem_euclid(7) as u32 + 1
+}
+
+// `dt` in UTC through the strftime directives `%Y %m %d %H %M %S %j %%`, any other
+// directive is written as it is
+pub(crate) fn format_datetime(dt: &Datetime, format: &str) -> String {
+    let days = days(dt);
+    let (year, month, day) = civil_from_days(days);
+    let seconds = dt.timestamp().rem_euclid(SECONDS_PER_DAY);
+    let mut out = String::with_capacity(format.len() + 8);
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => out.push_str(&format!("{year:04}")),
+            Some('m') => out.push_str(&format!("{month:02}")),
+            Some('d') => out.push_str(&format!("{day:02}")),
+            Some('H') => out.push_str(&format!("{:02}", seconds / 3_600)),
+            Some('M') => out.push_str(&format!("{:02}", seconds / 60 % 60)),
+            Some('S') => out.push_str(&format!("{:02}", seconds % 60)),
+            Some('j') => {
+                let day_of_year = days - days_from_civil(year, 1, 1) + 1;
+                out.push_str(&format!("{day_of_year:03}"));
+            }
+            Some('%') => out.push('%'),
+            Some(other) => {
+                out.push('%');
+                out.push(other);
+            }
+            None => out.push('%'),
+        }
+    }
+    out
+}
+
+// The ISO week belongs to the year holding its Thursday
+fn iso_week(days: i64) -> (i64, u32) {
+    let thursday = days - i64::from(weekday(days)) + 4;
+    let (year, _, _) = civil_from_days(thursday);
+    let week = (thursday - days_from_civil(year, 1, 1)) / 7 + 1;
+    (year, week as u32)
+}
+
+fn is_weekend(days: i64) -> bool {
+    weekday(days) >= 6
+}
+
+/// Decides which dates are holidays, weekends are always skipped on top of these
+pub trait HolidayCalendar {
+    fn is_holiday(&self, year: i64, month: u32, day: u32) -> bool;
+}
+
+/// No holidays, only weekends are skipped
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoHolidays;
+
+impl HolidayCalendar for NoHolidays {
+    fn is_holiday(&self, _year: i64, _month: u32, _day: u32) -> bool {
+        false
+    }
+}
+
+/// Holidays listed by date, plus ones recurring on the same day every year
+#[derive(Debug, Clone, Default)]
+pub struct FixedHolidays {
+    dates: HashSet<(i64, u32, u32)>,
+    yearly: HashSet<(u32, u32)>,
+}
+
+impl FixedHolidays {
+    pub fn date(mut self, year: i64, month: u32, day: u32) -> Self {
+        self.dates.insert((year, month, day));
+        self
+    }
+    pub fn yearly(mut self, month: u32, day: u32) -> Self {
+        self.yearly.insert((month, day));
+        self
+    }
+}
+
+impl HolidayCalendar for FixedHolidays {
+    fn is_holiday(&self, year: i64, month: u32, day: u32) -> bool {
+        self.dates.contains(&(year, month, day)) || self.yearly.contains(&(month, day))
+    }
+}
+
+fn is_business_day(days: i64, calendar: &impl HolidayCalendar) -> bool {
+    let (year, month, day) = civil_from_days(days);
+    !is_weekend(days) && !calendar.is_holiday(year, month, day)
+}
+
+// Business days in `[start, end)`, negative when `end` comes first
+fn business_days_between(start: i64, end: i64, calendar: &impl HolidayCalendar) -> i64 {
+    let (from, to, sign) = if start <= end {
+        (start, end, 1)
+    } else {
+        (end, start, -1)
+    };
+    // Whole weeks hold five weekdays each, only their holidays need checking
+    let weeks = (to - from) / 7;
+    let mut count = weeks * 5;
+    for days in from..from + weeks * 7 {
+        if !is_weekend(days) && !is_business_day(days, calendar) {
+            count -= 1;
+        }
+    }
+    count += (from + weeks * 7..to)
+        .filter(|&days| is_business_day(days, calendar))
+        .count() as i64;
+    sign * count
+}
+
+// Moves `n` business days, starting from the next business day when `days` isn't one
+fn offset_business_days(mut days: i64, n: i64, calendar: &impl HolidayCalendar) -> i64 {
+    let step = n.signum();
+    while !is_business_day(days, calendar) {
+        days += if step < 0 { -1 } else { 1 };
+    }
+    for _ in 0..n.abs() {
+        days += step;
+        while !is_business_day(days, calendar) {
+            days += step;
+        }
+    }
+    days
+}
+
+/// A fiscal year starting on the first day of `start_month`, named after the calendar year
+/// it ends in unless `named_by_start` is set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FiscalYear {
+    pub start_month: u32,
+    pub named_by_start: bool,
+}
+
+impl Default for FiscalYear {
+    fn default() -> Self {
+        Self {
+            start_month: 1,
+            named_by_start: false,
+        }
+    }
+}
+
+impl FiscalYear {
+    pub fn starting(start_month: u32) -> Result<Self, Error> {
+        if !(1..=12).contains(&start_month) {
+            return Err(CoalaError::OutOfRange.into());
+        }
+        Ok(Self {
+            start_month,
+            named_by_start: false,
+        })
+    }
+    pub fn named_by_start(mut self) -> Self {
+        self.named_by_start = true;
+        self
+    }
+    // Year and zero based month offset within the fiscal year
+    fn position(&self, year: i64, month: u32) -> (i64, u32) {
+        let offset = (month + 12 - self.start_month) % 12;
+        let start_year = if month >= self.start_month {
+            year
+        } else {
+            year - 1
+        };
+        let name = if self.named_by_start || self.start_month == 1 {
+            start_year
+        } else {
+            start_year + 1
+        };
+        (name, offset)
+    }
+    pub fn year(&self, dt: &Datetime) -> i64 {
+        let (year, month, _) = civil_from_days(days(dt));
+        self.position(year, month).0
+    }
+    /// Fiscal quarter, 1 to 4
+    pub fn quarter(&self, dt: &Datetime) -> u32 {
+        let (year, month, _) = civil_from_days(days(dt));
+        self.position(year, month).1 / 3 + 1
+    }
+    /// Fiscal month, 1 to 12
+    pub fn period(&self, dt: &Datetime) -> u32 {
+        let (year, month, _) = civil_from_days(days(dt));
+        self.position(year, month).1 + 1
+    }
+}
+
+/// Calendar accessors over a datetime column, each yielding one value per row. Null rows
+/// yield nulls, and are never set in masks
+pub struct DtViewer<'a> {
+    name: &'a str,
+    // Borrowed, unless the column is compressed
+    values: Cow<'a, [Datetime]>,
+    validity: Option<BitVec>,
+}
+
+impl DtViewer<'_> {
+    fn is_valid(&self, row: usize) -> bool {
+        col_parser::is_valid(self.validity.as_ref(), row)
+    }
+    fn map<F: Fn(&Datetime) -> DataValue>(&self, f: F) -> Vec<DataValue> {
+        self.values
+            .iter()
+            .enumerate()
+            .map(|(row, dt)| {
+                if self.is_valid(row) {
+                    f(dt)
+                } else {
+                    DataValue::Null
+                }
+            })
+            .collect()
+    }
+    fn mask<F: Fn(&Datetime) -> bool>(&self, f: F) -> Mask {
+        self.values
+            .iter()
+            .enumerate()
+            .map(|(row, dt)| self.is_valid(row) && f(dt))
+            .collect()
+    }
+    fn civil<F: Fn(i64, u32, u32) -> DataValue>(&self, f: F) -> Vec<DataValue> {
+        self.map(|dt| {
+            let (year, month, day) = civil_from_days(days(dt));
+            f(year, month, day)
+        })
+    }
+    pub fn year(&self) -> Vec<DataValue> {
+        self.civil(|year, _, _| DataValue::Integer(year))
+    }
+    pub fn month(&self) -> Vec<DataValue> {
+        self.civil(|_, month, _| DataValue::Integer(month.into()))
+    }
+    pub fn day(&self) -> Vec<DataValue> {
+        self.civil(|_, _, day| DataValue::Integer(day.into()))
+    }
+    /// ISO weekday, Monday is 1 and Sunday is 7
+    pub fn weekday(&self) -> Vec<DataValue> {
+        self.map(|dt| DataValue::Integer(weekday(days(dt)).into()))
+    }
+    pub fn quarter(&self) -> Vec<DataValue> {
+        self.civil(|_, month, _| DataValue::Integer(((month - 1) / 3 + 1).into()))
+    }
+    /// Week number, 1 to 53, in the ISO 8601 week-numbering year
+    pub fn iso_week(&self) -> Vec<DataValue> {
+        self.map(|dt| DataValue::Integer(iso_week(days(dt)).1.into()))
+    }
+    /// The year a date's ISO week belongs to, which differs from the calendar year around New Year
+    pub fn iso_year(&self) -> Vec<DataValue> {
+        self.map(|dt| DataValue::Integer(iso_week(days(dt)).0))
+    }
+    pub fn fiscal_year(&self, fiscal: FiscalYear) -> Vec<DataValue> {
+        self.map(|dt| DataValue::Integer(fiscal.year(dt)))
+    }
+    pub fn fiscal_quarter(&self, fiscal: FiscalYear) -> Vec<DataValue> {
+        self.map(|dt| DataValue::Integer(fiscal.quarter(dt).into()))
+    }
+    pub fn is_weekend(&self) -> Mask {
+        self.mask(|dt| is_weekend(days(dt)))
+    }
+    pub fn is_holiday(&self, calendar: &impl HolidayCalendar) -> Mask {
+        self.mask(|dt| {
+            let (year, month, day) = civil_from_days(days(dt));
+            calendar.is_holiday(year, month, day)
+        })
+    }
+    pub fn is_business_day(&self, calendar: &impl HolidayCalendar) -> Mask {
+        self.mask(|dt| is_business_day(days(dt), calendar))
+    }
+    /// Business days from each date up to, not including, the matching date of `end`
+    pub fn business_days_until(
+        &self,
+        end: &DtViewer,
+        calendar: &impl HolidayCalendar,
+    ) -> Result<Vec<DataValue>, Error> {
+        if self.values.len() != end.values.len() {
+            return Err(CoalaError::LengthMismatch {
+                left: self.name.to_string(),
+                left_len: self.values.len(),
+                right: end.name.to_string(),
+                right_len: end.values.len(),
+            }
+            .into());
+        }
+        Ok(self
+            .values
+            .iter()
+            .zip(end.values.iter())
+            .enumerate()
+            .map(|(row, (start, end_dt))| {
+                if self.is_valid(row) && end.is_valid(row) {
+                    DataValue::Integer(business_days_between(days(start), days(end_dt), calendar))
+                } else {
+                    DataValue::Null
+                }
+            })
+            .collect())
+    }
+    /// Shifts each date by `n` business days, keeping its time of day
+    pub fn add_business_days(&self, n: i64, calendar: &impl HolidayCalendar) -> Vec<DataValue> {
+        self.map(|dt| {
+            let from = days(dt);
+            let to = offset_business_days(from, n, calendar);
+            DataValue::DateTime(Datetime::from_timestamp(
+                dt.timestamp() + (to - from) * SECONDS_PER_DAY,
+            ))
+        })
+    }
+    /// Labels like `FY2024-Q3`, usable directly as a group key
+    pub fn fiscal_period(&self, fiscal: FiscalYear) -> Vec<DataValue> {
+        self.map(|dt| DataValue::String(format!("FY{}-Q{}", fiscal.year(dt), fiscal.quarter(dt))))
+    }
+}
+
+impl ColViewer<'_> {
+    pub fn dt(&self) -> Result<DtViewer<'_>, Error> {
+        let (values, validity) = match self.inner {
+            ColType::Datetime(col) => (Cow::Borrowed(col.values.as_slice()), col.validity.clone()),
+            ColType::Compressed(col) if let ColType::Datetime(col) = col.decode() => {
+                (Cow::Owned(col.values), col.validity)
+            }
+            ColType::View(col) if let ColType::Datetime(col) = col.materialize() => {
+                (Cow::Owned(col.values), col.validity)
+            }
+            col => {
+                return Err(CoalaError::InvalidType {
+                    col: col.name().to_string(),
+                }
+                .into());
+            }
+        };
+        Ok(DtViewer {
+            name: self.name(),
+            values,
+            validity,
+        })
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::{HashMap, HashSet};
+
+use miette::Error;
+
+use crate::{csv_parser::Csv, mask::Mask};
+
+/// Rows holding the same values, in ascending order. The first is the one `duplicated` keeps
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    pub rows: Vec<usize>,
+}
+
+impl DuplicateGroup {
+    pub fn first(&self) -> usize {
+        self.rows[0]
+    }
+    pub fn count(&self) -> usize {
+        self.rows.len()
+    }
+}
+
+impl Csv {
+    // Every column if `subset` is empty
+    pub(crate) fn subset_names<'a>(&'a self, subset: &[&'a str]) -> Vec<&'a str> {
+        if subset.is_empty() {
+            self.header.iter().map(String::as_str).collect()
+        } else {
+            subset.to_vec()
+        }
+    }
+    // Each row keyed by the dictionary codes of its values in `subset`
+    pub(crate) fn row_keys(&self, subset: &[&str]) -> Result<Vec<Vec<u32>>, Error> {
+        let names = self.subset_names(subset);
+        let mut keys = vec![Vec::with_capacity(names.len()); self.n_rows];
+        for name in names {
+            let codes = self.get_col(name)?.dictionary_encode().codes;
+            for (key, code) in keys.iter_mut().zip(codes) {
+                key.push(code);
+            }
+        }
+        Ok(keys)
+    }
+    /// Marks every row that repeats an earlier one on the columns of `subset`, all of them if
+    /// it's empty. The first occurrence is left unmarked and nulls match each other
+    pub fn duplicated(&self, subset: &[&str]) -> Result<Mask, Error> {
+        let mut seen = HashSet::new();
+        Ok(self
+            .row_keys(subset)?
+            .into_iter()
+            .map(|key| !seen.insert(key))
+            .collect())
+    }
+    /// Every set of identical rows appearing more than once, in order of first appearance
+    pub fn duplicate_report(&self) -> Result<Vec<DuplicateGroup>, Error> {
+        let mut groups: Vec<DuplicateGroup> = Vec::new();
+        let mut lookup: HashMap<Vec<u32>, usize> = HashMap::new();
+        for (row, key) in self.row_keys(&[])?.into_iter().enumerate() {
+            let group = *lookup.entry(key).or_insert_with(|| {
+                groups.push(DuplicateGroup { rows: Vec::new() });
+                groups.len() - 1
+            });
+            groups[group].rows.push(row);
+        }
+        groups.retain(|group| group.count() > 1);
+        Ok(groups)
+    }
+}
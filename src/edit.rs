@@ -0,0 +1,251 @@
+use std::{collections::HashMap, sync::Arc};
+
+use miette::{miette, Error};
+
+use crate::{
+    col_parser::{DType, DataValue},
+    csv_parser::Csv,
+    error::CoalaError,
+    events::ChangeEvent,
+};
+
+// Type of a value, named like the column types
+pub(crate) fn type_name(value: &DataValue) -> &'static str {
+    match value {
+        DataValue::Float(_) => "Float",
+        DataValue::Integer(_) => "Integer",
+        DataValue::Unsigned(_) => "Unsigned",
+        DataValue::String(_) => "String",
+        DataValue::DateTime(_) => "Datetime",
+        #[cfg(feature = "decimal")]
+        DataValue::Decimal(_) => "Decimal",
+        #[cfg(feature = "complex")]
+        DataValue::Complex(_) => "Complex",
+        #[cfg(feature = "bigint")]
+        DataValue::BigInt(_) => "BigInt",
+        DataValue::List(_) => "List",
+        DataValue::Struct(_) => "Struct",
+        DataValue::Null => "Null",
+    }
+}
+
+// `value` as a value of `dtype`, `None` when it can't be one. Integers widen to the other
+// numeric types and floats to complex, nothing narrows
+pub(crate) fn coerce(value: DataValue, dtype: DType) -> Option<DataValue> {
+    match (dtype, value) {
+        (_, DataValue::Null) => Some(DataValue::Null),
+        (DType::Float, DataValue::Float(v)) => Some(DataValue::Float(v)),
+        (DType::Float, DataValue::Integer(v)) => Some(DataValue::Float(v as f64)),
+        (DType::Float, DataValue::Unsigned(v)) => Some(DataValue::Float(v as f64)),
+        (DType::Integer, DataValue::Integer(v)) => Some(DataValue::Integer(v)),
+        (DType::Integer, DataValue::Unsigned(v)) => i64::try_from(v).ok().map(DataValue::Integer),
+        (DType::String, DataValue::String(v)) => Some(DataValue::String(v)),
+        (DType::Datetime, DataValue::DateTime(v)) => Some(DataValue::DateTime(v)),
+        #[cfg(feature = "decimal")]
+        (
+            DType::Decimal,
+            value @ (DataValue::Decimal(_) | DataValue::Integer(_) | DataValue::Unsigned(_)),
+        ) => value.as_decimal().map(DataValue::Decimal),
+        #[cfg(feature = "complex")]
+        (DType::Complex, value) => value.as_complex().map(DataValue::Complex),
+        #[cfg(feature = "bigint")]
+        (DType::BigInt, value) => value.as_bigint().map(DataValue::BigInt),
+        (DType::List, DataValue::List(v)) => Some(DataValue::List(v)),
+        (DType::Struct, DataValue::Struct(v)) => Some(DataValue::Struct(v)),
+        // Parsed by the kind when the column is rebuilt
+        (DType::Custom(_), DataValue::String(v)) => Some(DataValue::String(v)),
+        _ => None,
+    }
+}
+
+impl Csv {
+    // `value` checked against the type of the column at `index`. A column of nulls only has
+    // no type yet and takes anything
+    pub(crate) fn checked_value(&self, index: usize, value: DataValue) -> Result<DataValue, Error> {
+        let col = &self.cols[index];
+        if col.null_count() == col.len() {
+            return Ok(value);
+        }
+        let dtype = col.dtype();
+        let found = type_name(&value);
+        coerce(value, dtype).ok_or_else(|| {
+            CoalaError::TypeMismatch {
+                col: self.header[index].clone(),
+                expected: dtype.name().to_string(),
+                found: found.to_string(),
+            }
+            .into()
+        })
+    }
+    // Rebuilds the column at `index` from `values` keeping its type, dropping what was cached
+    // about it
+    pub(crate) fn replace_values(
+        &mut self,
+        index: usize,
+        values: Vec<DataValue>,
+    ) -> Result<(), Error> {
+        let name = self.header[index].clone();
+        self.cols[index] = Arc::new(self.cols[index].with_values(values)?);
+        self.cache.remove(&name);
+        Ok(())
+    }
+    /// Stores `value` in row `row` of `name`. It must have the column's type or widen to it,
+    /// like an integer into a float column. The column is rewritten, so for many cells at
+    /// once prefer `update`
+    pub fn set(&mut self, row: usize, name: &str, value: DataValue) -> Result<(), Error> {
+        let index = self.col_index(name)?;
+        if row >= self.n_rows {
+            return Err(CoalaError::OutOfRange.into());
+        }
+        let value = self.checked_value(index, value)?;
+        let col = &self.cols[index];
+        let mut values = (0..self.n_rows)
+            .map(|r| col.data_as_value(r))
+            .collect::<Result<Vec<_>, Error>>()?;
+        values[row] = value;
+        self.replace_values(index, values)?;
+        self.lineage.record(format!("set({row}, {name})"));
+        self.notify(ChangeEvent::CellUpdated {
+            row,
+            col: name.to_string(),
+        });
+        Ok(())
+    }
+    /// Copies the values of `patch` that aren't null into the rows of this frame with the same
+    /// `on` value, column by column, e.g. to apply a file of manual corrections. Every other
+    /// column of `patch` must exist here and its values must fit the column, as in `set`.
+    /// Patch rows matching no row are skipped, a later patch row wins over an earlier one.
+    /// Returns how many cells were written
+    pub fn update(&mut self, patch: &Csv, on: &str) -> Result<usize, Error> {
+        let mut rows: HashMap<String, Vec<usize>> = HashMap::new();
+        for (row, key) in self.get_col(on)?.iter().enumerate() {
+            if !matches!(key, DataValue::Null) {
+                rows.entry(key.to_string()).or_default().push(row);
+            }
+        }
+        let patch_keys: Vec<DataValue> = patch.get_col(on)?.iter().collect();
+        let mut written = Vec::new();
+        // Applied once every value checks out, so a bad one leaves the frame untouched
+        let mut replaced = Vec::new();
+        for name in patch.header.iter().filter(|name| *name != on) {
+            let index = self.col_index(name)?;
+            let source = patch.get_col(name)?;
+            let mut values: Option<Vec<DataValue>> = None;
+            for (key, value) in patch_keys.iter().zip(source.iter()) {
+                if matches!(value, DataValue::Null) {
+                    continue;
+                }
+                let Some(targets) = rows.get(&key.to_string()) else {
+                    continue;
+                };
+                let value = self.checked_value(index, value)?;
+                // Read only once a cell of the column actually changes
+                let values = match &mut values {
+                    Some(values) => values,
+                    None => values.insert(
+                        (0..self.n_rows)
+                            .map(|r| self.cols[index].data_as_value(r))
+                            .collect::<Result<_, Error>>()?,
+                    ),
+                };
+                for &row in targets {
+                    values[row] = value.clone();
+                    written.push((row, index));
+                }
+            }
+            if let Some(values) = values {
+                replaced.push((index, values));
+            }
+        }
+        for (index, values) in replaced {
+            self.replace_values(index, values)?;
+        }
+        self.lineage.record(format!("update(on = {on})"));
+        for &(row, index) in &written {
+            self.notify(ChangeEvent::CellUpdated {
+                row,
+                col: self.header[index].clone(),
+            });
+        }
+        Ok(written.len())
+    }
+    /// Appends a row holding `values`, one per column in order, each checked as in `set`.
+    /// Every column is rewritten, so build large frames with `from_records` instead
+    pub fn push_row(&mut self, values: &[DataValue]) -> Result<(), Error> {
+        if values.len() != self.header.len() {
+            return Err(miette!(
+                "The row has {} values, the Dataframe has {} columns",
+                values.len(),
+                self.header.len()
+            ));
+        }
+        let values = values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| self.checked_value(index, value.clone()))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let mut cols = Vec::with_capacity(values.len());
+        for (index, value) in values.into_iter().enumerate() {
+            let col = &self.cols[index];
+            let mut col_values = (0..self.n_rows)
+                .map(|r| col.data_as_value(r))
+                .collect::<Result<Vec<_>, Error>>()?;
+            col_values.push(value);
+            cols.push(col_values);
+        }
+        for (index, col_values) in cols.into_iter().enumerate() {
+            self.replace_values(index, col_values)?;
+        }
+        self.n_rows += 1;
+        self.extend_row_ids(1);
+        self.lineage.record("push_row".to_string());
+        self.notify(ChangeEvent::RowsAppended { count: 1 });
+        Ok(())
+    }
+    /// `push_row` with the values by column name, the columns left out get a null
+    pub fn push_row_map(&mut self, values: HashMap<&str, DataValue>) -> Result<(), Error> {
+        let mut row = vec![DataValue::Null; self.header.len()];
+        for (name, value) in values {
+            row[self.col_index(name)?] = value;
+        }
+        self.push_row(&row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::col_parser::{DType, DataValue};
+    use crate::csv_parser::Csv;
+
+    fn payloads() -> Csv {
+        let records = [r#"{"id": 1, "tag": "a"}"#, r#"{"id": 2, "tag": "b"}"#]
+            .map(|raw| vec![DataValue::from(raw)]);
+        let mut csv = Csv::from_records(records, &[("payload", DType::String)]).unwrap();
+        csv.unnest_json("payload").unwrap();
+        csv
+    }
+
+    #[test]
+    fn set_keeps_struct_type() {
+        let mut csv = payloads();
+        let value = DataValue::Struct(vec![
+            ("id".to_string(), DataValue::Integer(3)),
+            ("tag".to_string(), DataValue::from("c")),
+        ]);
+        csv.set(1, "payload", value.clone()).unwrap();
+        let col = csv.get_col("payload").unwrap();
+        assert_eq!(col.dtype(), DType::Struct);
+        assert_eq!(col.iter().nth(1), Some(value));
+    }
+
+    #[test]
+    fn push_row_keeps_struct_type() {
+        let mut csv = payloads();
+        csv.push_row(&[DataValue::Struct(vec![(
+            "id".to_string(),
+            DataValue::Integer(4),
+        )])])
+        .unwrap();
+        assert_eq!(csv.get_col("payload").unwrap().dtype(), DType::Struct);
+    }
+}
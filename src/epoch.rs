@@ -0,0 +1,39 @@
+use datetime::datetime::Datetime;
+
+// Detection only accepts values between 2000-01-01 and 2100-01-01, so small integers like ids
+// or counts aren't mistaken for timestamps
+const MIN_SECONDS: i64 = 946_684_800;
+const MAX_SECONDS: i64 = 4_102_444_800;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EpochUnit {
+    Seconds,
+    Millis,
+    Micros,
+}
+
+impl EpochUnit {
+    pub(crate) fn per_second(&self) -> i64 {
+        match self {
+            EpochUnit::Seconds => 1,
+            EpochUnit::Millis => 1_000,
+            EpochUnit::Micros => 1_000_000,
+        }
+    }
+    // Sub-second precision is dropped, `Datetime` counts whole seconds
+    pub fn to_datetime(&self, value: i64) -> Datetime {
+        Datetime::from_timestamp(value.div_euclid(self.per_second()))
+    }
+    /// Picks the unit under which every value lands in a plausible date range
+    pub fn detect(values: &[i64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        [EpochUnit::Seconds, EpochUnit::Millis, EpochUnit::Micros]
+            .into_iter()
+            .find(|unit| {
+                let range = MIN_SECONDS * unit.per_second()..MAX_SECONDS * unit.per_second();
+                values.iter().all(|v| range.contains(v))
+            })
+    }
+}
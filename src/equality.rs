@@ -0,0 +1,98 @@
+use std::hash::{Hash, Hasher};
+
+use crate::{
+    col_parser::{ColType, DataValue},
+    csv_parser::Csv,
+};
+
+// Bits of `value` under the float semantics of `DataValue`: every NaN is the same value and so
+// are both zeros
+pub(crate) fn float_bits(value: f64) -> u64 {
+    if value.is_nan() {
+        f64::NAN.to_bits()
+    } else if value == 0.0 {
+        0
+    } else {
+        value.to_bits()
+    }
+}
+
+/// Values are equal when they're the same variant holding the same value, `Integer(1)` isn't
+/// `Float(1.0)`. Floats compare by value except that NaN equals NaN, so the relation is a
+/// true equivalence and values can key sets and maps
+impl PartialEq for DataValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (DataValue::Float(a), DataValue::Float(b)) => float_bits(*a) == float_bits(*b),
+            (DataValue::Integer(a), DataValue::Integer(b)) => a == b,
+            (DataValue::Unsigned(a), DataValue::Unsigned(b)) => a == b,
+            (DataValue::String(a), DataValue::String(b)) => a == b,
+            (DataValue::DateTime(a), DataValue::DateTime(b)) => a.timestamp() == b.timestamp(),
+            #[cfg(feature = "decimal")]
+            (DataValue::Decimal(a), DataValue::Decimal(b)) => a == b,
+            #[cfg(feature = "complex")]
+            (DataValue::Complex(a), DataValue::Complex(b)) => {
+                float_bits(a.re) == float_bits(b.re) && float_bits(a.im) == float_bits(b.im)
+            }
+            #[cfg(feature = "bigint")]
+            (DataValue::BigInt(a), DataValue::BigInt(b)) => a == b,
+            (DataValue::List(a), DataValue::List(b)) => a == b,
+            (DataValue::Struct(a), DataValue::Struct(b)) => a == b,
+            (DataValue::Null, DataValue::Null) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for DataValue {}
+
+impl Hash for DataValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            DataValue::Float(value) => float_bits(*value).hash(state),
+            DataValue::Integer(value) => value.hash(state),
+            DataValue::Unsigned(value) => value.hash(state),
+            DataValue::String(value) => value.hash(state),
+            DataValue::DateTime(value) => value.timestamp().hash(state),
+            #[cfg(feature = "decimal")]
+            DataValue::Decimal(value) => value.hash(state),
+            #[cfg(feature = "complex")]
+            DataValue::Complex(value) => {
+                float_bits(value.re).hash(state);
+                float_bits(value.im).hash(state);
+            }
+            #[cfg(feature = "bigint")]
+            DataValue::BigInt(value) => value.hash(state),
+            DataValue::List(values) => values.hash(state),
+            DataValue::Struct(fields) => fields.hash(state),
+            DataValue::Null => {}
+        }
+    }
+}
+
+// Same name, type and values, however the values are stored: a view or a compressed column
+// equals the plain column it reads as
+impl PartialEq for ColType {
+    fn eq(&self, other: &Self) -> bool {
+        self.name() == other.name()
+            && self.dtype() == other.dtype()
+            && self.len() == other.len()
+            && (0..self.len()).all(|row| {
+                matches!(
+                    (self.data_as_value(row), other.data_as_value(row)),
+                    (Ok(a), Ok(b)) if a == b
+                )
+            })
+    }
+}
+
+/// Frames are equal when they have the same columns in the same order, with the same types
+/// and values. Units, metadata, lineage and display settings aren't compared
+impl PartialEq for Csv {
+    fn eq(&self, other: &Self) -> bool {
+        self.header == other.header
+            && self.n_rows == other.n_rows
+            && self.cols.iter().zip(&other.cols).all(|(a, b)| a == b)
+    }
+}
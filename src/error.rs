@@ -0,0 +1,120 @@
+use miette::Diagnostic;
+use thiserror::Error;
+
+use crate::embedded::ParseError;
+
+#[derive(Debug, Error, Diagnostic)]
+#[non_exhaustive]
+pub enum CoalaError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(
+        "Error parsing value `{value}` in column `{col}` at row {row}. String couldn't be converted safely."
+    )]
+    Parse {
+        row: usize,
+        col: String,
+        value: String,
+    },
+    #[error("Csv unexpectely ended")]
+    UnexpectedEof,
+    #[error("Column `{name}` not found in Dataframe")]
+    MissingColumn { name: String },
+    #[error("Column `{name}` already exists in Dataframe")]
+    DuplicateColumn { name: String },
+    #[error("Error in collumn named `{name}`. Invalid data type, couldn't match with any.")]
+    InvalidColType { name: String },
+    #[error("`{col}` invalid for calculations")]
+    InvalidType { col: String },
+    #[error("Index out of range for column")]
+    OutOfRange,
+    #[error("Quoted field {field} starting at line {line} is never closed")]
+    UnclosedQuote { line: usize, field: usize },
+    #[error("Unexpected `{found}` after the closing quote of field {field} at line {line}")]
+    TextAfterQuote {
+        line: usize,
+        field: usize,
+        found: char,
+    },
+    #[error("Record at row {row} has {found} fields, expected {expected}")]
+    RaggedRow {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    #[error("Number of lines to print (`{n}`) is greater than DataFrame len (`{len}`)")]
+    OutOfLines { n: usize, len: usize },
+    #[error("Columns `{left}` ({left_len}) and `{right}` ({right_len}) have different lengths")]
+    LengthMismatch {
+        left: String,
+        left_len: usize,
+        right: String,
+        right_len: usize,
+    },
+    #[error("Invalid quantile `{value}`, value must be between 0 and 1")]
+    InvalidQuantile { value: f64 },
+    #[error("Invalid trim proportion `{value}`, value must be at least 0 and below 0.5")]
+    InvalidTrim { value: f64 },
+    #[error("{left} and {right} values can't share a column")]
+    IncompatibleTypes { left: String, right: String },
+    #[error("Column `{col}` holds {expected} values, can't store a {found}")]
+    TypeMismatch {
+        col: String,
+        expected: String,
+        found: String,
+    },
+    #[error("Column `{col}` isn't sorted, row {row} is out of order")]
+    Unsorted { col: String, row: usize },
+    #[error(
+        "Column `{col}` is mostly numbers but holds {samples}, strict mode won't read it as text"
+    )]
+    NumericFallback { col: String, samples: String },
+    #[error("Every line has a single field, `{separator}` doesn't look like the separator")]
+    #[diagnostic(help(
+        "The first lines all hold {candidates}, try one of them with `CsvBuilder::separator`"
+    ))]
+    WrongSeparator {
+        separator: String,
+        candidates: String,
+    },
+    #[error("Column `{col}` holds {count} values outside {min}..={max}")]
+    #[diagnostic(help("First offending values: {samples}"))]
+    OutOfBounds {
+        col: String,
+        min: f64,
+        max: f64,
+        count: usize,
+        samples: String,
+    },
+    #[error("Column `{col}` holds {count} nulls")]
+    #[diagnostic(help("First null rows: {rows}"))]
+    NullValues {
+        col: String,
+        count: usize,
+        rows: String,
+    },
+    #[error("No rate to `{to}` for {codes}")]
+    #[diagnostic(help(
+        "Add rows with these currencies in `from` and `{to}` in `to` to the rates"
+    ))]
+    MissingRates { to: String, codes: String },
+    #[error("Column cannot be empty")]
+    EmptyColumn,
+    #[error("Sum of column `{col}` overflows")]
+    Overflow { col: String },
+}
+
+impl From<ParseError> for CoalaError {
+    fn from(error: ParseError) -> Self {
+        match error {
+            ParseError::UnclosedQuote { line, field } => CoalaError::UnclosedQuote { line, field },
+            ParseError::TextAfterQuote { line, field, found } => {
+                CoalaError::TextAfterQuote { line, field, found }
+            }
+            ParseError::InvalidUtf8 { .. } => CoalaError::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                error.to_string(),
+            )),
+        }
+    }
+}
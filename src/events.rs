@@ -0,0 +1,94 @@
+use std::{fmt, sync::Arc};
+
+use miette::Error;
+
+use crate::{csv_parser::Csv, mask::Mask};
+
+/// A change made in place to a frame, passed to the observers registered with `on_change`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeEvent {
+    /// Column `name` was appended, by `add_col`, `with_column` or `unflatten`/`flatten`
+    ColumnAdded { name: String },
+    /// Column `name` is gone, replaced by its fields or leaves in `flatten`/`unflatten`
+    ColumnRemoved { name: String },
+    /// Every value of column `name` may have changed, e.g. after `split` or `where_mask`
+    ColumnReplaced { name: String },
+    /// `retain` dropped `removed` rows and kept `kept`
+    RowsFiltered { kept: usize, removed: usize },
+    /// `count` rows were appended at the end
+    RowsAppended { count: usize },
+    /// The cell at `row` of column `col` was written by `set` or `update`
+    CellUpdated { row: usize, col: String },
+}
+
+/// Handle of an observer, to remove it with `remove_observer`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObserverId(usize);
+
+type Observer = Arc<dyn Fn(&ChangeEvent) + Send + Sync>;
+
+// Observers watch one frame: a clone, or a frame derived from it, starts without any
+#[derive(Default)]
+pub(crate) struct Observers {
+    next_id: usize,
+    observers: Vec<(ObserverId, Observer)>,
+}
+
+impl Clone for Observers {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Observers({})", self.observers.len())
+    }
+}
+
+impl Csv {
+    /// Calls `observer` with every change made in place to this frame from now on, after the
+    /// change is made. Lets a GUI or a cache update what changed instead of rescanning the frame
+    pub fn on_change(
+        &mut self,
+        observer: impl Fn(&ChangeEvent) + Send + Sync + 'static,
+    ) -> ObserverId {
+        let id = ObserverId(self.observers.next_id);
+        self.observers.next_id += 1;
+        self.observers.observers.push((id, Arc::new(observer)));
+        id
+    }
+    /// Stops calling the observer `id`, `false` when it wasn't registered
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        let before = self.observers.observers.len();
+        self.observers.observers.retain(|(other, _)| *other != id);
+        self.observers.observers.len() != before
+    }
+    pub(crate) fn notify(&self, event: ChangeEvent) {
+        for (_, observer) in &self.observers.observers {
+            observer(&event);
+        }
+    }
+    // Columns added and removed going from header `before` to the current one
+    pub(crate) fn notify_header_change(&self, before: &[String]) {
+        for name in before.iter().filter(|name| !self.header.contains(name)) {
+            self.notify(ChangeEvent::ColumnRemoved { name: name.clone() });
+        }
+        for name in self.header.iter().filter(|name| !before.contains(name)) {
+            self.notify(ChangeEvent::ColumnAdded { name: name.clone() });
+        }
+    }
+    /// `filter` in place, dropping the rows where `mask` isn't set
+    pub fn retain(&mut self, mask: &Mask) -> Result<(), Error> {
+        let filtered = self.filter(mask)?;
+        let kept = filtered.n_rows;
+        let removed = self.n_rows - kept;
+        self.cols = filtered.cols;
+        self.n_rows = kept;
+        self.row_ids = filtered.row_ids;
+        self.lineage = filtered.lineage;
+        self.cache.clear();
+        self.notify(ChangeEvent::RowsFiltered { kept, removed });
+        Ok(())
+    }
+}
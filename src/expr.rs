@@ -0,0 +1,211 @@
+use std::{cmp::Ordering, ops::Not};
+
+use miette::{miette, Error};
+
+use crate::{col_parser::DataValue, csv_parser::Csv, mask::Mask};
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Col(String),
+    Lit(DataValue),
+    Compare(Box<Expr>, CmpOp, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    When {
+        branches: Vec<(Expr, Expr)>,
+        otherwise: Box<Expr>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl CmpOp {
+    fn matches(self, ordering: Ordering) -> bool {
+        match self {
+            CmpOp::Eq => ordering.is_eq(),
+            CmpOp::Ne => ordering.is_ne(),
+            CmpOp::Gt => ordering.is_gt(),
+            CmpOp::Ge => ordering.is_ge(),
+            CmpOp::Lt => ordering.is_lt(),
+            CmpOp::Le => ordering.is_le(),
+        }
+    }
+}
+
+pub fn col(name: &str) -> Expr {
+    Expr::Col(name.to_string())
+}
+
+pub fn lit(value: impl Into<DataValue>) -> Expr {
+    Expr::Lit(value.into())
+}
+
+pub fn when(cond: Expr) -> When {
+    When {
+        branches: Vec::new(),
+        cond,
+    }
+}
+
+pub struct When {
+    branches: Vec<(Expr, Expr)>,
+    cond: Expr,
+}
+
+impl When {
+    pub fn then(mut self, value: impl Into<Expr>) -> Then {
+        self.branches.push((self.cond, value.into()));
+        Then {
+            branches: self.branches,
+        }
+    }
+}
+
+pub struct Then {
+    branches: Vec<(Expr, Expr)>,
+}
+
+impl Then {
+    pub fn when(self, cond: Expr) -> When {
+        When {
+            branches: self.branches,
+            cond,
+        }
+    }
+    pub fn otherwise(self, value: impl Into<Expr>) -> Expr {
+        Expr::When {
+            branches: self.branches,
+            otherwise: Box::new(value.into()),
+        }
+    }
+}
+
+impl Expr {
+    fn compare(self, op: CmpOp, other: impl Into<Expr>) -> Expr {
+        Expr::Compare(Box::new(self), op, Box::new(other.into()))
+    }
+    pub fn eq(self, other: impl Into<Expr>) -> Expr {
+        self.compare(CmpOp::Eq, other)
+    }
+    pub fn ne(self, other: impl Into<Expr>) -> Expr {
+        self.compare(CmpOp::Ne, other)
+    }
+    pub fn gt(self, other: impl Into<Expr>) -> Expr {
+        self.compare(CmpOp::Gt, other)
+    }
+    pub fn ge(self, other: impl Into<Expr>) -> Expr {
+        self.compare(CmpOp::Ge, other)
+    }
+    pub fn lt(self, other: impl Into<Expr>) -> Expr {
+        self.compare(CmpOp::Lt, other)
+    }
+    pub fn le(self, other: impl Into<Expr>) -> Expr {
+        self.compare(CmpOp::Le, other)
+    }
+    /// `lt`, reading better on dates
+    pub fn before(self, other: impl Into<Expr>) -> Expr {
+        self.lt(other)
+    }
+    /// `gt`, reading better on dates
+    pub fn after(self, other: impl Into<Expr>) -> Expr {
+        self.gt(other)
+    }
+    pub fn and(self, other: Expr) -> Expr {
+        Expr::And(Box::new(self), Box::new(other))
+    }
+    pub fn or(self, other: Expr) -> Expr {
+        Expr::Or(Box::new(self), Box::new(other))
+    }
+    fn is_predicate(&self) -> bool {
+        matches!(
+            self,
+            Expr::Compare(..) | Expr::And(..) | Expr::Or(..) | Expr::Not(_)
+        )
+    }
+    fn values(&self, csv: &Csv) -> Result<Vec<DataValue>, Error> {
+        match self {
+            Expr::Col(name) => Ok(csv.get_col(name)?.iter().collect()),
+            Expr::Lit(value) => Ok(vec![value.clone(); csv.n_rows]),
+            Expr::When {
+                branches,
+                otherwise,
+            } => {
+                let mut result = otherwise.values(csv)?;
+                // Earlier branches win, so they're applied last
+                for (cond, value) in branches.iter().rev() {
+                    let cond = cond.mask(csv)?;
+                    let value = value.values(csv)?;
+                    for (i, v) in value.into_iter().enumerate() {
+                        if cond.get(i) == Some(true) {
+                            result[i] = v;
+                        }
+                    }
+                }
+                Ok(result)
+            }
+            predicate => Err(miette!(
+                "`{predicate:?}` evaluates to a mask, not to column values"
+            )),
+        }
+    }
+    fn mask(&self, csv: &Csv) -> Result<Mask, Error> {
+        match self {
+            Expr::Compare(left, op, right) => {
+                let (left, right) = (left.values(csv)?, right.values(csv)?);
+                Ok(left
+                    .iter()
+                    .zip(&right)
+                    .map(|(l, r)| l.compare(r).is_some_and(|o| op.matches(o)))
+                    .collect())
+            }
+            Expr::And(left, right) => Ok(left.mask(csv)? & &right.mask(csv)?),
+            Expr::Or(left, right) => Ok(left.mask(csv)? | &right.mask(csv)?),
+            Expr::Not(inner) => Ok(!inner.mask(csv)?),
+            value => Err(miette!("`{value:?}` isn't a condition")),
+        }
+    }
+}
+
+impl Not for Expr {
+    type Output = Expr;
+
+    fn not(self) -> Expr {
+        Expr::Not(Box::new(self))
+    }
+}
+
+impl<T: Into<DataValue>> From<T> for Expr {
+    fn from(value: T) -> Self {
+        Expr::Lit(value.into())
+    }
+}
+
+impl Csv {
+    pub fn eval(&self, expr: &Expr) -> Result<Vec<DataValue>, Error> {
+        expr.values(self)
+    }
+    pub fn eval_mask(&self, expr: &Expr) -> Result<Mask, Error> {
+        if !expr.is_predicate() {
+            return Err(miette!("`{expr:?}` isn't a condition"));
+        }
+        expr.mask(self)
+    }
+    /// View of the rows where `condition` holds. Rows where it compares with a null are
+    /// dropped
+    pub fn filter_where(&self, condition: &Expr) -> Result<Csv, Error> {
+        self.filter(&self.eval_mask(condition)?)
+    }
+    pub fn with_column(&mut self, name: &str, expr: &Expr) -> Result<(), Error> {
+        let values = self.eval(expr)?;
+        self.add_col(name, values)
+    }
+}
@@ -0,0 +1,385 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+use datetime::datetime::Datetime;
+use miette::{miette, Error};
+
+use crate::{
+    agg::{Accumulator, Agg, AggExpr},
+    col_parser::{ColType, CsvCol, DataValue},
+    csv_parser::Csv,
+    error::CoalaError,
+    perf::Timer,
+    timeseries::step_seconds,
+};
+
+/// Rows of a frame split by the values of its key columns, from `Csv::group_by`. Groups come
+/// in order of first appearance
+pub struct GroupBy<'a> {
+    csv: &'a Csv,
+    keys: Vec<String>,
+    groups: Vec<Vec<usize>>,
+    windows: Option<Windows>,
+}
+
+// The time window of each group, from `Csv::group_by_dynamic`
+struct Windows {
+    index: String,
+    every: i64,
+    period: i64,
+    starts: Vec<i64>,
+}
+
+/// The key columns of `Csv::group_by`: one name, or a slice or array of names
+pub trait GroupKeys {
+    fn names(&self) -> Vec<String>;
+}
+
+impl GroupKeys for &str {
+    fn names(&self) -> Vec<String> {
+        vec![self.to_string()]
+    }
+}
+
+impl GroupKeys for &[&str] {
+    fn names(&self) -> Vec<String> {
+        self.iter().map(|name| name.to_string()).collect()
+    }
+}
+
+impl<const N: usize> GroupKeys for &[&str; N] {
+    fn names(&self) -> Vec<String> {
+        self.as_slice().names()
+    }
+}
+
+// Statistics of every numeric column in `describe`, with the suffix or row name they get
+pub(crate) const DESCRIBE: [(&str, Agg); 8] = [
+    ("count", Agg::Count),
+    ("mean", Agg::Mean),
+    ("std", Agg::Std),
+    ("min", Agg::Min),
+    ("25%", Agg::Quantile(0.25)),
+    ("50%", Agg::Median),
+    ("75%", Agg::Quantile(0.75)),
+    ("max", Agg::Max),
+];
+
+impl Csv {
+    /// Splits the rows by the values of `by`, a column or several, whatever their types.
+    /// Nulls match each other, so they form groups of their own. The grouped frames get a
+    /// column per key
+    pub fn group_by(&self, by: impl GroupKeys) -> Result<GroupBy<'_>, Error> {
+        let keys = by.names();
+        if keys.is_empty() {
+            return Err(miette!("Grouping needs at least one key column"));
+        }
+        let _timer = Timer::start(|| format!("Grouping by {keys:?}"));
+        let names: Vec<&str> = keys.iter().map(String::as_str).collect();
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        let mut lookup = HashMap::new();
+        for (row, key) in self.row_keys(&names)?.into_iter().enumerate() {
+            let group = *lookup.entry(key).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[group].push(row);
+        }
+        Ok(GroupBy {
+            csv: self,
+            keys,
+            groups,
+            windows: None,
+        })
+    }
+    /// Groups by `by`, then splits every group into windows of `period` on the datetime
+    /// column `index`, a window starting every `every` counting from the Unix epoch. The
+    /// first window of a group is the one holding its earliest row. A row lands in each
+    /// window covering it, rows with a null `index` in none, and windows without rows are
+    /// left out. The grouped frames hold each window's start in a column
+    /// named `index`, after the keys
+    pub fn group_by_dynamic(
+        &self,
+        by: impl GroupKeys,
+        index: &str,
+        every: Duration,
+        period: Duration,
+    ) -> Result<GroupBy<'_>, Error> {
+        let (every, period) = (step_seconds(every)?, step_seconds(period)?);
+        let timestamps = self.timestamps(index)?;
+        let by_key = self.group_by(by)?;
+        let (mut groups, mut starts) = (Vec::new(), Vec::new());
+        for rows in by_key.groups {
+            let Some(earliest) = rows.iter().filter_map(|&row| timestamps[row]).min() else {
+                continue;
+            };
+            let mut windows: BTreeMap<i64, Vec<usize>> = BTreeMap::new();
+            for row in rows {
+                let Some(timestamp) = timestamps[row] else {
+                    continue;
+                };
+                // Windows starting after `timestamp - period` and no later than `timestamp`
+                let first =
+                    ((timestamp - period).div_euclid(every) + 1).max(earliest.div_euclid(every));
+                for window in first..=timestamp.div_euclid(every) {
+                    windows.entry(window * every).or_default().push(row);
+                }
+            }
+            for (start, rows) in windows {
+                starts.push(start);
+                groups.push(rows);
+            }
+        }
+        Ok(GroupBy {
+            csv: self,
+            keys: by_key.keys,
+            groups,
+            windows: Some(Windows {
+                index: index.to_string(),
+                every,
+                period,
+                starts,
+            }),
+        })
+    }
+}
+
+impl GroupBy<'_> {
+    pub fn n_groups(&self) -> usize {
+        self.groups.len()
+    }
+    /// Orders the rows within each group by `name` ascending, ties keeping row order. Sets
+    /// what `Agg::First`, `Last` and `Nth` pick, e.g. the latest record per user
+    pub fn order_by(mut self, name: &str) -> Result<Self, Error> {
+        let mut rank = vec![0; self.csv.n_rows];
+        for (position, row) in self.csv.get_col(name)?.argsort().into_iter().enumerate() {
+            rank[row] = position;
+        }
+        for rows in &mut self.groups {
+            rows.sort_by_key(|&row| rank[row]);
+        }
+        Ok(self)
+    }
+    /// Rows of each group, in the same order as the rows of the aggregated frames
+    pub fn groups(&self) -> &[Vec<usize>] {
+        &self.groups
+    }
+    // How the rows were grouped, for the lineage of the frames built from the groups
+    fn operation(&self) -> String {
+        let keys = self.keys.join(", ");
+        match &self.windows {
+            Some(windows) => format!(
+                "group_by_dynamic({keys}, {}, {}s, {}s)",
+                windows.index, windows.every, windows.period
+            ),
+            None => format!("group_by({keys})"),
+        }
+    }
+    // The rows `pick` keeps of each group, one group after the other
+    fn pick_rows(&self, operation: String, pick: impl Fn(&[usize]) -> &[usize]) -> Csv {
+        let rows: Vec<usize> = self
+            .groups
+            .iter()
+            .flat_map(|rows| pick(rows).iter().copied())
+            .collect();
+        let mut csv = self.csv.take_rows(&rows);
+        csv.lineage
+            .record(format!("{}.{operation}", self.operation()));
+        csv
+    }
+    /// The first `n` rows of every group, in row order or the order of `order_by`. Groups
+    /// follow each other in order of first appearance
+    pub fn head(&self, n: usize) -> Csv {
+        self.pick_rows(format!("head({n})"), |rows| &rows[..n.min(rows.len())])
+    }
+    /// The last `n` rows of every group, like `head`. After `order_by` these are the `n`
+    /// largest, e.g. the best selling products per category
+    pub fn tail(&self, n: usize) -> Csv {
+        self.pick_rows(format!("tail({n})"), |rows| {
+            &rows[rows.len().saturating_sub(n)..]
+        })
+    }
+    // `agg` of `name` over every group
+    fn aggregate(&self, name: &str, agg: Agg) -> Result<Vec<DataValue>, Error> {
+        agg.validate()?;
+        let col = self.csv.get_col(name)?;
+        if agg.is_numeric() && !col.is_numeric() {
+            return Err(CoalaError::InvalidType {
+                col: name.to_string(),
+            }
+            .into());
+        }
+        self.groups
+            .iter()
+            .map(|rows| {
+                let mut accumulator = Accumulator::new(agg);
+                for &row in rows {
+                    accumulator.push_value(&col.get(row)?);
+                }
+                Ok(accumulator.finish())
+            })
+            .collect()
+    }
+    // One row per group: the keys, then a column per `(name, agg, output name)`
+    fn frame(&self, outputs: Vec<(&str, Agg, String)>) -> Result<Csv, Error> {
+        let firsts: Vec<usize> = self.groups.iter().map(|rows| rows[0]).collect();
+        let mut cols = Vec::with_capacity(self.keys.len() + outputs.len());
+        for key in &self.keys {
+            cols.push(self.csv.cols[self.csv.col_index(key)?].take(&firsts));
+        }
+        let mut header = self.keys.clone();
+        if let Some(windows) = &self.windows {
+            let starts = windows.starts.iter().map(|&s| Datetime::from_timestamp(s));
+            cols.push(ColType::Datetime(CsvCol::new(
+                windows.index.clone(),
+                starts.collect(),
+            )));
+            header.push(windows.index.clone());
+        }
+        for (name, agg, output) in outputs {
+            if header.contains(&output) {
+                return Err(CoalaError::DuplicateColumn { name: output }.into());
+            }
+            let values = self.aggregate(name, agg)?;
+            cols.push(ColType::from_data_values(values, output.clone())?);
+            header.push(output);
+        }
+        let mut csv = Csv::from_cols(cols, header, self.groups.len());
+        csv.lineage = self.csv.lineage.clone();
+        csv.lineage.record(self.operation());
+        Ok(csv)
+    }
+    /// One row per group holding its key and a column per aggregation, named by its alias
+    /// or like `price_mean`
+    pub fn agg(&self, aggs: &[AggExpr]) -> Result<Csv, Error> {
+        self.frame(
+            aggs.iter()
+                .map(|expr| (expr.col.as_str(), expr.agg, expr.output_name()))
+                .collect(),
+        )
+    }
+    // The columns besides the keys, only the numeric ones if `numeric`
+    fn value_cols(&self, numeric: bool) -> Result<Vec<&str>, Error> {
+        let mut names = Vec::new();
+        for name in &self.csv.header {
+            let is_window = self.windows.as_ref().is_some_and(|w| &w.index == name);
+            if self.keys.contains(name)
+                || is_window
+                || numeric && !self.csv.get_col(name)?.is_numeric()
+            {
+                continue;
+            }
+            names.push(name.as_str());
+        }
+        Ok(names)
+    }
+    /// `agg` of every column besides the keys, only the numeric ones for a numeric
+    /// aggregation. The columns keep their names
+    pub fn agg_each(&self, agg: Agg) -> Result<Csv, Error> {
+        let outputs = self
+            .value_cols(agg.is_numeric())?
+            .into_iter()
+            .map(|name| (name, agg, name.to_string()))
+            .collect();
+        self.frame(outputs)
+    }
+    /// Values that aren't null in each column, per group
+    pub fn count(&self) -> Result<Csv, Error> {
+        self.agg_each(Agg::Count)
+    }
+    pub fn mean(&self) -> Result<Csv, Error> {
+        self.agg_each(Agg::Mean)
+    }
+    pub fn median(&self) -> Result<Csv, Error> {
+        self.agg_each(Agg::Median)
+    }
+    pub fn quantile(&self, quantile: f64) -> Result<Csv, Error> {
+        self.agg_each(Agg::Quantile(quantile))
+    }
+    pub fn std(&self) -> Result<Csv, Error> {
+        self.agg_each(Agg::Std)
+    }
+    /// Count, mean, standard deviation, minimum, quartiles and maximum of every numeric
+    /// column besides the keys, one row per group
+    pub fn describe(&self) -> Result<Csv, Error> {
+        let mut outputs = Vec::new();
+        for name in self.value_cols(true)? {
+            for (suffix, agg) in DESCRIBE {
+                outputs.push((name, agg, format!("{name}_{suffix}")));
+            }
+        }
+        self.frame(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::col_parser::DType;
+
+    fn sales() -> Csv {
+        let rows = [
+            ("b", Some(1)),
+            ("a", Some(2)),
+            ("b", Some(3)),
+            ("", Some(4)),
+            ("", None),
+        ];
+        let records = rows.map(|(key, value)| {
+            let key = if key.is_empty() {
+                DataValue::Null
+            } else {
+                DataValue::from(key)
+            };
+            vec![key, value.map_or(DataValue::Null, DataValue::Integer)]
+        });
+        Csv::from_records(records, &[("k", DType::String), ("v", DType::Integer)]).unwrap()
+    }
+
+    fn values(csv: &Csv, name: &str) -> Vec<DataValue> {
+        csv.get_col(name).unwrap().iter().collect()
+    }
+
+    #[test]
+    fn groups_follow_first_appearance_and_nulls_group_together() {
+        let csv = sales();
+        let grouped = csv.group_by("k").unwrap();
+        assert_eq!(grouped.groups(), [vec![0, 2], vec![1], vec![3, 4]]);
+    }
+
+    #[test]
+    fn aggregations_get_a_column_each() {
+        let csv = sales();
+        let summed = csv
+            .group_by("k")
+            .unwrap()
+            .agg(&[Agg::Sum.of("v").alias("total"), Agg::Count.of("v")])
+            .unwrap();
+        assert_eq!(summed.header, ["k", "total", "v_count"]);
+        assert_eq!(
+            values(&summed, "k")[..2],
+            [DataValue::from("b"), DataValue::from("a")]
+        );
+        let totals: Vec<Option<f64>> = values(&summed, "total")
+            .iter()
+            .map(|v| v.as_f64())
+            .collect();
+        assert_eq!(totals, [Some(4.0), Some(2.0), Some(4.0)]);
+        assert_eq!(values(&summed, "v_count")[2], DataValue::Integer(1));
+    }
+
+    #[test]
+    fn ordered_tail_keeps_the_largest() {
+        let csv = sales();
+        let top = csv.group_by("k").unwrap().order_by("v").unwrap().tail(1);
+        assert_eq!(values(&top, "v")[0], DataValue::Integer(3));
+    }
+
+    #[test]
+    fn grouping_needs_a_key() {
+        let keys: &[&str] = &[];
+        assert!(sales().group_by(keys).is_err());
+    }
+}
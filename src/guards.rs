@@ -0,0 +1,75 @@
+use miette::Error;
+
+use crate::{col_parser::DataValue, csv_parser::Csv, error::CoalaError};
+
+// Offending rows quoted by a failed guard, the count covers the rest
+const SAMPLE_ROWS: usize = 5;
+
+impl Csv {
+    /// Fails when a value of `name` falls outside `min..=max`, with the number of such values
+    /// and the first rows holding them. Nulls pass, `assert_non_null` guards against them
+    pub fn assert_in_range(
+        &self,
+        name: &str,
+        min: impl Into<f64>,
+        max: impl Into<f64>,
+    ) -> Result<(), Error> {
+        let (min, max) = (min.into(), max.into());
+        let col = self.get_col(name)?;
+        if !col.is_numeric() {
+            return Err(CoalaError::InvalidType {
+                col: name.to_string(),
+            }
+            .into());
+        }
+        let mut count = 0;
+        let mut samples = Vec::new();
+        for (row, value) in col.iter().enumerate() {
+            if matches!(value, DataValue::Null)
+                || value.as_f64().is_some_and(|v| (min..=max).contains(&v))
+            {
+                continue;
+            }
+            count += 1;
+            if samples.len() < SAMPLE_ROWS {
+                samples.push(format!("row {row} (`{value}`)"));
+            }
+        }
+        if count == 0 {
+            return Ok(());
+        }
+        Err(CoalaError::OutOfBounds {
+            col: name.to_string(),
+            min,
+            max,
+            count,
+            samples: samples.join(", "),
+        }
+        .into())
+    }
+    /// Fails on the first column of `cols`, every column if it's empty, holding a null, with
+    /// the number of nulls and the first rows holding them
+    pub fn assert_non_null(&self, cols: &[&str]) -> Result<(), Error> {
+        for name in self.subset_names(cols) {
+            let col = self.get_col(name)?;
+            let count = col.null_count();
+            if count == 0 {
+                continue;
+            }
+            let rows: Vec<String> = col
+                .iter()
+                .enumerate()
+                .filter(|(_, value)| matches!(value, DataValue::Null))
+                .take(SAMPLE_ROWS)
+                .map(|(row, _)| row.to_string())
+                .collect();
+            return Err(CoalaError::NullValues {
+                col: name.to_string(),
+                count,
+                rows: rows.join(", "),
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use miette::Error;
+
+use crate::{
+    bootstrap::interpolate,
+    csv_parser::{ColViewer, Csv},
+    error::CoalaError,
+    statistics::RunningStats,
+};
+
+/// How the bin edges of a histogram are chosen
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bins {
+    /// This many bins of equal width between the minimum and the maximum
+    Count(usize),
+    /// `log2(n) + 1` bins, fine for roughly normal data
+    Sturges,
+    /// Width from the interquartile range, robust to outliers and heavy tails
+    FreedmanDiaconis,
+    /// Width from the standard deviation
+    Scott,
+    /// This many bins of equal width in log space, for positive data spanning several orders
+    /// of magnitude
+    Log(usize),
+    /// Explicit ascending edges, values outside them aren't counted
+    Edges(Vec<f64>),
+}
+
+/// `counts[i]` is the total weight of the values in `edges[i]..edges[i + 1]`, the last bin
+/// also holding its upper edge. Without weights every value weighs 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pub edges: Vec<f64>,
+    pub counts: Vec<f64>,
+}
+
+fn linear_edges(min: f64, max: f64, n: usize) -> Vec<f64> {
+    let width = (max - min) / n as f64;
+    (0..=n).map(|i| min + width * i as f64).collect()
+}
+
+fn sturges(n: f64) -> usize {
+    n.log2().ceil() as usize + 1
+}
+
+// Bins of `width`, never fewer than one
+fn count_for_width(min: f64, max: f64, width: f64) -> usize {
+    if width > 0.0 {
+        ((max - min) / width).ceil().max(1.0) as usize
+    } else {
+        1
+    }
+}
+
+impl Bins {
+    // `sorted` is non-empty and finite
+    pub(crate) fn edges(&self, sorted: &[f64]) -> Result<Vec<f64>, Error> {
+        let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+        // A single value still gets a bin wide enough to hold it
+        let (min, max) = if min == max {
+            (min - 0.5, max + 0.5)
+        } else {
+            (min, max)
+        };
+        let n = sorted.len() as f64;
+        let count = match self {
+            Bins::Count(0) | Bins::Log(0) => return Err(CoalaError::OutOfRange.into()),
+            Bins::Count(count) => *count,
+            Bins::Sturges => sturges(n),
+            Bins::FreedmanDiaconis => {
+                let iqr = interpolate(sorted, 0.75) - interpolate(sorted, 0.25);
+                // Falls back to Sturges when most values are equal
+                if iqr > 0.0 {
+                    count_for_width(min, max, 2.0 * iqr / n.cbrt())
+                } else {
+                    sturges(n)
+                }
+            }
+            Bins::Scott => {
+                let stddev = sorted
+                    .iter()
+                    .copied()
+                    .collect::<RunningStats>()
+                    .stddev()
+                    .unwrap_or(0.0);
+                count_for_width(min, max, 3.49 * stddev / n.cbrt())
+            }
+            Bins::Log(count) => {
+                if min <= 0.0 {
+                    return Err(CoalaError::OutOfRange.into());
+                }
+                let edges = linear_edges(min.ln(), max.ln(), *count);
+                return Ok(edges.into_iter().map(f64::exp).collect());
+            }
+            Bins::Edges(edges) => {
+                if edges.len() < 2 || edges.windows(2).any(|pair| pair[0] >= pair[1]) {
+                    return Err(CoalaError::OutOfRange.into());
+                }
+                return Ok(edges.clone());
+            }
+        };
+        Ok(linear_edges(min, max, count))
+    }
+    // `None` for values outside explicit edges
+    pub(crate) fn bin_of(&self, edges: &[f64], value: f64) -> Option<usize> {
+        let last = edges[edges.len() - 1];
+        // Rounding in the log or linear edges can leave the extremes a hair outside
+        let value = match self {
+            Bins::Edges(_) => value,
+            _ => value.clamp(edges[0], last),
+        };
+        if value < edges[0] || value > last {
+            return None;
+        }
+        let bin = edges
+            .partition_point(|&edge| edge <= value)
+            .saturating_sub(1);
+        Some(bin.min(edges.len() - 2))
+    }
+    // Bin of each value, `None` for the missing ones and those outside explicit edges
+    pub(crate) fn assign(&self, values: &[Option<f64>]) -> Result<Vec<Option<usize>>, Error> {
+        let mut sorted: Vec<f64> = values.iter().flatten().copied().collect();
+        if sorted.is_empty() {
+            return Err(CoalaError::EmptyColumn.into());
+        }
+        sorted.sort_unstable_by(f64::total_cmp);
+        let edges = self.edges(&sorted)?;
+        Ok(values
+            .iter()
+            .map(|value| self.bin_of(&edges, (*value)?))
+            .collect())
+    }
+}
+
+impl Histogram {
+    fn build(values: &[(f64, f64)], bins: &Bins) -> Result<Self, Error> {
+        let mut sorted: Vec<f64> = values.iter().map(|&(value, _)| value).collect();
+        if sorted.is_empty() {
+            return Err(CoalaError::EmptyColumn.into());
+        }
+        sorted.sort_unstable_by(f64::total_cmp);
+        let edges = bins.edges(&sorted)?;
+        let mut counts = vec![0.0; edges.len() - 1];
+        for &(value, weight) in values {
+            if let Some(bin) = bins.bin_of(&edges, value) {
+                counts[bin] += weight;
+            }
+        }
+        Ok(Self { edges, counts })
+    }
+}
+
+impl ColViewer<'_> {
+    // Finite numeric values, as f64
+    pub(crate) fn finite_values(&self) -> Result<Vec<Option<f64>>, Error> {
+        if !self.is_numeric() {
+            return Err(CoalaError::InvalidType {
+                col: self.name().to_string(),
+            }
+            .into());
+        }
+        Ok(self
+            .iter()
+            .map(|v| v.as_f64().filter(|x| x.is_finite()))
+            .collect())
+    }
+    /// Counts of the column's values per bin. Values that aren't finite are left out
+    pub fn histogram(&self, bins: &Bins) -> Result<Histogram, Error> {
+        let values: Vec<(f64, f64)> = self
+            .finite_values()?
+            .into_iter()
+            .flatten()
+            .map(|value| (value, 1.0))
+            .collect();
+        Histogram::build(&values, bins)
+    }
+}
+
+impl Csv {
+    /// Histogram of `name` where each row adds its value of `weights` to its bin instead of 1.
+    /// Bin widths are chosen from the unweighted values
+    pub fn weighted_histogram(
+        &self,
+        name: &str,
+        weights: &str,
+        bins: &Bins,
+    ) -> Result<Histogram, Error> {
+        let values = self.get_col(name)?.finite_values()?;
+        let weights = self.get_col(weights)?.finite_values()?;
+        let pairs: Vec<(f64, f64)> = values
+            .into_iter()
+            .zip(weights)
+            .filter_map(|(value, weight)| Some((value?, weight?)))
+            .collect();
+        Histogram::build(&pairs, bins)
+    }
+}
@@ -0,0 +1,173 @@
+use std::str::FromStr;
+
+use miette::{miette, Error};
+
+use crate::{col_parser::DataValue, csv_parser::Csv};
+
+// Key of the column metadata holding the display format, so it follows the column through
+// selections, filters and sidecar files like the rest of its metadata
+const META_KEY: &str = "display";
+
+const BYTE_UNITS: [&str; 7] = ["B", "kB", "MB", "GB", "TB", "PB", "EB"];
+const COUNT_SUFFIXES: [&str; 7] = ["", "k", "M", "G", "T", "P", "E"];
+
+// Units of durations from a minute up, largest first
+const DURATION_PARTS: [(&str, f64); 4] = [("d", 86_400.0), ("h", 3_600.0), ("m", 60.0), ("s", 1.0)];
+// Units of durations under a second, largest first
+const SUBSECOND_UNITS: [(&str, f64); 3] = [("ms", 1e-3), ("µs", 1e-6), ("ns", 1e-9)];
+
+/// Unit of the values of a duration column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DurationUnit {
+    Seconds,
+    Millis,
+    Micros,
+    Nanos,
+}
+
+impl DurationUnit {
+    fn seconds(&self) -> f64 {
+        match self {
+            DurationUnit::Seconds => 1.0,
+            DurationUnit::Millis => 1e-3,
+            DurationUnit::Micros => 1e-6,
+            DurationUnit::Nanos => 1e-9,
+        }
+    }
+}
+
+/// How `head` and `describe` print the values of a numeric column, instead of the raw number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayFormat {
+    /// Byte counts in decimal units, e.g. `1.2 GB`
+    Bytes,
+    /// Large counts with a metric suffix, e.g. `3.4 M`
+    Count,
+    /// Durations in the two largest units, e.g. `2h 31m`
+    Duration(DurationUnit),
+}
+
+// `value` scaled down by 1000 until under 1000, with the suffix of the step it stopped at
+fn scaled(value: f64, suffixes: &[&str]) -> String {
+    let mut scaled = value;
+    let mut step = 0;
+    while scaled.abs() >= 1000.0 && step + 1 < suffixes.len() {
+        scaled /= 1000.0;
+        step += 1;
+    }
+    match step {
+        0 => format!("{value} {}", suffixes[0]),
+        _ => format!("{scaled:.1} {}", suffixes[step]),
+    }
+    .trim_end()
+    .to_string()
+}
+
+fn duration(seconds: f64) -> String {
+    let sign = if seconds < 0.0 { "-" } else { "" };
+    let seconds = seconds.abs();
+    if seconds == 0.0 {
+        return "0s".to_string();
+    }
+    if seconds < 1.0 {
+        let (unit, size) = SUBSECOND_UNITS
+            .into_iter()
+            .find(|(_, size)| seconds >= *size)
+            .unwrap_or(SUBSECOND_UNITS[SUBSECOND_UNITS.len() - 1]);
+        return format!("{sign}{}{unit}", round_tenths(seconds / size));
+    }
+    if seconds < 60.0 {
+        return format!("{sign}{}s", round_tenths(seconds));
+    }
+    // Whole seconds from here, the largest two units are shown
+    let mut rest = seconds.round();
+    let mut parts = Vec::new();
+    for (unit, size) in DURATION_PARTS {
+        let amount = (rest / size).floor();
+        rest -= amount * size;
+        if amount > 0.0 || !parts.is_empty() {
+            parts.push((amount, unit));
+        }
+    }
+    let shown: Vec<String> = parts
+        .iter()
+        .take(2)
+        .filter(|(amount, _)| *amount > 0.0)
+        .map(|(amount, unit)| format!("{amount}{unit}"))
+        .collect();
+    format!("{sign}{}", shown.join(" "))
+}
+
+// Tenths are dropped when they're zero, `1.5` but `2`
+fn round_tenths(value: f64) -> String {
+    let rounded = (value * 10.0).round() / 10.0;
+    format!("{rounded}")
+}
+
+impl DisplayFormat {
+    /// `value` as this format prints it. Nulls and values that aren't numbers print as usual
+    pub fn render(&self, value: &DataValue) -> String {
+        let Some(number) = value.as_f64().filter(|n| n.is_finite()) else {
+            return value.to_string();
+        };
+        match self {
+            DisplayFormat::Bytes => scaled(number, &BYTE_UNITS),
+            DisplayFormat::Count => scaled(number, &COUNT_SUFFIXES),
+            DisplayFormat::Duration(unit) => duration(number * unit.seconds()),
+        }
+    }
+}
+
+impl std::fmt::Display for DisplayFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            DisplayFormat::Bytes => "bytes",
+            DisplayFormat::Count => "count",
+            DisplayFormat::Duration(DurationUnit::Seconds) => "duration_s",
+            DisplayFormat::Duration(DurationUnit::Millis) => "duration_ms",
+            DisplayFormat::Duration(DurationUnit::Micros) => "duration_us",
+            DisplayFormat::Duration(DurationUnit::Nanos) => "duration_ns",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for DisplayFormat {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Ok(match name {
+            "bytes" => DisplayFormat::Bytes,
+            "count" => DisplayFormat::Count,
+            "duration_s" => DisplayFormat::Duration(DurationUnit::Seconds),
+            "duration_ms" => DisplayFormat::Duration(DurationUnit::Millis),
+            "duration_us" => DisplayFormat::Duration(DurationUnit::Micros),
+            "duration_ns" => DisplayFormat::Duration(DurationUnit::Nanos),
+            _ => return Err(miette!("Unknown display format `{name}`")),
+        })
+    }
+}
+
+impl Csv {
+    /// Prints the values of column `name` in `format` in `head` and `describe`, the values
+    /// themselves are left alone. `None` goes back to the raw numbers
+    pub fn set_display_format(
+        &mut self,
+        name: &str,
+        format: Option<DisplayFormat>,
+    ) -> Result<(), Error> {
+        match format {
+            Some(format) => self.set_meta(name, META_KEY, &format.to_string()),
+            None => {
+                self.get_col(name)?;
+                if let Some(meta) = self.meta.get_mut(name) {
+                    meta.remove(META_KEY);
+                }
+                Ok(())
+            }
+        }
+    }
+    pub fn display_format(&self, name: &str) -> Option<DisplayFormat> {
+        self.column_meta(name)?.get(META_KEY)?.parse().ok()
+    }
+}
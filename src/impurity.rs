@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use miette::Error;
+
+use crate::{
+    col_parser::DataValue,
+    csv_parser::{ColViewer, Csv},
+    error::CoalaError,
+    histogram::Bins,
+};
+
+// Shannon entropy in bits of a distribution given by counts
+pub(crate) fn entropy_of(counts: impl IntoIterator<Item = usize>) -> f64 {
+    let counts: Vec<usize> = counts.into_iter().filter(|&c| c > 0).collect();
+    let total = counts.iter().sum::<usize>() as f64;
+    counts
+        .iter()
+        .map(|&c| {
+            let p = c as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+fn gini_of(counts: &[usize]) -> f64 {
+    let total = counts.iter().sum::<usize>() as f64;
+    1.0 - counts
+        .iter()
+        .map(|&c| (c as f64 / total).powi(2))
+        .sum::<f64>()
+}
+
+impl ColViewer<'_> {
+    // How often each distinct value shows up, nulls left out
+    fn value_counts(&self) -> Result<Vec<usize>, Error> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for value in self.iter() {
+            if !matches!(value, DataValue::Null) {
+                *counts.entry(value.to_string()).or_default() += 1;
+            }
+        }
+        if counts.is_empty() {
+            return Err(CoalaError::EmptyColumn.into());
+        }
+        Ok(counts.into_values().collect())
+    }
+    // A label per row for counting, the bin of numeric values and the distinct value of others.
+    // `None` for nulls
+    fn labels(&self, bins: &Bins) -> Result<Vec<Option<usize>>, Error> {
+        if self.is_numeric() {
+            return bins.assign(&self.finite_values()?);
+        }
+        let mut ids: HashMap<String, usize> = HashMap::new();
+        Ok(self
+            .iter()
+            .map(|value| match value {
+                DataValue::Null => None,
+                value => {
+                    let next = ids.len();
+                    Some(*ids.entry(value.to_string()).or_insert(next))
+                }
+            })
+            .collect())
+    }
+    /// Shannon entropy of the distinct values in bits, 0 when every value is the same.
+    /// Nulls are left out
+    pub fn entropy(&self) -> Result<f64, Error> {
+        Ok(entropy_of(self.value_counts()?))
+    }
+    /// `entropy` divided by its largest possible value for this many distinct values, from 0
+    /// to 1 whatever the number of categories
+    pub fn normalized_entropy(&self) -> Result<f64, Error> {
+        let counts = self.value_counts()?;
+        Ok(match counts.len() {
+            1 => 0.0,
+            n => entropy_of(counts) / (n as f64).log2(),
+        })
+    }
+    /// Gini impurity, the chance that two values drawn at random differ. Nulls are left out
+    pub fn gini(&self) -> Result<f64, Error> {
+        Ok(gini_of(&self.value_counts()?))
+    }
+    /// `gini` divided by its largest possible value for this many distinct values, from 0 to 1
+    pub fn normalized_gini(&self) -> Result<f64, Error> {
+        let counts = self.value_counts()?;
+        Ok(match counts.len() {
+            1 => 0.0,
+            n => gini_of(&counts) / (1.0 - 1.0 / n as f64),
+        })
+    }
+}
+
+impl Csv {
+    /// Mutual information in bits between `a` and `b`, how much knowing one tells about the
+    /// other. Unlike correlation it catches any dependence, not just a linear one. Numeric
+    /// columns are split into `bins`, other columns count each distinct value as a category.
+    /// Rows with a null in either column are left out
+    pub fn mutual_information(&self, a: &str, b: &str, bins: &Bins) -> Result<f64, Error> {
+        let a = self.get_col(a)?.labels(bins)?;
+        let b = self.get_col(b)?.labels(bins)?;
+        let mut joint: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut left: HashMap<usize, usize> = HashMap::new();
+        let mut right: HashMap<usize, usize> = HashMap::new();
+        for (a, b) in a.into_iter().zip(b).filter_map(|(a, b)| Some((a?, b?))) {
+            *joint.entry((a, b)).or_default() += 1;
+            *left.entry(a).or_default() += 1;
+            *right.entry(b).or_default() += 1;
+        }
+        if joint.is_empty() {
+            return Err(CoalaError::EmptyColumn.into());
+        }
+        let information = entropy_of(left.into_values()) + entropy_of(right.into_values())
+            - entropy_of(joint.into_values());
+        // Rounding can leave independent columns a hair below zero
+        Ok(information.max(0.0))
+    }
+}
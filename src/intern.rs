@@ -0,0 +1,43 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError},
+};
+
+// Every string interned by a load, shared by all frames of the process
+static POOL: OnceLock<Mutex<HashSet<Arc<str>>>> = OnceLock::new();
+
+fn pool() -> MutexGuard<'static, HashSet<Arc<str>>> {
+    POOL.get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+// The pooled copy of every value, pooling the new ones. The pool is locked once for all
+pub(crate) fn intern_all<'v>(values: impl IntoIterator<Item = &'v str>) -> Vec<Arc<str>> {
+    let mut pool = pool();
+    values
+        .into_iter()
+        .map(|value| match pool.get(value) {
+            Some(pooled) => Arc::clone(pooled),
+            None => {
+                let pooled: Arc<str> = Arc::from(value);
+                pool.insert(Arc::clone(&pooled));
+                pooled
+            }
+        })
+        .collect()
+}
+
+/// Distinct strings in the pool of `CsvConfig::intern_strings`
+pub fn pool_size() -> usize {
+    pool().len()
+}
+
+/// Drops the pooled strings no column holds anymore and returns how many. The pool never
+/// lets go of a string on its own, long running programs call this once frames are dropped
+pub fn release_unused() -> usize {
+    let mut pool = pool();
+    let before = pool.len();
+    pool.retain(|value| Arc::strong_count(value) > 1);
+    before - pool.len()
+}
@@ -0,0 +1,242 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use miette::{miette, Error};
+
+use crate::{
+    bootstrap::interpolate,
+    csv_parser::Csv,
+    error::CoalaError,
+    histogram::{Bins, Histogram},
+    kind::{kind_column, ColumnKind},
+};
+
+/// Which ends of an interval belong to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Closed {
+    /// `[a, b)`
+    #[default]
+    Left,
+    /// `(a, b]`
+    Right,
+    /// `[a, b]`
+    Both,
+    /// `(a, b)`
+    Neither,
+}
+
+impl Closed {
+    fn lower(&self) -> bool {
+        matches!(self, Closed::Left | Closed::Both)
+    }
+    fn upper(&self) -> bool {
+        matches!(self, Closed::Right | Closed::Both)
+    }
+    fn from_ends(lower: bool, upper: bool) -> Self {
+        match (lower, upper) {
+            (true, false) => Closed::Left,
+            (false, true) => Closed::Right,
+            (true, true) => Closed::Both,
+            (false, false) => Closed::Neither,
+        }
+    }
+}
+
+/// Range of numbers between `lower` and `upper`, printed like `[0, 10)`. Intervals order by
+/// their lower end then their upper end, an end that's in the interval coming first at the
+/// bottom and last at the top
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Interval {
+    pub lower: f64,
+    pub upper: f64,
+    pub closed: Closed,
+}
+
+impl Interval {
+    pub fn new(lower: f64, upper: f64, closed: Closed) -> Self {
+        Self {
+            lower,
+            upper,
+            closed,
+        }
+    }
+    pub fn contains(&self, value: f64) -> bool {
+        let above = if self.closed.lower() {
+            value >= self.lower
+        } else {
+            value > self.lower
+        };
+        let below = if self.closed.upper() {
+            value <= self.upper
+        } else {
+            value < self.upper
+        };
+        above && below
+    }
+    pub fn width(&self) -> f64 {
+        self.upper - self.lower
+    }
+    pub fn mid(&self) -> f64 {
+        (self.lower + self.upper) / 2.0
+    }
+}
+
+impl PartialOrd for Interval {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        let lower = self
+            .lower
+            .partial_cmp(&other.lower)?
+            .then_with(|| other.closed.lower().cmp(&self.closed.lower()));
+        let upper = self
+            .upper
+            .partial_cmp(&other.upper)?
+            .then_with(|| self.closed.upper().cmp(&other.closed.upper()));
+        Some(lower.then(upper))
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let open = if self.closed.lower() { '[' } else { '(' };
+        let close = if self.closed.upper() { ']' } else { ')' };
+        write!(f, "{open}{}, {}{close}", self.lower, self.upper)
+    }
+}
+
+impl FromStr for Interval {
+    type Err = Error;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let invalid = || miette!("`{raw}` isn't an interval like `[0, 10)`");
+        let raw = raw.trim();
+        let lower_closed = match raw.chars().next() {
+            Some('[') => true,
+            Some('(') => false,
+            _ => return Err(invalid()),
+        };
+        let upper_closed = match raw.chars().last() {
+            Some(']') => true,
+            Some(')') => false,
+            _ => return Err(invalid()),
+        };
+        let (lower, upper) = raw[1..raw.len() - 1].split_once(',').ok_or_else(invalid)?;
+        let lower: f64 = lower.trim().parse().map_err(|_| invalid())?;
+        let upper: f64 = upper.trim().parse().map_err(|_| invalid())?;
+        if lower > upper {
+            return Err(invalid());
+        }
+        Ok(Interval::new(
+            lower,
+            upper,
+            Closed::from_ends(lower_closed, upper_closed),
+        ))
+    }
+}
+
+/// Column kind of intervals, the type of the columns `cut` and `qcut` add. Register it to
+/// read cells like `[0, 10)` back as intervals
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IntervalKind;
+
+impl ColumnKind for IntervalKind {
+    type Value = Interval;
+
+    fn name(&self) -> &'static str {
+        "interval"
+    }
+    fn parse(&self, raw: &str) -> Option<Interval> {
+        raw.parse().ok()
+    }
+}
+
+// Bins between consecutive edges, closed on the left but the last one closed on both ends,
+// the way `Bins` assigns values
+fn edge_intervals(edges: &[f64]) -> Vec<Interval> {
+    let last = edges.len().saturating_sub(2);
+    edges
+        .windows(2)
+        .enumerate()
+        .map(|(i, pair)| {
+            let closed = if i == last {
+                Closed::Both
+            } else {
+                Closed::Left
+            };
+            Interval::new(pair[0], pair[1], closed)
+        })
+        .collect()
+}
+
+impl Histogram {
+    /// The bins as intervals, `counts[i]` is the weight of `bins()[i]`
+    pub fn bins(&self) -> Vec<Interval> {
+        edge_intervals(&self.edges)
+    }
+    /// One row per bin, with a `bin` interval column and its `count`
+    pub fn to_csv(&self) -> Result<Csv, Error> {
+        let bins: Vec<Option<Interval>> = self.bins().into_iter().map(Some).collect();
+        let mut csv = Csv::from_cols(Vec::new(), Vec::new(), bins.len());
+        csv.push_col(kind_column(IntervalKind, "bin", bins), "histogram")?;
+        csv.add_col(
+            "count",
+            self.counts.iter().map(|&count| count.into()).collect(),
+        )?;
+        Ok(csv)
+    }
+}
+
+impl Csv {
+    /// Adds column `into` holding the bin of each value of the numeric column `name`, as an
+    /// interval. Missing values and values outside explicit edges get null
+    pub fn cut(&mut self, name: &str, bins: &Bins, into: &str) -> Result<(), Error> {
+        let values = self.get_col(name)?.finite_values()?;
+        let mut sorted: Vec<f64> = values.iter().flatten().copied().collect();
+        sorted.sort_unstable_by(f64::total_cmp);
+        if sorted.is_empty() {
+            return Err(CoalaError::EmptyColumn.into());
+        }
+        let edges = bins.edges(&sorted)?;
+        self.push_bins(name, &values, bins, &edges, into, "cut")
+    }
+    /// `cut` into `n` bins holding about as many values each, the edges being quantiles of the
+    /// column. Edges that fall on the same value are merged, so heavy ties give fewer bins
+    pub fn qcut(&mut self, name: &str, n: usize, into: &str) -> Result<(), Error> {
+        if n == 0 {
+            return Err(CoalaError::OutOfRange.into());
+        }
+        let values = self.get_col(name)?.finite_values()?;
+        let mut sorted: Vec<f64> = values.iter().flatten().copied().collect();
+        sorted.sort_unstable_by(f64::total_cmp);
+        if sorted.is_empty() {
+            return Err(CoalaError::EmptyColumn.into());
+        }
+        let mut edges: Vec<f64> = (0..=n)
+            .map(|i| interpolate(&sorted, i as f64 / n as f64))
+            .collect();
+        edges.dedup();
+        if edges.len() < 2 {
+            // Every value is the same, one bin holds them
+            edges.push(edges[0]);
+        }
+        let bins = Bins::Edges(edges.clone());
+        self.push_bins(name, &values, &bins, &edges, into, "qcut")
+    }
+    fn push_bins(
+        &mut self,
+        name: &str,
+        values: &[Option<f64>],
+        bins: &Bins,
+        edges: &[f64],
+        into: &str,
+        operation: &str,
+    ) -> Result<(), Error> {
+        let intervals = edge_intervals(edges);
+        let cells: Vec<Option<Interval>> = values
+            .iter()
+            .map(|value| Some(intervals[bins.bin_of(edges, (*value)?)?]))
+            .collect();
+        self.push_col(
+            kind_column(IntervalKind, into, cells),
+            &format!("{operation}({name})"),
+        )
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    cmp::Ordering,
+    fmt::{self, Debug, Display},
+    sync::Arc,
+};
+
+use bitvec::vec::BitVec;
+use miette::{miette, Error};
+
+use crate::col_parser::{ColType, CsvCol, DataValue};
+
+pub trait ColumnKind: Clone + Send + Sync + 'static {
+    type Value: Clone + PartialOrd + Display + Send + Sync + 'static;
+
+    fn name(&self) -> &'static str;
+    fn parse(&self, raw: &str) -> Option<Self::Value>;
+    fn to_value(&self, value: &Self::Value) -> DataValue {
+        DataValue::String(value.to_string())
+    }
+    // Kinds returning a number here get mean, median and quantile for free
+    fn to_f64(&self, _value: &Self::Value) -> Option<f64> {
+        None
+    }
+}
+
+pub trait KindFactory: Send + Sync {
+    fn kind_name(&self) -> &'static str;
+    #[doc(hidden)]
+    fn build(&self, elements: &[String], name: &str) -> Result<Box<dyn ErasedColumn>, Error>;
+}
+
+pub type SharedKind = Arc<dyn KindFactory>;
+
+impl<K: ColumnKind> KindFactory for K {
+    fn kind_name(&self) -> &'static str {
+        self.name()
+    }
+    fn build(&self, elements: &[String], name: &str) -> Result<Box<dyn ErasedColumn>, Error> {
+        let values = elements
+            .iter()
+            .enumerate()
+            .map(|(row, raw)| {
+                self.parse(raw)
+                    .ok_or_else(|| crate::col_parser::parse_error(row, name, raw))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Box::new(KindColumn {
+            kind: self.clone(),
+            col: CsvCol::new(name.to_string(), values),
+        }))
+    }
+}
+
+#[doc(hidden)]
+pub trait ErasedColumn: Debug + Send + Sync {
+    fn kind_name(&self) -> &'static str;
+    fn name(&self) -> &str;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn get(&self, index: usize) -> Option<DataValue>;
+    fn display(&self, index: usize) -> Option<String>;
+    fn to_f64_values(&self) -> Option<Vec<f64>>;
+    fn extreme_index(&self, wanted: Ordering) -> Option<usize>;
+    fn take(&self, indices: &[usize]) -> Box<dyn ErasedColumn>;
+    /// Row indices in ascending order of the kind's values, stable for equal values
+    fn argsort(&self) -> Vec<usize>;
+    /// A column of the same kind and name holding `values`, parsed from their text
+    fn with_values(&self, values: &[DataValue]) -> Result<Box<dyn ErasedColumn>, Error>;
+}
+
+// Column of `kind` from values already parsed, `None` being null
+pub(crate) fn kind_column<K: ColumnKind>(
+    kind: K,
+    name: &str,
+    values: Vec<Option<K::Value>>,
+) -> ColType
+where
+    K::Value: Default,
+{
+    let validity: BitVec = values.iter().map(Option::is_some).collect();
+    let mut col = CsvCol::new(
+        name.to_string(),
+        values.into_iter().map(Option::unwrap_or_default).collect(),
+    );
+    col.validity = (!validity.all()).then_some(validity);
+    ColType::Custom(Box::new(KindColumn { kind, col }))
+}
+
+struct KindColumn<K: ColumnKind> {
+    kind: K,
+    col: CsvCol<K::Value>,
+}
+
+impl<K: ColumnKind> Debug for KindColumn<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KindColumn")
+            .field("kind", &self.kind.name())
+            .field("col_name", &self.col.col_name)
+            .field("n_elements", &self.col.n_elements)
+            .finish()
+    }
+}
+
+impl<K: ColumnKind> ErasedColumn for KindColumn<K> {
+    fn kind_name(&self) -> &'static str {
+        self.kind.name()
+    }
+    fn name(&self) -> &str {
+        &self.col.col_name
+    }
+    fn len(&self) -> usize {
+        self.col.n_elements
+    }
+    fn get(&self, index: usize) -> Option<DataValue> {
+        let value = self.col.values.get(index)?;
+        Some(match self.col.is_valid(index) {
+            true => self.kind.to_value(value),
+            false => DataValue::Null,
+        })
+    }
+    fn display(&self, index: usize) -> Option<String> {
+        let value = self.col.values.get(index)?;
+        Some(match self.col.is_valid(index) {
+            true => value.to_string(),
+            false => DataValue::Null.to_string(),
+        })
+    }
+    fn to_f64_values(&self) -> Option<Vec<f64>> {
+        self.col
+            .valid_values()
+            .iter()
+            .map(|v| self.kind.to_f64(v))
+            .collect()
+    }
+    fn extreme_index(&self, wanted: Ordering) -> Option<usize> {
+        self.col.extreme_index(wanted)
+    }
+    fn take(&self, indices: &[usize]) -> Box<dyn ErasedColumn> {
+        Box::new(KindColumn {
+            kind: self.kind.clone(),
+            col: self.col.take(indices),
+        })
+    }
+    fn with_values(&self, values: &[DataValue]) -> Result<Box<dyn ErasedColumn>, Error> {
+        let name = &self.col.col_name;
+        let parsed = values
+            .iter()
+            .enumerate()
+            .map(|(row, value)| match value {
+                DataValue::Null => Ok(None),
+                value => {
+                    let raw = value.to_string();
+                    self.kind
+                        .parse(&raw)
+                        .map(Some)
+                        .ok_or_else(|| crate::col_parser::parse_error(row, name, &raw))
+                }
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        // Nulls stand in for the first value, a column of nulls only for one already stored
+        let Some(placeholder) = parsed
+            .iter()
+            .flatten()
+            .chain(self.col.values.first())
+            .next()
+            .cloned()
+        else {
+            return Err(miette!(
+                "Column `{name}` of kind `{}` needs a value to hold nulls",
+                self.kind.name()
+            ));
+        };
+        let validity: BitVec = parsed.iter().map(Option::is_some).collect();
+        let mut col = CsvCol::new(
+            name.clone(),
+            parsed
+                .into_iter()
+                .map(|value| value.unwrap_or_else(|| placeholder.clone()))
+                .collect(),
+        );
+        col.validity = (!validity.all()).then_some(validity);
+        Ok(Box::new(KindColumn {
+            kind: self.kind.clone(),
+            col,
+        }))
+    }
+    fn argsort(&self) -> Vec<usize> {
+        let values = &self.col.values;
+        let mut indices: Vec<usize> = (0..values.len()).collect();
+        indices.sort_by(|&a, &b| values[a].partial_cmp(&values[b]).unwrap_or(Ordering::Equal));
+        indices
+    }
+}
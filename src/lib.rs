@@ -1,3 +1,99 @@
+extern crate alloc;
+
+pub mod agg;
+mod anomaly;
+pub mod assert;
+pub mod audit;
+mod autocorr;
+#[cfg(feature = "bigint")]
+mod bigint;
+mod boolean;
+pub mod bootstrap;
+mod changepoint;
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
 pub mod col_parser;
+pub mod column_order;
+pub mod column_stats;
+#[cfg(feature = "complex")]
+mod complex;
+mod compress;
+pub mod concat;
+pub mod contingency;
 pub mod csv_parser;
-pub(crate) mod statistics;
+mod currency;
+#[cfg(feature = "decimal")]
+mod decimal;
+mod decompose;
+pub mod dictionary;
+pub(crate) mod display;
+mod drift;
+pub mod dt;
+pub mod duplicates;
+mod edit;
+pub mod epoch;
+mod equality;
+pub mod error;
+pub mod events;
+pub mod expr;
+pub mod groupby;
+mod guards;
+pub mod histogram;
+pub mod humanize;
+mod impurity;
+pub mod intern;
+pub mod interval;
+pub mod kind;
+pub mod lineage;
+mod list;
+pub mod mask;
+mod merge;
+pub mod metrics;
+mod nested;
+pub mod normalize;
+pub mod nulls;
+mod perf;
+pub mod prelude;
+pub mod promote;
+mod query_cache;
+pub mod reader;
+mod reconcile;
+mod records;
+pub mod rng;
+pub mod row;
+mod row_id;
+pub mod row_index;
+pub mod runtime;
+pub mod schema;
+pub mod shared;
+mod shortcuts;
+pub mod sidecar;
+pub mod sketch;
+mod small_string;
+mod sort;
+pub mod sorted;
+mod special;
+mod spill;
+#[cfg(any(feature = "postgres", feature = "mysql"))]
+pub mod sql;
+pub mod statistics;
+pub mod stats;
+pub mod stream;
+mod strings;
+mod summary;
+pub mod tail;
+pub mod testing;
+pub mod timeseries;
+pub(crate) mod tokenizer;
+mod trace;
+mod view;
+#[cfg(feature = "watch")]
+pub mod watch;
+pub mod writer;
+
+/// The tokenizer, plain values and running statistics without `std`
+pub use coala_embedded as embedded;
+/// The type of the values in `DataValue::DateTime`
+pub use datetime::datetime::Datetime;
+/// Entry points named like their pandas counterparts, with the default configs
+pub use shortcuts::{concat, merge, read_csv};
@@ -0,0 +1,40 @@
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::csv_parser::Csv;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lineage {
+    pub source: Option<String>,
+    pub loaded_at: SystemTime,
+    pub operations: Vec<String>,
+}
+
+impl Default for Lineage {
+    fn default() -> Self {
+        Self {
+            source: None,
+            loaded_at: SystemTime::now(),
+            operations: Vec::new(),
+        }
+    }
+}
+
+impl Lineage {
+    pub(crate) fn from_source(path: &str) -> Self {
+        Self {
+            source: Some(path.to_string()),
+            ..Self::default()
+        }
+    }
+    pub(crate) fn record(&mut self, operation: String) {
+        self.operations.push(operation);
+    }
+}
+
+impl Csv {
+    pub fn lineage(&self) -> &Lineage {
+        &self.lineage
+    }
+}
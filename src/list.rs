@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use bitvec::vec::BitVec;
+use miette::Error;
+
+use crate::{
+    agg::{Accumulator, Agg},
+    col_parser::{ColType, CsvCol, DataValue},
+    csv_parser::{ColViewer, Csv},
+    error::CoalaError,
+    events::ChangeEvent,
+};
+
+fn parse_element(raw: &str) -> DataValue {
+    let raw = raw.trim();
+    if let Ok(i) = raw.parse() {
+        return DataValue::Integer(i);
+    }
+    if let Ok(f) = raw.parse() {
+        return DataValue::Float(f);
+    }
+    DataValue::String(raw.to_string())
+}
+
+impl Csv {
+    /// Turns every value of `name` into a list by splitting it on `separator`
+    pub fn split(&mut self, name: &str, separator: &str) -> Result<(), Error> {
+        let index = self.col_index(name)?;
+        let col = &self.cols[index];
+        let mut lists = Vec::with_capacity(self.n_rows);
+        let mut validity = BitVec::with_capacity(self.n_rows);
+        for row in 0..self.n_rows {
+            let value = col.data_as_value(row)?;
+            validity.push(!matches!(value, DataValue::Null));
+            let value = value.to_string();
+            lists.push(if value.is_empty() || !validity[row] {
+                Vec::new()
+            } else {
+                value.split(separator).map(parse_element).collect()
+            });
+        }
+        let mut lists = CsvCol::new(name.to_string(), lists);
+        lists.validity = (!validity.all()).then_some(validity);
+        self.cols[index] = Arc::new(ColType::List(lists));
+        self.cache.remove(name);
+        self.lineage.record(format!("split({name}, {separator:?})"));
+        self.notify(ChangeEvent::ColumnReplaced {
+            name: name.to_string(),
+        });
+        Ok(())
+    }
+    /// Gives every element of the list column `name` its own row, repeating the other columns.
+    /// Rows holding an empty list or null are dropped
+    pub fn explode(&self, name: &str) -> Result<Csv, Error> {
+        let index = self.col_index(name)?;
+        let ColType::List(list_col) = &*self.cols[index] else {
+            return Err(CoalaError::InvalidType {
+                col: name.to_string(),
+            }
+            .into());
+        };
+        let mut rows = Vec::new();
+        let mut elements = Vec::new();
+        for (row, list) in list_col.values.iter().enumerate() {
+            if !list_col.is_valid(row) {
+                continue;
+            }
+            rows.extend(std::iter::repeat_n(row, list.len()));
+            elements.extend(list.iter().cloned());
+        }
+        let mut csv = self.take_rows(&rows);
+        csv.cols[index] = Arc::new(ColType::from_data_values(elements, name.to_string())?);
+        csv.lineage.record(format!("explode({name})"));
+        Ok(csv)
+    }
+}
+
+impl ColViewer<'_> {
+    // One per row, `None` where the row is null
+    fn lists(&self) -> Result<Vec<Option<&[DataValue]>>, Error> {
+        match self.inner {
+            ColType::List(col) => Ok(col
+                .values
+                .iter()
+                .enumerate()
+                .map(|(row, list)| col.is_valid(row).then_some(list.as_slice()))
+                .collect()),
+            col => Err(CoalaError::InvalidType {
+                col: col.name().to_string(),
+            }
+            .into()),
+        }
+    }
+    /// Length of every list, a null counting as empty
+    pub fn list_len(&self) -> Result<Vec<usize>, Error> {
+        Ok(self
+            .lists()?
+            .iter()
+            .map(|list| list.map_or(0, <[DataValue]>::len))
+            .collect())
+    }
+    /// Element `i` of every list, `Null` where the list is shorter or null
+    pub fn list_get(&self, i: usize) -> Result<Vec<DataValue>, Error> {
+        Ok(self
+            .lists()?
+            .iter()
+            .map(|list| {
+                list.and_then(|list| list.get(i))
+                    .cloned()
+                    .unwrap_or(DataValue::Null)
+            })
+            .collect())
+    }
+    /// Aggregates the elements of each list on its own, yielding one value per row
+    pub fn list_agg(&self, agg: Agg) -> Result<Vec<DataValue>, Error> {
+        agg.validate()?;
+        Ok(self
+            .lists()?
+            .into_iter()
+            .map(|list| {
+                let Some(list) = list else {
+                    return DataValue::Null;
+                };
+                let mut acc = Accumulator::new(agg);
+                for value in list {
+                    acc.push_value(value);
+                }
+                acc.finish()
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    cmp::Ordering,
+    ops::{BitAnd, BitOr, Not},
+    sync::Arc,
+};
+
+use bitvec::vec::BitVec;
+use miette::{miette, Error};
+
+use crate::{
+    col_parser::DataValue,
+    csv_parser::{ColViewer, Csv},
+    events::ChangeEvent,
+};
+
+/// One bit per row, set on the rows a condition holds for. Built by comparisons and
+/// combined with `&`, `|` and `!`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mask {
+    bits: BitVec,
+}
+
+impl Mask {
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+    pub fn count(&self) -> usize {
+        self.bits.count_ones()
+    }
+    pub fn get(&self, index: usize) -> Option<bool> {
+        self.bits.get(index).map(|bit| *bit)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        self.bits.iter().by_vals()
+    }
+    /// Whether any row is set
+    pub fn any(&self) -> bool {
+        self.bits.any()
+    }
+    /// Whether every row is set, `true` for an empty mask
+    pub fn all(&self) -> bool {
+        self.bits.all()
+    }
+    /// Rows where the mask is set, in order
+    pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.bits.iter_ones()
+    }
+    fn check_len(&self, other: &Mask) {
+        assert_eq!(self.len(), other.len(), "Masks must have the same length");
+    }
+}
+
+impl From<Vec<bool>> for Mask {
+    fn from(bits: Vec<bool>) -> Self {
+        bits.into_iter().collect()
+    }
+}
+
+impl FromIterator<bool> for Mask {
+    fn from_iter<I: IntoIterator<Item = bool>>(bits: I) -> Self {
+        Self {
+            bits: bits.into_iter().collect(),
+        }
+    }
+}
+
+impl BitAnd<&Mask> for Mask {
+    type Output = Mask;
+
+    fn bitand(mut self, other: &Mask) -> Mask {
+        self.check_len(other);
+        self.bits &= &other.bits;
+        self
+    }
+}
+
+impl BitOr<&Mask> for Mask {
+    type Output = Mask;
+
+    fn bitor(mut self, other: &Mask) -> Mask {
+        self.check_len(other);
+        self.bits |= &other.bits;
+        self
+    }
+}
+
+impl Not for Mask {
+    type Output = Mask;
+
+    fn not(self) -> Mask {
+        Mask { bits: !self.bits }
+    }
+}
+
+// Comparisons against null, or between values that can't be ordered, never match
+impl ColViewer<'_> {
+    fn compare_with(&self, value: &DataValue, wanted: fn(Ordering) -> bool) -> Mask {
+        self.iter()
+            .map(|v| v.compare(value).is_some_and(wanted))
+            .collect()
+    }
+    pub fn eq(&self, value: &DataValue) -> Mask {
+        self.compare_with(value, Ordering::is_eq)
+    }
+    pub fn ne(&self, value: &DataValue) -> Mask {
+        self.compare_with(value, Ordering::is_ne)
+    }
+    pub fn gt(&self, value: &DataValue) -> Mask {
+        self.compare_with(value, Ordering::is_gt)
+    }
+    pub fn ge(&self, value: &DataValue) -> Mask {
+        self.compare_with(value, Ordering::is_ge)
+    }
+    pub fn lt(&self, value: &DataValue) -> Mask {
+        self.compare_with(value, Ordering::is_lt)
+    }
+    pub fn le(&self, value: &DataValue) -> Mask {
+        self.compare_with(value, Ordering::is_le)
+    }
+    pub fn is_null(&self) -> Mask {
+        self.iter().map(|v| matches!(v, DataValue::Null)).collect()
+    }
+    /// Rows equal to any of `values`. Nulls match nothing
+    pub fn isin(&self, values: &[DataValue]) -> Mask {
+        self.iter()
+            .map(|v| {
+                values
+                    .iter()
+                    .any(|value| v.compare(value).is_some_and(Ordering::is_eq))
+            })
+            .collect()
+    }
+    /// Rows whose value, as displayed, contains `pattern`. Nulls match nothing
+    pub fn grep(&self, pattern: &str) -> Mask {
+        self.iter()
+            .map(|v| !matches!(v, DataValue::Null) && v.to_string().contains(pattern))
+            .collect()
+    }
+}
+
+impl Csv {
+    /// Keeps cells on rows where `mask` is set and replaces every other cell with `other`.
+    /// `other` must fit every column as in `set`, null always does
+    pub fn where_mask(&mut self, mask: &Mask, other: DataValue) -> Result<(), Error> {
+        if mask.len() != self.n_rows {
+            return Err(miette!(
+                "Mask has {} entries, the Dataframe has {} rows",
+                mask.len(),
+                self.n_rows
+            ));
+        }
+        let cols = self
+            .cols
+            .iter()
+            .enumerate()
+            .map(|(index, col)| {
+                let other = self.checked_value(index, other.clone())?;
+                let values = ColViewer::new(col)
+                    .iter()
+                    .zip(mask.iter())
+                    .map(|(v, keep)| if keep { v } else { other.clone() })
+                    .collect();
+                col.with_values(values)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        self.cols = cols.into_iter().map(Arc::new).collect();
+        self.cache.clear();
+        self.lineage.record(format!("where_mask({other})"));
+        for name in &self.header {
+            self.notify(ChangeEvent::ColumnReplaced { name: name.clone() });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use std::{collections::HashMap, sync::Arc};
+
+use miette::Error;
+
+use crate::{
+    col_parser::{ColType, DataValue},
+    csv_parser::Csv,
+};
+
+// Added to the name of a column of the right frame the left one already has
+const RIGHT_SUFFIX: &str = "_right";
+
+impl Csv {
+    /// Inner join of this frame with `other` on column `on`: a row for every pair of rows with
+    /// the same `on` value, in the order of this frame then of `other`. Null keys match
+    /// nothing. The columns of this frame come first, then those of `other` but `on`, with
+    /// `_right` added to the names this frame already has
+    pub fn merge(&self, other: &Csv, on: &str) -> Result<Csv, Error> {
+        let mut right_rows: HashMap<String, Vec<usize>> = HashMap::new();
+        for (row, key) in other.get_col(on)?.iter().enumerate() {
+            if !matches!(key, DataValue::Null) {
+                right_rows.entry(key.to_string()).or_default().push(row);
+            }
+        }
+        let (mut left, mut right) = (Vec::new(), Vec::new());
+        for (row, key) in self.get_col(on)?.iter().enumerate() {
+            if matches!(key, DataValue::Null) {
+                continue;
+            }
+            for &other_row in right_rows.get(&key.to_string()).into_iter().flatten() {
+                left.push(row);
+                right.push(other_row);
+            }
+        }
+        let mut csv = self.take_rows(&left);
+        for (i, (name, col)) in other.header.iter().zip(&other.cols).enumerate() {
+            if name == on {
+                continue;
+            }
+            let col = if csv.header.contains(name) {
+                let values = right
+                    .iter()
+                    .map(|&row| col.data_as_value(row))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                ColType::from_data_values(values, format!("{name}{RIGHT_SUFFIX}"))?
+            } else {
+                col.take(&right)
+            };
+            let merged_name = col.name().to_string();
+            if let Some(units) = &mut csv.units {
+                let unit = other.units.as_ref().and_then(|units| units.get(i).cloned());
+                units.push(unit.unwrap_or_default());
+            }
+            if let Some(meta) = other.meta.get(name) {
+                csv.meta.insert(merged_name.clone(), meta.clone());
+            }
+            csv.header.push(merged_name);
+            csv.cols.push(Arc::new(col));
+        }
+        csv.n_cols = csv.cols.len();
+        csv.lineage.record(format!("merge(on = {on})"));
+        Ok(csv)
+    }
+}
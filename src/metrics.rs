@@ -0,0 +1,122 @@
+use std::fmt::Write;
+
+use miette::Error;
+
+use crate::{
+    agg::{Accumulator, Agg},
+    csv_parser::Csv,
+    embedded::LineEnding,
+};
+
+/// What the last load went through, skipped bytes belong to columns left out of `use_columns`
+/// and rows before `row_offset`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScanMetrics {
+    pub records: usize,
+    pub bytes_read: usize,
+    pub bytes_skipped: usize,
+    /// Line break convention of the file, `None` for a single line without one
+    pub line_ending: Option<LineEnding>,
+}
+
+// Gauges of `to_prometheus_metrics` over numeric columns, with their help text
+const NUMERIC_GAUGES: [(&str, Agg, &str); 2] = [
+    ("mean", Agg::Mean, "Mean of the column's values"),
+    (
+        "p95",
+        Agg::Quantile(0.95),
+        "95th percentile of the column's values",
+    ),
+];
+
+// Metric names only allow ASCII letters, digits, underscores and colons, and can't start with
+// a digit
+fn metric_name(prefix: &str, suffix: &str) -> String {
+    let name: String = format!("{prefix}_{suffix}")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == ':' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{name}")
+    } else {
+        name
+    }
+}
+
+fn label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn sample_value(value: f64) -> String {
+    match value {
+        f64::INFINITY => "+Inf".to_string(),
+        f64::NEG_INFINITY => "-Inf".to_string(),
+        value if value.is_nan() => "NaN".to_string(),
+        value => value.to_string(),
+    }
+}
+
+impl Csv {
+    pub fn scan_metrics(&self) -> ScanMetrics {
+        self.metrics
+    }
+    /// Column statistics in the Prometheus text exposition format, one gauge per statistic
+    /// labelled by column: `{prefix}_mean` and `{prefix}_p95` of the numeric columns and
+    /// `{prefix}_null_ratio` of every column. Statistics of columns without values are left out
+    pub fn to_prometheus_metrics(&self, prefix: &str) -> Result<String, Error> {
+        let mut text = String::new();
+        let mut gauge = |suffix: &str, help: &str, samples: Vec<(&String, f64)>| {
+            let name = metric_name(prefix, suffix);
+            // Writing to a string can't fail
+            let _ = writeln!(text, "# HELP {name} {help}");
+            let _ = writeln!(text, "# TYPE {name} gauge");
+            for (column, value) in samples {
+                let _ = writeln!(
+                    text,
+                    "{name}{{column=\"{}\"}} {}",
+                    label_value(column),
+                    sample_value(value)
+                );
+            }
+        };
+        for (suffix, agg, help) in NUMERIC_GAUGES {
+            let mut samples = Vec::new();
+            for name in &self.header {
+                let col = self.get_col(name)?;
+                if !col.is_numeric() {
+                    continue;
+                }
+                let mut accumulator = Accumulator::new(agg);
+                for value in col.iter() {
+                    accumulator.push_value(&value);
+                }
+                if let Some(value) = accumulator.finish().as_f64() {
+                    samples.push((name, value));
+                }
+            }
+            gauge(suffix, help, samples);
+        }
+        let mut null_ratios = Vec::new();
+        for name in &self.header {
+            if self.n_rows > 0 {
+                let nulls = self.get_col(name)?.null_count();
+                null_ratios.push((name, nulls as f64 / self.n_rows as f64));
+            }
+        }
+        gauge(
+            "null_ratio",
+            "Share of the column's rows that are null",
+            null_ratios,
+        );
+        Ok(text)
+    }
+}
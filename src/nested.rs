@@ -0,0 +1,261 @@
+use std::sync::Arc;
+
+use miette::{Error, IntoDiagnostic};
+use serde_json::{Map, Value};
+
+use crate::{
+    col_parser::{ColType, DataValue},
+    csv_parser::Csv,
+    error::CoalaError,
+    events::ChangeEvent,
+    shared::unshare,
+};
+
+type Object = Map<String, Value>;
+
+// Fields keep their full dotted path as name, e.g. `payload.user.id` inside `payload.user`
+#[derive(Debug)]
+pub(crate) struct StructCol {
+    pub(crate) col_name: String,
+    pub(crate) fields: Vec<ColType>,
+    n_rows: usize,
+}
+
+impl StructCol {
+    pub(crate) fn len(&self) -> usize {
+        self.n_rows
+    }
+    fn key<'a>(&self, field: &'a ColType) -> &'a str {
+        let name = field.name();
+        name.strip_prefix(&self.col_name)
+            .and_then(|rest| rest.strip_prefix('.'))
+            .unwrap_or(name)
+    }
+    pub(crate) fn get(&self, row: usize) -> Result<DataValue, Error> {
+        let fields = self
+            .fields
+            .iter()
+            .map(|field| Ok((self.key(field).to_string(), field.data_as_value(row)?)))
+            .collect::<Result<_, Error>>()?;
+        Ok(DataValue::Struct(fields))
+    }
+    pub(crate) fn take(&self, indices: &[usize]) -> Self {
+        Self {
+            col_name: self.col_name.clone(),
+            fields: self.fields.iter().map(|f| f.take(indices)).collect(),
+            n_rows: indices.len(),
+        }
+    }
+    // Fields in order of first appearance among the values, missing ones and null rows
+    // reading as nulls
+    pub(crate) fn from_values(col_name: String, values: Vec<DataValue>) -> Result<Self, Error> {
+        let rows: Vec<&[(String, DataValue)]> = values
+            .iter()
+            .map(|value| match value {
+                DataValue::Struct(fields) => fields.as_slice(),
+                _ => &[],
+            })
+            .collect();
+        let mut keys: Vec<&String> = Vec::new();
+        for (key, _) in rows.iter().flat_map(|fields| fields.iter()) {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        let fields = keys
+            .into_iter()
+            .map(|key| {
+                let name = format!("{col_name}.{key}");
+                let field: Vec<DataValue> = rows
+                    .iter()
+                    .map(|fields| {
+                        fields
+                            .iter()
+                            .find(|(k, _)| k == key)
+                            .map_or(DataValue::Null, |(_, v)| v.clone())
+                    })
+                    .collect();
+                if field.iter().any(|v| matches!(v, DataValue::Struct(_)))
+                    && field
+                        .iter()
+                        .all(|v| matches!(v, DataValue::Struct(_) | DataValue::Null))
+                {
+                    return Ok(ColType::Struct(Self::from_values(name, field)?));
+                }
+                ColType::from_data_values(field, name)
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self {
+            col_name,
+            fields,
+            n_rows: values.len(),
+        })
+    }
+    fn from_objects(col_name: String, rows: &[Option<&Object>]) -> Result<Self, Error> {
+        let mut keys: Vec<&String> = Vec::new();
+        for key in rows.iter().flatten().flat_map(|object| object.keys()) {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        let fields = keys
+            .into_iter()
+            .map(|key| {
+                let name = format!("{col_name}.{key}");
+                let values: Vec<Option<&Value>> = rows
+                    .iter()
+                    .map(|row| row.and_then(|object| object.get(key)))
+                    .collect();
+                let is_object = values
+                    .iter()
+                    .all(|v| matches!(v, None | Some(Value::Null) | Some(Value::Object(_))));
+                if is_object {
+                    let objects: Vec<_> = values
+                        .iter()
+                        .map(|v| v.and_then(Value::as_object))
+                        .collect();
+                    return Ok(ColType::Struct(Self::from_objects(name, &objects)?));
+                }
+                // Missing keys and JSON nulls come out empty, read as nulls like empty cells
+                let raw: Vec<String> = values.into_iter().map(raw_value).collect();
+                ColType::from_values_with_nulls(&raw, name, None, &[], &[String::new()])
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self {
+            col_name,
+            fields,
+            n_rows: rows.len(),
+        })
+    }
+    fn into_leaves(self, leaves: &mut Vec<ColType>) {
+        for field in self.fields {
+            match field {
+                ColType::Struct(inner) => inner.into_leaves(leaves),
+                field => leaves.push(field),
+            }
+        }
+    }
+}
+
+// Leaves are re-inferred from text, missing keys and nulls become empty strings
+fn raw_value(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+enum Slot {
+    Col(ColType),
+    Group(String, Vec<ColType>),
+}
+
+fn nest(cols: Vec<ColType>, prefix: &str, n_rows: usize) -> Vec<ColType> {
+    let mut slots: Vec<Slot> = Vec::new();
+    for col in cols {
+        let rest = col.name().strip_prefix(prefix).unwrap_or(col.name());
+        let Some((head, _)) = rest.split_once('.') else {
+            slots.push(Slot::Col(col));
+            continue;
+        };
+        let group = format!("{prefix}{head}");
+        match slots
+            .iter_mut()
+            .find(|slot| matches!(slot, Slot::Group(name, _) if *name == group))
+        {
+            Some(Slot::Group(_, members)) => members.push(col),
+            _ => slots.push(Slot::Group(group, vec![col])),
+        }
+    }
+    slots
+        .into_iter()
+        .map(|slot| match slot {
+            Slot::Col(col) => col,
+            Slot::Group(name, members) => {
+                let fields = nest(members, &format!("{name}."), n_rows);
+                ColType::Struct(StructCol {
+                    col_name: name,
+                    fields,
+                    n_rows,
+                })
+            }
+        })
+        .collect()
+}
+
+impl Csv {
+    /// Parses every value of `name` as a JSON object and replaces the column with a struct
+    /// whose fields are reachable as `name.key.subkey`
+    pub fn unnest_json(&mut self, name: &str) -> Result<(), Error> {
+        let index = self.col_index(name)?;
+        let values = (0..self.n_rows)
+            .map(|row| {
+                let raw = self.cols[index].data_as_value(row)?.to_string();
+                if raw.is_empty() {
+                    return Ok(Value::Null);
+                }
+                serde_json::from_str(&raw).into_diagnostic()
+            })
+            .collect::<Result<Vec<Value>, Error>>()?;
+        if values
+            .iter()
+            .any(|v| !matches!(v, Value::Null | Value::Object(_)))
+        {
+            return Err(CoalaError::InvalidType {
+                col: name.to_string(),
+            }
+            .into());
+        }
+        let objects: Vec<_> = values.iter().map(Value::as_object).collect();
+        let col = StructCol::from_objects(name.to_string(), &objects)?;
+        self.cols[index] = Arc::new(ColType::Struct(col));
+        self.cache.remove(name);
+        self.lineage.record(format!("unnest_json({name})"));
+        self.notify(ChangeEvent::ColumnReplaced {
+            name: name.to_string(),
+        });
+        Ok(())
+    }
+    /// Replaces every struct column with its leaf columns, named by their dotted path
+    pub fn flatten(&mut self) {
+        let mut cols = Vec::with_capacity(self.cols.len());
+        for col in self.cols.drain(..) {
+            match unshare(col) {
+                ColType::Struct(col) => col.into_leaves(&mut cols),
+                col => cols.push(col),
+            }
+        }
+        let before = self.header.clone();
+        self.set_cols(cols);
+        self.lineage.record("flatten".to_string());
+        self.notify_header_change(&before);
+    }
+    /// Groups columns sharing a dotted prefix back into struct columns
+    pub fn unflatten(&mut self) {
+        let cols = std::mem::take(&mut self.cols);
+        let cols = nest(cols.into_iter().map(unshare).collect(), "", self.n_rows);
+        let before = self.header.clone();
+        self.set_cols(cols);
+        self.lineage.record("unflatten".to_string());
+        self.notify_header_change(&before);
+    }
+    fn set_cols(&mut self, cols: Vec<ColType>) {
+        let header: Vec<String> = cols.iter().map(|c| c.name().to_string()).collect();
+        if let Some(units) = &self.units {
+            let unit_of = |name: &String| {
+                let i = self.header.iter().position(|h| h == name)?;
+                units.get(i).cloned()
+            };
+            self.units = Some(
+                header
+                    .iter()
+                    .map(|h| unit_of(h).unwrap_or_default())
+                    .collect(),
+            );
+        }
+        self.header = header;
+        self.n_cols = cols.len();
+        self.cols = cols.into_iter().map(Arc::new).collect();
+    }
+}
@@ -0,0 +1,76 @@
+use std::{borrow::Cow, collections::HashSet};
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{col_parser::DataValue, csv_parser::ColViewer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnicodeForm {
+    Nfc,
+    Nfkc,
+}
+
+// Applied to string values before they're compared or hashed, so e.g. "São Paulo" and
+// "sa\u{0303}o paulo " can be treated as the same key
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StringNormalization {
+    pub form: Option<UnicodeForm>,
+    pub case_fold: bool,
+    pub trim: bool,
+}
+
+impl StringNormalization {
+    pub fn nfc(mut self) -> Self {
+        self.form = Some(UnicodeForm::Nfc);
+        self
+    }
+    pub fn nfkc(mut self) -> Self {
+        self.form = Some(UnicodeForm::Nfkc);
+        self
+    }
+    pub fn case_fold(mut self) -> Self {
+        self.case_fold = true;
+        self
+    }
+    pub fn trim(mut self) -> Self {
+        self.trim = true;
+        self
+    }
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+    pub fn apply<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        let mut value = Cow::Borrowed(if self.trim { value.trim() } else { value });
+        match self.form {
+            Some(UnicodeForm::Nfc) => value = Cow::Owned(value.nfc().collect()),
+            Some(UnicodeForm::Nfkc) => value = Cow::Owned(value.nfkc().collect()),
+            None => {}
+        }
+        if self.case_fold {
+            value = Cow::Owned(value.to_lowercase());
+        }
+        value
+    }
+    // Only strings are touched, every other value is its own key
+    pub(crate) fn key(&self, value: &DataValue) -> String {
+        match value {
+            DataValue::String(s) => self.apply(s).into_owned(),
+            value => value.to_string(),
+        }
+    }
+}
+
+impl ColViewer<'_> {
+    /// Distinct values in order of first appearance
+    pub fn unique(&self) -> Vec<DataValue> {
+        self.unique_normalized(&StringNormalization::default())
+    }
+    /// Like `unique`, but strings equal after `normalization` count once, represented by
+    /// their first spelling
+    pub fn unique_normalized(&self, normalization: &StringNormalization) -> Vec<DataValue> {
+        let mut seen = HashSet::new();
+        self.iter()
+            .filter(|value| seen.insert(normalization.key(value)))
+            .collect()
+    }
+}
@@ -0,0 +1,226 @@
+use std::cmp::Ordering;
+
+use bitvec::vec::BitVec;
+use miette::Error;
+
+use crate::{
+    col_parser::{ColConfig, ColType, DataValue},
+    csv_parser::{ColViewer, Csv},
+    kind::SharedKind,
+    mask::Mask,
+    strings::StringCol,
+};
+
+/// Cells read as null unless `CsvConfig::null_values` says otherwise
+pub const DEFAULT_NULL_VALUES: [&str; 8] = ["", "NA", "N/A", "n/a", "NULL", "null", "None", "<NA>"];
+
+/// Whether a null matches another null, for operations that compare values for equality
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NullSemantics {
+    /// A null equals nothing, not even another null, like `=` in SQL
+    #[default]
+    Sql,
+    /// Nulls equal each other, the way pandas matches missing keys in merges and `duplicated`
+    Pandas,
+}
+
+impl NullSemantics {
+    pub fn eq(&self, a: &DataValue, b: &DataValue) -> bool {
+        match (a, b) {
+            (DataValue::Null, DataValue::Null) => *self == NullSemantics::Pandas,
+            _ => a.compare(b).is_some_and(Ordering::is_eq),
+        }
+    }
+    /// Under `Sql` a comparison with null is unknown, so it isn't "not equal" either
+    pub fn ne(&self, a: &DataValue, b: &DataValue) -> bool {
+        match self {
+            NullSemantics::Sql => a.compare(b).is_some_and(Ordering::is_ne),
+            NullSemantics::Pandas => !self.eq(a, b),
+        }
+    }
+}
+
+impl ColType {
+    // Columns that can't hold nulls have none
+    pub(crate) fn validity(&self) -> Option<&BitVec> {
+        match self {
+            Self::Float(col) => col.validity.as_ref(),
+            Self::Integer(col) => col.validity.as_ref(),
+            Self::String(col) => col.validity.as_ref(),
+            Self::Datetime(col) => col.validity.as_ref(),
+            #[cfg(feature = "decimal")]
+            Self::Decimal(col) => col.validity.as_ref(),
+            #[cfg(feature = "complex")]
+            Self::Complex(col) => col.validity.as_ref(),
+            #[cfg(feature = "bigint")]
+            Self::BigInt(col) => col.validity.as_ref(),
+            Self::List(col) => col.validity.as_ref(),
+            Self::Struct(_) | Self::Custom(_) | Self::Compressed(_) | Self::View(_) => None,
+        }
+    }
+    // `None` for the columns that can't hold nulls
+    fn validity_mut(&mut self) -> Option<&mut Option<BitVec>> {
+        match self {
+            Self::Float(col) => Some(&mut col.validity),
+            Self::Integer(col) => Some(&mut col.validity),
+            Self::String(col) => Some(&mut col.validity),
+            Self::Datetime(col) => Some(&mut col.validity),
+            #[cfg(feature = "decimal")]
+            Self::Decimal(col) => Some(&mut col.validity),
+            #[cfg(feature = "complex")]
+            Self::Complex(col) => Some(&mut col.validity),
+            #[cfg(feature = "bigint")]
+            Self::BigInt(col) => Some(&mut col.validity),
+            Self::List(col) => Some(&mut col.validity),
+            Self::Struct(_) | Self::Custom(_) | Self::Compressed(_) | Self::View(_) => None,
+        }
+    }
+    pub(crate) fn is_null_at(&self, index: usize) -> bool {
+        match self {
+            Self::Struct(_) | Self::Custom(_) | Self::Compressed(_) | Self::View(_) => {
+                matches!(self.data_as_value(index), Ok(DataValue::Null))
+            }
+            col => col
+                .validity()
+                .is_some_and(|validity| validity.get(index).is_some_and(|valid| !*valid)),
+        }
+    }
+    pub(crate) fn null_count(&self) -> usize {
+        match self.validity() {
+            Some(validity) => validity.count_zeros(),
+            None => (0..self.len()).filter(|&i| self.is_null_at(i)).count(),
+        }
+    }
+    fn all_null(name: String, len: usize) -> Self {
+        let mut col = StringCol::new(name, std::iter::repeat_n("", len));
+        col.validity = Some(BitVec::repeat(false, len));
+        Self::String(col)
+    }
+    // Nulls stand in for the first value, so the column takes the type of the others
+    pub(crate) fn from_nullable_values(
+        values: Vec<DataValue>,
+        name: String,
+    ) -> Result<Self, Error> {
+        let validity: BitVec = values
+            .iter()
+            .map(|v| !matches!(v, DataValue::Null))
+            .collect();
+        let Some(placeholder) = values
+            .iter()
+            .find(|v| !matches!(v, DataValue::Null))
+            .cloned()
+        else {
+            return Ok(Self::all_null(name, values.len()));
+        };
+        let filled = values
+            .into_iter()
+            .map(|v| match v {
+                DataValue::Null => placeholder.clone(),
+                v => v,
+            })
+            .collect();
+        let mut col = Self::from_data_values(filled, name)?;
+        // Every type built from values can hold nulls
+        if let Some(slot) = col.validity_mut() {
+            *slot = (!validity.all()).then_some(validity);
+        }
+        Ok(col)
+    }
+    /// `from_values` with the elements in `null_values` read as nulls
+    pub(crate) fn from_values_with_nulls<S: AsRef<str>>(
+        elements: &[S],
+        name: String,
+        config: Option<ColConfig>,
+        kinds: &[SharedKind],
+        null_values: &[String],
+    ) -> Result<Self, Error> {
+        let validity: BitVec = elements
+            .iter()
+            .map(|e| !null_values.iter().any(|null| null == e.as_ref()))
+            .collect();
+        Self::from_values_with_validity(elements, validity, name, config, kinds)
+    }
+    /// `from_values` with the elements whose bit is unset in `validity` as nulls, whatever
+    /// they hold
+    pub(crate) fn from_values_with_validity<S: AsRef<str>>(
+        elements: &[S],
+        validity: BitVec,
+        name: String,
+        config: Option<ColConfig>,
+        kinds: &[SharedKind],
+    ) -> Result<Self, Error> {
+        if validity.all() {
+            return Self::from_values(elements, name, config, kinds);
+        }
+        let Some(placeholder) = elements
+            .iter()
+            .zip(validity.iter())
+            .find_map(|(e, valid)| valid.then_some(e.as_ref()))
+        else {
+            return Ok(Self::all_null(name, elements.len()));
+        };
+        // Parsed whole rather than without the nulls, so errors point at the right row
+        let filled: Vec<&str> = elements
+            .iter()
+            .zip(validity.iter())
+            .map(|(e, valid)| if *valid { e.as_ref() } else { placeholder })
+            .collect();
+        let mut col = Self::from_values(&filled, name.clone(), config, kinds)?;
+        match col.validity_mut() {
+            Some(slot) => {
+                *slot = Some(validity);
+                Ok(col)
+            }
+            // Kinds can't hold nulls, they get the cells as written
+            None => Self::from_values(elements, name, config, kinds),
+        }
+    }
+}
+
+impl ColViewer<'_> {
+    pub fn null_count(&self) -> usize {
+        self.inner.null_count()
+    }
+    /// The values that aren't null, in row order
+    pub fn drop_nulls(&self) -> Vec<DataValue> {
+        self.iter()
+            .filter(|v| !matches!(v, DataValue::Null))
+            .collect()
+    }
+    /// `eq` with an explicit null policy. Plain `eq` follows `NullSemantics::Sql`
+    pub fn eq_with(&self, value: &DataValue, semantics: NullSemantics) -> Mask {
+        self.iter().map(|v| semantics.eq(&v, value)).collect()
+    }
+    /// `ne` with an explicit null policy. Plain `ne` follows `NullSemantics::Sql`
+    pub fn ne_with(&self, value: &DataValue, semantics: NullSemantics) -> Mask {
+        self.iter().map(|v| semantics.ne(&v, value)).collect()
+    }
+}
+
+impl Csv {
+    /// View of the rows without a null in any column of `subset`, all of them if it's empty
+    pub fn drop_nulls(&self, subset: &[&str]) -> Result<Csv, Error> {
+        let mut keep = Mask::from(vec![true; self.n_rows]);
+        for name in self.subset_names(subset) {
+            keep = keep & &!self.get_col(name)?.is_null();
+        }
+        self.filter(&keep)
+    }
+    /// `duplicated` with an explicit null policy. Plain `duplicated` follows
+    /// `NullSemantics::Pandas`, under `Sql` a row with a null in `subset` repeats nothing
+    pub fn duplicated_with(
+        &self,
+        subset: &[&str],
+        semantics: NullSemantics,
+    ) -> Result<Mask, Error> {
+        let duplicated = self.duplicated(subset)?;
+        if semantics == NullSemantics::Pandas {
+            return Ok(duplicated);
+        }
+        let mut any_null = Mask::from(vec![false; self.n_rows]);
+        for name in self.subset_names(subset) {
+            any_null = any_null | &self.get_col(name)?.is_null();
+        }
+        Ok(duplicated & &!any_null)
+    }
+}
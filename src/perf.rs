@@ -0,0 +1,50 @@
+#[cfg(feature = "perf-log")]
+use std::time::Instant;
+
+#[cfg(feature = "perf-log")]
+use log::{debug, log_enabled, warn, Level};
+
+// Target of every event, so applications can turn them on without the rest of the crate's logs
+#[cfg(feature = "perf-log")]
+const TARGET: &str = "coala::perf";
+
+// Warns that `operation` did more work than it had to and why. The reason is only built when
+// the warning is logged
+#[cfg(feature = "perf-log")]
+pub(crate) fn slow_path(operation: &str, reason: impl FnOnce() -> String) {
+    if log_enabled!(target: TARGET, Level::Warn) {
+        warn!(target: TARGET, "{operation} took a slow path: {}", reason());
+    }
+}
+
+#[cfg(not(feature = "perf-log"))]
+pub(crate) fn slow_path(_: &str, _: impl FnOnce() -> String) {}
+
+// Logs how long it lived once dropped, so every return of the timed operation is covered
+pub(crate) struct Timer {
+    #[cfg(feature = "perf-log")]
+    started: Option<(String, Instant)>,
+}
+
+impl Timer {
+    #[cfg(feature = "perf-log")]
+    pub(crate) fn start(operation: impl FnOnce() -> String) -> Self {
+        Self {
+            started: log_enabled!(target: TARGET, Level::Debug)
+                .then(|| (operation(), Instant::now())),
+        }
+    }
+    #[cfg(not(feature = "perf-log"))]
+    pub(crate) fn start(_: impl FnOnce() -> String) -> Self {
+        Self {}
+    }
+}
+
+#[cfg(feature = "perf-log")]
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if let Some((operation, started)) = &self.started {
+            debug!(target: TARGET, "{operation} took {:?}", started.elapsed());
+        }
+    }
+}
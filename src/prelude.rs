@@ -0,0 +1,12 @@
+// What most programs need, all at once with `use coala::prelude::*`
+
+pub use crate::{
+    agg::{Agg, AggExpr},
+    col_parser::{DType, DataValue},
+    csv_parser::{ColViewer, Csv, CsvConfig},
+    expr::{col, lit, when, Expr},
+    groupby::GroupKeys,
+    mask::Mask,
+    statistics::{RunningStats, Statistics},
+    Datetime,
+};
@@ -0,0 +1,69 @@
+use miette::Error;
+
+use crate::{
+    col_parser::{DType, DataValue},
+    edit::coerce,
+    error::CoalaError,
+};
+
+/// Whether stacking a column of strings with one of another type turns both into strings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StringPromotion {
+    /// It's an error, which usually points at a badly parsed file
+    #[default]
+    Error,
+    /// The other values are written as text. Datetimes still refuse, mixing them with strings
+    /// means some dates failed to parse
+    ToString,
+}
+
+/// The type a column takes when values of `a` and `b` are stacked, e.g. by `Csv::concat`.
+/// Integers widen to any other numeric type, decimals and big integers meet at float and
+/// every number fits in complex. Strings mix with nothing, see `promote_with`
+pub fn promote(a: DType, b: DType) -> Result<DType, Error> {
+    promote_with(a, b, StringPromotion::Error)
+}
+
+/// `promote` with the policy for mixing strings with other types
+pub fn promote_with(a: DType, b: DType, strings: StringPromotion) -> Result<DType, Error> {
+    use DType::*;
+    let promoted = match (a, b) {
+        (a, b) if a == b => Some(a),
+        (Integer, Float) | (Float, Integer) => Some(Float),
+        #[cfg(feature = "decimal")]
+        (Integer, Decimal) | (Decimal, Integer) => Some(Decimal),
+        #[cfg(feature = "decimal")]
+        (Float, Decimal) | (Decimal, Float) => Some(Float),
+        #[cfg(feature = "bigint")]
+        (Integer, BigInt) | (BigInt, Integer) => Some(BigInt),
+        #[cfg(feature = "bigint")]
+        (Float, BigInt) | (BigInt, Float) => Some(Float),
+        #[cfg(all(feature = "decimal", feature = "bigint"))]
+        (Decimal, BigInt) | (BigInt, Decimal) => Some(Float),
+        #[cfg(feature = "complex")]
+        (Complex, other) | (other, Complex) if other.is_numeric() => Some(Complex),
+        (Datetime, String) | (String, Datetime) => None,
+        (String, _) | (_, String) if strings == StringPromotion::ToString => Some(String),
+        _ => None,
+    };
+    promoted.ok_or_else(|| {
+        CoalaError::IncompatibleTypes {
+            left: a.name().to_string(),
+            right: b.name().to_string(),
+        }
+        .into()
+    })
+}
+
+// `value` as a value of `dtype`, the type `promote` picked for it
+pub(crate) fn convert(value: DataValue, dtype: DType) -> Option<DataValue> {
+    match (dtype, value) {
+        (_, DataValue::Null) => Some(DataValue::Null),
+        (DType::Float, value @ DataValue::Float(_)) => Some(value),
+        (DType::Float, value) => value.as_f64().map(DataValue::Float),
+        (DType::String, value) => Some(DataValue::String(value.to_string())),
+        // Kinds keep the values as they come
+        (DType::Custom(_), value) => Some(value),
+        (dtype, value) => coerce(value, dtype),
+    }
+}
@@ -0,0 +1,85 @@
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use miette::Error;
+
+use crate::{agg::Agg, col_parser::DataValue, csv_parser::Csv, expr::Expr, mask::Mask};
+
+#[derive(Debug, Clone)]
+enum Cached {
+    Mask(Mask),
+    Value(DataValue),
+}
+
+// Materialized query results keyed by a fingerprint of the query. Entries belong to one state
+// of the frame, any change recorded in its lineage or to its row count drops them all
+#[derive(Debug, Clone, Default)]
+pub(crate) struct QueryCache {
+    state: (usize, usize),
+    entries: HashMap<u64, Cached>,
+}
+
+// Expressions aren't `Hash` because of the floats they hold, their debug text stands in
+fn fingerprint(query: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    query.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl QueryCache {
+    fn get(&mut self, state: (usize, usize), key: u64) -> Option<&Cached> {
+        if self.state != state {
+            self.state = state;
+            self.entries.clear();
+        }
+        self.entries.get(&key)
+    }
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+impl Csv {
+    fn query_state(&self) -> (usize, usize) {
+        (self.lineage.operations.len(), self.n_rows)
+    }
+    /// `eval_mask` that reuses the mask of an identical condition already evaluated on this
+    /// frame, for interactive sessions that rerun the same filter
+    pub fn cached_mask(&mut self, condition: &Expr) -> Result<Mask, Error> {
+        let key = fingerprint(&format!("mask({condition:?})"));
+        if let Some(Cached::Mask(mask)) = self.queries.get(self.query_state(), key) {
+            return Ok(mask.clone());
+        }
+        let mask = self.eval_mask(condition)?;
+        self.queries.entries.insert(key, Cached::Mask(mask.clone()));
+        Ok(mask)
+    }
+    /// `agg_where` that reuses the result of an identical query already run on this frame
+    pub fn cached_agg_where(
+        &mut self,
+        name: &str,
+        condition: &Expr,
+        agg: Agg,
+    ) -> Result<DataValue, Error> {
+        let key = fingerprint(&format!("agg_where({name}, {condition:?}, {agg:?})"));
+        if let Some(Cached::Value(value)) = self.queries.get(self.query_state(), key) {
+            return Ok(value.clone());
+        }
+        let value = self.agg_where(name, condition, agg)?;
+        self.queries
+            .entries
+            .insert(key, Cached::Value(value.clone()));
+        Ok(value)
+    }
+    /// Number of query results currently cached
+    pub fn cached_queries(&self) -> usize {
+        self.queries.len()
+    }
+    /// Drops every cached query result, for changes the frame can't see such as a file
+    /// rewritten under it
+    pub fn invalidate_queries(&mut self) {
+        self.queries.entries.clear();
+    }
+}
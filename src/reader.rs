@@ -0,0 +1,369 @@
+use std::{collections::HashMap, env, fs::File, io::BufReader, path::PathBuf};
+
+use miette::{miette, Error};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    agg::{Accumulator, Agg},
+    col_parser::{ColConfig, ColType, DType, DataValue},
+    csv_parser::{rename_header, Csv, CsvConfig},
+    error::CoalaError,
+    lineage::Lineage,
+    tokenizer::{Dialect, Record, Records},
+};
+
+pub(crate) const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+pub struct CsvReader {
+    path: String,
+    pub(crate) dialect: Dialect,
+    skip_rows: usize,
+    chunk_size: usize,
+    pub(crate) spill_dir: PathBuf,
+    pub(crate) max_chunk_bytes: Option<usize>,
+    null_values: Vec<String>,
+    rename_map: HashMap<String, String>,
+}
+
+pub(crate) struct RecordChunks {
+    records: Records<BufReader<File>>,
+    chunk_size: usize,
+    max_bytes: Option<usize>,
+}
+
+impl RecordChunks {
+    pub(crate) fn project(&mut self, keep: Vec<bool>) {
+        self.records.project(keep);
+    }
+    // Right after the last record read
+    fn position(&self, rows: usize) -> Checkpoint {
+        Checkpoint {
+            offset: self.records.bytes_read(),
+            line: self.records.line_no(),
+            rows,
+        }
+    }
+}
+
+/// Where a load through `Batches` got to, to pick it up from there with `CsvReader::resume`
+/// instead of starting over when it was interrupted. Serializable, so it can outlive the process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Byte offset in the file right after the last fully parsed record
+    pub offset: usize,
+    /// Lines of the file before `offset`
+    pub line: usize,
+    /// Data rows before `offset`
+    pub rows: usize,
+}
+
+impl Iterator for RecordChunks {
+    type Item = Result<Vec<Record>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk = Vec::with_capacity(self.chunk_size);
+        let start = self.records.bytes_read();
+        while let Some(record) = self.records.next() {
+            match record {
+                Ok(record) => chunk.push(record),
+                Err(e) => return Some(Err(e)),
+            }
+            let bytes = self.records.bytes_read() - start;
+            if chunk.len() == self.chunk_size || self.max_bytes.is_some_and(|max| bytes >= max) {
+                break;
+            }
+        }
+        (!chunk.is_empty()).then_some(Ok(chunk))
+    }
+}
+
+impl CsvReader {
+    pub fn new(path: &str, config: &CsvConfig) -> Self {
+        Self {
+            path: path.to_string(),
+            dialect: Dialect::from(config),
+            skip_rows: config.header.unwrap_or(0),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            spill_dir: env::temp_dir(),
+            max_chunk_bytes: None,
+            null_values: config.null_values.clone(),
+            rename_map: config.rename_map.clone(),
+        }
+    }
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+    /// Where operations too big for memory write their temporary files, the system temporary
+    /// directory by default
+    pub fn with_spill_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.spill_dir = dir.into();
+        self
+    }
+    pub(crate) fn open(&self) -> Result<(Vec<String>, RecordChunks), Error> {
+        let file = File::open(&self.path).map_err(CoalaError::from)?;
+        let mut records = Records::new(BufReader::new(file), self.dialect);
+        for _ in 0..self.skip_rows {
+            records.next().transpose()?;
+        }
+        let mut header: Vec<String> = match records.next() {
+            Some(header) => header?.into_iter().map(String::from).collect(),
+            None => return Err(CoalaError::UnexpectedEof.into()),
+        };
+        rename_header(&self.rename_map, &mut header)?;
+        let chunks = RecordChunks {
+            records,
+            chunk_size: self.chunk_size,
+            max_bytes: self.max_chunk_bytes,
+        };
+        Ok((header, chunks))
+    }
+    pub fn aggregate(&self, aggs: &[(&str, Agg)]) -> Result<Vec<DataValue>, Error> {
+        let (header, mut chunks) = self.open()?;
+        let targets = aggs
+            .iter()
+            .map(|(name, agg)| {
+                agg.validate()?;
+                header.iter().position(|h| h == name).ok_or_else(|| {
+                    CoalaError::MissingColumn {
+                        name: name.to_string(),
+                    }
+                    .into()
+                })
+            })
+            .collect::<Result<Vec<usize>, Error>>()?;
+        // Columns no aggregation reads are never stored, targets then index the kept fields
+        let keep: Vec<bool> = (0..header.len()).map(|i| targets.contains(&i)).collect();
+        let targets: Vec<usize> = targets
+            .iter()
+            .map(|&t| keep[..t].iter().filter(|&&k| k).count())
+            .collect();
+        chunks.project(keep);
+        let mut totals: Vec<Accumulator> =
+            aggs.iter().map(|(_, agg)| Accumulator::new(*agg)).collect();
+        for chunk in chunks {
+            let chunk = chunk?;
+            // Each chunk is folded on its own and merged, so only one chunk is alive at a time
+            let mut partials: Vec<Accumulator> =
+                aggs.iter().map(|(_, agg)| Accumulator::new(*agg)).collect();
+            for record in &chunk {
+                for (i, (name, agg)) in aggs.iter().enumerate() {
+                    let field = record
+                        .get(targets[i])
+                        .ok_or_else(|| miette!("Record has no field for column `{name}`"))?;
+                    if self.null_values.iter().any(|null| null == field.as_ref()) {
+                        partials[i].push_value(&DataValue::Null);
+                        continue;
+                    }
+                    let value = if agg.is_numeric() {
+                        Some(field.parse::<f64>().map_err(|_| {
+                            miette!("Value `{field}` in column `{name}` isn't numeric")
+                        })?)
+                    } else {
+                        None
+                    };
+                    partials[i].push(field, value);
+                }
+            }
+            for (total, partial) in totals.iter_mut().zip(&partials) {
+                total.merge(partial)?;
+            }
+        }
+        Ok(totals.iter_mut().map(Accumulator::finish).collect())
+    }
+    /// The file as frames of up to the chunk size rows, read as they're asked for
+    pub fn batches(&self) -> Result<Batches, Error> {
+        let (header, chunks) = self.open()?;
+        Ok(Batches {
+            path: self.path.clone(),
+            dtypes: vec![None; header.len()],
+            header,
+            checkpoint: chunks.position(0),
+            chunks,
+            null_values: self.null_values.clone(),
+            rows: 0,
+        })
+    }
+    /// `batches` starting where `checkpoint` was taken, on the same file. Column types are
+    /// inferred again on the first batch read
+    pub fn resume(&self, checkpoint: Checkpoint) -> Result<Batches, Error> {
+        let mut batches = self.batches()?;
+        let header_end = batches.checkpoint.offset;
+        if checkpoint.offset < header_end {
+            return Err(miette!(
+                "Checkpoint at byte {} is before the end of the header at byte {header_end}",
+                checkpoint.offset
+            ));
+        }
+        batches
+            .chunks
+            .records
+            .seek(checkpoint.offset, checkpoint.line)?;
+        batches.checkpoint = checkpoint;
+        batches.rows = checkpoint.rows;
+        Ok(batches)
+    }
+}
+
+/// Frames of consecutive rows from `CsvReader::batches`, only one of them read at a time.
+/// Column types are inferred on the first batch holding values, every later batch must read
+/// as the same types
+pub struct Batches {
+    path: String,
+    header: Vec<String>,
+    chunks: RecordChunks,
+    null_values: Vec<String>,
+    // `None` until a batch held a value in the column
+    dtypes: Vec<Option<DType>>,
+    rows: usize,
+    checkpoint: Checkpoint,
+}
+
+impl Batches {
+    /// Position right after the last batch returned, or after the header before the first
+    pub fn checkpoint(&self) -> Checkpoint {
+        self.checkpoint
+    }
+    fn batch(&mut self, chunk: Vec<Record>) -> Result<Csv, Error> {
+        let n_cols = self.header.len();
+        if let Some((row, record)) = chunk
+            .iter()
+            .enumerate()
+            .find(|(_, record)| record.len() != n_cols)
+        {
+            return Err(CoalaError::RaggedRow {
+                row: self.rows + row,
+                expected: n_cols,
+                found: record.len(),
+            }
+            .into());
+        }
+        let n_rows = chunk.len();
+        let mut columns = vec![Vec::with_capacity(n_rows); n_cols];
+        for record in chunk {
+            for (col, field) in columns.iter_mut().zip(record) {
+                col.push(field);
+            }
+        }
+        let mut cols = Vec::with_capacity(n_cols);
+        for ((data, name), dtype) in columns.iter().zip(&self.header).zip(&mut self.dtypes) {
+            let config = ColConfig {
+                dtype: *dtype,
+                ..Default::default()
+            };
+            let col = ColType::from_values_with_nulls(
+                data,
+                name.clone(),
+                Some(config),
+                &[],
+                &self.null_values,
+            )?;
+            // A batch of nulls says nothing about the type
+            if col.null_count() < col.len() {
+                match dtype {
+                    Some(expected) if *expected != col.dtype() => {
+                        return Err(miette!(
+                            "Column `{name}` was read as {} but rows {}..{} hold {} values",
+                            expected.name(),
+                            self.rows,
+                            self.rows + n_rows,
+                            col.dtype().name()
+                        ));
+                    }
+                    Some(_) => {}
+                    None => *dtype = Some(col.dtype()),
+                }
+            }
+            cols.push(col);
+        }
+        let mut csv = Csv::from_cols(cols, self.header.clone(), n_rows);
+        csv.lineage = Lineage::from_source(&self.path);
+        csv.lineage
+            .record(format!("rows({}..{})", self.rows, self.rows + n_rows));
+        self.rows += n_rows;
+        Ok(csv)
+    }
+}
+
+impl Iterator for Batches {
+    type Item = Result<Csv, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let batch = self.chunks.next()?.and_then(|chunk| self.batch(chunk));
+        // A failed batch leaves the checkpoint on the last good one, to retry from
+        if batch.is_ok() {
+            self.checkpoint = self.chunks.position(self.rows);
+        }
+        Some(batch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    fn reader(name: &str, contents: &str, chunk_size: usize) -> (String, CsvReader) {
+        let path = env::temp_dir().join(format!("coala-reader-{}-{name}.csv", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        fs::write(&path, contents).unwrap();
+        let reader = CsvReader::new(&path, &CsvConfig::default()).with_chunk_size(chunk_size);
+        (path, reader)
+    }
+
+    fn firsts(batch: &Csv) -> Vec<DataValue> {
+        batch.get_col("a").unwrap().iter().collect()
+    }
+
+    #[test]
+    fn batches_cover_the_rows_in_order() {
+        let (path, reader) = reader("batches", "a,b\n1,\"x\ny\"\n2,z\n3,w\n", 2);
+        let batches: Vec<Csv> = reader.batches().unwrap().map(Result::unwrap).collect();
+        fs::remove_file(&path).unwrap();
+        let sizes: Vec<usize> = batches.iter().map(|batch| batch.n_rows).collect();
+        assert_eq!(sizes, vec![2, 1]);
+        assert_eq!(firsts(&batches[1]), vec![DataValue::Integer(3)]);
+    }
+
+    #[test]
+    fn resume_carries_on_after_the_checkpoint() {
+        let (path, reader) = reader("resume", "a\n1\n2\n3\n4\n5\n", 2);
+        let mut batches = reader.batches().unwrap();
+        batches.next().unwrap().unwrap();
+        let checkpoint = batches.checkpoint();
+        assert_eq!(checkpoint.rows, 2);
+        let rest: Vec<Csv> = reader
+            .resume(checkpoint)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        fs::remove_file(&path).unwrap();
+        let values: Vec<DataValue> = rest.iter().flat_map(firsts).collect();
+        assert_eq!(values, [3, 4, 5].map(DataValue::Integer));
+    }
+
+    #[test]
+    fn checkpoint_stays_on_the_last_good_batch() {
+        let (path, reader) = reader("failed", "a\n1\n2\nx\n", 2);
+        let mut batches = reader.batches().unwrap();
+        batches.next().unwrap().unwrap();
+        let good = batches.checkpoint();
+        assert!(batches.next().unwrap().is_err());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(batches.checkpoint(), good);
+    }
+
+    #[test]
+    fn checkpoint_inside_the_header_is_rejected() {
+        let (path, reader) = reader("header", "a\n1\n", 2);
+        let checkpoint = Checkpoint {
+            offset: 0,
+            line: 0,
+            rows: 0,
+        };
+        let resumed = reader.resume(checkpoint);
+        fs::remove_file(&path).unwrap();
+        assert!(resumed.is_err());
+    }
+}
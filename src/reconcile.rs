@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use miette::{miette, Error};
+
+use crate::{col_parser::DataValue, csv_parser::Csv, error::CoalaError};
+
+// Columns of the report besides the key
+const REPORT_COLS: [&str; 6] = ["column", "left", "right", "abs_diff", "rel_diff", "issue"];
+
+// Row of each key of `csv`, nulls left out. A key can only name one row
+fn key_rows(csv: &Csv, key: &str) -> Result<(Vec<String>, HashMap<String, usize>), Error> {
+    let mut keys = Vec::new();
+    let mut rows = HashMap::new();
+    for (row, value) in csv.get_col(key)?.iter().enumerate() {
+        if matches!(value, DataValue::Null) {
+            continue;
+        }
+        let text = value.to_string();
+        if rows.insert(text.clone(), row).is_some() {
+            return Err(miette!(
+                "Key `{text}` of column `{key}` names more than one row"
+            ));
+        }
+        keys.push(text);
+    }
+    Ok((keys, rows))
+}
+
+// Whether `left` and `right` agree: numbers within `tolerance`, anything else equal
+fn agrees(left: &DataValue, right: &DataValue, tolerance: f64) -> bool {
+    match (left.as_f64(), right.as_f64()) {
+        (Some(l), Some(r)) => (l - r).abs() <= tolerance || l == r,
+        _ => left == right,
+    }
+}
+
+impl Csv {
+    /// Break report of `other` against this frame, rows matched on `key`: a row per key and
+    /// column of `value_cols` whose values disagree, numbers by more than `tolerance`, and a
+    /// row per key only one frame has. Columns are the key, the `column` compared, the `left`
+    /// and `right` values as text, their `abs_diff` and `rel_diff` relative to the left value
+    /// when both are numbers, and the `issue`: `mismatch`, `missing_left` or `missing_right`.
+    /// Keys come in the order of this frame, then those only `other` has
+    pub fn reconcile(
+        &self,
+        other: &Csv,
+        key: &str,
+        value_cols: &[&str],
+        tolerance: f64,
+    ) -> Result<Csv, Error> {
+        if REPORT_COLS.contains(&key) {
+            return Err(CoalaError::DuplicateColumn {
+                name: key.to_string(),
+            }
+            .into());
+        }
+        let (left_keys, left_rows) = key_rows(self, key)?;
+        let (right_keys, right_rows) = key_rows(other, key)?;
+        let left_cols = value_cols
+            .iter()
+            .map(|name| self.get_col(name))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let right_cols = value_cols
+            .iter()
+            .map(|name| other.get_col(name))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let keys = left_keys.iter().chain(
+            right_keys
+                .iter()
+                .filter(|key| !left_rows.contains_key(*key)),
+        );
+        let mut rows: [Vec<DataValue>; 7] = Default::default();
+        let text = |value: Option<&DataValue>| match value {
+            None | Some(DataValue::Null) => DataValue::Null,
+            Some(value) => DataValue::String(value.to_string()),
+        };
+        for key_value in keys {
+            let (left_row, right_row) = (left_rows.get(key_value), right_rows.get(key_value));
+            let issue = match (left_row, right_row) {
+                (Some(_), None) => Some("missing_right"),
+                (None, Some(_)) => Some("missing_left"),
+                _ => None,
+            };
+            for (i, name) in value_cols.iter().enumerate() {
+                let left = left_row.map(|&row| left_cols[i].get(row)).transpose()?;
+                let right = right_row.map(|&row| right_cols[i].get(row)).transpose()?;
+                let issue = match (issue, &left, &right) {
+                    (Some(issue), _, _) => issue,
+                    (None, Some(l), Some(r)) if !agrees(l, r, tolerance) => "mismatch",
+                    _ => continue,
+                };
+                let numbers = left
+                    .as_ref()
+                    .and_then(DataValue::as_f64)
+                    .zip(right.as_ref().and_then(DataValue::as_f64));
+                let abs_diff = numbers.map(|(l, r)| (r - l).abs());
+                let rel_diff = numbers
+                    .filter(|(l, _)| *l != 0.0)
+                    .map(|(l, r)| (r - l).abs() / l.abs());
+                let float = |v: Option<f64>| v.map_or(DataValue::Null, DataValue::Float);
+                let row = [
+                    DataValue::String(key_value.clone()),
+                    DataValue::String(name.to_string()),
+                    text(left.as_ref()),
+                    text(right.as_ref()),
+                    float(abs_diff),
+                    float(rel_diff),
+                    DataValue::String(issue.to_string()),
+                ];
+                for (values, value) in rows.iter_mut().zip(row) {
+                    values.push(value);
+                }
+            }
+        }
+        let n_rows = rows[0].len();
+        let [keys, column, left, right, abs_diff, rel_diff, issue] = rows;
+        self.summary_frame(
+            &format!("reconcile(on = {key})"),
+            std::iter::once((key, keys))
+                .chain(
+                    REPORT_COLS
+                        .into_iter()
+                        .zip([column, left, right, abs_diff, rel_diff, issue]),
+                )
+                .collect(),
+            n_rows,
+        )
+    }
+}
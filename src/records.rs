@@ -0,0 +1,58 @@
+use miette::{miette, Error};
+
+use crate::{
+    col_parser::{ColType, DType, DataValue},
+    csv_parser::Csv,
+    edit::{coerce, type_name},
+    error::CoalaError,
+};
+
+impl Csv {
+    /// A frame from rows produced by code, e.g. pages of an API or results of a computation,
+    /// with the columns and types of `schema`. Values must have their column's type or widen
+    /// to it as in `set`, nulls go anywhere
+    pub fn from_records<I>(records: I, schema: &[(&str, DType)]) -> Result<Csv, Error>
+    where
+        I: IntoIterator<Item = Vec<DataValue>>,
+    {
+        for (i, (name, _)) in schema.iter().enumerate() {
+            if schema[..i].iter().any(|(other, _)| other == name) {
+                return Err(CoalaError::DuplicateColumn {
+                    name: name.to_string(),
+                }
+                .into());
+            }
+        }
+        let mut cols: Vec<Vec<DataValue>> = vec![Vec::new(); schema.len()];
+        let mut n_rows = 0;
+        for record in records {
+            if record.len() != schema.len() {
+                return Err(miette!(
+                    "Record {n_rows} has {} values, the schema has {} columns",
+                    record.len(),
+                    schema.len()
+                ));
+            }
+            for ((value, (name, dtype)), col) in record.into_iter().zip(schema).zip(&mut cols) {
+                let found = type_name(&value);
+                let value = coerce(value, *dtype).ok_or_else(|| CoalaError::TypeMismatch {
+                    col: name.to_string(),
+                    expected: dtype.name().to_string(),
+                    found: found.to_string(),
+                })?;
+                col.push(value);
+            }
+            n_rows += 1;
+        }
+        let header: Vec<String> = schema.iter().map(|(name, _)| name.to_string()).collect();
+        let cols = cols
+            .into_iter()
+            .zip(&header)
+            .map(|(values, name)| ColType::from_data_values(values, name.clone()))
+            .collect::<Result<_, Error>>()?;
+        let mut csv = Csv::from_cols(cols, header, n_rows);
+        csv.lineage
+            .record(format!("from_records({n_rows} records)"));
+        Ok(csv)
+    }
+}
@@ -0,0 +1,163 @@
+use miette::Error;
+use rand::{seq::SliceRandom, Rng as _, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{csv_parser::Csv, error::CoalaError};
+
+/// How many rows `sample_stratified` draws, and how they're spread over the groups
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleSize {
+    /// This many rows in total, split in proportion to the size of each group
+    Total(usize),
+    /// The same fraction of every group
+    Fraction(f64),
+    /// This many rows from every group, or all of a smaller group
+    PerGroup(usize),
+}
+
+impl SampleSize {
+    // Rows to draw from each group. A total is split by largest remainder, so the quotas
+    // always add up to it
+    fn quotas(self, sizes: &[usize]) -> Result<Vec<usize>, Error> {
+        let n_rows: usize = sizes.iter().sum();
+        let fraction = match self {
+            SampleSize::PerGroup(n) => return Ok(sizes.iter().map(|&size| size.min(n)).collect()),
+            SampleSize::Fraction(f) if (0.0..=1.0).contains(&f) => f,
+            SampleSize::Fraction(_) => return Err(CoalaError::OutOfRange.into()),
+            SampleSize::Total(_) if n_rows == 0 => return Ok(vec![0; sizes.len()]),
+            SampleSize::Total(n) => n.min(n_rows) as f64 / n_rows as f64,
+        };
+        let exact: Vec<f64> = sizes.iter().map(|&size| size as f64 * fraction).collect();
+        let mut quotas: Vec<usize> = exact.iter().map(|q| q.floor() as usize).collect();
+        let target = (n_rows as f64 * fraction).round() as usize;
+        let mut by_remainder: Vec<usize> = (0..sizes.len()).collect();
+        by_remainder.sort_by(|&a, &b| {
+            (exact[b] - exact[b].floor()).total_cmp(&(exact[a] - exact[a].floor()))
+        });
+        let short = target.saturating_sub(quotas.iter().sum());
+        for &group in by_remainder.iter().take(short) {
+            quotas[group] += 1;
+        }
+        Ok(quotas)
+    }
+}
+
+// ChaCha8 has a fixed output stream for a seed, unlike `StdRng` which may change between
+// rand releases, so seeded operations give the same result on every platform and version
+#[derive(Debug, Clone)]
+pub struct Rng {
+    inner: ChaCha8Rng,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            inner: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.inner.random()
+    }
+    /// Uniform in `[0, 1)`
+    pub fn next_f64(&mut self) -> f64 {
+        self.inner.random()
+    }
+    /// Uniform in `[0, n)`
+    pub fn below(&mut self, n: usize) -> usize {
+        self.inner.random_range(0..n)
+    }
+    pub fn shuffle<T>(&mut self, values: &mut [T]) {
+        values.shuffle(&mut self.inner);
+    }
+    /// `k` distinct indices out of `0..n`, in ascending order
+    pub fn sample_indices(&mut self, n: usize, k: usize) -> Vec<usize> {
+        let mut indices = rand::seq::index::sample(&mut self.inner, n, k.min(n)).into_vec();
+        indices.sort_unstable();
+        indices
+    }
+}
+
+impl Csv {
+    /// Draws `n` rows without replacement, keeping their original order
+    pub fn sample(&self, n: usize, seed: u64) -> Csv {
+        let indices = Rng::new(seed).sample_indices(self.n_rows, n);
+        let mut sample = self.take_rows(&indices);
+        sample.lineage.record(format!("sample({n}, seed = {seed})"));
+        sample
+    }
+    /// Every row in a random order, the same one for the same seed and number of rows
+    pub fn shuffle(&self, seed: u64) -> Csv {
+        let mut order: Vec<usize> = (0..self.n_rows).collect();
+        Rng::new(seed).shuffle(&mut order);
+        let mut shuffled = self.take_rows(&order);
+        shuffled.lineage.record(format!("shuffle(seed = {seed})"));
+        shuffled
+    }
+    /// Draws from every group of `by` separately so each is represented, keeping the
+    /// original row order
+    pub fn sample_stratified(&self, size: SampleSize, by: &str, seed: u64) -> Result<Csv, Error> {
+        let codes = self.get_col(by)?.dictionary_encode();
+        let mut groups = vec![Vec::new(); codes.len()];
+        for (row, &code) in codes.codes.iter().enumerate() {
+            groups[code as usize].push(row);
+        }
+        let sizes: Vec<usize> = groups.iter().map(Vec::len).collect();
+        let mut rng = Rng::new(seed);
+        let mut indices: Vec<usize> = groups
+            .iter()
+            .zip(size.quotas(&sizes)?)
+            .flat_map(|(rows, quota)| {
+                rng.sample_indices(rows.len(), quota)
+                    .into_iter()
+                    .map(|i| rows[i])
+            })
+            .collect();
+        indices.sort_unstable();
+        let mut sample = self.take_rows(&indices);
+        sample.lineage.record(format!(
+            "sample_stratified({size:?}, by = {by}, seed = {seed})"
+        ));
+        Ok(sample)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::col_parser::{DType, DataValue};
+
+    fn numbers(n: i64) -> Csv {
+        let records = (0..n).map(|i| vec![DataValue::Integer(i)]);
+        Csv::from_records(records, &[("n", DType::Integer)]).unwrap()
+    }
+
+    fn values(csv: &Csv) -> Vec<i64> {
+        let col = csv.get_col("n").unwrap();
+        col.iter().map(|value| value.as_i64().unwrap()).collect()
+    }
+
+    #[test]
+    fn shuffle_is_a_seeded_permutation() {
+        let csv = numbers(50);
+        let shuffled = values(&csv.shuffle(7));
+        assert_eq!(shuffled, values(&csv.shuffle(7)));
+        assert_ne!(shuffled, values(&csv));
+        let mut sorted = shuffled;
+        sorted.sort_unstable();
+        assert_eq!(sorted, values(&csv));
+    }
+
+    #[test]
+    fn shuffle_carries_row_ids() {
+        let mut csv = numbers(10);
+        csv.track_row_ids();
+        let shuffled = csv.shuffle(3);
+        let ids: Vec<i64> = shuffled
+            .row_ids()
+            .unwrap()
+            .iter()
+            .map(|&id| id as i64)
+            .collect();
+        assert_eq!(ids, values(&shuffled));
+    }
+}
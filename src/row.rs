@@ -0,0 +1,107 @@
+use std::{collections::HashMap, sync::Arc};
+
+use miette::Error;
+
+use crate::{
+    col_parser::{ColType, CsvCol, DataValue},
+    csv_parser::Csv,
+    error::CoalaError,
+    mask::Mask,
+};
+
+/// One row of a frame, from `Csv::rows` or handed to the closure of `Csv::filter_rows`
+#[derive(Clone, Copy)]
+pub struct RowView<'a> {
+    csv: &'a Csv,
+    index: usize,
+}
+
+impl<'a> RowView<'a> {
+    /// Position of the row in the frame
+    pub fn index(&self) -> usize {
+        self.index
+    }
+    /// The value of column `name` in this row
+    pub fn get(&self, name: &str) -> Result<DataValue, Error> {
+        self.csv.get_col(name)?.get(self.index)
+    }
+    /// The values of every column in this row, in header order
+    pub fn iter(&self) -> impl Iterator<Item = DataValue> + 'a {
+        let index = self.index;
+        self.csv
+            .cols
+            .iter()
+            .map(move |col| col.data_as_value(index).unwrap_or(DataValue::Null))
+    }
+    /// `iter` collected
+    pub fn values(&self) -> Vec<DataValue> {
+        self.iter().collect()
+    }
+}
+
+impl Csv {
+    /// The rows in order, each reading its values from the columns on demand
+    pub fn rows(&self) -> impl ExactSizeIterator<Item = RowView<'_>> {
+        (0..self.n_rows).map(|index| RowView { csv: self, index })
+    }
+    /// View of the rows `keep` returns true for, like `filter` with a mask. Prefer `filter_where`
+    /// for plain comparisons, it reads each column once instead of once per row
+    pub fn filter_rows(&self, mut keep: impl FnMut(RowView) -> bool) -> Result<Csv, Error> {
+        let mask: Mask = (0..self.n_rows)
+            .map(|index| keep(RowView { csv: self, index }))
+            .collect();
+        self.filter(&mask)
+    }
+    /// The rows `mask` selects and the rest, both in their original order
+    pub fn partition(&self, mask: &Mask) -> Result<(Csv, Csv), Error> {
+        Ok((self.filter(mask)?, self.filter(&!mask.clone())?))
+    }
+    /// The rows split by the key `key` gives each of them, e.g. to route rows to one output
+    /// per region. Frames come in order of their key's first appearance, nulls and keys
+    /// printing the same count as equal
+    pub fn partition_by(&self, mut key: impl FnMut(RowView) -> DataValue) -> Vec<(DataValue, Csv)> {
+        let mut parts: Vec<(DataValue, Vec<usize>)> = Vec::new();
+        let mut lookup: HashMap<(bool, String), usize> = HashMap::new();
+        for index in 0..self.n_rows {
+            let value = key(RowView { csv: self, index });
+            let lookup_key = (matches!(value, DataValue::Null), value.to_string());
+            let part = *lookup.entry(lookup_key).or_insert_with(|| {
+                parts.push((value, Vec::new()));
+                parts.len() - 1
+            });
+            parts[part].1.push(index);
+        }
+        parts
+            .into_iter()
+            .map(|(value, rows)| {
+                let mut csv = self.take_rows(&rows);
+                csv.lineage.record(format!("partition_by({value})"));
+                (value, csv)
+            })
+            .collect()
+    }
+    /// Copy of the frame with a first column `name` numbering the rows from `offset`, so their
+    /// position survives later filters and sorts
+    pub fn with_row_index(&self, name: &str, offset: usize) -> Result<Csv, Error> {
+        if self.header.iter().any(|h| h == name) {
+            return Err(CoalaError::DuplicateColumn {
+                name: name.to_string(),
+            }
+            .into());
+        }
+        let index = (offset..offset + self.n_rows).map(|i| i as i64).collect();
+        let mut csv = self.clone();
+        csv.cols.insert(
+            0,
+            Arc::new(ColType::Integer(CsvCol::new(name.to_string(), index))),
+        );
+        csv.header.insert(0, name.to_string());
+        csv.n_cols += 1;
+        if let Some(units) = &mut csv.units {
+            units.insert(0, String::new());
+        }
+        csv.lineage
+            .record(format!("with_row_index({name}, {offset})"));
+        Ok(csv)
+    }
+}
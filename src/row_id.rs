@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use miette::{miette, Error};
+
+use crate::csv_parser::Csv;
+
+impl Csv {
+    /// Numbers the rows as they are now, and keeps each row's number through filters, sorts,
+    /// slices and samples so a row of a derived frame can be traced back. Frames loaded with
+    /// `CsvBuilder::row_ids` already number their rows from the first data row of the file.
+    /// Does nothing when the rows are already numbered
+    pub fn track_row_ids(&mut self) {
+        if self.row_ids.is_none() {
+            self.row_ids = Some((0..self.n_rows).collect());
+        }
+    }
+    /// Number of each row, when they're tracked
+    pub fn row_ids(&self) -> Option<&[usize]> {
+        self.row_ids.as_deref()
+    }
+    /// The frame with the row numbers as its column `name`
+    pub fn with_row_id(&self, name: &str) -> Result<Csv, Error> {
+        let ids = self.row_ids.as_ref().ok_or_else(|| {
+            miette!("Row ids aren't tracked, call `track_row_ids` or load with `row_ids(true)`")
+        })?;
+        let mut csv = self.clone();
+        csv.add_col(name, ids.iter().map(|&id| (id as i64).into()).collect())?;
+        Ok(csv)
+    }
+    // Numbers of the rows at `indices`, for frames built from them
+    pub(crate) fn row_ids_at(&self, indices: impl Iterator<Item = usize>) -> Option<Arc<[usize]>> {
+        let ids = self.row_ids.as_ref()?;
+        Some(indices.map(|i| ids[i]).collect())
+    }
+    // Numbers of stacked frames one after the other, when every one of them tracks them
+    pub(crate) fn stacked_row_ids<'a>(
+        frames: impl IntoIterator<Item = &'a Csv>,
+    ) -> Option<Arc<[usize]>> {
+        let mut ids = Vec::new();
+        for frame in frames {
+            ids.extend_from_slice(frame.row_ids.as_ref()?);
+        }
+        Some(ids.into())
+    }
+    // Numbers for `count` rows appended to the frame, following the largest one
+    pub(crate) fn extend_row_ids(&mut self, count: usize) {
+        if let Some(ids) = &self.row_ids {
+            let next = ids.iter().max().map_or(0, |&max| max + 1);
+            let extended: Vec<usize> = ids.iter().copied().chain(next..next + count).collect();
+            self.row_ids = Some(extended.into());
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::PathBuf,
+};
+
+use log::warn;
+use miette::{Error, IntoDiagnostic};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    csv_parser::{Csv, CsvBuilder, CsvConfig},
+    error::CoalaError,
+    tokenizer::{Dialect, Records},
+};
+
+const INDEX_EXTENSION: &str = "coala.idx.json";
+
+pub fn index_path(path: &str) -> PathBuf {
+    PathBuf::from(format!("{path}.{INDEX_EXTENSION}"))
+}
+
+/// Byte offset of every data row of a file, so a range of rows is read by seeking straight to
+/// it. Saved next to the file by `Csv::build_index`, where `Csv::read_range` and `row_offset`
+/// pick it up
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RowIndex {
+    /// Length of the indexed file, one of another length was rewritten since
+    pub source_len: u64,
+    /// Lines skipped before the header and the units row, which change what the rows are
+    pub skip_rows: usize,
+    pub units_row: Option<usize>,
+    /// The separator and quoting decide where records end, a quoted separator being text
+    pub separator: char,
+    pub quote_char: char,
+    pub escape_char: Option<char>,
+    /// Where each row starts, then where the last one ends
+    pub offsets: Vec<usize>,
+}
+
+impl RowIndex {
+    pub fn n_rows(&self) -> usize {
+        self.offsets.len().saturating_sub(1)
+    }
+    /// Where row `row` starts, the end of the data past the last row
+    pub fn offset(&self, row: usize) -> usize {
+        self.offsets[row.min(self.n_rows())]
+    }
+    /// The index saved next to `path`, `None` when there's none or the file changed since
+    pub fn read(path: &str) -> Result<Option<Self>, Error> {
+        let index_path = index_path(path);
+        if !index_path.exists() {
+            return Ok(None);
+        }
+        let source_len = fs::metadata(path).map_err(CoalaError::from)?.len();
+        let content = fs::read_to_string(&index_path).map_err(CoalaError::from)?;
+        let index: RowIndex = match serde_json::from_str(&content) {
+            Ok(index) => index,
+            Err(e) => {
+                warn!(
+                    "Ignoring unreadable row index `{}`. Reason: {e}",
+                    index_path.display()
+                );
+                return Ok(None);
+            }
+        };
+        Ok((index.source_len == source_len).then_some(index))
+    }
+    pub fn write(&self, path: &str) -> Result<(), Error> {
+        let content = serde_json::to_string(self).into_diagnostic()?;
+        fs::write(index_path(path), content).map_err(|e| CoalaError::from(e).into())
+    }
+    // Whether the rows were indexed the way `config` reads the file
+    pub(crate) fn matches(&self, config: &CsvConfig) -> bool {
+        self.skip_rows == config.header.unwrap_or(0)
+            && self.units_row == config.units_row
+            && self.separator == config.separator
+            && self.quote_char == config.quote_char
+            && self.escape_char == config.escape_char
+    }
+}
+
+impl CsvBuilder<'_> {
+    /// Scans `path` for where its rows start, storing no field, and saves the index next to
+    /// it. Later loads of a range of rows seek to it instead of scanning the rows before
+    pub fn build_index(&self, path: &str) -> Result<RowIndex, Error> {
+        let config = &self.config;
+        let file = File::open(path).map_err(CoalaError::from)?;
+        let source_len = file.metadata().map_err(CoalaError::from)?.len();
+        let mut records = Records::new(BufReader::new(file), Dialect::from(config));
+        records.skip_records(config.header.unwrap_or(0) + 1)?;
+        let mut offsets = Vec::new();
+        let mut record = 0;
+        loop {
+            let start = records.bytes_read();
+            if records.skip_records(1)? == 0 {
+                offsets.push(start);
+                break;
+            }
+            if config.units_row != Some(record) {
+                offsets.push(start);
+            }
+            record += 1;
+        }
+        let index = RowIndex {
+            source_len,
+            skip_rows: config.header.unwrap_or(0),
+            units_row: config.units_row,
+            separator: config.separator,
+            quote_char: config.quote_char,
+            escape_char: config.escape_char,
+            offsets,
+        };
+        index.write(path)?;
+        Ok(index)
+    }
+}
+
+impl Csv {
+    /// `build_index` with the default config
+    pub fn build_index(path: &str) -> Result<RowIndex, Error> {
+        Csv::reader().build_index(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    fn indexed(name: &str, contents: &str) -> (String, RowIndex) {
+        let path = env::temp_dir().join(format!("coala-index-{}-{name}.csv", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        fs::write(&path, contents).unwrap();
+        let index = Csv::build_index(&path).unwrap();
+        (path, index)
+    }
+
+    fn remove(path: &str) {
+        fs::remove_file(path).unwrap();
+        fs::remove_file(index_path(path)).unwrap();
+    }
+
+    #[test]
+    fn offsets_skip_quoted_line_breaks() {
+        let (path, index) = indexed("offsets", "a,b\n1,\"x\ny\"\n2,z\n");
+        let read = RowIndex::read(&path).unwrap();
+        remove(&path);
+        assert_eq!(index.offsets, vec![4, 12, 16]);
+        assert_eq!(index.n_rows(), 2);
+        assert_eq!(index.offset(5), 16);
+        assert_eq!(read, Some(index));
+    }
+
+    #[test]
+    fn changed_separator_invalidates_index() {
+        let (path, index) = indexed("separator", "a;b\n1;2\n");
+        remove(&path);
+        let mut config = CsvConfig::default();
+        assert!(index.matches(&config));
+        config.separator = ';';
+        assert!(!index.matches(&config));
+    }
+
+    #[test]
+    fn rewritten_file_invalidates_index() {
+        let (path, _) = indexed("rewritten", "a,b\n1,2\n");
+        fs::write(&path, "a,b\n1,2\n3,4\n").unwrap();
+        let read = RowIndex::read(&path).unwrap();
+        remove(&path);
+        assert_eq!(read, None);
+    }
+}
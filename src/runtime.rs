@@ -0,0 +1,52 @@
+use crate::reader::{CsvReader, DEFAULT_CHUNK_SIZE};
+
+/// Resource limits shared by the loader and the chunked operators, so several jobs can share
+/// a machine without each assuming it owns all of it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Runtime {
+    pub threads: usize,
+    pub chunk_size: usize,
+    /// Soft cap in bytes on the data held at once. Operators pick a slower path that stays
+    /// under it, without accounting for every allocation
+    pub memory_budget: Option<usize>,
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self {
+            threads: 1,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            memory_budget: None,
+        }
+    }
+}
+
+impl Runtime {
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+    /// Every core the process may use
+    pub fn all_threads(self) -> Self {
+        let threads = std::thread::available_parallelism().map_or(1, usize::from);
+        self.threads(threads)
+    }
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+    pub fn memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = Some(bytes);
+        self
+    }
+}
+
+impl CsvReader {
+    /// Reads chunks of `runtime.chunk_size` records, cut short once a chunk's raw bytes reach
+    /// the memory budget
+    pub fn with_runtime(self, runtime: &Runtime) -> Self {
+        let mut reader = self.with_chunk_size(runtime.chunk_size);
+        reader.max_chunk_bytes = runtime.memory_budget;
+        reader
+    }
+}
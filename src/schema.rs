@@ -0,0 +1,170 @@
+use std::{fs::File, io::BufReader};
+
+use datetime::datetime::Datetime;
+use miette::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    col_parser::DType,
+    csv_parser::{rename_header, Csv, CsvBuilder, CsvConfig},
+    error::CoalaError,
+    tokenizer::{Dialect, Records},
+};
+
+// Tried on text columns by `infer_schema`, most common first
+const DATE_FORMATS: [&str; 8] = [
+    "%Y-%m-%d",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y/%m/%d",
+    "%d/%m/%Y",
+    "%m/%d/%Y",
+    "%d.%m.%Y",
+    "%d-%m-%Y",
+];
+
+/// What loading a column would give, from `infer_schema`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub dtype: DType,
+    /// Distinct cells of the sample read as null
+    pub null_tokens: Vec<String>,
+    /// Formats every value of the sample parses with, for text and datetime columns
+    pub date_formats: Vec<String>,
+}
+
+/// The columns a load would give, inferred on the first rows of a file. Types can be changed
+/// before loading with it through `CsvBuilder::schema`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Schema {
+    pub columns: Vec<ColumnSchema>,
+    /// Rows the types were inferred on
+    pub sample_rows: usize,
+}
+
+impl Schema {
+    pub fn column(&self, name: &str) -> Option<&ColumnSchema> {
+        self.columns.iter().find(|col| col.name == name)
+    }
+    /// Overrides the type a column will be read as
+    pub fn set_dtype(&mut self, name: &str, dtype: DType) -> Result<(), Error> {
+        let col = self
+            .columns
+            .iter_mut()
+            .find(|col| col.name == name)
+            .ok_or_else(|| CoalaError::MissingColumn {
+                name: name.to_string(),
+            })?;
+        col.dtype = dtype;
+        Ok(())
+    }
+}
+
+// The header and first `sample_rows` data records of the file as text, renamed as the config asks
+fn sample(
+    path: &str,
+    config: &CsvConfig,
+    sample_rows: usize,
+) -> Result<(Vec<String>, Vec<Vec<String>>), Error> {
+    let file = File::open(path).map_err(CoalaError::from)?;
+    let mut records = Records::new(BufReader::new(file), Dialect::from(config));
+    for _ in 0..config.header.unwrap_or(0) {
+        records.next().transpose()?;
+    }
+    let mut header: Vec<String> = match records.next() {
+        Some(header) => header?.into_iter().map(String::from).collect(),
+        None => return Err(CoalaError::UnexpectedEof.into()),
+    };
+    rename_header(&config.rename_map, &mut header)?;
+    let mut rows = Vec::new();
+    for (i, record) in records.enumerate() {
+        if rows.len() == sample_rows {
+            break;
+        }
+        let record = record?;
+        if config.units_row != Some(i) {
+            rows.push(record.into_iter().map(String::from).collect());
+        }
+    }
+    Ok((header, rows))
+}
+
+impl<'a> CsvBuilder<'a> {
+    /// The columns `finish` would give, inferred on the first `sample_rows` rows alone. Only
+    /// those rows are read, so it's cheap on huge files
+    pub fn infer_schema(&self, path: &str, sample_rows: usize) -> Result<Schema, Error> {
+        let mut config = self.config.clone();
+        config.n_rows = Some(sample_rows);
+        let preview = Csv::new(path, config.clone())?;
+        let (header, rows) = sample(path, &config, sample_rows)?;
+        let columns = preview
+            .header
+            .iter()
+            .zip(&preview.cols)
+            .map(|(name, col)| {
+                let index = header.iter().position(|h| h == name);
+                let cells: Vec<&str> = rows
+                    .iter()
+                    .filter_map(|row| row.get(index?).map(String::as_str))
+                    .collect();
+                let mut null_tokens: Vec<String> = Vec::new();
+                for cell in &cells {
+                    if config.null_values.iter().any(|null| null == cell)
+                        && !null_tokens.iter().any(|token| token == cell)
+                    {
+                        null_tokens.push(cell.to_string());
+                    }
+                }
+                let values: Vec<&str> = cells
+                    .into_iter()
+                    .filter(|cell| !null_tokens.iter().any(|token| token == cell))
+                    .collect();
+                let date_formats = match col.dtype() {
+                    DType::String | DType::Datetime if !values.is_empty() => DATE_FORMATS
+                        .iter()
+                        .filter(|format| {
+                            values
+                                .iter()
+                                .all(|value| Datetime::from_str(value, format).is_ok())
+                        })
+                        .map(|format| format.to_string())
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                ColumnSchema {
+                    name: name.clone(),
+                    dtype: col.dtype(),
+                    null_tokens,
+                    date_formats,
+                }
+            })
+            .collect();
+        Ok(Schema {
+            columns,
+            sample_rows: preview.n_rows,
+        })
+    }
+    /// Reads every column of `schema` as its type. Datetime columns try its date formats,
+    /// or guess when it has none
+    pub fn schema(mut self, schema: &'a Schema) -> Self {
+        for col in &schema.columns {
+            self = match col.dtype {
+                DType::Datetime if col.date_formats.is_empty() => self.dates(&[&col.name]),
+                DType::Datetime => col.date_formats.iter().fold(self, |builder, format| {
+                    builder.date_format(&col.name, format)
+                }),
+                DType::Custom(_) | DType::List | DType::Struct => self,
+                dtype => self.dtype(&col.name, dtype),
+            };
+        }
+        self
+    }
+}
+
+impl Csv {
+    /// `infer_schema` with the default config
+    pub fn infer_schema(path: &str, sample_rows: usize) -> Result<Schema, Error> {
+        Csv::reader().infer_schema(path, sample_rows)
+    }
+}
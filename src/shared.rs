@@ -0,0 +1,74 @@
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+use miette::Error;
+
+use crate::{col_parser::ColType, csv_parser::Csv};
+
+// Takes a column out of its `Arc`, copying it only while another frame still shares it.
+// Views come out as columns of their own
+pub(crate) fn unshare(col: Arc<ColType>) -> ColType {
+    match &*col {
+        ColType::View(view) => view.materialize(),
+        _ => {
+            Arc::try_unwrap(col).unwrap_or_else(|col| col.take(&(0..col.len()).collect::<Vec<_>>()))
+        }
+    }
+}
+
+/// A frame several threads read while others transform it. Cloning a `Csv` shares its column
+/// buffers, so writers work on a cheap copy and publish it when done: readers keep the
+/// snapshot they took and never wait for a transform to finish
+#[derive(Debug)]
+pub struct SharedCsv {
+    current: RwLock<Arc<Csv>>,
+    // Writers take turns, so no update is built on a stale copy and lost
+    writer: Mutex<()>,
+}
+
+impl SharedCsv {
+    pub fn new(csv: Csv) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(csv)),
+            writer: Mutex::new(()),
+        }
+    }
+    /// The latest published frame, unaffected by updates made after it was taken
+    pub fn snapshot(&self) -> Arc<Csv> {
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+    /// Runs `transform` on a copy of the latest frame and publishes the result if it
+    /// succeeds. Columns the transform doesn't replace stay shared with earlier snapshots
+    pub fn update<R>(
+        &self,
+        transform: impl FnOnce(&mut Csv) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        let _turn = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut csv = Csv::clone(&self.snapshot());
+        let result = transform(&mut csv)?;
+        *self.current.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(csv);
+        Ok(result)
+    }
+}
+
+impl Csv {
+    /// A copy holding column buffers of its own, e.g. so a small slice doesn't keep the
+    /// buffers of a large frame alive. Views come out as plain columns
+    pub fn deep_copy(&self) -> Csv {
+        let mut csv = self.clone();
+        // `self` still holds every column, so each is copied
+        csv.cols = self
+            .cols
+            .iter()
+            .map(|col| Arc::new(unshare(Arc::clone(col))))
+            .collect();
+        csv
+    }
+    /// A read-only handle to the frame, cheap to clone and to send to other threads. It
+    /// shares the column buffers of `self`
+    pub fn share(&self) -> Arc<Csv> {
+        Arc::new(self.clone())
+    }
+}
@@ -0,0 +1,19 @@
+use miette::Error;
+
+use crate::{concat::MissingColumns, csv_parser::Csv};
+
+/// Loads the file at `path` with the default config, `Csv::reader().finish(path)`
+pub fn read_csv(path: &str) -> Result<Csv, Error> {
+    Csv::reader().finish(path)
+}
+
+/// The rows of every frame in turn, matching columns by name and filling the columns a frame
+/// lacks with nulls, `Csv::union_by_name` with `MissingColumns::FillNull`
+pub fn concat(frames: &[Csv]) -> Result<Csv, Error> {
+    Csv::union_by_name(frames, MissingColumns::FillNull)
+}
+
+/// Inner join of `left` and `right` on column `on`, `Csv::merge`
+pub fn merge(left: &Csv, right: &Csv, on: &str) -> Result<Csv, Error> {
+    left.merge(right, on)
+}
@@ -0,0 +1,172 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use log::warn;
+use miette::{Error, IntoDiagnostic};
+use serde::{Deserialize, Serialize};
+
+use crate::{col_parser::DType, csv_parser::Csv, error::CoalaError, lineage::Lineage};
+
+const SIDECAR_EXTENSION: &str = "coala.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sidecar {
+    pub source_len: u64,
+    pub n_rows: usize,
+    pub columns: Vec<ColumnSummary>,
+    #[serde(default)]
+    pub lineage: Option<Lineage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnSummary {
+    pub name: String,
+    pub dtype: DType,
+    pub min: Option<String>,
+    pub max: Option<String>,
+    pub null_count: usize,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub meta: BTreeMap<String, String>,
+}
+
+pub fn sidecar_path(path: &str) -> PathBuf {
+    PathBuf::from(format!("{path}.{SIDECAR_EXTENSION}"))
+}
+
+impl Sidecar {
+    pub fn read(path: &str) -> Result<Option<Self>, Error> {
+        let sidecar_path = sidecar_path(path);
+        if !sidecar_path.exists() {
+            return Ok(None);
+        }
+        let source_len = fs::metadata(path).map_err(CoalaError::from)?.len();
+        let content = fs::read_to_string(&sidecar_path).map_err(CoalaError::from)?;
+        let sidecar: Sidecar = match serde_json::from_str(&content) {
+            Ok(sidecar) => sidecar,
+            Err(e) => {
+                warn!(
+                    "Ignoring unreadable sidecar `{}`. Reason: {e}",
+                    sidecar_path.display()
+                );
+                return Ok(None);
+            }
+        };
+        // A size mismatch means the csv was rewritten after the sidecar was emitted
+        Ok((sidecar.source_len == source_len).then_some(sidecar))
+    }
+    pub fn write(&self, path: &str) -> Result<(), Error> {
+        let content = serde_json::to_string_pretty(self).into_diagnostic()?;
+        fs::write(sidecar_path(path), content).map_err(|e| CoalaError::from(e).into())
+    }
+    pub(crate) fn matches(&self, header: &[String], n_rows: usize) -> bool {
+        self.n_rows == n_rows
+            && self.columns.len() == header.len()
+            && self.columns.iter().zip(header).all(|(c, h)| c.name == *h)
+    }
+    pub(crate) fn populate_cache(&self, csv: &mut Csv) {
+        for col in &self.columns {
+            if !col.meta.is_empty() {
+                csv.meta.insert(col.name.clone(), col.meta.clone());
+            }
+            let parse = |raw: &Option<String>| {
+                raw.as_deref()
+                    .and_then(|raw| col.dtype.parse::<&str>(raw, &[]).ok())
+            };
+            let (min, max) = (parse(&col.min), parse(&col.max));
+            if min.is_none() && max.is_none() {
+                continue;
+            }
+            let cache = csv.cache.entry(col.name.clone()).or_default();
+            cache.min = min;
+            cache.max = max;
+        }
+    }
+}
+
+impl Csv {
+    pub fn write_sidecar(&mut self, path: &str) -> Result<(), Error> {
+        let source_len = fs::metadata(path).map_err(CoalaError::from)?.len();
+        let mut columns = Vec::with_capacity(self.n_cols);
+        for i in 0..self.n_cols {
+            let name = self.header[i].clone();
+            columns.push(ColumnSummary {
+                dtype: self.cols[i].dtype(),
+                min: self.min(&name).ok().map(|v| v.to_string()),
+                max: self.max(&name).ok().map(|v| v.to_string()),
+                null_count: self.cols[i].null_count(),
+                meta: self.meta.get(&name).cloned().unwrap_or_default(),
+                name,
+            });
+        }
+        Sidecar {
+            source_len,
+            n_rows: self.n_rows,
+            columns,
+            lineage: Some(self.lineage.clone()),
+        }
+        .write(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::col_parser::DataValue;
+
+    fn source(name: &str, contents: &str) -> String {
+        let path = env::temp_dir().join(format!("coala-sidecar-{}-{name}.csv", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn remove(path: &str) {
+        fs::remove_file(path).unwrap();
+        let _ = fs::remove_file(sidecar_path(path));
+    }
+
+    // Loads `path` with its sidecar, after making the sidecar claim `min` for column `a`
+    fn load_with_min(path: &str, min: &str) -> Csv {
+        let mut sidecar = Sidecar::read(path).unwrap().unwrap();
+        sidecar.columns[0].min = Some(min.to_string());
+        sidecar.write(path).unwrap();
+        Csv::reader().use_sidecar(true).finish(path).unwrap()
+    }
+
+    #[test]
+    fn loads_take_cached_statistics() {
+        let path = source("cached", "a,b\n3,x\n5,y\n");
+        let mut csv = crate::read_csv(&path).unwrap();
+        csv.write_sidecar(&path).unwrap();
+        let mut loaded = load_with_min(&path, "-1");
+        remove(&path);
+        assert_eq!(loaded.min("a").unwrap().as_i64(), Some(-1));
+        assert_eq!(loaded.max("a").unwrap().as_i64(), Some(5));
+        assert!(matches!(loaded.min("b").unwrap(), DataValue::String(s) if s == "x"));
+    }
+
+    #[test]
+    fn rewritten_source_ignores_sidecar() {
+        let path = source("rewritten", "a\n3\n5\n");
+        let mut csv = crate::read_csv(&path).unwrap();
+        csv.write_sidecar(&path).unwrap();
+        let mut sidecar = Sidecar::read(&path).unwrap().unwrap();
+        sidecar.columns[0].min = Some("-1".to_string());
+        sidecar.write(&path).unwrap();
+        fs::write(&path, "a\n30\n50\n").unwrap();
+        assert!(Sidecar::read(&path).unwrap().is_none());
+        let mut loaded = Csv::reader().use_sidecar(true).finish(&path).unwrap();
+        remove(&path);
+        assert_eq!(loaded.min("a").unwrap().as_i64(), Some(30));
+    }
+
+    #[test]
+    fn unreadable_sidecar_is_ignored() {
+        let path = source("unreadable", "a\n1\n");
+        fs::write(sidecar_path(&path), "not json").unwrap();
+        let read = Sidecar::read(&path).unwrap();
+        remove(&path);
+        assert!(read.is_none());
+    }
+}
@@ -0,0 +1,237 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use miette::{miette, Error};
+
+const DEFAULT_COMPRESSION: f64 = 100.0;
+const BUFFER_SIZE: usize = 512;
+const DEFAULT_PRECISION: u8 = 14;
+const DEFAULT_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+#[derive(Debug, Clone, Copy)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+// Merging t-digest: values are buffered and periodically folded into a bounded set of centroids
+#[derive(Debug, Clone)]
+pub struct TDigest {
+    compression: f64,
+    centroids: Vec<Centroid>,
+    buffer: Vec<f64>,
+    min: f64,
+    max: f64,
+}
+
+impl Default for TDigest {
+    fn default() -> Self {
+        Self::new(DEFAULT_COMPRESSION)
+    }
+}
+
+impl TDigest {
+    pub fn new(compression: f64) -> Self {
+        Self {
+            compression,
+            centroids: Vec::new(),
+            buffer: Vec::with_capacity(BUFFER_SIZE),
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+    pub fn push(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.buffer.push(value);
+        if self.buffer.len() >= BUFFER_SIZE {
+            self.compress();
+        }
+    }
+    pub fn merge(&mut self, other: &TDigest) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.centroids.extend_from_slice(&other.centroids);
+        self.buffer.extend_from_slice(&other.buffer);
+        self.compress();
+    }
+    pub fn count(&self) -> f64 {
+        self.centroids.iter().map(|c| c.weight).sum::<f64>() + self.buffer.len() as f64
+    }
+    pub fn quantile(&mut self, quantile: f64) -> Option<f64> {
+        self.compress();
+        let (first, last) = (self.centroids.first()?, self.centroids.last()?);
+        let total: f64 = self.centroids.iter().map(|c| c.weight).sum();
+        let target = quantile.clamp(0.0, 1.0) * total;
+        if target <= first.weight / 2.0 {
+            return Some(interpolate(
+                self.min,
+                first.mean,
+                target / (first.weight / 2.0),
+            ));
+        }
+        if target >= total - last.weight / 2.0 {
+            let remaining = (total - target) / (last.weight / 2.0);
+            return Some(interpolate(self.max, last.mean, remaining));
+        }
+        let mut cumulative = first.weight / 2.0;
+        for pair in self.centroids.windows(2) {
+            let step = (pair[0].weight + pair[1].weight) / 2.0;
+            if target <= cumulative + step {
+                return Some(interpolate(
+                    pair[0].mean,
+                    pair[1].mean,
+                    (target - cumulative) / step,
+                ));
+            }
+            cumulative += step;
+        }
+        Some(last.mean)
+    }
+    fn compress(&mut self) {
+        if self.buffer.is_empty() && self.centroids.len() <= 1 {
+            return;
+        }
+        let mut all = std::mem::take(&mut self.centroids);
+        all.extend(
+            self.buffer
+                .drain(..)
+                .map(|mean| Centroid { mean, weight: 1.0 }),
+        );
+        all.sort_unstable_by(|a, b| a.mean.total_cmp(&b.mean));
+        let total: f64 = all.iter().map(|c| c.weight).sum();
+        let mut merged = Vec::with_capacity(all.len());
+        let mut current = all[0];
+        let mut weight_so_far = 0.0;
+        for next in all.into_iter().skip(1) {
+            let q = (weight_so_far + current.weight + next.weight / 2.0) / total;
+            let limit = (4.0 * total * q * (1.0 - q) / self.compression).max(1.0);
+            if current.weight + next.weight <= limit {
+                let weight = current.weight + next.weight;
+                current.mean += (next.mean - current.mean) * next.weight / weight;
+                current.weight = weight;
+            } else {
+                weight_so_far += current.weight;
+                merged.push(current);
+                current = next;
+            }
+        }
+        merged.push(current);
+        self.centroids = merged;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    n_bits: u64,
+    n_hashes: u32,
+}
+
+impl BloomFilter {
+    pub fn with_capacity(expected_items: usize) -> Self {
+        Self::with_rate(expected_items, DEFAULT_FALSE_POSITIVE_RATE)
+    }
+    pub fn with_rate(expected_items: usize, false_positive_rate: f64) -> Self {
+        let n = expected_items.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let n_bits = (-n * false_positive_rate.clamp(1e-9, 0.5).ln() / (ln2 * ln2))
+            .ceil()
+            .max(64.0) as u64;
+        let n_hashes = ((n_bits as f64 / n) * ln2).round().clamp(1.0, 16.0) as u32;
+        Self {
+            bits: vec![0; n_bits.div_ceil(64) as usize],
+            n_bits,
+            n_hashes,
+        }
+    }
+    pub fn insert<T: Hash + ?Sized>(&mut self, value: &T) {
+        for bit in self.bit_positions(value) {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+    pub fn contains<T: Hash + ?Sized>(&self, value: &T) -> bool {
+        self.bit_positions(value)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+    fn bit_positions<T: Hash + ?Sized>(&self, value: &T) -> impl Iterator<Item = u64> + use<T> {
+        let hash = hash_of(value);
+        let (h1, h2) = (hash & 0xffff_ffff, hash >> 32);
+        let n_bits = self.n_bits;
+        // Double hashing derives every probe from a single 64 bit hash
+        (0..self.n_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % n_bits)
+    }
+}
+
+fn hash_of<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn interpolate(from: f64, to: f64, fraction: f64) -> f64 {
+    from + (to - from) * fraction.clamp(0.0, 1.0)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_PRECISION)
+    }
+}
+
+impl HyperLogLog {
+    pub fn new(precision: u8) -> Self {
+        let precision = precision.clamp(4, 18);
+        Self {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+    pub fn push<T: Hash + ?Sized>(&mut self, value: &T) {
+        let hash = hash_of(value);
+        let p = self.precision as u32;
+        let index = (hash >> (64 - p)) as usize;
+        // The sentinel bit bounds the run of zeros when the remaining bits are all zero
+        let rest = (hash << p) | (1 << (p - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+    pub fn merge(&mut self, other: &HyperLogLog) -> Result<(), Error> {
+        if self.precision != other.precision {
+            return Err(miette!(
+                "Can't merge HyperLogLog sketches with precision {} and {}",
+                self.precision,
+                other.precision
+            ));
+        }
+        for (register, other) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(*other);
+        }
+        Ok(())
+    }
+    pub fn estimate(&self) -> f64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let estimate = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            estimate
+        }
+    }
+}
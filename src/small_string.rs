@@ -0,0 +1,69 @@
+use std::{fmt, ops::Deref};
+
+const INLINE_CAPACITY: usize = 22;
+
+// Same size as a `String`, but values up to 22 bytes are stored inline. Most cells of a typical
+// file are short codes, numbers or dates, so the tokenizer mostly never touches the heap
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) enum SmallString {
+    Inline {
+        len: u8,
+        bytes: [u8; INLINE_CAPACITY],
+    },
+    Heap(Box<str>),
+}
+
+impl From<&str> for SmallString {
+    fn from(value: &str) -> Self {
+        if value.len() > INLINE_CAPACITY {
+            return SmallString::Heap(value.into());
+        }
+        let mut bytes = [0; INLINE_CAPACITY];
+        bytes[..value.len()].copy_from_slice(value.as_bytes());
+        SmallString::Inline {
+            len: value.len() as u8,
+            bytes,
+        }
+    }
+}
+
+impl From<SmallString> for String {
+    fn from(value: SmallString) -> Self {
+        match value {
+            SmallString::Inline { .. } => value.to_string(),
+            SmallString::Heap(value) => value.into_string(),
+        }
+    }
+}
+
+impl Deref for SmallString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            // Only ever filled from a whole `&str`, so the bytes are valid UTF-8
+            SmallString::Inline { len, bytes } => {
+                std::str::from_utf8(&bytes[..*len as usize]).unwrap_or_default()
+            }
+            SmallString::Heap(value) => value,
+        }
+    }
+}
+
+impl AsRef<str> for SmallString {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl fmt::Display for SmallString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self)
+    }
+}
+
+impl fmt::Debug for SmallString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
@@ -0,0 +1,176 @@
+use std::cmp::Ordering;
+
+use datetime::datetime::Datetime;
+use miette::Error;
+
+use crate::{
+    col_parser::{ColType, DataValue},
+    csv_parser::{ColViewer, Csv},
+    perf::Timer,
+};
+
+// Below this, a comparison sort beats the fixed cost of the radix passes
+const RADIX_THRESHOLD: usize = 256;
+
+/// How a column's values get sorted, so integers and floats skip the generic `partial_cmp` path
+pub(crate) trait SortKey: Clone {
+    fn sort(values: &mut [Self]);
+    /// Row indices in ascending order of their value, stable for equal values
+    fn argsort(values: &[Self]) -> Vec<usize>;
+}
+
+fn partial_order<T: PartialOrd>(a: &T, b: &T) -> Ordering {
+    a.partial_cmp(b).unwrap_or(Ordering::Equal)
+}
+
+// Flipping the sign bit makes the unsigned order of the key match the signed order
+fn i64_key(value: i64) -> u64 {
+    (value as u64) ^ (1 << 63)
+}
+
+// LSD radix sort, one byte per pass. Passes where every key shares the byte are skipped
+fn radix_sort_by_key<T: Copy, F: Fn(&T) -> u64>(items: &mut [T], key: F) {
+    let mut scratch = items.to_vec();
+    let (mut from, mut to) = (items, scratch.as_mut_slice());
+    let mut swapped = false;
+    for shift in (0..64).step_by(8) {
+        let mut counts = [0usize; 256];
+        for item in from.iter() {
+            counts[((key(item) >> shift) & 0xff) as usize] += 1;
+        }
+        if counts.contains(&from.len()) {
+            continue;
+        }
+        let mut offsets = [0usize; 256];
+        for byte in 1..256 {
+            offsets[byte] = offsets[byte - 1] + counts[byte - 1];
+        }
+        for item in from.iter() {
+            let byte = ((key(item) >> shift) & 0xff) as usize;
+            to[offsets[byte]] = *item;
+            offsets[byte] += 1;
+        }
+        std::mem::swap(&mut from, &mut to);
+        swapped = !swapped;
+    }
+    if swapped {
+        to.copy_from_slice(from);
+    }
+}
+
+impl SortKey for i64 {
+    fn sort(values: &mut [Self]) {
+        if values.len() < RADIX_THRESHOLD {
+            values.sort_unstable();
+        } else {
+            radix_sort_by_key(values, |&v| i64_key(v));
+        }
+    }
+    fn argsort(values: &[Self]) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..values.len()).collect();
+        if values.len() < RADIX_THRESHOLD {
+            indices.sort_by_key(|&i| values[i]);
+        } else {
+            radix_sort_by_key(&mut indices, |&i| i64_key(values[i]));
+        }
+        indices
+    }
+}
+
+// `total_cmp` orders NaNs after every number instead of treating them as equal to anything
+impl SortKey for f64 {
+    fn sort(values: &mut [Self]) {
+        values.sort_unstable_by(f64::total_cmp);
+    }
+    fn argsort(values: &[Self]) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..values.len()).collect();
+        indices.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+        indices
+    }
+}
+
+macro_rules! partial_sort_key {
+    ($($t:ty),*) => ($(
+        impl SortKey for $t {
+            fn sort(values: &mut [Self]) {
+                values.sort_unstable_by(partial_order);
+            }
+            fn argsort(values: &[Self]) -> Vec<usize> {
+                let mut indices: Vec<usize> = (0..values.len()).collect();
+                indices.sort_by(|&a, &b| partial_order(&values[a], &values[b]));
+                indices
+            }
+        }
+    )*)
+}
+
+partial_sort_key!(Datetime);
+#[cfg(feature = "decimal")]
+partial_sort_key!(rust_decimal::Decimal);
+#[cfg(feature = "bigint")]
+partial_sort_key!(num_bigint::BigInt);
+
+impl ColViewer<'_> {
+    /// Row indices that put the column in ascending order, nulls last. Equal values keep their
+    /// row order and values that can't be compared count as equal
+    pub fn argsort(&self) -> Vec<usize> {
+        let mut indices = self.inner.with_plain(|col| match col {
+            ColType::Integer(col) => i64::argsort(&col.values),
+            ColType::Float(col) => f64::argsort(&col.values),
+            ColType::Datetime(col) => Datetime::argsort(&col.values),
+            ColType::String(col) => {
+                let mut indices: Vec<usize> = (0..col.len()).collect();
+                indices.sort_by_key(|&i| col.get(i));
+                indices
+            }
+            ColType::Custom(col) => col.argsort(),
+            _ => {
+                let values: Vec<DataValue> = self.iter().collect();
+                let mut indices: Vec<usize> = (0..values.len()).collect();
+                indices.sort_by(|&a, &b| values[a].compare(&values[b]).unwrap_or(Ordering::Equal));
+                indices
+            }
+        });
+        // Stable, so the nulls stay in row order
+        if self.null_count() > 0 {
+            indices.sort_by_key(|&i| self.inner.is_null_at(i));
+        }
+        indices
+    }
+}
+
+impl Csv {
+    /// Reorders the rows so `name` is ascending, keeping ties in their original order
+    pub fn sort_by(&self, name: &str) -> Result<Csv, Error> {
+        let _timer = Timer::start(|| format!("Sorting by `{name}`"));
+        let order = self.get_col(name)?.argsort();
+        let mut csv = self.take_rows(&order);
+        csv.lineage.record(format!("sort_by({name})"));
+        Ok(csv)
+    }
+    /// The rows in reverse order, the last one first
+    pub fn reverse(&self) -> Csv {
+        let order: Vec<usize> = (0..self.n_rows).rev().collect();
+        let mut csv = self.take_rows(&order);
+        csv.lineage.record("reverse".to_string());
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::col_parser::{DType, DataValue};
+    use crate::csv_parser::Csv;
+
+    #[test]
+    fn reverse_puts_the_last_row_first() {
+        let records = [1, 2, 3].map(|i| vec![DataValue::Integer(i)]);
+        let mut csv = Csv::from_records(records, &[("n", DType::Integer)]).unwrap();
+        csv.track_row_ids();
+        let reversed = csv.reverse();
+        let values: Vec<DataValue> = reversed.get_col("n").unwrap().iter().collect();
+        assert_eq!(values, [3, 2, 1].map(DataValue::Integer));
+        assert_eq!(reversed.row_ids(), Some(&[2, 1, 0][..]));
+        assert_eq!(csv.reverse().reverse().row_ids(), csv.row_ids());
+    }
+}
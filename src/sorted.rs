@@ -0,0 +1,97 @@
+use std::cmp::Ordering;
+
+use miette::Error;
+
+use crate::{
+    col_parser::ColType,
+    csv_parser::{ColViewer, Csv},
+    error::CoalaError,
+};
+
+// Values that can't be ordered against their neighbour, nulls included, break every order
+impl ColViewer<'_> {
+    /// First row out of order with its predecessor
+    fn first_unordered(&self, wanted: fn(Ordering) -> bool) -> Option<usize> {
+        let mut values = self.iter();
+        let mut previous = values.next()?;
+        for (row, value) in values.enumerate() {
+            if !previous.compare(&value).is_some_and(wanted) {
+                return Some(row + 1);
+            }
+            previous = value;
+        }
+        None
+    }
+    pub fn is_monotonic_increasing(&self) -> bool {
+        self.first_unordered(Ordering::is_le).is_none()
+    }
+    pub fn is_monotonic_decreasing(&self) -> bool {
+        self.first_unordered(Ordering::is_ge).is_none()
+    }
+    pub fn is_strictly_increasing(&self) -> bool {
+        self.first_unordered(Ordering::is_lt).is_none()
+    }
+    /// Whether the column is ordered in either direction
+    pub fn is_sorted(&self) -> bool {
+        self.is_monotonic_increasing() || self.is_monotonic_decreasing()
+    }
+}
+
+impl Csv {
+    /// Fails unless `name` is in ascending order, naming the first row out of place
+    pub fn assert_sorted_by(&self, name: &str) -> Result<(), Error> {
+        match self.get_col(name)?.first_unordered(Ordering::is_le) {
+            Some(row) => Err(CoalaError::Unsorted {
+                col: name.to_string(),
+                row,
+            }
+            .into()),
+            None => Ok(()),
+        }
+    }
+    /// Interleaves the rows of two frames already sorted ascending on `on`, in linear time.
+    /// Both frames need the same columns, ties keep the rows of `self` first.
+    /// Statistics already computed on both frames carry over combined
+    pub fn merge_sorted(&self, other: &Csv, on: &str) -> Result<Csv, Error> {
+        self.assert_sorted_by(on)?;
+        other.assert_sorted_by(on)?;
+        let other_cols = self.matching_cols(other)?;
+        let (left, right) = (self.get_col(on)?, other.get_col(on)?);
+        let (mut left, mut right) = (left.iter().peekable(), right.iter().peekable());
+        // `true` takes the next row of `self`
+        let mut order = Vec::with_capacity(self.n_rows + other.n_rows);
+        while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+            let from_left = l.compare(r).is_none_or(Ordering::is_le);
+            order.push(from_left);
+            if from_left {
+                left.next();
+            } else {
+                right.next();
+            }
+        }
+        order.extend(left.map(|_| true));
+        order.extend(right.map(|_| false));
+        let cols = self
+            .cols
+            .iter()
+            .zip(other_cols)
+            .map(|(col, j)| {
+                let (left, right) = (ColViewer::new(col), ColViewer::new(&other.cols[j]));
+                let (mut left, mut right) = (left.iter(), right.iter());
+                let values = order
+                    .iter()
+                    .map(|&from_left| if from_left { left.next() } else { right.next() })
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or(CoalaError::OutOfRange)?;
+                ColType::from_data_values(values, col.name().to_string())
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let mut csv = Csv::from_cols(cols, self.header.clone(), order.len());
+        csv.units = self.units.clone();
+        csv.meta = self.meta.clone();
+        csv.cache = self.combined_cache(other);
+        csv.lineage = self.lineage.clone();
+        csv.lineage.record(format!("merge_sorted({on})"));
+        Ok(csv)
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    fs::{self, File},
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{self, AtomicUsize},
+};
+
+use miette::Error;
+
+use crate::{
+    error::CoalaError,
+    reader::CsvReader,
+    tokenizer::{self, Dialect, Record, Records},
+};
+
+// Runs merged at once. More runs than this are merged in several passes, so a sort never holds
+// more open files than this
+const MAX_FAN_IN: usize = 64;
+
+// Tells apart the spill files of operations running at the same time in one process
+static NEXT_SPILL: AtomicUsize = AtomicUsize::new(0);
+
+// Temporary files, removed on drop whether the operation succeeded or not
+#[derive(Default)]
+struct SpillFiles {
+    paths: Vec<PathBuf>,
+}
+
+impl SpillFiles {
+    fn create(&mut self, dir: &Path) -> Result<(PathBuf, BufWriter<File>), Error> {
+        let id = NEXT_SPILL.fetch_add(1, atomic::Ordering::Relaxed);
+        let path = dir.join(format!("coala-spill-{}-{id}.csv", process::id()));
+        let file = File::create(&path).map_err(CoalaError::from)?;
+        self.paths.push(path.clone());
+        Ok((path, BufWriter::new(file)))
+    }
+    fn remove(&mut self, paths: &[PathBuf]) {
+        for path in paths {
+            let _ = fs::remove_file(path);
+        }
+        self.paths.retain(|path| !paths.contains(path));
+    }
+}
+
+impl Drop for SpillFiles {
+    fn drop(&mut self) {
+        for path in &self.paths {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Order of sort keys read as text: numbers first and by value, then everything else as
+/// strings, which puts ISO dates in chronological order
+fn key_order(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+// The next record of a run. Ordered backwards so the max-heap pops the smallest key, and the
+// earliest run among equal keys to keep the sort stable
+struct Head {
+    record: Record,
+    key: usize,
+    run: usize,
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        key_order(&other.record[other.key], &self.record[self.key]).then(other.run.cmp(&self.run))
+    }
+}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+fn write(out: &mut impl Write, record: &[impl AsRef<str>], dialect: Dialect) -> Result<(), Error> {
+    tokenizer::write_record(out, record, dialect).map_err(CoalaError::from)?;
+    Ok(())
+}
+
+// K-way merge of sorted runs into `out`
+fn merge_runs(
+    runs: &[PathBuf],
+    key: usize,
+    dialect: Dialect,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    let mut readers = runs
+        .iter()
+        .map(|path| {
+            let file = File::open(path).map_err(CoalaError::from)?;
+            Ok(Records::new(BufReader::new(file), dialect))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    let mut heap = BinaryHeap::with_capacity(readers.len());
+    for (run, records) in readers.iter_mut().enumerate() {
+        if let Some(record) = records.next().transpose()? {
+            heap.push(Head { record, key, run });
+        }
+    }
+    while let Some(Head { record, run, .. }) = heap.pop() {
+        write(out, &record, dialect)?;
+        if let Some(record) = readers[run].next().transpose()? {
+            heap.push(Head { record, key, run });
+        }
+    }
+    out.flush().map_err(CoalaError::from)?;
+    Ok(())
+}
+
+impl CsvReader {
+    /// Writes the file sorted ascending on `by` to `output`, rows with equal keys staying in
+    /// file order. Works for files larger than memory: each chunk is sorted and spilled to the
+    /// spill directory, then the sorted runs are merged
+    pub fn sort_to(&self, by: &str, output: &str) -> Result<(), Error> {
+        let (header, chunks) = self.open()?;
+        let key = header
+            .iter()
+            .position(|h| h == by)
+            .ok_or_else(|| CoalaError::MissingColumn {
+                name: by.to_string(),
+            })?;
+        let mut spill = SpillFiles::default();
+        let mut runs = Vec::new();
+        let mut rows = 0;
+        for chunk in chunks {
+            let mut chunk = chunk?;
+            if let Some((row, record)) = chunk
+                .iter()
+                .enumerate()
+                .find(|(_, record)| record.len() != header.len())
+            {
+                return Err(CoalaError::RaggedRow {
+                    row: rows + row,
+                    expected: header.len(),
+                    found: record.len(),
+                }
+                .into());
+            }
+            rows += chunk.len();
+            chunk.sort_by(|a, b| key_order(&a[key], &b[key]));
+            let (path, mut out) = spill.create(&self.spill_dir)?;
+            for record in &chunk {
+                write(&mut out, record, self.dialect)?;
+            }
+            out.flush().map_err(CoalaError::from)?;
+            runs.push(path);
+        }
+        while runs.len() > MAX_FAN_IN {
+            let mut merged = Vec::with_capacity(runs.len().div_ceil(MAX_FAN_IN));
+            for group in runs.chunks(MAX_FAN_IN) {
+                let (path, mut out) = spill.create(&self.spill_dir)?;
+                merge_runs(group, key, self.dialect, &mut out)?;
+                merged.push(path);
+            }
+            spill.remove(&runs);
+            runs = merged;
+        }
+        let mut out = BufWriter::new(File::create(output).map_err(CoalaError::from)?);
+        write(&mut out, &header, self.dialect)?;
+        merge_runs(&runs, key, self.dialect, &mut out)
+    }
+}
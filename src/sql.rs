@@ -0,0 +1,355 @@
+use bitvec::vec::BitVec;
+use miette::{miette, Error, IntoDiagnostic};
+
+use crate::{
+    col_parser::{ColConfig, ColType, DType},
+    csv_parser::Csv,
+    error::CoalaError,
+    lineage::Lineage,
+    writer::{Quoting, WriteConfig},
+};
+
+/// What `to_sql` does when the table is already there
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IfExists {
+    /// Leaves the table alone and fails
+    #[default]
+    Fail,
+    /// Drops the table and creates it again from the frame's schema
+    Replace,
+    /// Adds the rows to the table, whose columns must match the frame's names
+    Append,
+}
+
+// The server behind a connection string, told apart by its scheme
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    #[cfg(feature = "postgres")]
+    Postgres,
+    #[cfg(feature = "mysql")]
+    MySql,
+}
+
+impl Backend {
+    fn of(conn_str: &str) -> Result<Self, Error> {
+        let scheme = conn_str.split_once("://").map_or("", |(scheme, _)| scheme);
+        match scheme {
+            #[cfg(feature = "postgres")]
+            "postgres" | "postgresql" => Ok(Backend::Postgres),
+            #[cfg(feature = "mysql")]
+            "mysql" => Ok(Backend::MySql),
+            _ => Err(miette!(
+                "Unsupported connection string scheme `{scheme}`, is its database feature enabled?"
+            )),
+        }
+    }
+    // Quotes every part of a possibly schema qualified name
+    fn quote(&self, name: &str) -> String {
+        let quote = match self {
+            #[cfg(feature = "postgres")]
+            Backend::Postgres => '"',
+            #[cfg(feature = "mysql")]
+            Backend::MySql => '`',
+        };
+        name.split('.')
+            .map(|part| {
+                let doubled = part.replace(quote, &quote.to_string().repeat(2));
+                format!("{quote}{doubled}{quote}")
+            })
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+    fn sql_type(&self, dtype: DType) -> &'static str {
+        match (self, dtype) {
+            (_, DType::Integer) => "BIGINT",
+            #[cfg(feature = "postgres")]
+            (Backend::Postgres, DType::Float) => "DOUBLE PRECISION",
+            #[cfg(feature = "postgres")]
+            (Backend::Postgres, DType::Datetime) => "TIMESTAMP",
+            #[cfg(all(feature = "postgres", feature = "decimal"))]
+            (Backend::Postgres, DType::Decimal) => "NUMERIC",
+            #[cfg(all(feature = "postgres", feature = "bigint"))]
+            (Backend::Postgres, DType::BigInt) => "NUMERIC",
+            #[cfg(feature = "mysql")]
+            (Backend::MySql, DType::Float) => "DOUBLE",
+            #[cfg(feature = "mysql")]
+            (Backend::MySql, DType::Datetime) => "DATETIME",
+            #[cfg(all(feature = "mysql", feature = "decimal"))]
+            (Backend::MySql, DType::Decimal) => "DECIMAL(65, 28)",
+            #[cfg(all(feature = "mysql", feature = "bigint"))]
+            (Backend::MySql, DType::BigInt) => "DECIMAL(65, 0)",
+            // Lists, structs and custom kinds go as their text
+            _ => "TEXT",
+        }
+    }
+    fn create_table(&self, csv: &Csv, table: &str) -> String {
+        let cols: Vec<String> = csv
+            .header
+            .iter()
+            .zip(&csv.cols)
+            .map(|(name, col)| format!("{} {}", self.quote(name), self.sql_type(col.dtype())))
+            .collect();
+        format!("CREATE TABLE {} ({})", self.quote(table), cols.join(", "))
+    }
+    fn columns(&self, csv: &Csv) -> String {
+        let names: Vec<String> = csv.header.iter().map(|name| self.quote(name)).collect();
+        names.join(", ")
+    }
+}
+
+// The statements run before the rows are loaded, given whether the table exists
+fn prepare(
+    backend: Backend,
+    csv: &Csv,
+    table: &str,
+    exists: bool,
+    if_exists: IfExists,
+) -> Result<Vec<String>, Error> {
+    let create = backend.create_table(csv, table);
+    match (exists, if_exists) {
+        (false, _) => Ok(vec![create]),
+        (true, IfExists::Fail) => Err(miette!("Table `{table}` already exists")),
+        (true, IfExists::Replace) => {
+            Ok(vec![format!("DROP TABLE {}", backend.quote(table)), create])
+        }
+        (true, IfExists::Append) => Ok(Vec::new()),
+    }
+}
+
+// CSV quoting every value but the nulls, which COPY and LOAD DATA read back as NULL when
+// left bare. Datetimes go as printed, which both servers read as timestamps
+fn load_config(null_value: &str) -> WriteConfig {
+    WriteConfig::default()
+        .header(false)
+        .quoting(Quoting::NonNull)
+        .null_value(null_value)
+}
+
+// A column of a query result, its cells as the server writes them in text
+struct SqlCol {
+    name: String,
+    dtype: Option<DType>,
+    cells: Vec<Option<String>>,
+}
+
+// Cells are parsed like those of a file, the column's SQL type as the requested type. Types
+// without a counterpart are inferred
+fn frame(query: &str, cols: Vec<SqlCol>, n_rows: usize) -> Result<Csv, Error> {
+    let mut header: Vec<String> = Vec::with_capacity(cols.len());
+    let mut parsed = Vec::with_capacity(cols.len());
+    for col in cols {
+        if header.contains(&col.name) {
+            return Err(CoalaError::DuplicateColumn { name: col.name }.into());
+        }
+        let validity: BitVec = col.cells.iter().map(Option::is_some).collect();
+        let elements: Vec<&str> = col
+            .cells
+            .iter()
+            .map(|cell| cell.as_deref().unwrap_or(""))
+            .collect();
+        let config = ColConfig {
+            dtype: col.dtype,
+            ..ColConfig::default()
+        };
+        parsed.push(ColType::from_values_with_validity(
+            &elements,
+            validity,
+            col.name.clone(),
+            Some(config),
+            &[],
+        )?);
+        header.push(col.name);
+    }
+    let mut csv = Csv::from_cols(parsed, header, n_rows);
+    // The query rather than the connection string, which may hold a password
+    csv.lineage = Lineage::from_source(query);
+    Ok(csv)
+}
+
+#[cfg(feature = "postgres")]
+fn postgres_dtype(type_name: &str) -> Option<DType> {
+    match type_name {
+        "int2" | "int4" | "int8" | "oid" => Some(DType::Integer),
+        "float4" | "float8" => Some(DType::Float),
+        #[cfg(feature = "decimal")]
+        "numeric" => Some(DType::Decimal),
+        #[cfg(not(feature = "decimal"))]
+        "numeric" => Some(DType::Float),
+        "timestamp" | "timestamptz" | "date" => Some(DType::Datetime),
+        "text" | "varchar" | "bpchar" | "name" | "uuid" | "bool" | "json" | "jsonb" => {
+            Some(DType::String)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(feature = "mysql")]
+fn mysql_dtype(column_type: mysql::consts::ColumnType) -> Option<DType> {
+    use mysql::consts::ColumnType::*;
+
+    match column_type {
+        MYSQL_TYPE_TINY | MYSQL_TYPE_SHORT | MYSQL_TYPE_INT24 | MYSQL_TYPE_LONG
+        | MYSQL_TYPE_LONGLONG | MYSQL_TYPE_YEAR => Some(DType::Integer),
+        MYSQL_TYPE_FLOAT | MYSQL_TYPE_DOUBLE => Some(DType::Float),
+        #[cfg(feature = "decimal")]
+        MYSQL_TYPE_DECIMAL | MYSQL_TYPE_NEWDECIMAL => Some(DType::Decimal),
+        #[cfg(not(feature = "decimal"))]
+        MYSQL_TYPE_DECIMAL | MYSQL_TYPE_NEWDECIMAL => Some(DType::Float),
+        MYSQL_TYPE_DATE
+        | MYSQL_TYPE_DATETIME
+        | MYSQL_TYPE_DATETIME2
+        | MYSQL_TYPE_TIMESTAMP
+        | MYSQL_TYPE_TIMESTAMP2 => Some(DType::Datetime),
+        MYSQL_TYPE_VARCHAR
+        | MYSQL_TYPE_VAR_STRING
+        | MYSQL_TYPE_STRING
+        | MYSQL_TYPE_ENUM
+        | MYSQL_TYPE_SET
+        | MYSQL_TYPE_JSON => Some(DType::String),
+        _ => None,
+    }
+}
+
+impl Csv {
+    /// Runs `query` against the database at `conn_str`, a `postgres://` or `mysql://` URL,
+    /// and loads its result. Columns take the type matching their SQL type, SQL nulls become
+    /// nulls
+    pub fn from_sql(conn_str: &str, query: &str) -> Result<Csv, Error> {
+        match Backend::of(conn_str)? {
+            #[cfg(feature = "postgres")]
+            Backend::Postgres => Self::from_postgres(conn_str, query),
+            #[cfg(feature = "mysql")]
+            Backend::MySql => Self::from_mysql(conn_str, query),
+        }
+    }
+    #[cfg(feature = "postgres")]
+    fn from_postgres(conn_str: &str, query: &str) -> Result<Csv, Error> {
+        use postgres::{Client, NoTls, SimpleQueryMessage};
+
+        let mut client = Client::connect(conn_str, NoTls).into_diagnostic()?;
+        // Prepared for the column types only, the rows come as text from the simple protocol
+        let statement = client.prepare(query).into_diagnostic()?;
+        let mut cols: Vec<SqlCol> = statement
+            .columns()
+            .iter()
+            .map(|col| SqlCol {
+                name: col.name().to_string(),
+                dtype: postgres_dtype(col.type_().name()),
+                cells: Vec::new(),
+            })
+            .collect();
+        let mut n_rows = 0;
+        for message in client.simple_query(query).into_diagnostic()? {
+            if let SimpleQueryMessage::Row(row) = message {
+                for (i, col) in cols.iter_mut().enumerate() {
+                    col.cells.push(row.get(i).map(str::to_string));
+                }
+                n_rows += 1;
+            }
+        }
+        frame(query, cols, n_rows)
+    }
+    #[cfg(feature = "mysql")]
+    fn from_mysql(conn_str: &str, query: &str) -> Result<Csv, Error> {
+        use mysql::{prelude::Queryable, Conn, Opts, Value};
+
+        let mut conn = Conn::new(Opts::from_url(conn_str).into_diagnostic()?).into_diagnostic()?;
+        let mut result = conn.query_iter(query).into_diagnostic()?;
+        let mut cols: Vec<SqlCol> = result
+            .columns()
+            .as_ref()
+            .iter()
+            .map(|col| SqlCol {
+                name: col.name_str().into_owned(),
+                dtype: mysql_dtype(col.column_type()),
+                cells: Vec::new(),
+            })
+            .collect();
+        let mut n_rows = 0;
+        for row in result.by_ref() {
+            for (col, value) in cols.iter_mut().zip(row.into_diagnostic()?.unwrap()) {
+                col.cells.push(match value {
+                    Value::NULL => None,
+                    // The text protocol sends every other value as bytes
+                    Value::Bytes(bytes) => Some(String::from_utf8_lossy(&bytes).into_owned()),
+                    value => Some(value.as_sql(true)),
+                });
+            }
+            n_rows += 1;
+        }
+        frame(query, cols, n_rows)
+    }
+    /// Writes the frame to `table` of the database at `conn_str`, a `postgres://` or
+    /// `mysql://` URL. A new table gets a column per column of the frame, typed after it.
+    /// Rows are streamed with `COPY` on PostgreSQL and `LOAD DATA LOCAL INFILE` on MySQL,
+    /// which the server must allow
+    pub fn to_sql(&self, conn_str: &str, table: &str, if_exists: IfExists) -> Result<(), Error> {
+        match Backend::of(conn_str)? {
+            #[cfg(feature = "postgres")]
+            Backend::Postgres => self.to_postgres(conn_str, table, if_exists),
+            #[cfg(feature = "mysql")]
+            Backend::MySql => self.to_mysql(conn_str, table, if_exists),
+        }
+    }
+    #[cfg(feature = "postgres")]
+    fn to_postgres(&self, conn_str: &str, table: &str, if_exists: IfExists) -> Result<(), Error> {
+        use postgres::{Client, NoTls};
+
+        let backend = Backend::Postgres;
+        let mut client = Client::connect(conn_str, NoTls).into_diagnostic()?;
+        let exists: bool = client
+            .query_one(
+                "SELECT to_regclass($1) IS NOT NULL",
+                &[&backend.quote(table)],
+            )
+            .into_diagnostic()?
+            .get(0);
+        let mut transaction = client.transaction().into_diagnostic()?;
+        for statement in prepare(backend, self, table, exists, if_exists)? {
+            transaction.batch_execute(&statement).into_diagnostic()?;
+        }
+        let copy = format!(
+            "COPY {} ({}) FROM STDIN WITH (FORMAT csv)",
+            backend.quote(table),
+            backend.columns(self)
+        );
+        let mut writer = transaction.copy_in(&copy).into_diagnostic()?;
+        self.write_csv(&mut writer, &load_config(""))?;
+        writer.finish().into_diagnostic()?;
+        transaction.commit().into_diagnostic()
+    }
+    #[cfg(feature = "mysql")]
+    fn to_mysql(&self, conn_str: &str, table: &str, if_exists: IfExists) -> Result<(), Error> {
+        use std::io::Write;
+
+        use mysql::{prelude::Queryable, Conn, LocalInfileHandler, Opts};
+
+        let backend = Backend::MySql;
+        let mut conn = Conn::new(Opts::from_url(conn_str).into_diagnostic()?).into_diagnostic()?;
+        let exists: Option<u8> = conn
+            .exec_first(
+                "SELECT 1 FROM information_schema.tables \
+                 WHERE table_schema = DATABASE() AND table_name = ?",
+                (table,),
+            )
+            .into_diagnostic()?;
+        for statement in prepare(backend, self, table, exists.is_some(), if_exists)? {
+            conn.query_drop(statement).into_diagnostic()?;
+        }
+        // The handler outlives this call, so it gets the rows rendered up front
+        let mut rows = Vec::new();
+        self.write_csv(&mut rows, &load_config("NULL"))?;
+        conn.set_local_infile_handler(Some(LocalInfileHandler::new(move |_, stream| {
+            stream.write_all(&rows)
+        })));
+        // Without an escape character a bare NULL reads as null and backslashes stay as they are
+        let load = format!(
+            "LOAD DATA LOCAL INFILE 'coala' INTO TABLE {} CHARACTER SET utf8mb4 \
+             FIELDS TERMINATED BY ',' ENCLOSED BY '\"' ESCAPED BY '' \
+             LINES TERMINATED BY '\\n' ({})",
+            backend.quote(table),
+            backend.columns(self)
+        );
+        conn.query_drop(load).into_diagnostic()
+    }
+}
@@ -1,117 +1,409 @@
-use miette::{Diagnostic, Error};
-use thiserror::Error;
+use std::sync::{Mutex, PoisonError};
 
-use crate::col_parser::{CsvCol, DataValue};
+use miette::Error;
+
+pub use crate::embedded::RunningStats;
+use crate::{
+    col_parser::{ColType, CsvCol, DataValue},
+    csv_parser::ColViewer,
+    embedded::CompensatedSum,
+    error::CoalaError,
+};
 
 pub trait Statistics {
+    fn sum(&self) -> Result<DataValue, Error>;
     fn mean(&self) -> Result<DataValue, Error>;
     fn median(&self) -> Result<DataValue, Error>;
     fn quantile(&self, quantile: f64) -> Result<DataValue, Error>;
+    /// `quantile` of each of `quantiles` in order, sorting the values once for all of them
+    fn quantiles(&self, quantiles: &[f64]) -> Result<Vec<DataValue>, Error> {
+        quantiles.iter().map(|&q| self.quantile(q)).collect()
+    }
     fn stddev(&self) -> Result<DataValue, Error>;
+    /// Median distance of the values from their median, unscaled. Unlike `stddev` a few wild
+    /// values barely move it
+    fn median_absolute_deviation(&self) -> Result<DataValue, Error>;
+    /// Sample standard deviation once `proportion` of the values is dropped from each end
+    fn trimmed_std(&self, proportion: f64) -> Result<DataValue, Error>;
+}
+
+// Statistics of a column computed once however many threads ask for them at the same time.
+// The first caller computes under the lock while the others wait for its result
+#[derive(Debug, Default)]
+pub(crate) struct Memo {
+    mean: Mutex<Option<DataValue>>,
+    median: Mutex<Option<DataValue>>,
+    stddev: Mutex<Option<DataValue>>,
+}
+
+// The value in `cell`, computed by `compute` on first use. Errors aren't kept, the next call
+// tries again
+fn memoized(
+    cell: &Mutex<Option<DataValue>>,
+    compute: impl FnOnce() -> Result<DataValue, Error>,
+) -> Result<DataValue, Error> {
+    let mut cell = cell.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(value) = &*cell {
+        return Ok(value.clone());
+    }
+    let value = compute()?;
+    *cell = Some(value.clone());
+    Ok(value)
+}
+
+fn validate_quantiles(quantiles: &[f64]) -> Result<(), Error> {
+    match quantiles.iter().find(|q| !(0.0..=1.0).contains(*q)) {
+        Some(&value) => Err(CoalaError::InvalidQuantile { value }.into()),
+        None => Ok(()),
+    }
+}
+
+// Linear interpolation between the closest ranks of `sorted`, which isn't empty
+fn float_quantile(sorted: &[f64], quantile: f64) -> f64 {
+    let n = sorted.len();
+    let index = quantile * (n - 1) as f64;
+    let index = if index < 0.0 {
+        0.0
+    } else if index > (n - 1) as f64 {
+        (n - 1) as f64
+    } else {
+        index
+    };
+    let lower_idx = index.floor() as usize;
+    let upper_idx = lower_idx + 1;
+    if upper_idx >= n {
+        return sorted[lower_idx];
+    }
+    let fraction = index - lower_idx as f64;
+    sorted[lower_idx] * (1.0 - fraction) + sorted[upper_idx] * fraction
+}
+
+// Nearest rank of `sorted`, which isn't empty, so the quantile is one of the values
+fn integer_quantile(sorted: &[i64], quantile: f64) -> i64 {
+    let n = sorted.len();
+    let index = ((quantile * n as f64).ceil() as usize).saturating_sub(1);
+    sorted[index.min(n - 1)]
+}
+
+// Sample standard deviation, `Null` when fewer than two values are present
+pub(crate) fn stddev_of(values: impl IntoIterator<Item = f64>) -> Result<DataValue, Error> {
+    let stats: RunningStats = values.into_iter().collect();
+    if stats.count() == 0 {
+        return Err(CoalaError::EmptyColumn.into());
+    }
+    Ok(stats.stddev().map_or(DataValue::Null, DataValue::Float))
+}
+
+fn sorted_median(sorted: &[f64]) -> f64 {
+    let mid = sorted.len() / 2;
+    match sorted.len().is_multiple_of(2) {
+        true => 0.5 * (sorted[mid - 1] + sorted[mid]),
+        false => sorted[mid],
+    }
+}
+
+pub(crate) fn mad_of(values: impl IntoIterator<Item = f64>) -> Result<DataValue, Error> {
+    let mut values: Vec<f64> = values.into_iter().collect();
+    if values.is_empty() {
+        return Err(CoalaError::EmptyColumn.into());
+    }
+    values.sort_unstable_by(f64::total_cmp);
+    let median = sorted_median(&values);
+    let mut deviations: Vec<f64> = values.iter().map(|v| (v - median).abs()).collect();
+    deviations.sort_unstable_by(f64::total_cmp);
+    Ok(DataValue::Float(sorted_median(&deviations)))
+}
+
+// `Null` when fewer than two values are left after trimming
+pub(crate) fn trimmed_std_of(
+    values: impl IntoIterator<Item = f64>,
+    proportion: f64,
+) -> Result<DataValue, Error> {
+    if !(0.0..0.5).contains(&proportion) {
+        return Err(CoalaError::InvalidTrim { value: proportion }.into());
+    }
+    let mut values: Vec<f64> = values.into_iter().collect();
+    values.sort_unstable_by(f64::total_cmp);
+    let cut = (proportion * values.len() as f64) as usize;
+    stddev_of(values[cut..values.len() - cut].iter().copied())
 }
 
-#[derive(Error, Debug, Diagnostic)]
-pub(crate) enum StatisticsError {
-    #[error("Invalid quantile `{value}`, value must be between 0 and 1")]
-    InvalidQuantile { value: f64 },
-    #[error("Column cannot be empty")]
-    EmptyColumn,
-    #[error("`{col}` invalid for calculations")]
-    InvalidType { col: String },
+// Plain summation loses low order bits once the running total dwarfs the addends, which
+// becomes visible on columns with millions of rows
+const COMPENSATED_SUM_THRESHOLD: usize = 1 << 16;
+
+pub(crate) fn float_sum(values: &[f64]) -> f64 {
+    if values.len() < COMPENSATED_SUM_THRESHOLD {
+        return values.iter().sum();
+    }
+    let mut sum = CompensatedSum::default();
+    values.iter().for_each(|&v| sum.add(v));
+    sum.value()
 }
 
 impl Statistics for CsvCol<f64> {
+    fn sum(&self) -> Result<DataValue, Error> {
+        Ok(DataValue::Float(float_sum(&self.valid_values())))
+    }
     fn mean(&self) -> Result<DataValue, Error> {
-        if self.n_elements == 0 {
-            return Err(StatisticsError::EmptyColumn.into());
-        }
-        let mean = self.values.iter().sum::<f64>();
-        let mean = mean / self.values.len() as f64;
-        Ok(DataValue::Float(mean))
+        memoized(&self.memo.mean, || {
+            let values = self.valid_values();
+            if values.is_empty() {
+                return Err(CoalaError::EmptyColumn.into());
+            }
+            let mean = float_sum(&values) / values.len() as f64;
+            Ok(DataValue::Float(mean))
+        })
     }
     fn median(&self) -> Result<DataValue, Error> {
-        if self.n_elements == 0 {
-            return Err(StatisticsError::EmptyColumn.into());
-        }
-        let col = self.get_sorted();
-        if self.n_elements.is_multiple_of(2) {
-            return Ok(DataValue::Float(col[col.len() / 2]));
-        }
-        Ok(DataValue::Float(
-            0.5 * (col[col.len() / 2] + col[col.len() / 2 - 1]),
-        ))
+        memoized(&self.memo.median, || {
+            let col = self.get_sorted();
+            if col.is_empty() {
+                return Err(CoalaError::EmptyColumn.into());
+            }
+            let mid = col.len() / 2;
+            if !col.len().is_multiple_of(2) {
+                return Ok(DataValue::Float(col[mid]));
+            }
+            Ok(DataValue::Float(0.5 * (col[mid - 1] + col[mid])))
+        })
     }
     fn quantile(&self, quantile: f64) -> Result<DataValue, Error> {
-        if !(0.0..1.0).contains(&quantile) {
-            return Err(StatisticsError::InvalidQuantile { value: quantile }.into());
-        }
+        self.quantiles(&[quantile])?
+            .pop()
+            .ok_or_else(|| CoalaError::EmptyColumn.into())
+    }
+    fn quantiles(&self, quantiles: &[f64]) -> Result<Vec<DataValue>, Error> {
+        validate_quantiles(quantiles)?;
         let col = self.get_sorted();
-        let n = col.len();
-        let index = quantile * (n - 1) as f64;
-        let index = if index < 0.0 {
-            0.0
-        } else if index > (n - 1) as f64 {
-            (n - 1) as f64
-        } else {
-            index
-        };
-        let lower_idx = index.floor() as usize;
-        let upper_idx = lower_idx + 1;
-        if upper_idx >= n {
-            return Ok(DataValue::Float(col[lower_idx]));
+        if col.is_empty() {
+            return Err(CoalaError::EmptyColumn.into());
         }
-        let fraction = index - lower_idx as f64;
-        let lower_val = col[lower_idx];
-        let upper_val = col[upper_idx];
-        let value = lower_val * (1.0 - fraction) + upper_val * fraction;
-        Ok(DataValue::Float(value))
+        Ok(quantiles
+            .iter()
+            .map(|&q| DataValue::Float(float_quantile(&col, q)))
+            .collect())
     }
     fn stddev(&self) -> Result<DataValue, Error> {
-        todo!()
+        memoized(&self.memo.stddev, || {
+            stddev_of(self.valid_values().iter().copied())
+        })
+    }
+    fn median_absolute_deviation(&self) -> Result<DataValue, Error> {
+        mad_of(self.valid_values().iter().copied())
+    }
+    fn trimmed_std(&self, proportion: f64) -> Result<DataValue, Error> {
+        trimmed_std_of(self.valid_values().iter().copied(), proportion)
+    }
+}
+
+impl CsvCol<i64> {
+    pub(crate) fn sum_checked(&self) -> Result<i64, Error> {
+        self.valid_values()
+            .iter()
+            .try_fold(0i64, |acc, &x| acc.checked_add(x))
+            .ok_or_else(|| {
+                CoalaError::Overflow {
+                    col: self.col_name.clone(),
+                }
+                .into()
+            })
+    }
+    pub(crate) fn sum_saturating(&self) -> i64 {
+        self.valid_values()
+            .iter()
+            .fold(0i64, |acc, &x| acc.saturating_add(x))
+    }
+    pub(crate) fn sum_as_f64(&self) -> f64 {
+        self.valid_values().iter().map(|&x| x as f64).sum()
     }
 }
 
 impl Statistics for CsvCol<i64> {
-    fn mean(&self) -> Result<DataValue, Error> {
-        if self.n_elements == 0 {
-            return Err(StatisticsError::EmptyColumn.into());
+    fn sum(&self) -> Result<DataValue, Error> {
+        let sum = self.sum_checked();
+        #[cfg(feature = "bigint")]
+        if sum.is_err() {
+            let sum = self
+                .valid_values()
+                .iter()
+                .map(|&x| num_bigint::BigInt::from(x))
+                .sum();
+            return Ok(DataValue::BigInt(sum));
         }
-        let sum: f64 = self.values.iter().map(|&x| x as f64).sum();
-        Ok(DataValue::Float(sum / self.n_elements as f64))
+        sum.map(DataValue::Integer)
+    }
+
+    fn mean(&self) -> Result<DataValue, Error> {
+        memoized(&self.memo.mean, || {
+            let values = self.valid_values();
+            if values.is_empty() {
+                return Err(CoalaError::EmptyColumn.into());
+            }
+            let sum = values.iter().map(|&x| x as i128).sum::<i128>() as f64;
+            Ok(DataValue::Float(sum / values.len() as f64))
+        })
     }
 
     fn median(&self) -> Result<DataValue, Error> {
-        if self.n_elements == 0 {
-            return Err(StatisticsError::EmptyColumn.into());
-        }
-        let mut col = self.values.to_vec();
-        col.sort_unstable();
-        if !self.n_elements.is_multiple_of(2) {
-            return Ok(DataValue::Integer(col[col.len() / 2]));
-        };
-        Ok(DataValue::Integer(
-            col[col.len() / 2] / 2 + col[col.len() / 2 - 1],
-        ))
+        memoized(&self.memo.median, || {
+            let col = self.get_sorted();
+            if col.is_empty() {
+                return Err(CoalaError::EmptyColumn.into());
+            }
+            let mid = col.len() / 2;
+            if !col.len().is_multiple_of(2) {
+                return Ok(DataValue::Integer(col[mid]));
+            }
+            // Halfway between two integers needn't be one
+            Ok(DataValue::Float(
+                0.5 * (col[mid - 1] as f64 + col[mid] as f64),
+            ))
+        })
     }
 
     fn quantile(&self, quantile: f64) -> Result<DataValue, Error> {
-        if !(0.0..1.0).contains(&quantile) {
-            return Err(StatisticsError::InvalidQuantile { value: quantile }.into());
-        }
+        self.quantiles(&[quantile])?
+            .pop()
+            .ok_or_else(|| CoalaError::EmptyColumn.into())
+    }
+
+    fn quantiles(&self, quantiles: &[f64]) -> Result<Vec<DataValue>, Error> {
+        validate_quantiles(quantiles)?;
         let col = self.get_sorted();
-        let n = col.len();
-        let index = (quantile * n as f64).ceil() as usize - 1;
-        let index = if index == usize::MAX {
-            0
-        } else if index >= n {
-            n - 1
-        } else {
-            index
-        };
-        Ok(DataValue::Integer(col[index]))
+        if col.is_empty() {
+            return Err(CoalaError::EmptyColumn.into());
+        }
+        Ok(quantiles
+            .iter()
+            .map(|&q| DataValue::Integer(integer_quantile(&col, q)))
+            .collect())
     }
 
     fn stddev(&self) -> Result<DataValue, Error> {
-        todo!()
+        memoized(&self.memo.stddev, || {
+            stddev_of(self.valid_values().iter().map(|&x| x as f64))
+        })
+    }
+
+    fn median_absolute_deviation(&self) -> Result<DataValue, Error> {
+        mad_of(self.valid_values().iter().map(|&x| x as f64))
+    }
+
+    fn trimmed_std(&self, proportion: f64) -> Result<DataValue, Error> {
+        trimmed_std_of(self.valid_values().iter().map(|&x| x as f64), proportion)
+    }
+}
+
+impl ColViewer<'_> {
+    // Values strictly below `value`, values at or below it, and the column length, all by
+    // binary search on the sorted cache
+    fn ranks(&self, value: &DataValue) -> Result<(usize, usize, usize), Error> {
+        let ranks = self.inner.with_plain(|col| match (col, value) {
+            (ColType::Datetime(col), DataValue::DateTime(value)) => {
+                let sorted = col.get_sorted();
+                Ok((
+                    sorted.partition_point(|v| v < value),
+                    sorted.partition_point(|v| v <= value),
+                    sorted.len(),
+                ))
+            }
+            (ColType::Float(col), value) if let Some(x) = value.as_f64() => {
+                let sorted = col.get_sorted();
+                Ok((
+                    sorted.partition_point(|&v| v < x),
+                    sorted.partition_point(|&v| v <= x),
+                    sorted.len(),
+                ))
+            }
+            (ColType::Integer(col), value) if let Some(x) = value.as_f64() => {
+                let sorted = col.get_sorted();
+                Ok((
+                    sorted.partition_point(|&v| (v as f64) < x),
+                    sorted.partition_point(|&v| (v as f64) <= x),
+                    sorted.len(),
+                ))
+            }
+            _ => Err(Error::from(CoalaError::InvalidType {
+                col: self.name().to_string(),
+            })),
+        })?;
+        match ranks.2 {
+            0 => Err(CoalaError::EmptyColumn.into()),
+            _ => Ok(ranks),
+        }
+    }
+    /// Share of values at or below `value`
+    pub fn ecdf(&self, value: &DataValue) -> Result<f64, Error> {
+        let (_, at_or_below, n) = self.ranks(value)?;
+        Ok(at_or_below as f64 / n as f64)
+    }
+    /// Percentile rank of `value` from 0 to 100, the inverse of `quantile`. Values equal to it
+    /// count half, so the rank of a repeated value sits in the middle of its run
+    pub fn percentile_of(&self, value: &DataValue) -> Result<f64, Error> {
+        let (below, at_or_below, n) = self.ranks(value)?;
+        Ok(50.0 * (below + at_or_below) as f64 / n as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn floats(values: &[f64]) -> CsvCol<f64> {
+        CsvCol::new("x".to_string(), values.to_vec())
+    }
+
+    fn integers(values: &[i64]) -> CsvCol<i64> {
+        CsvCol::new("x".to_string(), values.to_vec())
+    }
+
+    #[test]
+    fn float_median() {
+        assert_eq!(floats(&[4.0]).median().unwrap(), DataValue::Float(4.0));
+        assert_eq!(floats(&[4.0, 1.0]).median().unwrap(), DataValue::Float(2.5));
+        assert_eq!(
+            floats(&[3.0, 1.0, 2.0]).median().unwrap(),
+            DataValue::Float(2.0)
+        );
+    }
+
+    #[test]
+    fn integer_median() {
+        assert_eq!(integers(&[4]).median().unwrap(), DataValue::Integer(4));
+        assert_eq!(integers(&[4, 1]).median().unwrap(), DataValue::Float(2.5));
+        assert_eq!(
+            integers(&[3, 1, 2]).median().unwrap(),
+            DataValue::Integer(2)
+        );
+    }
+
+    #[test]
+    fn median_of_one_valid_value() {
+        let values = vec![DataValue::Null, DataValue::Float(2.0), DataValue::Null];
+        let col = ColType::from_data_values(values, "x".to_string()).unwrap();
+        assert_eq!(col.median().unwrap(), DataValue::Float(2.0));
+    }
+
+    #[test]
+    fn integer_quantile_ends() {
+        let col = integers(&[5, 1, 3]);
+        assert_eq!(col.quantile(0.0).unwrap(), DataValue::Integer(1));
+        assert_eq!(col.quantile(0.5).unwrap(), DataValue::Integer(3));
+        assert_eq!(col.quantile(1.0).unwrap(), DataValue::Integer(5));
+        assert!(col.quantile(1.5).is_err());
+    }
+
+    #[test]
+    fn float_quantile_ends() {
+        let col = floats(&[2.0, 8.0, 4.0]);
+        assert_eq!(col.quantile(0.0).unwrap(), DataValue::Float(2.0));
+        assert_eq!(col.quantile(1.0).unwrap(), DataValue::Float(8.0));
+    }
+
+    #[test]
+    fn empty_median() {
+        assert!(floats(&[]).median().is_err());
+        assert!(integers(&[]).median().is_err());
     }
 }
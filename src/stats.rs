@@ -0,0 +1,84 @@
+use miette::Error;
+
+use crate::{
+    col_parser::{ColType, CsvCol},
+    csv_parser::{ColViewer, Csv},
+    error::CoalaError,
+    special::normal_quantile,
+};
+
+pub mod tests;
+
+/// Outcome of a hypothesis test
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TestResult {
+    pub statistic: f64,
+    /// Chance of a statistic at least this extreme if the null hypothesis holds
+    pub p_value: f64,
+}
+
+/// Reference distributions for `qq_against`, in their standard form
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Distribution {
+    /// Mean 0 and standard deviation 1
+    Normal,
+    /// Between 0 and 1
+    Uniform,
+    /// Rate 1
+    Exponential,
+}
+
+impl Distribution {
+    // Inverse of the distribution function, `p` within 0 and 1 excluded
+    fn quantile(&self, p: f64) -> f64 {
+        match self {
+            Distribution::Normal => normal_quantile(p),
+            Distribution::Uniform => p,
+            Distribution::Exponential => -(1.0 - p).ln(),
+        }
+    }
+}
+
+// Finite values in ascending order, from the column's sorted cache when it has one
+pub(crate) fn sorted_values(col: &ColViewer) -> Result<Vec<f64>, Error> {
+    let sorted = col.inner.with_plain(|plain| match plain {
+        ColType::Float(plain) => Some(plain.get_sorted()),
+        ColType::Integer(plain) => Some(plain.get_sorted().into_iter().map(|v| v as f64).collect()),
+        _ => None,
+    });
+    let mut sorted = match sorted {
+        Some(sorted) => sorted,
+        None => {
+            let mut values: Vec<f64> = col.finite_values()?.into_iter().flatten().collect();
+            values.sort_unstable_by(f64::total_cmp);
+            values
+        }
+    };
+    sorted.retain(|v| v.is_finite());
+    if sorted.is_empty() {
+        return Err(CoalaError::EmptyColumn.into());
+    }
+    Ok(sorted)
+}
+
+impl ColViewer<'_> {
+    /// Points of a Q-Q plot against `distribution`, a frame of `theoretical` and `sample`
+    /// quantiles, one row per finite value. They fall on a straight line when the values follow
+    /// the distribution up to location and scale, bends show skew and heavy tails
+    pub fn qq_against(&self, distribution: Distribution) -> Result<Csv, Error> {
+        let sample = sorted_values(self)?;
+        let n = sample.len();
+        let theoretical = (0..n)
+            .map(|i| distribution.quantile((i as f64 + 0.5) / n as f64))
+            .collect();
+        let cols = vec![
+            ColType::Float(CsvCol::new("theoretical".to_string(), theoretical)),
+            ColType::Float(CsvCol::new("sample".to_string(), sample)),
+        ];
+        let header = vec!["theoretical".to_string(), "sample".to_string()];
+        let mut csv = Csv::from_cols(cols, header, n);
+        csv.lineage
+            .record(format!("qq_against({}, {distribution:?})", self.name()));
+        Ok(csv)
+    }
+}
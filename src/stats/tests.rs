@@ -0,0 +1,121 @@
+use miette::{miette, Error};
+
+use crate::{
+    csv_parser::ColViewer,
+    error::CoalaError,
+    special::{kolmogorov_survival, normal_quantile, normal_survival},
+    stats::{sorted_values, TestResult},
+};
+
+/// Two-sample Kolmogorov-Smirnov test, the largest gap between the empirical distributions
+/// of `a` and `b`. A small p-value means the samples are unlikely to come from the same
+/// distribution. The p-value is asymptotic, rough below a few dozen values per sample
+pub fn ks_test(a: &ColViewer, b: &ColViewer) -> Result<TestResult, Error> {
+    let (a, b) = (sorted_values(a)?, sorted_values(b)?);
+    let (n, m) = (a.len() as f64, b.len() as f64);
+    let (mut i, mut j, mut statistic) = (0, 0, 0.0f64);
+    while i < a.len() && j < b.len() {
+        // Ties move both sides so the gap is measured between distinct values
+        let value = a[i].min(b[j]);
+        while i < a.len() && a[i] == value {
+            i += 1;
+        }
+        while j < b.len() && b[j] == value {
+            j += 1;
+        }
+        statistic = statistic.max((i as f64 / n - j as f64 / m).abs());
+    }
+    let effective = (n * m / (n + m)).sqrt();
+    let lambda = (effective + 0.12 + 0.11 / effective) * statistic;
+    Ok(TestResult {
+        statistic,
+        p_value: kolmogorov_survival(lambda),
+    })
+}
+
+// Coefficients of the Shapiro-Wilk statistic for `n` values, by Royston's approximation
+fn shapiro_wilk_weights(n: usize) -> Vec<f64> {
+    if n == 3 {
+        let half = 0.5f64.sqrt();
+        return vec![-half, 0.0, half];
+    }
+    let m: Vec<f64> = (1..=n)
+        .map(|i| normal_quantile((i as f64 - 0.375) / (n as f64 + 0.25)))
+        .collect();
+    let ssq: f64 = m.iter().map(|v| v * v).sum();
+    let u = 1.0 / (n as f64).sqrt();
+    let polynomial =
+        |coefficients: [f64; 6]| coefficients.iter().rev().fold(0.0, |acc, c| acc * u + c);
+    let last = m[n - 1] / ssq.sqrt()
+        + polynomial([0.0, 0.221_157, -0.147_981, -2.071_19, 4.434_685, -2.706_056]);
+    let mut weights = vec![0.0; n];
+    // Only the outermost one or two pairs are fitted, the middle weights are the scaled
+    // expected order statistics
+    if n > 5 {
+        let second = m[n - 2] / ssq.sqrt()
+            + polynomial([
+                0.0, 0.042_981, -0.293_762, -1.752_461, 5.682_633, -3.582_633,
+            ]);
+        let phi = (ssq - 2.0 * m[n - 1].powi(2) - 2.0 * m[n - 2].powi(2))
+            / (1.0 - 2.0 * last.powi(2) - 2.0 * second.powi(2));
+        weights[n - 2] = second;
+        weights[1] = -second;
+        for i in 2..n - 2 {
+            weights[i] = m[i] / phi.sqrt();
+        }
+    } else {
+        let phi = (ssq - 2.0 * m[n - 1].powi(2)) / (1.0 - 2.0 * last.powi(2));
+        for i in 1..n - 1 {
+            weights[i] = m[i] / phi.sqrt();
+        }
+    }
+    weights[n - 1] = last;
+    weights[0] = -last;
+    weights
+}
+
+impl ColViewer<'_> {
+    /// Shapiro-Wilk test of whether the column's values come from a normal distribution. A
+    /// small p-value means they likely don't, so robust summaries like the median and
+    /// `median_absolute_deviation` describe it better than the mean and `stddev`. Needs 3
+    /// values at least, the p-value is calibrated up to 5000
+    pub fn normality_test(&self) -> Result<TestResult, Error> {
+        let sorted = sorted_values(self)?;
+        let n = sorted.len();
+        if n < 3 {
+            return Err(CoalaError::OutOfRange.into());
+        }
+        let mean = sorted.iter().sum::<f64>() / n as f64;
+        let spread: f64 = sorted.iter().map(|v| (v - mean).powi(2)).sum();
+        if spread == 0.0 {
+            return Err(miette!("All values of `{}` are equal", self.name()));
+        }
+        let weights = shapiro_wilk_weights(n);
+        let projection: f64 = weights.iter().zip(&sorted).map(|(a, x)| a * x).sum();
+        let statistic = (projection * projection / spread).min(1.0);
+        let n = n as f64;
+        let p_value = if n == 3.0 {
+            let p = 6.0 / std::f64::consts::PI * (statistic.sqrt().asin() - 0.75f64.sqrt().asin());
+            p.max(0.0)
+        } else {
+            // Royston's normalizing transformations of `1 - W`
+            let (w, mean, stddev) = if n <= 11.0 {
+                let gamma = 0.459 * n - 2.273;
+                (
+                    -(gamma - (1.0 - statistic).ln()).ln(),
+                    0.544 - 0.39978 * n + 0.025054 * n.powi(2) - 0.0006714 * n.powi(3),
+                    (1.3822 - 0.77857 * n + 0.062767 * n.powi(2) - 0.0020322 * n.powi(3)).exp(),
+                )
+            } else {
+                let l = n.ln();
+                (
+                    (1.0 - statistic).ln(),
+                    -1.5861 - 0.31082 * l - 0.083751 * l.powi(2) + 0.0038915 * l.powi(3),
+                    (-0.4803 - 0.082676 * l + 0.0030302 * l.powi(2)).exp(),
+                )
+            };
+            normal_survival((w - mean) / stddev)
+        };
+        Ok(TestResult { statistic, p_value })
+    }
+}
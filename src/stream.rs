@@ -0,0 +1,137 @@
+use std::collections::VecDeque;
+
+use miette::{miette, Error};
+use serde_json::Value;
+
+use crate::{
+    col_parser::{ColType, DataValue},
+    csv_parser::Csv,
+    statistics::RunningStats,
+};
+
+/// The latest `capacity` records of an NDJSON stream, the oldest dropped first, for
+/// monitoring a feed as it arrives. Keys become columns as they first show up, a record without
+/// one holds a null. Running statistics cover every record seen, the window's own come
+/// from `frame`
+pub struct RollingFrame {
+    capacity: usize,
+    header: Vec<String>,
+    // Values by column position, shorter for records older than a column
+    rows: VecDeque<Vec<DataValue>>,
+    stats: Vec<RunningStats>,
+    n_seen: usize,
+}
+
+// Numbers and strings keep their type, other JSON values their text
+fn json_value(value: Value) -> DataValue {
+    match value {
+        Value::Null => DataValue::Null,
+        Value::Number(n) => match n.as_i64() {
+            Some(n) => DataValue::Integer(n),
+            None => n.as_f64().map_or(DataValue::Null, DataValue::Float),
+        },
+        Value::String(s) => DataValue::String(s),
+        other => DataValue::String(other.to_string()),
+    }
+}
+
+impl RollingFrame {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            header: Vec::new(),
+            rows: VecDeque::new(),
+            stats: Vec::new(),
+            n_seen: 0,
+        }
+    }
+    /// Adds one line holding a JSON object, blank lines are skipped
+    pub fn push(&mut self, line: &str) -> Result<(), Error> {
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+        let record = match serde_json::from_str(line) {
+            Ok(Value::Object(record)) => record,
+            Ok(_) => return Err(miette!("Record {} isn't a JSON object", self.n_seen)),
+            Err(e) => return Err(miette!("Record {} isn't valid JSON: {e}", self.n_seen)),
+        };
+        let mut row = vec![DataValue::Null; self.header.len()];
+        for (key, value) in record {
+            let index = match self.header.iter().position(|h| *h == key) {
+                Some(index) => index,
+                None => {
+                    self.header.push(key);
+                    self.stats.push(RunningStats::default());
+                    row.push(DataValue::Null);
+                    self.header.len() - 1
+                }
+            };
+            let value = json_value(value);
+            if let Some(number) = value.as_f64() {
+                self.stats[index].push(number);
+            }
+            row[index] = value;
+        }
+        if self.rows.len() == self.capacity {
+            self.rows.pop_front();
+        }
+        self.rows.push_back(row);
+        self.n_seen += 1;
+        Ok(())
+    }
+    /// Pushes every line of `lines`, e.g. `stdin().lines()` mapped to strings or the payloads
+    /// of a message queue consumer. Stops at the first bad record. Returns how many lines
+    /// were read
+    pub fn ingest<I: IntoIterator<Item = String>>(&mut self, lines: I) -> Result<usize, Error> {
+        let mut read = 0;
+        for line in lines {
+            self.push(&line)?;
+            read += 1;
+        }
+        Ok(read)
+    }
+    /// Records in the window
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+    /// Records pushed since the start, the dropped ones included
+    pub fn n_seen(&self) -> usize {
+        self.n_seen
+    }
+    pub fn header(&self) -> &[String] {
+        &self.header
+    }
+    /// Statistics of the numbers `name` held in every record seen
+    pub fn stats(&self, name: &str) -> Option<&RunningStats> {
+        self.header
+            .iter()
+            .position(|h| h == name)
+            .map(|i| &self.stats[i])
+    }
+    /// The window as a frame, oldest record first
+    pub fn frame(&self) -> Result<Csv, Error> {
+        let cols = self
+            .header
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let values = self
+                    .rows
+                    .iter()
+                    .map(|row| row.get(i).cloned().unwrap_or(DataValue::Null))
+                    .collect();
+                ColType::from_data_values(values, name.clone())
+            })
+            .collect::<Result<_, Error>>()?;
+        let mut csv = Csv::from_cols(cols, self.header.clone(), self.rows.len());
+        csv.lineage.record(format!(
+            "rolling(last {} of {} records)",
+            self.rows.len(),
+            self.n_seen
+        ));
+        Ok(csv)
+    }
+}
@@ -0,0 +1,129 @@
+use std::{cmp::Ordering, sync::Arc};
+
+use bitvec::vec::BitVec;
+use miette::Error;
+
+use crate::{
+    col_parser::{self, DataValue},
+    error::CoalaError,
+    intern,
+    sketch::BloomFilter,
+};
+
+#[derive(Debug)]
+enum Cells {
+    // Every value of the column lives in one buffer, cell `i` spanning `ends[i - 1]..ends[i]`,
+    // so a column costs a couple of allocations instead of one per cell
+    Packed { buffer: String, ends: Vec<usize> },
+    // Handles into the pool of `intern`, a string repeated across columns is stored once
+    Interned(Vec<Arc<str>>),
+}
+
+#[derive(Debug)]
+pub(crate) struct StringCol {
+    pub(crate) col_name: String,
+    cells: Cells,
+    pub(crate) filter: Option<BloomFilter>,
+    // Unset where the row is null, which holds a placeholder, as in `CsvCol`
+    pub(crate) validity: Option<BitVec>,
+}
+
+impl StringCol {
+    pub(crate) fn new<I, S>(col_name: String, values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let values = values.into_iter();
+        let mut buffer = String::new();
+        let mut ends = Vec::with_capacity(values.size_hint().0);
+        for value in values {
+            buffer.push_str(value.as_ref());
+            ends.push(buffer.len());
+        }
+        Self {
+            col_name,
+            cells: Cells::Packed { buffer, ends },
+            filter: None,
+            validity: None,
+        }
+    }
+    // Moves the values to the crate-wide pool of strings
+    pub(crate) fn intern(&mut self) {
+        if let Cells::Packed { .. } = self.cells {
+            self.cells = Cells::Interned(intern::intern_all(self.iter()));
+        }
+    }
+    pub(crate) fn len(&self) -> usize {
+        match &self.cells {
+            Cells::Packed { ends, .. } => ends.len(),
+            Cells::Interned(values) => values.len(),
+        }
+    }
+    pub(crate) fn get(&self, index: usize) -> Option<&str> {
+        match &self.cells {
+            Cells::Packed { buffer, ends } => {
+                let end = *ends.get(index)?;
+                let start = index.checked_sub(1).map_or(0, |i| ends[i]);
+                Some(&buffer[start..end])
+            }
+            Cells::Interned(values) => values.get(index).map(|value| &**value),
+        }
+    }
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &str> + '_ {
+        (0..self.len()).filter_map(|i| self.get(i))
+    }
+    pub(crate) fn is_valid(&self, index: usize) -> bool {
+        col_parser::is_valid(self.validity.as_ref(), index)
+    }
+    pub(crate) fn display_at(&self, index: usize) -> String {
+        match self.get(index) {
+            Some(value) if self.is_valid(index) => value.to_string(),
+            _ => DataValue::Null.to_string(),
+        }
+    }
+    pub(crate) fn take(&self, indices: &[usize]) -> Self {
+        let mut col = match &self.cells {
+            Cells::Packed { .. } => Self::new(
+                self.col_name.clone(),
+                indices.iter().filter_map(|&i| self.get(i)),
+            ),
+            Cells::Interned(values) => Self {
+                col_name: self.col_name.clone(),
+                cells: Cells::Interned(
+                    indices
+                        .iter()
+                        .filter_map(|&i| values.get(i).cloned())
+                        .collect(),
+                ),
+                filter: None,
+                validity: None,
+            },
+        };
+        col.validity = col_parser::take_validity(self.validity.as_ref(), indices);
+        col
+    }
+    pub(crate) fn get_range_as_strings(
+        &self,
+        beg: usize,
+        end: usize,
+    ) -> Result<Vec<String>, Error> {
+        if end > self.len() || beg > end {
+            return Err(CoalaError::OutOfRange.into());
+        }
+        Ok((beg..end).map(|i| self.display_at(i)).collect())
+    }
+    pub(crate) fn extreme_index(&self, wanted: Ordering) -> Option<usize> {
+        let mut best: Option<(usize, &str)> = None;
+        for (i, value) in self.iter().enumerate() {
+            if !self.is_valid(i) {
+                continue;
+            }
+            match best {
+                Some((_, b)) if value.cmp(b) != wanted => {}
+                _ => best = Some((i, value)),
+            }
+        }
+        best.map(|(i, _)| i)
+    }
+}
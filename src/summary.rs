@@ -0,0 +1,390 @@
+use std::collections::HashMap;
+
+use miette::Error;
+
+use crate::{
+    agg::{Accumulator, Agg},
+    bootstrap::interpolate,
+    col_parser::{ColType, DType, DataValue},
+    csv_parser::Csv,
+    error::CoalaError,
+    groupby::{GroupKeys, DESCRIBE},
+    humanize::{DisplayFormat, DurationUnit},
+};
+
+// Example values per column in `data_dictionary`
+const DICTIONARY_EXAMPLES: usize = 3;
+
+// The `DESCRIBE` statistics of `values`, all as floats so the column keeps one type. The
+// quantiles are read off one sort of the values rather than sorting for each
+fn describe_values(values: &[DataValue]) -> Vec<DataValue> {
+    let mut sorted: Vec<f64> = values.iter().filter_map(DataValue::as_f64).collect();
+    sorted.sort_unstable_by(f64::total_cmp);
+    DESCRIBE
+        .iter()
+        .map(|(_, agg)| {
+            let value = match agg {
+                Agg::Median | Agg::Quantile(_) if sorted.is_empty() => None,
+                Agg::Median => Some(interpolate(&sorted, 0.5)),
+                Agg::Quantile(q) => Some(interpolate(&sorted, *q)),
+                agg => {
+                    let mut accumulator = Accumulator::new(*agg);
+                    for value in values {
+                        accumulator.push_value(value);
+                    }
+                    accumulator.finish().as_f64()
+                }
+            };
+            value.map_or(DataValue::Null, DataValue::Float)
+        })
+        .collect()
+}
+
+impl Csv {
+    // The summary frame of `cols`, lineage continuing from this frame
+    pub(crate) fn summary_frame(
+        &self,
+        operation: &str,
+        cols: Vec<(&str, Vec<DataValue>)>,
+        n_rows: usize,
+    ) -> Result<Csv, Error> {
+        let header = cols.iter().map(|(name, _)| name.to_string()).collect();
+        let cols = cols
+            .into_iter()
+            .map(|(name, values)| ColType::from_data_values(values, name.to_string()))
+            .collect::<Result<_, Error>>()?;
+        let mut csv = Csv::from_cols(cols, header, n_rows);
+        csv.lineage = self.lineage.clone();
+        csv.lineage.record(operation.to_string());
+        Ok(csv)
+    }
+    /// Count, mean, standard deviation, minimum, quartiles and maximum of every numeric column.
+    /// One row per statistic, named in the `statistic` column, and a column per numeric column
+    pub fn describe(&self) -> Result<Csv, Error> {
+        self.describe_frame("describe")
+    }
+    /// `describe`, named alongside `describe_categorical` and `describe_datetime`
+    pub fn describe_numeric(&self) -> Result<Csv, Error> {
+        self.describe_frame("describe_numeric")
+    }
+    /// One row per text column: how many values it holds and how many are null, how many are
+    /// distinct, and the most frequent value with its count, the earliest one on ties
+    pub fn describe_categorical(&self) -> Result<Csv, Error> {
+        let mut rows: [Vec<DataValue>; 6] = Default::default();
+        for name in &self.header {
+            let col = self.get_col(name)?;
+            if col.dtype() != DType::String {
+                continue;
+            }
+            // Count and first row of each value
+            let mut counts: HashMap<DataValue, (usize, usize)> = HashMap::new();
+            for (row, value) in col.iter().enumerate() {
+                if !matches!(value, DataValue::Null) {
+                    counts.entry(value).or_insert((0, row)).0 += 1;
+                }
+            }
+            let top = counts
+                .iter()
+                .max_by(|(_, (a, a_row)), (_, (b, b_row))| a.cmp(b).then(b_row.cmp(a_row)));
+            let null_count = col.null_count();
+            let row = [
+                DataValue::String(name.clone()),
+                DataValue::Integer((col.len() - null_count) as i64),
+                DataValue::Integer(null_count as i64),
+                DataValue::Integer(counts.len() as i64),
+                top.map_or(DataValue::Null, |(value, _)| value.clone()),
+                top.map_or(DataValue::Null, |(_, (freq, _))| {
+                    DataValue::Integer(*freq as i64)
+                }),
+            ];
+            for (values, value) in rows.iter_mut().zip(row) {
+                values.push(value);
+            }
+        }
+        let n_rows = rows[0].len();
+        let [column, count, null_count, unique, top, freq] = rows;
+        self.summary_frame(
+            "describe_categorical",
+            vec![
+                ("column", column),
+                ("count", count),
+                ("null_count", null_count),
+                ("unique", unique),
+                ("top", top),
+                ("freq", freq),
+            ],
+            n_rows,
+        )
+    }
+    /// One row per datetime column: how many values it holds and how many are null, the
+    /// earliest and latest, the time between them, and the most common gap between
+    /// consecutive distinct values with the share of gaps it makes up. Times are in seconds,
+    /// printed as durations
+    pub fn describe_datetime(&self) -> Result<Csv, Error> {
+        let mut rows: [Vec<DataValue>; 8] = Default::default();
+        for name in &self.header {
+            let col = self.get_col(name)?;
+            if col.dtype() != DType::Datetime {
+                continue;
+            }
+            let mut timestamps: Vec<i64> = col
+                .iter()
+                .filter_map(|value| match value {
+                    DataValue::DateTime(dt) => Some(dt.timestamp()),
+                    _ => None,
+                })
+                .collect();
+            timestamps.sort_unstable();
+            timestamps.dedup();
+            let mut gaps: HashMap<i64, usize> = HashMap::new();
+            for pair in timestamps.windows(2) {
+                *gaps.entry(pair[1] - pair[0]).or_default() += 1;
+            }
+            // The shortest of the most common gaps on ties
+            let common_gap = gaps
+                .iter()
+                .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)));
+            let n_gaps = timestamps.len().saturating_sub(1);
+            let null_count = col.null_count();
+            let row = [
+                DataValue::String(name.clone()),
+                DataValue::Integer((col.len() - null_count) as i64),
+                DataValue::Integer(null_count as i64),
+                col.min().unwrap_or(DataValue::Null),
+                col.max().unwrap_or(DataValue::Null),
+                match (timestamps.first(), timestamps.last()) {
+                    (Some(first), Some(last)) => DataValue::Integer(last - first),
+                    _ => DataValue::Null,
+                },
+                common_gap.map_or(DataValue::Null, |(gap, _)| DataValue::Integer(*gap)),
+                common_gap.map_or(DataValue::Null, |(_, count)| {
+                    DataValue::Float(*count as f64 / n_gaps as f64)
+                }),
+            ];
+            for (values, value) in rows.iter_mut().zip(row) {
+                values.push(value);
+            }
+        }
+        let n_rows = rows[0].len();
+        let [column, count, null_count, min, max, range, common_gap, common_gap_share] = rows;
+        let mut described = self.summary_frame(
+            "describe_datetime",
+            vec![
+                ("column", column),
+                ("count", count),
+                ("null_count", null_count),
+                ("min", min),
+                ("max", max),
+                ("range", range),
+                ("common_gap", common_gap),
+                ("common_gap_share", common_gap_share),
+            ],
+            n_rows,
+        )?;
+        for name in ["range", "common_gap"] {
+            described
+                .set_display_format(name, Some(DisplayFormat::Duration(DurationUnit::Seconds)))?;
+        }
+        Ok(described)
+    }
+    fn describe_frame(&self, operation: &str) -> Result<Csv, Error> {
+        if self.header.iter().any(|h| h == "statistic") {
+            return Err(CoalaError::DuplicateColumn {
+                name: "statistic".to_string(),
+            }
+            .into());
+        }
+        let names = DESCRIBE
+            .iter()
+            .map(|(name, _)| DataValue::String(name.to_string()))
+            .collect();
+        let mut cols = vec![("statistic", names)];
+        for name in &self.header {
+            let col = self.get_col(name)?;
+            if !col.is_numeric() {
+                continue;
+            }
+            let values: Vec<DataValue> = col.iter().collect();
+            cols.push((name, describe_values(&values)));
+        }
+        let mut described = self.summary_frame(operation, cols, DESCRIBE.len())?;
+        // The statistics are in the unit of their column, but for the count
+        described.unformatted_rows = DESCRIBE
+            .iter()
+            .position(|(_, agg)| *agg == Agg::Count)
+            .into_iter()
+            .collect();
+        for name in &self.header {
+            if let Some(format) = self
+                .display_format(name)
+                .filter(|_| described.header.contains(name))
+            {
+                described.set_display_format(name, Some(format))?;
+            }
+        }
+        Ok(described)
+    }
+    /// `describe` of each group of rows sharing the values of `by`, stacked into one frame.
+    /// The key columns come first, then `statistic` and a column per numeric column besides
+    /// the keys. Groups are in order of first appearance
+    pub fn describe_by(&self, by: impl GroupKeys) -> Result<Csv, Error> {
+        let keys = by.names();
+        if keys.iter().any(|k| k == "statistic") || self.header.iter().any(|h| h == "statistic") {
+            return Err(CoalaError::DuplicateColumn {
+                name: "statistic".to_string(),
+            }
+            .into());
+        }
+        let grouped = self.group_by(by)?;
+        let groups = grouped.groups();
+        let key_cols = keys
+            .iter()
+            .map(|key| self.get_col(key))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let mut value_cols = Vec::new();
+        for name in &self.header {
+            let col = self.get_col(name)?;
+            if col.is_numeric() && !keys.contains(name) {
+                value_cols.push((name.as_str(), col));
+            }
+        }
+        let mut cols: Vec<(&str, Vec<DataValue>)> = keys
+            .iter()
+            .map(|key| (key.as_str(), Vec::new()))
+            .chain(std::iter::once(("statistic", Vec::new())))
+            .chain(value_cols.iter().map(|(name, _)| (*name, Vec::new())))
+            .collect();
+        for rows in groups {
+            for (i, key) in key_cols.iter().enumerate() {
+                let value = key.get(rows[0])?;
+                cols[i].1.extend(std::iter::repeat_n(value, DESCRIBE.len()));
+            }
+            cols[keys.len()].1.extend(
+                DESCRIBE
+                    .iter()
+                    .map(|(name, _)| DataValue::String(name.to_string())),
+            );
+            for (i, (_, col)) in value_cols.iter().enumerate() {
+                let values = rows
+                    .iter()
+                    .map(|&row| col.get(row))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                cols[keys.len() + 1 + i].1.extend(describe_values(&values));
+            }
+        }
+        let operation = format!("describe_by({})", keys.join(", "));
+        self.summary_frame(&operation, cols, groups.len() * DESCRIBE.len())
+    }
+    /// One row per column: its name and type, how many values it holds, how many are null,
+    /// how many are distinct, and its minimum and maximum as text when it has an order.
+    /// `profile_report` gives the same as plain values
+    pub fn profile(&self) -> Result<Csv, Error> {
+        let mut rows: [Vec<DataValue>; 7] = Default::default();
+        let text = |value: Option<String>| value.map_or(DataValue::Null, DataValue::String);
+        for col in self.profile_report()?.columns {
+            let row = [
+                DataValue::String(col.name),
+                DataValue::String(col.dtype.name().to_string()),
+                DataValue::Integer(col.count as i64),
+                DataValue::Integer(col.null_count as i64),
+                DataValue::Integer(col.n_unique as i64),
+                text(col.min),
+                text(col.max),
+            ];
+            for (values, value) in rows.iter_mut().zip(row) {
+                values.push(value);
+            }
+        }
+        let [column, dtype, count, null_count, n_unique, min, max] = rows;
+        self.summary_frame(
+            "profile",
+            vec![
+                ("column", column),
+                ("dtype", dtype),
+                ("count", count),
+                ("null_count", null_count),
+                ("n_unique", n_unique),
+                ("min", min),
+                ("max", max),
+            ],
+            self.header.len(),
+        )
+    }
+    /// One row per column with its number of nulls and their share of the rows, null for a
+    /// frame without rows
+    pub fn null_summary(&self) -> Result<Csv, Error> {
+        let mut column = Vec::with_capacity(self.header.len());
+        let mut null_count = Vec::with_capacity(self.header.len());
+        let mut null_fraction = Vec::with_capacity(self.header.len());
+        for name in &self.header {
+            let nulls = self.get_col(name)?.null_count();
+            column.push(DataValue::String(name.clone()));
+            null_count.push(DataValue::Integer(nulls as i64));
+            null_fraction.push(if self.n_rows == 0 {
+                DataValue::Null
+            } else {
+                DataValue::Float(nulls as f64 / self.n_rows as f64)
+            });
+        }
+        self.summary_frame(
+            "null_summary",
+            vec![
+                ("column", column),
+                ("null_count", null_count),
+                ("null_fraction", null_fraction),
+            ],
+            self.header.len(),
+        )
+    }
+    /// Documentation of the dataset, one row per column: its name and type, the percentage of
+    /// nulls, up to three distinct example values and the `description` set with `set_meta`.
+    /// `to_markdown` turns it into a table to share
+    pub fn data_dictionary(&self) -> Result<Csv, Error> {
+        let mut rows: [Vec<DataValue>; 5] = Default::default();
+        for name in &self.header {
+            let col = self.get_col(name)?;
+            let mut examples: Vec<String> = Vec::new();
+            for value in col.iter() {
+                if examples.len() == DICTIONARY_EXAMPLES {
+                    break;
+                }
+                let text = value.to_string();
+                if !matches!(value, DataValue::Null) && !examples.contains(&text) {
+                    examples.push(text);
+                }
+            }
+            let null_percent = match self.n_rows {
+                0 => DataValue::Null,
+                // Rounded to hundredths, plenty for documentation
+                n => DataValue::Float(
+                    (10_000.0 * col.null_count() as f64 / n as f64).round() / 100.0,
+                ),
+            };
+            let description = col
+                .meta("description")
+                .map_or(DataValue::Null, |d| DataValue::String(d.to_string()));
+            let row = [
+                DataValue::String(name.clone()),
+                DataValue::String(col.dtype().name().to_string()),
+                null_percent,
+                DataValue::String(examples.join(", ")),
+                description,
+            ];
+            for (values, value) in rows.iter_mut().zip(row) {
+                values.push(value);
+            }
+        }
+        let [column, dtype, null_percent, examples, description] = rows;
+        self.summary_frame(
+            "data_dictionary",
+            vec![
+                ("column", column),
+                ("dtype", dtype),
+                ("null_percent", null_percent),
+                ("examples", examples),
+                ("description", description),
+            ],
+            self.header.len(),
+        )
+    }
+}
@@ -0,0 +1,315 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    thread,
+    time::Duration,
+};
+
+use miette::Error;
+
+use crate::{
+    col_parser::{DType, DataValue},
+    csv_parser::{Csv, CsvConfig},
+    embedded::{parse_line, ParseError, RecordState},
+    error::CoalaError,
+    small_string::SmallString,
+    statistics::RunningStats,
+    tokenizer::{read_line, Dialect, Record},
+};
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+pub struct CsvTail {
+    reader: BufReader<File>,
+    dialect: Dialect,
+    header: Vec<String>,
+    dtypes: Vec<DType>,
+    date_formats: Vec<Vec<String>>,
+    // Cells read as null, as on load
+    null_values: Vec<String>,
+    // `None` for the columns that aren't numeric, as in `describe`
+    stats: Vec<Option<RunningStats>>,
+    n_rows: usize,
+    // The line read so far, without its line break while the writer is still on it
+    line: Vec<u8>,
+    chunk: Vec<u8>,
+    // Fields of the record being read, kept across polls while a quoted field spans lines
+    state: RecordState<'static, SmallString>,
+    line_no: usize,
+    record_line: usize,
+    // The last line ended in `\r`, the `\n` of a `\r\n` may come with the next write
+    after_cr: bool,
+    poll_interval: Duration,
+}
+
+impl CsvTail {
+    pub fn follow(path: &str, config: CsvConfig) -> Result<Self, Error> {
+        let dialect = Dialect::from(&config);
+        let date_formats_config = config.parser_as_date.clone();
+        let fallback_date_formats = config.fallback_date_formats.clone();
+        let null_values = config.null_values.clone();
+        // Opened before the load, rows appended meanwhile are either in the load or past the
+        // offset it stopped at
+        let mut file = File::open(path).map_err(CoalaError::from)?;
+        let existing = Csv::new(path, config)?;
+        let mut stats: Vec<Option<RunningStats>> = existing
+            .cols
+            .iter()
+            .map(|col| col.dtype().is_numeric().then(RunningStats::default))
+            .collect();
+        for (col, stats) in existing.cols.iter().zip(stats.iter_mut()) {
+            let Some(stats) = stats else {
+                continue;
+            };
+            for i in 0..existing.n_rows {
+                if let Some(value) = col.data_as_value(i)?.as_f64() {
+                    stats.push(value);
+                }
+            }
+        }
+        let date_formats = existing
+            .header
+            .iter()
+            .map(|name| {
+                date_formats_config
+                    .as_ref()
+                    .and_then(|formats| formats.get(name))
+                    .into_iter()
+                    .flatten()
+                    .chain(&fallback_date_formats)
+                    .map(|format| format.to_string())
+                    .collect()
+            })
+            .collect();
+        // Lines of the loaded part, so errors in followed rows point at the right line
+        let mut loaded = BufReader::new((&file).take(existing.metrics.bytes_read as u64));
+        let (mut line, mut line_no) = (Vec::new(), 0);
+        while read_line(&mut loaded, &mut line)? > 0 {
+            line_no += 1;
+        }
+        file.seek(SeekFrom::Start(existing.metrics.bytes_read as u64))
+            .map_err(CoalaError::from)?;
+        Ok(Self {
+            reader: BufReader::new(file),
+            dialect,
+            dtypes: existing.cols.iter().map(|c| c.dtype()).collect(),
+            header: existing.header,
+            date_formats,
+            null_values,
+            stats,
+            n_rows: existing.n_rows,
+            line: Vec::new(),
+            chunk: Vec::new(),
+            state: RecordState::new(None),
+            line_no,
+            record_line: line_no + 1,
+            after_cr: false,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        })
+    }
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+    pub fn header(&self) -> &[String] {
+        &self.header
+    }
+    /// Running statistics of the numeric column `name`, `None` for the other columns
+    pub fn stats(&self, name: &str) -> Option<&RunningStats> {
+        let i = self.header.iter().position(|h| h == name)?;
+        self.stats[i].as_ref()
+    }
+    /// Rows the writer left once it's done, the last one may lack its line break. A quoted
+    /// field that's still open is an `UnclosedQuote` error
+    pub fn finish(mut self) -> Result<Vec<Vec<DataValue>>, Error> {
+        let mut rows = Vec::new();
+        while let Some(record) = self.read_record()? {
+            rows.push(self.parse_row(record)?);
+        }
+        let line = std::mem::take(&mut self.line);
+        if !line.is_empty() && self.parse_line(&line)? {
+            let record = self.take_record()?;
+            rows.push(self.parse_row(record)?);
+        }
+        if self.state.in_quotes {
+            return Err(CoalaError::UnclosedQuote {
+                line: self.record_line,
+                field: self.state.index + 1,
+            }
+            .into());
+        }
+        Ok(rows)
+    }
+    // The next record the writer has finished, `None` until there's one. A line without its
+    // line break and a record still in quotes are kept for the next poll
+    fn read_record(&mut self) -> Result<Option<Record>, Error> {
+        loop {
+            if read_line(&mut self.reader, &mut self.chunk)? == 0 {
+                return Ok(None);
+            }
+            self.line.extend_from_slice(&self.chunk);
+            if !self.line.ends_with(b"\n") && !self.line.ends_with(b"\r") {
+                continue;
+            }
+            let line = std::mem::take(&mut self.line);
+            if std::mem::replace(&mut self.after_cr, line.ends_with(b"\r")) && line == b"\n" {
+                continue;
+            }
+            if self.parse_line(&line)? {
+                return self.take_record().map(Some);
+            }
+        }
+    }
+    // Feeds a whole line to the record being read, returns whether it completed the record.
+    // Blank lines between records are skipped
+    fn parse_line(&mut self, line: &[u8]) -> Result<bool, Error> {
+        self.line_no += 1;
+        let line = std::str::from_utf8(line)
+            .map_err(|_| CoalaError::from(ParseError::InvalidUtf8 { line: self.line_no }))?;
+        if self.state.is_empty() && !self.state.in_quotes {
+            if line.trim_end().is_empty() {
+                return Ok(false);
+            }
+            self.record_line = self.line_no;
+        }
+        self.state.line = self.line_no;
+        Ok(parse_line(line, self.dialect, &mut self.state))
+    }
+    fn take_record(&mut self) -> Result<Record, Error> {
+        let state = std::mem::replace(&mut self.state, RecordState::new(None));
+        match state.problem {
+            Some(problem) => Err(CoalaError::from(problem).into()),
+            None => Ok(state.fields),
+        }
+    }
+    fn parse_row(&mut self, fields: Record) -> Result<Vec<DataValue>, Error> {
+        if fields.len() != self.dtypes.len() {
+            return Err(CoalaError::RaggedRow {
+                row: self.n_rows,
+                expected: self.dtypes.len(),
+                found: fields.len(),
+            }
+            .into());
+        }
+        let row = fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                if self.null_values.iter().any(|null| null == field.as_ref()) {
+                    return Ok(DataValue::Null);
+                }
+                self.dtypes[i].parse(field, &self.date_formats[i])
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for (value, stats) in row.iter().zip(self.stats.iter_mut()) {
+            if let (Some(value), Some(stats)) = (value.as_f64(), stats) {
+                stats.push(value);
+            }
+        }
+        self.n_rows += 1;
+        Ok(row)
+    }
+}
+
+impl Iterator for CsvTail {
+    type Item = Result<Vec<DataValue>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.read_record() {
+                Ok(Some(record)) => return Some(self.parse_row(record)),
+                // The writer hasn't finished the record yet, keep what we have and wait
+                Ok(None) => thread::sleep(self.poll_interval),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, io::Write};
+
+    use super::CsvTail;
+    use crate::col_parser::DataValue;
+    use crate::csv_parser::CsvConfig;
+
+    fn followed(name: &str, contents: &str) -> (String, CsvTail) {
+        let path = env::temp_dir().join(format!("coala-tail-{}-{name}.csv", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        fs::write(&path, contents).unwrap();
+        let tail = CsvTail::follow(&path, CsvConfig::default()).unwrap();
+        (path, tail)
+    }
+
+    fn append(path: &str, text: &str) {
+        let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn quoted_field_spans_polls() {
+        let (path, mut tail) = followed("multiline", "id,note\n1,a\n");
+        append(&path, "2,\"two\n");
+        assert!(tail.read_record().unwrap().is_none());
+        append(&path, "lines\"\n");
+        let row = tail.next().unwrap().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            row,
+            vec![DataValue::Integer(2), DataValue::from("two\nlines")]
+        );
+    }
+
+    #[test]
+    fn lone_cr_ends_rows() {
+        let (path, mut tail) = followed("cr", "id,v\r1,2\r");
+        append(&path, "3,4\r");
+        let row = tail.next().unwrap().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(row, vec![DataValue::Integer(3), DataValue::Integer(4)]);
+    }
+
+    #[test]
+    fn finish_rejects_unclosed_quote() {
+        let (path, tail) = followed("unclosed", "id,note\n1,a\n");
+        append(&path, "2,\"never closed\n");
+        let result = tail.finish();
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn finish_reads_last_row_without_line_break() {
+        let (path, tail) = followed("unterminated", "id,note\n1,a\n");
+        append(&path, "2,b\n3,c");
+        let rows = tail.finish().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1], vec![DataValue::Integer(3), DataValue::from("c")]);
+    }
+
+    #[test]
+    fn configured_null_values_read_as_nulls() {
+        let path = env::temp_dir().join(format!("coala-tail-{}-nulls.csv", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        fs::write(&path, "id,v\n1,2\n").unwrap();
+        let config = CsvConfig::default().null_values(&["NA"]);
+        let mut tail = CsvTail::follow(&path, config).unwrap();
+        append(&path, "2,NA\n");
+        let row = tail.next().unwrap().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(row, vec![DataValue::Integer(2), DataValue::Null]);
+        assert_eq!(tail.stats("v").unwrap().count(), 1);
+    }
+
+    #[test]
+    fn only_numeric_columns_have_stats() {
+        let (path, mut tail) = followed("numeric", "id,name\n1,a\n");
+        append(&path, "2,b\n");
+        tail.next().unwrap().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(tail.stats("id").unwrap().count(), 2);
+        assert!(tail.stats("name").is_none());
+    }
+}
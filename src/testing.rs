@@ -0,0 +1,297 @@
+use std::time::Duration;
+
+use datetime::datetime::Datetime;
+use miette::{miette, Error};
+
+use crate::{
+    col_parser::{ColType, DataValue},
+    csv_parser::Csv,
+    display::dtype_label,
+    error::CoalaError,
+    rng::Rng,
+    timeseries::step_seconds,
+};
+
+/// How `generate` fills a column
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnSpec {
+    /// Floats drawn from a normal distribution
+    Normal { mean: f64, std: f64 },
+    /// Integers drawn uniformly from `low..=high`
+    UniformInt { low: i64, high: i64 },
+    /// Labels drawn with probability proportional to their weight
+    Categorical(Vec<(String, f64)>),
+    /// Datetimes from `start`, one `every` apart
+    DateRange { start: Datetime, every: Duration },
+    /// The values of the inner spec, each null with probability `fraction`
+    WithNulls(Box<ColumnSpec>, f64),
+}
+
+impl ColumnSpec {
+    pub fn normal(mean: f64, std: f64) -> Self {
+        ColumnSpec::Normal { mean, std }
+    }
+    pub fn uniform_int(low: i64, high: i64) -> Self {
+        ColumnSpec::UniformInt { low, high }
+    }
+    pub fn categorical(weights: &[(&str, f64)]) -> Self {
+        ColumnSpec::Categorical(
+            weights
+                .iter()
+                .map(|&(label, weight)| (label.to_string(), weight))
+                .collect(),
+        )
+    }
+    pub fn date_range(start: Datetime, every: Duration) -> Self {
+        ColumnSpec::DateRange { start, every }
+    }
+    pub fn with_nulls(self, fraction: f64) -> Self {
+        ColumnSpec::WithNulls(Box::new(self), fraction)
+    }
+    fn values(&self, n_rows: usize, rng: &mut Rng) -> Result<Vec<DataValue>, Error> {
+        Ok(match self {
+            ColumnSpec::Normal { mean, std } => {
+                if std.is_nan() || *std < 0.0 {
+                    return Err(CoalaError::OutOfRange.into());
+                }
+                (0..n_rows)
+                    .map(|_| {
+                        // Box-Muller, 1 - u keeps the logarithm finite
+                        let (u, v) = (1.0 - rng.next_f64(), rng.next_f64());
+                        let z = (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos();
+                        DataValue::Float(mean + std * z)
+                    })
+                    .collect()
+            }
+            ColumnSpec::UniformInt { low, high } => {
+                if low > high {
+                    return Err(CoalaError::OutOfRange.into());
+                }
+                let span = high.abs_diff(*low);
+                (0..n_rows)
+                    .map(|_| {
+                        let offset = match span.checked_add(1) {
+                            Some(n) => rng.next_u64() % n,
+                            None => rng.next_u64(),
+                        };
+                        DataValue::Integer(low.wrapping_add_unsigned(offset))
+                    })
+                    .collect()
+            }
+            ColumnSpec::Categorical(weights) => {
+                let total: f64 = weights.iter().map(|(_, weight)| weight).sum();
+                if weights
+                    .iter()
+                    .any(|(_, weight)| weight.is_nan() || *weight < 0.0)
+                    || total.is_nan()
+                    || total <= 0.0
+                {
+                    return Err(miette!(
+                        "Categories need non-negative weights, some positive"
+                    ));
+                }
+                (0..n_rows)
+                    .map(|_| {
+                        let mut target = rng.next_f64() * total;
+                        let (label, _) = weights
+                            .iter()
+                            .find(|(_, weight)| {
+                                target -= weight;
+                                target < 0.0
+                            })
+                            // Rounding can leave the target a hair above the total
+                            .unwrap_or(&weights[weights.len() - 1]);
+                        DataValue::String(label.clone())
+                    })
+                    .collect()
+            }
+            ColumnSpec::DateRange { start, every } => {
+                let step = step_seconds(*every)?;
+                (0..n_rows as i64)
+                    .map(|i| {
+                        DataValue::DateTime(Datetime::from_timestamp(start.timestamp() + i * step))
+                    })
+                    .collect()
+            }
+            ColumnSpec::WithNulls(inner, fraction) => {
+                if !(0.0..=1.0).contains(fraction) {
+                    return Err(CoalaError::OutOfRange.into());
+                }
+                let mut values = inner.values(n_rows, rng)?;
+                for value in values.iter_mut() {
+                    if rng.next_f64() < *fraction {
+                        *value = DataValue::Null;
+                    }
+                }
+                values
+            }
+        })
+    }
+}
+
+/// A frame of `n_rows` rows with a column per spec, for examples, benchmarks and fuzzing.
+/// The same seed gives the same frame
+pub fn generate(n_rows: usize, specs: &[(&str, ColumnSpec)], seed: u64) -> Result<Csv, Error> {
+    let mut rng = Rng::new(seed);
+    let mut header: Vec<String> = Vec::with_capacity(specs.len());
+    let mut cols = Vec::with_capacity(specs.len());
+    for (name, spec) in specs {
+        if header.iter().any(|h| h == name) {
+            return Err(CoalaError::DuplicateColumn {
+                name: name.to_string(),
+            }
+            .into());
+        }
+        let values = spec.values(n_rows, &mut rng)?;
+        cols.push(ColType::from_data_values(values, name.to_string())?);
+        header.push(name.to_string());
+    }
+    let mut csv = Csv::from_cols(cols, header, n_rows);
+    csv.lineage
+        .record(format!("generate({n_rows}, seed = {seed})"));
+    Ok(csv)
+}
+
+#[cfg(feature = "proptest")]
+mod arbitrary {
+    use std::time::Duration;
+
+    use datetime::datetime::Datetime;
+    use proptest::prelude::*;
+
+    use super::{generate, ColumnSpec};
+    use crate::csv_parser::Csv;
+
+    impl Arbitrary for ColumnSpec {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            let leaf = prop_oneof![
+                (-1e6..1e6, 0.0..1e3).prop_map(|(mean, std)| ColumnSpec::normal(mean, std)),
+                (any::<i32>(), 0..i32::MAX).prop_map(|(low, span)| {
+                    ColumnSpec::uniform_int(low.into(), i64::from(low) + i64::from(span))
+                }),
+                prop::collection::vec(("[a-z]{1,8}", 0.1..10.0), 1..8)
+                    .prop_map(ColumnSpec::Categorical),
+                (0..4_102_444_800i64, 1..86_400u64).prop_map(|(start, every)| {
+                    ColumnSpec::date_range(
+                        Datetime::from_timestamp(start),
+                        Duration::from_secs(every),
+                    )
+                }),
+            ];
+            (leaf, prop::option::of(0.0..=1.0))
+                .prop_map(|(spec, nulls)| match nulls {
+                    Some(fraction) => spec.with_nulls(fraction),
+                    None => spec,
+                })
+                .boxed()
+        }
+    }
+
+    /// Frames of up to `max_rows` rows and `max_cols` columns of any spec, for property tests
+    pub fn arb_frame(max_rows: usize, max_cols: usize) -> impl Strategy<Value = Csv> {
+        (
+            0..=max_rows,
+            prop::collection::vec(any::<ColumnSpec>(), 1..=max_cols.max(1)),
+            any::<u64>(),
+        )
+            .prop_map(|(n_rows, specs, seed)| {
+                let names: Vec<String> = (0..specs.len()).map(|i| format!("col{i}")).collect();
+                let specs: Vec<(&str, ColumnSpec)> =
+                    names.iter().map(String::as_str).zip(specs).collect();
+                generate(n_rows, &specs, seed).expect("Arbitrary specs are valid")
+            })
+    }
+}
+
+#[cfg(feature = "proptest")]
+pub use arbitrary::arb_frame;
+
+/// How `to_snapshot_string_with` normalizes a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotOptions {
+    /// Columns by name instead of in frame order, so reordering them doesn't break snapshots
+    pub sort_columns: bool,
+    /// Digits after the point for floats, which hides rounding noise between platforms
+    pub float_precision: usize,
+}
+
+impl Default for SnapshotOptions {
+    fn default() -> Self {
+        Self {
+            sort_columns: false,
+            float_precision: 6,
+        }
+    }
+}
+
+impl SnapshotOptions {
+    pub fn sort_columns(mut self, sort_columns: bool) -> Self {
+        self.sort_columns = sort_columns;
+        self
+    }
+    pub fn float_precision(mut self, float_precision: usize) -> Self {
+        self.float_precision = float_precision;
+        self
+    }
+    fn cell(&self, value: DataValue) -> String {
+        match value {
+            DataValue::Float(v) if v.is_nan() => "NaN".to_string(),
+            DataValue::Float(v) => {
+                let text = format!("{v:.*}", self.float_precision);
+                // Values that round to zero from below print as "-0.000"
+                if text.starts_with('-') && text[1..].chars().all(|c| c == '0' || c == '.') {
+                    text[1..].to_string()
+                } else {
+                    text
+                }
+            }
+            value => value.to_string(),
+        }
+    }
+}
+
+impl Csv {
+    /// `to_snapshot_string_with` the default options
+    pub fn to_snapshot_string(&self) -> String {
+        self.to_snapshot_string_with(&SnapshotOptions::default())
+    }
+    /// Every row and column as aligned text with the shape and types on top, for snapshot
+    /// tests. Unlike printing, nothing is cut to fit the terminal, so the same frame always
+    /// gives the same text
+    pub fn to_snapshot_string_with(&self, options: &SnapshotOptions) -> String {
+        let mut order: Vec<usize> = (0..self.cols.len()).collect();
+        if options.sort_columns {
+            order.sort_by(|&a, &b| self.header[a].cmp(&self.header[b]));
+        }
+        let cols: Vec<Vec<String>> = order
+            .iter()
+            .map(|&i| {
+                let col = &self.cols[i];
+                let mut cells = vec![self.header[i].clone(), dtype_label(col.dtype())];
+                cells
+                    .extend((0..self.n_rows).map(|row| {
+                        options.cell(col.data_as_value(row).unwrap_or(DataValue::Null))
+                    }));
+                cells
+            })
+            .collect();
+        let widths: Vec<usize> = cols
+            .iter()
+            .map(|cells| cells.iter().map(|c| c.chars().count()).max().unwrap_or(0))
+            .collect();
+        let mut text = format!("shape: ({}, {})\n", self.n_rows, self.cols.len());
+        for line in 0..self.n_rows + 2 {
+            let cells: Vec<String> = cols
+                .iter()
+                .zip(&widths)
+                .map(|(cells, &width)| format!("{:<width$}", cells[line]))
+                .collect();
+            text.push_str(cells.join(" | ").trim_end());
+            text.push('\n');
+        }
+        text
+    }
+}
@@ -0,0 +1,108 @@
+use std::{collections::HashMap, ops::RangeInclusive, time::Duration};
+
+use datetime::datetime::Datetime;
+use miette::Error;
+
+use crate::{
+    col_parser::{ColType, DataValue},
+    csv_parser::Csv,
+    error::CoalaError,
+};
+
+/// A run of missing timestamps, `start` and `end` being the first and last of them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeGap {
+    pub start: Datetime,
+    pub end: Datetime,
+    pub missing: usize,
+}
+
+pub(crate) fn step_seconds(interval: Duration) -> Result<i64, Error> {
+    match i64::try_from(interval.as_secs()) {
+        Ok(step) if step > 0 => Ok(step),
+        _ => Err(CoalaError::OutOfRange.into()),
+    }
+}
+
+impl Csv {
+    // One per row, `None` where the row is null
+    pub(crate) fn timestamps(&self, on: &str) -> Result<Vec<Option<i64>>, Error> {
+        self.cols[self.col_index(on)?].with_plain(|col| match col {
+            ColType::Datetime(col) => Ok(col
+                .values
+                .iter()
+                .enumerate()
+                .map(|(row, dt)| col.is_valid(row).then(|| dt.timestamp()))
+                .collect()),
+            col => Err(CoalaError::InvalidType {
+                col: col.name().to_string(),
+            }
+            .into()),
+        })
+    }
+    /// Reports every stretch where consecutive timestamps of `on` are further apart than
+    /// `expected_interval`. Rows don't need to be sorted
+    pub fn find_gaps(&self, on: &str, expected_interval: Duration) -> Result<Vec<TimeGap>, Error> {
+        let step = step_seconds(expected_interval)?;
+        let mut timestamps: Vec<i64> = self.timestamps(on)?.into_iter().flatten().collect();
+        timestamps.sort_unstable();
+        timestamps.dedup();
+        Ok(timestamps
+            .windows(2)
+            .filter(|pair| pair[1] - pair[0] > step)
+            .map(|pair| {
+                let missing = (pair[1] - pair[0] - 1) / step;
+                TimeGap {
+                    start: Datetime::from_timestamp(pair[0] + step),
+                    end: Datetime::from_timestamp(pair[0] + missing * step),
+                    missing: missing as usize,
+                }
+            })
+            .collect())
+    }
+    /// Rebuilds the frame on a regular grid of `on` spanning `range` every `freq`. Rows off the
+    /// grid are dropped and missing ones get `fill` in every other column
+    pub fn reindex_time(
+        &self,
+        on: &str,
+        range: RangeInclusive<Datetime>,
+        freq: Duration,
+        fill: DataValue,
+    ) -> Result<Csv, Error> {
+        let step = step_seconds(freq)?;
+        let on_index = self.col_index(on)?;
+        let mut rows = HashMap::new();
+        for (row, timestamp) in self.timestamps(on)?.into_iter().enumerate() {
+            if let Some(timestamp) = timestamp {
+                rows.entry(timestamp).or_insert(row);
+            }
+        }
+        let (start, end) = (range.start().timestamp(), range.end().timestamp());
+        let grid: Vec<i64> = (start..=end).step_by(step as usize).collect();
+        let cols = self
+            .cols
+            .iter()
+            .enumerate()
+            .map(|(i, col)| {
+                let values = grid
+                    .iter()
+                    .map(|timestamp| match rows.get(timestamp) {
+                        Some(&row) => col.data_as_value(row),
+                        None if i == on_index => {
+                            Ok(DataValue::DateTime(Datetime::from_timestamp(*timestamp)))
+                        }
+                        None => Ok(fill.clone()),
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                ColType::from_data_values(values, col.name().to_string())
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let mut csv = Csv::from_cols(cols, self.header.clone(), grid.len());
+        csv.units = self.units.clone();
+        csv.meta = self.meta.clone();
+        csv.lineage = self.lineage.clone();
+        csv.lineage
+            .record(format!("reindex_time({on}, {}s, {fill})", step));
+        Ok(csv)
+    }
+}
@@ -0,0 +1,477 @@
+use std::{
+    io::{self, BufRead, Seek, SeekFrom, Write},
+    thread,
+};
+
+use miette::{miette, Error};
+
+pub(crate) use crate::embedded::Dialect;
+use crate::{
+    csv_parser::CsvConfig,
+    embedded::{line_len, parse_line, LineEnding, ParseError, RecordState},
+    error::CoalaError,
+    small_string::SmallString,
+};
+
+impl From<&CsvConfig<'_>> for Dialect {
+    fn from(config: &CsvConfig) -> Self {
+        Self {
+            separator: config.separator,
+            quote: config.quote_char,
+            escape: config.escape_char,
+        }
+    }
+}
+
+pub(crate) type Record = Vec<SmallString>;
+
+// Reads the next line of `reader` with its line break into `line`, returning the bytes read
+pub(crate) fn read_line<R: BufRead>(reader: &mut R, line: &mut Vec<u8>) -> Result<usize, Error> {
+    line.clear();
+    loop {
+        let available = reader.fill_buf().map_err(CoalaError::from)?;
+        // A `\r` that ended the last buffer is either a line break or the start of `\r\n`
+        if line.last() == Some(&b'\r') {
+            if available.first() == Some(&b'\n') {
+                line.push(b'\n');
+                reader.consume(1);
+            }
+            break;
+        }
+        if available.is_empty() {
+            break;
+        }
+        let len = line_len(available);
+        line.extend_from_slice(&available[..len]);
+        let complete = len < available.len() || line.last() == Some(&b'\n');
+        reader.consume(len);
+        if complete {
+            break;
+        }
+    }
+    Ok(line.len())
+}
+
+// Reads whole records instead of lines, so quoted fields may span several physical lines.
+// Lines may end in `\n`, `\r\n` or a lone `\r`, the last one with no line break at all
+pub(crate) struct Records<R> {
+    reader: R,
+    dialect: Dialect,
+    line: Vec<u8>,
+    line_no: usize,
+    line_ending: Option<LineEnding>,
+    projection: Option<Vec<bool>>,
+    bytes_read: usize,
+    skipped_bytes: usize,
+}
+
+impl<R: BufRead> Records<R> {
+    pub(crate) fn new(reader: R, dialect: Dialect) -> Self {
+        Self {
+            reader,
+            dialect,
+            line: Vec::new(),
+            line_no: 0,
+            line_ending: None,
+            projection: None,
+            bytes_read: 0,
+            skipped_bytes: 0,
+        }
+    }
+    /// Only fields whose index is `true` are kept from now on
+    pub(crate) fn project(&mut self, keep: Vec<bool>) {
+        self.projection = Some(keep);
+    }
+    pub(crate) fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+    pub(crate) fn skipped_bytes(&self) -> usize {
+        self.skipped_bytes
+    }
+    pub(crate) fn line_no(&self) -> usize {
+        self.line_no
+    }
+    /// Line break convention of the lines read so far
+    pub(crate) fn line_ending(&self) -> Option<LineEnding> {
+        self.line_ending
+    }
+    /// Reads past up to `n` records without storing their fields, returns how many there were
+    pub(crate) fn skip_records(&mut self, n: usize) -> Result<usize, Error> {
+        let projection = self.projection.replace(Vec::new());
+        let mut skipped = 0;
+        let result = loop {
+            if skipped == n {
+                break Ok(skipped);
+            }
+            match self.read_record() {
+                Ok(Some(_)) => skipped += 1,
+                Ok(None) => break Ok(skipped),
+                Err(e) => break Err(e),
+            }
+        };
+        self.projection = projection;
+        result
+    }
+    fn read_record(&mut self) -> Result<Option<Record>, Error> {
+        let mut state = RecordState::new(self.projection.as_deref());
+        let start_line = self.line_no + 1;
+        let record = loop {
+            let read = read_line(&mut self.reader, &mut self.line)?;
+            self.bytes_read += read;
+            if read == 0 {
+                if state.in_quotes {
+                    return Err(CoalaError::UnclosedQuote {
+                        line: start_line,
+                        field: state.index + 1,
+                    }
+                    .into());
+                }
+                if state.is_empty() {
+                    return Ok(None);
+                }
+                state.end_field();
+                break state.fields;
+            }
+            self.line_no += 1;
+            self.line_ending = LineEnding::merge(self.line_ending, LineEnding::of(&self.line));
+            let line = std::str::from_utf8(&self.line)
+                .map_err(|_| CoalaError::from(ParseError::InvalidUtf8 { line: self.line_no }))?;
+            if !state.in_quotes && state.is_empty() && line.trim_end().is_empty() {
+                continue;
+            }
+            state.line = self.line_no;
+            if parse_line(line, self.dialect, &mut state) {
+                break state.fields;
+            }
+        };
+        if let Some(problem) = state.problem {
+            return Err(CoalaError::from(problem).into());
+        }
+        self.skipped_bytes += state.skipped_bytes;
+        Ok(Some(record))
+    }
+}
+
+impl<R: BufRead + Seek> Records<R> {
+    // Carries on at byte `offset` of the input, which must start a record. `line_no` is the
+    // number of lines before it, so errors keep their line numbers
+    pub(crate) fn seek(&mut self, offset: usize, line_no: usize) -> Result<(), Error> {
+        self.reader
+            .seek(SeekFrom::Start(offset as u64))
+            .map_err(CoalaError::from)?;
+        self.bytes_read = offset;
+        self.line_no = line_no;
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = Result<Record, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+// Byte offsets splitting `text` into at most `n` chunks of similar size, each starting on a
+// record boundary. Only quoting and escapes are tracked, which is much cheaper than parsing
+fn chunk_starts(text: &str, dialect: Dialect, n: usize) -> Vec<usize> {
+    let target = text.len() / n.max(1) + 1;
+    let mut starts = vec![0];
+    let mut chars = text.char_indices().peekable();
+    let (mut in_quotes, mut field_empty) = (false, true);
+    while let Some((i, c)) = chars.next() {
+        if Some(c) == dialect.escape {
+            chars.next();
+            field_empty = false;
+            continue;
+        }
+        if in_quotes {
+            if c == dialect.quote {
+                if chars.peek().is_some_and(|&(_, next)| next == dialect.quote) {
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            }
+            continue;
+        }
+        match c {
+            c if c == dialect.quote && field_empty => {
+                in_quotes = true;
+                field_empty = false;
+            }
+            c if c == dialect.separator => field_empty = true,
+            // Part of a `\r\n`, otherwise a line break on its own
+            '\r' if chars.peek().is_some_and(|&(_, next)| next == '\n') => {}
+            '\n' | '\r' => {
+                field_empty = true;
+                let next = i + 1;
+                if next - starts[starts.len() - 1] >= target && next < text.len() {
+                    starts.push(next);
+                }
+            }
+            _ => field_empty = false,
+        }
+    }
+    starts
+}
+
+// Records of `parse_parallel`, chunk by chunk
+pub(crate) struct ParallelParse {
+    pub(crate) chunks: Vec<Vec<Record>>,
+    pub(crate) skipped_bytes: usize,
+    pub(crate) line_ending: Option<LineEnding>,
+}
+
+/// Tokenizes `text` on up to `threads` threads, chunks are split on record boundaries and come
+/// back in order. Line numbers in errors are relative to the chunk
+pub(crate) fn parse_parallel(
+    text: &str,
+    dialect: Dialect,
+    threads: usize,
+    projection: Option<&[bool]>,
+) -> Result<ParallelParse, Error> {
+    let mut starts = chunk_starts(text, dialect, threads);
+    starts.push(text.len());
+    let chunks = thread::scope(|scope| {
+        let handles: Vec<_> = starts
+            .windows(2)
+            .map(|range| {
+                let chunk = &text[range[0]..range[1]];
+                scope.spawn(move || {
+                    let mut records = Records::new(chunk.as_bytes(), dialect);
+                    if let Some(keep) = projection {
+                        records.project(keep.to_vec());
+                    }
+                    let parsed = records.by_ref().collect::<Result<Vec<_>, _>>()?;
+                    Ok::<_, Error>((parsed, records.skipped_bytes(), records.line_ending()))
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .map_err(|_| miette!("Parser thread panicked"))?
+            })
+            .collect::<Result<Vec<_>, Error>>()
+    })?;
+    let skipped_bytes = chunks.iter().map(|(_, skipped, _)| skipped).sum();
+    let line_ending = chunks.iter().fold(None, |ending, (_, _, chunk)| {
+        LineEnding::merge(ending, *chunk)
+    });
+    let chunks = chunks.into_iter().map(|(records, _, _)| records).collect();
+    Ok(ParallelParse {
+        chunks,
+        skipped_bytes,
+        line_ending,
+    })
+}
+
+// Fields go straight into their column as records come off the tokenizer, so a row-major copy
+// of the whole file never sits next to the columns
+pub(crate) struct ColumnBuilder {
+    cols: Vec<Record>,
+    units_row: Option<usize>,
+    units: Option<Record>,
+    records: usize,
+}
+
+impl ColumnBuilder {
+    pub(crate) fn new(n_cols: usize, units_row: Option<usize>) -> Self {
+        Self {
+            cols: (0..n_cols).map(|_| Vec::new()).collect(),
+            units_row,
+            units: None,
+            records: 0,
+        }
+    }
+    pub(crate) fn push(&mut self, record: Record) -> Result<(), Error> {
+        if self.units_row == Some(self.records) {
+            self.records += 1;
+            self.units = Some(record);
+            return Ok(());
+        }
+        if record.len() != self.cols.len() {
+            return Err(CoalaError::RaggedRow {
+                row: self.n_rows(),
+                expected: self.cols.len(),
+                found: record.len(),
+            }
+            .into());
+        }
+        self.records += 1;
+        for (col, field) in self.cols.iter_mut().zip(record) {
+            col.push(field);
+        }
+        Ok(())
+    }
+    /// Records pushed so far, the units row included
+    pub(crate) fn records(&self) -> usize {
+        self.records
+    }
+    pub(crate) fn n_rows(&self) -> usize {
+        self.records - usize::from(self.units.is_some())
+    }
+    /// The columns and the units row, which must have been seen if one was asked for
+    pub(crate) fn finish(self) -> Result<(Vec<Record>, Option<Record>), Error> {
+        if self.units_row.is_some() && self.units.is_none() {
+            return Err(CoalaError::UnexpectedEof.into());
+        }
+        Ok((self.cols, self.units))
+    }
+}
+
+/// Writes `record` as one line that `Records` reads back unchanged. Special characters are
+/// escaped when the dialect has an escape character, otherwise the field is quoted
+pub(crate) fn write_record<W: Write, S: AsRef<str>>(
+    out: &mut W,
+    record: &[S],
+    dialect: Dialect,
+) -> io::Result<()> {
+    let special = |c: char| c == dialect.separator || c == dialect.quote || c == '\n' || c == '\r';
+    let mut line = String::new();
+    for (i, field) in record.iter().enumerate() {
+        if i > 0 {
+            line.push(dialect.separator);
+        }
+        let field = field.as_ref();
+        // A blank line would be skipped on the way back in
+        if field.is_empty() && record.len() == 1 {
+            line.extend([dialect.quote, dialect.quote]);
+            continue;
+        }
+        match dialect.escape {
+            Some(escape) => {
+                for c in field.chars() {
+                    if special(c) || c == escape {
+                        line.push(escape);
+                    }
+                    line.push(c);
+                }
+            }
+            None if field.contains(special) => {
+                line.push(dialect.quote);
+                for c in field.chars() {
+                    if c == dialect.quote {
+                        line.push(c);
+                    }
+                    line.push(c);
+                }
+                line.push(dialect.quote);
+            }
+            None => line.push_str(field),
+        }
+    }
+    line.push('\n');
+    out.write_all(line.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn as_strings(records: impl IntoIterator<Item = Record>) -> Vec<Vec<String>> {
+        records
+            .into_iter()
+            .map(|record| record.into_iter().map(String::from).collect())
+            .collect()
+    }
+
+    fn read(text: &str, dialect: Dialect) -> Vec<Vec<String>> {
+        let records = Records::new(text.as_bytes(), dialect);
+        as_strings(records.collect::<Result<Vec<_>, _>>().unwrap())
+    }
+
+    #[test]
+    fn quoted_fields_span_lines() {
+        let records = read(
+            "a,b\n1,\"x\ny\"\n\n2,\"say \"\"hi\"\"\"\n",
+            Dialect::default(),
+        );
+        assert_eq!(
+            records,
+            [["a", "b"], ["1", "x\ny"], ["2", "say \"hi\""]].map(|r| r.map(String::from))
+        );
+    }
+
+    #[test]
+    fn every_line_break_ends_records() {
+        for (text, ending) in [
+            ("a\r\n1\r\n", LineEnding::CrLf),
+            ("a\r1\r", LineEnding::Cr),
+            ("a\n1\r\n", LineEnding::Mixed),
+        ] {
+            let mut records = Records::new(text.as_bytes(), Dialect::default());
+            let parsed = as_strings(records.by_ref().map(Result::unwrap));
+            assert_eq!(parsed, [["a"], ["1"]].map(|r| r.map(String::from)));
+            assert_eq!(records.line_ending(), Some(ending));
+        }
+    }
+
+    #[test]
+    fn escaped_characters_are_text() {
+        let dialect = Dialect {
+            escape: Some('\\'),
+            ..Dialect::default()
+        };
+        assert_eq!(read("a\\,b,c\\\"\n", dialect), [["a,b", "c\""]]);
+    }
+
+    #[test]
+    fn unclosed_quote_is_an_error() {
+        let mut records = Records::new("a\n\"b\n".as_bytes(), Dialect::default());
+        records.next();
+        assert!(records.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn written_records_read_back_unchanged() {
+        let record = [
+            "plain",
+            "with,comma",
+            "with \"quote\"",
+            "two\nlines",
+            "cr\rhere",
+            "",
+        ];
+        let escaped = Dialect {
+            escape: Some('\\'),
+            ..Dialect::default()
+        };
+        for dialect in [Dialect::default(), escaped] {
+            let mut out = Vec::new();
+            write_record(&mut out, &record, dialect).unwrap();
+            write_record(&mut out, &[""], dialect).unwrap();
+            let text = String::from_utf8(out).unwrap();
+            assert_eq!(read(&text, dialect), vec![record.to_vec(), vec![""]]);
+        }
+    }
+
+    #[test]
+    fn parallel_chunks_match_serial_parse() {
+        let text: String = (0..200)
+            .map(|i| format!("{i},\"note\r\n{i}\"\r\n"))
+            .collect();
+        let serial = read(&text, Dialect::default());
+        let parsed = parse_parallel(&text, Dialect::default(), 4, None).unwrap();
+        assert!(parsed.chunks.len() > 1);
+        assert_eq!(as_strings(parsed.chunks.into_iter().flatten()), serial);
+        assert_eq!(parsed.line_ending, Some(LineEnding::CrLf));
+    }
+
+    #[test]
+    fn chunks_start_on_record_boundaries() {
+        let text = "1,\"a\nb\"\n2,c\n3,\"d\ne\"\n4,f\n";
+        let mut records = Records::new(text.as_bytes(), Dialect::default());
+        let mut record_starts = vec![0];
+        while records.next().is_some() {
+            record_starts.push(records.bytes_read());
+        }
+        let starts = chunk_starts(text, Dialect::default(), 4);
+        assert!(starts.len() > 1);
+        assert!(starts.iter().all(|start| record_starts.contains(start)));
+    }
+}
@@ -0,0 +1,37 @@
+// Spans and events for applications' observability stacks with the `tracing` feature, plain
+// `log` records otherwise. Spans cost nothing without the feature, their fields aren't even
+// evaluated
+
+#[cfg(feature = "tracing")]
+pub(crate) type SpanGuard = Option<tracing::span::EnteredSpan>;
+#[cfg(not(feature = "tracing"))]
+pub(crate) type SpanGuard = Option<()>;
+
+// Enters a span named `$name` until the returned guard drops
+macro_rules! span {
+    ($name:literal $(, $field:ident = $value:expr)* $(,)?) => {{
+        #[cfg(feature = "tracing")]
+        let guard = Some(tracing::info_span!($name $(, $field = $value)*).entered());
+        // Mentions the fields without evaluating them, so they don't read as unused
+        #[cfg(not(feature = "tracing"))]
+        let guard: $crate::trace::SpanGuard = {
+            let _ = || {
+                $(let _ = &$value;)*
+            };
+            None
+        };
+        guard
+    }};
+}
+
+// `info!` of whichever logging facade is on
+macro_rules! info {
+    ($($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        tracing::info!($($arg)+);
+        #[cfg(not(feature = "tracing"))]
+        log::info!($($arg)+);
+    }};
+}
+
+pub(crate) use {info, span};
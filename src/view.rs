@@ -0,0 +1,288 @@
+use std::{ops::Range, sync::Arc};
+
+use miette::{miette, Error};
+
+use crate::{
+    col_parser::{ColType, DType, DataValue},
+    csv_parser::{ColViewer, Csv},
+    error::CoalaError,
+    mask::Mask,
+};
+
+#[derive(Debug, Clone)]
+enum Rows {
+    Range(Range<usize>),
+    // Shared by every column of a filtered frame
+    Indices(Arc<[usize]>),
+}
+
+impl Rows {
+    fn len(&self) -> usize {
+        match self {
+            Rows::Range(range) => range.len(),
+            Rows::Indices(indices) => indices.len(),
+        }
+    }
+    fn get(&self, index: usize) -> Option<usize> {
+        match self {
+            Rows::Range(range) => (index < range.len()).then(|| range.start + index),
+            Rows::Indices(indices) => indices.get(index).copied(),
+        }
+    }
+    // Rows of the parent behind `range` of the view
+    fn parent_rows(&self, range: Range<usize>) -> Vec<usize> {
+        match self {
+            Rows::Range(rows) => (rows.start + range.start..rows.start + range.end).collect(),
+            Rows::Indices(indices) => indices[range].to_vec(),
+        }
+    }
+    // `self` applied after `outer`, for a view taken of a view
+    fn through(&self, outer: &Rows) -> Rows {
+        match (outer, self) {
+            (Rows::Range(outer), Rows::Range(inner)) => {
+                Rows::Range(outer.start + inner.start..outer.start + inner.end)
+            }
+            _ => Rows::Indices(
+                (0..self.len())
+                    .filter_map(|i| outer.get(self.get(i)?))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+// Some rows of a column shared with the frame it was derived from. Reads go through the
+// parent, which the view keeps alive even once that frame is dropped
+#[derive(Debug)]
+pub(crate) struct ColView {
+    parent: Arc<ColType>,
+    rows: Rows,
+}
+
+impl ColView {
+    // A view of a view points straight at the shared column
+    fn wrap(col: &Arc<ColType>, rows: Rows) -> Arc<ColType> {
+        let view = match &**col {
+            ColType::View(view) => ColView {
+                parent: view.parent.clone(),
+                rows: rows.through(&view.rows),
+            },
+            _ => ColView {
+                parent: col.clone(),
+                rows,
+            },
+        };
+        Arc::new(ColType::View(view))
+    }
+    pub(crate) fn len(&self) -> usize {
+        self.rows.len()
+    }
+    pub(crate) fn name(&self) -> &str {
+        self.parent.name()
+    }
+    pub(crate) fn dtype(&self) -> DType {
+        self.parent.dtype()
+    }
+    pub(crate) fn get(&self, index: usize) -> Option<DataValue> {
+        self.parent.data_as_value(self.rows.get(index)?).ok()
+    }
+    /// Copies the rows at `indices` of the view out of the parent
+    pub(crate) fn take(&self, indices: &[usize]) -> ColType {
+        let rows: Vec<usize> = indices.iter().filter_map(|&i| self.rows.get(i)).collect();
+        self.parent.take(&rows)
+    }
+    pub(crate) fn print_range_lines(&self, beg: usize, end: usize) -> Result<Vec<String>, Error> {
+        if beg > end || end > self.len() {
+            return Err(CoalaError::OutOfRange.into());
+        }
+        let rows = self.rows.parent_rows(beg..end);
+        self.parent.take(&rows).print_range_lines(0, rows.len())
+    }
+    /// A column of its own holding the viewed rows
+    pub(crate) fn materialize(&self) -> ColType {
+        self.parent.take(&self.rows.parent_rows(0..self.len()))
+    }
+}
+
+impl<'a> ColViewer<'a> {
+    /// Whether the column shares its buffer with the frame it was derived from
+    pub fn is_view(&self) -> bool {
+        matches!(self.inner, ColType::View(_))
+    }
+    // The typed values behind the column when its rows are stored contiguously: a plain
+    // column, or a slice of one
+    fn contiguous<T>(&self, values: fn(&'a ColType) -> Option<&'a [T]>) -> Option<&'a [T]> {
+        match self.inner {
+            ColType::View(ColView {
+                parent,
+                rows: Rows::Range(range),
+            }) => values(parent).map(|values| &values[range.clone()]),
+            col => values(col),
+        }
+    }
+    /// The values of a `Float` column without copying them, for numeric crates that take
+    /// slices. `None` for other types and for compressed or filtered columns. Null rows hold
+    /// a placeholder, `is_null` tells them apart
+    pub fn as_f64_slice(&self) -> Option<&'a [f64]> {
+        self.contiguous(|col| match col {
+            ColType::Float(col) => Some(&col.values),
+            _ => None,
+        })
+    }
+    /// `as_f64_slice` for `Integer` columns
+    pub fn as_i64_slice(&self) -> Option<&'a [i64]> {
+        self.contiguous(|col| match col {
+            ColType::Integer(col) => Some(&col.values),
+            _ => None,
+        })
+    }
+    /// The text of a `String` column borrowed from its buffer, `None` for nulls. `None` for
+    /// other types and for compressed columns
+    pub fn as_str_iter(&self) -> Option<impl Iterator<Item = Option<&'a str>> + 'a> {
+        let (col, rows) = match self.inner {
+            ColType::String(col) => (col, None),
+            ColType::View(view) => match &*view.parent {
+                ColType::String(col) => (col, Some(&view.rows)),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some((0..self.len()).map(move |i| {
+            let row = rows.map_or(Some(i), |rows| rows.get(i))?;
+            col.is_valid(row).then(|| col.get(row)).flatten()
+        }))
+    }
+}
+
+impl Csv {
+    fn view(&self, rows: Rows, operation: String) -> Csv {
+        let n_rows = rows.len();
+        let cols = self
+            .cols
+            .iter()
+            .map(|col| ColView::wrap(col, rows.clone()))
+            .collect();
+        let mut csv = Csv::from_shared(cols, self.header.clone(), n_rows);
+        csv.row_ids = self.row_ids_at((0..n_rows).filter_map(|i| rows.get(i)));
+        csv.units = self.units.clone();
+        csv.meta = self.meta.clone();
+        csv.column_order = self.column_order.clone();
+        csv.lineage = self.lineage.clone();
+        csv.lineage.record(operation);
+        csv
+    }
+    /// The columns `names` in that order. The new frame shares the columns with `self` and
+    /// keeps their cached statistics
+    pub fn select(&self, names: &[&str]) -> Result<Csv, Error> {
+        let indices = names
+            .iter()
+            .map(|name| self.col_index(name))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let cols = indices.iter().map(|&i| self.cols[i].clone()).collect();
+        let header: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+        let mut csv = Csv::from_shared(cols, header, self.n_rows);
+        csv.row_ids = self.row_ids.clone();
+        csv.units = self
+            .units
+            .as_ref()
+            .map(|units| indices.iter().map(|&i| units[i].clone()).collect());
+        for name in names {
+            if let Some(meta) = self.meta.get(*name) {
+                csv.meta.insert(name.to_string(), meta.clone());
+            }
+            if let Some(stats) = self.cache.get(*name) {
+                csv.cache.insert(name.to_string(), stats.clone());
+            }
+        }
+        csv.lineage = self.lineage.clone();
+        csv.lineage.record(format!("select({})", names.join(", ")));
+        csv.column_order = self.column_order.clone();
+        csv.apply_column_order();
+        Ok(csv)
+    }
+    /// The columns whose type is one of `dtypes`, in frame order, e.g. the numeric ones with
+    /// `&[DType::Float, DType::Integer]`. Shares the columns with `self` like `select`
+    pub fn select_dtypes(&self, dtypes: &[DType]) -> Result<Csv, Error> {
+        let names: Vec<&str> = self
+            .header
+            .iter()
+            .zip(&self.cols)
+            .filter(|(_, col)| dtypes.contains(&col.dtype()))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        let mut csv = self.select(&names)?;
+        csv.lineage = self.lineage.clone();
+        let dtypes: Vec<&str> = dtypes.iter().map(DType::name).collect();
+        csv.lineage
+            .record(format!("select_dtypes({})", dtypes.join(", ")));
+        Ok(csv)
+    }
+    /// Up to `len` rows starting at `offset`, sharing the column buffers of `self`
+    pub fn slice(&self, offset: usize, len: usize) -> Csv {
+        let start = offset.min(self.n_rows);
+        let end = start + len.min(self.n_rows - start);
+        self.view(Rows::Range(start..end), format!("slice({offset}, {len})"))
+    }
+    /// Consecutive slices of `chunk_rows` rows covering the frame, the last one shorter when
+    /// the rows don't divide evenly. Each shares the column buffers of `self`. Fails when
+    /// `chunk_rows` is 0
+    pub fn iter_chunks(
+        &self,
+        chunk_rows: usize,
+    ) -> Result<impl ExactSizeIterator<Item = Csv> + '_, Error> {
+        if chunk_rows == 0 {
+            return Err(miette!("Chunks need at least one row"));
+        }
+        Ok((0..self.n_rows.div_ceil(chunk_rows))
+            .map(move |i| self.slice(i * chunk_rows, chunk_rows)))
+    }
+    /// The rows where `mask` is set, sharing the column buffers of `self`. Only the row
+    /// indices are stored, once for all columns
+    pub fn filter(&self, mask: &Mask) -> Result<Csv, Error> {
+        if mask.len() != self.n_rows {
+            return Err(miette!(
+                "Mask has {} entries, the Dataframe has {} rows",
+                mask.len(),
+                self.n_rows
+            ));
+        }
+        let indices: Arc<[usize]> = mask.indices().collect();
+        let kept = indices.len();
+        Ok(self.view(Rows::Indices(indices), format!("filter({kept} rows)")))
+    }
+    /// Copies the rows of every view into a column of its own, letting go of the buffers
+    /// shared with the frame it was derived from
+    pub fn materialize(&mut self) {
+        for col in self.cols.iter_mut() {
+            if let ColType::View(view) = &**col {
+                *col = Arc::new(view.materialize());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::col_parser::{DType, DataValue};
+    use crate::csv_parser::Csv;
+
+    fn numbers(n: i64) -> Csv {
+        let records = (0..n).map(|i| vec![DataValue::Integer(i)]);
+        Csv::from_records(records, &[("n", DType::Integer)]).unwrap()
+    }
+
+    #[test]
+    fn last_chunk_holds_the_rest() {
+        let csv = numbers(7);
+        let chunks: Vec<Csv> = csv.iter_chunks(3).unwrap().collect();
+        let sizes: Vec<usize> = chunks.iter().map(|chunk| chunk.n_rows).collect();
+        assert_eq!(sizes, vec![3, 3, 1]);
+        let last = chunks[2].get_col("n").unwrap().iter().collect::<Vec<_>>();
+        assert_eq!(last, vec![DataValue::Integer(6)]);
+    }
+
+    #[test]
+    fn chunks_of_no_rows_are_rejected() {
+        assert!(numbers(2).iter_chunks(0).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+use std::{ops::ControlFlow, path::Path, sync::mpsc};
+
+use log::warn;
+use miette::{Error, IntoDiagnostic};
+use notify::{EventKind, RecursiveMode, Watcher};
+
+use crate::{
+    csv_parser::{Csv, CsvConfig},
+    error::CoalaError,
+    perf,
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameDiff {
+    pub old_rows: usize,
+    pub new_rows: usize,
+    pub added_cols: Vec<String>,
+    pub removed_cols: Vec<String>,
+}
+
+impl FrameDiff {
+    pub fn between(old: &Csv, new: &Csv) -> Self {
+        Self {
+            old_rows: old.n_rows,
+            new_rows: new.n_rows,
+            added_cols: new
+                .header
+                .iter()
+                .filter(|h| !old.header.contains(h))
+                .cloned()
+                .collect(),
+            removed_cols: old
+                .header
+                .iter()
+                .filter(|h| !new.header.contains(h))
+                .cloned()
+                .collect(),
+        }
+    }
+    pub fn rows_added(&self) -> isize {
+        self.new_rows as isize - self.old_rows as isize
+    }
+    pub fn is_empty(&self) -> bool {
+        self.old_rows == self.new_rows && self.added_cols.is_empty() && self.removed_cols.is_empty()
+    }
+}
+
+impl Csv {
+    pub fn watch<F>(path: &str, config: CsvConfig, mut callback: F) -> Result<(), Error>
+    where
+        F: FnMut(&Csv, &FrameDiff) -> ControlFlow<()>,
+    {
+        let file = Path::new(path).canonicalize().map_err(CoalaError::from)?;
+        let dir = file.parent().unwrap_or(Path::new("."));
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx).into_diagnostic()?;
+        // Watching the directory keeps working when editors replace the file instead of writing it
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .into_diagnostic()?;
+
+        let mut current = Csv::new(path, config.clone())?;
+        for event in rx {
+            let event = event.into_diagnostic()?;
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                || !event.paths.contains(&file)
+            {
+                continue;
+            }
+            perf::slow_path("Reload", || {
+                format!("`{path}` changed, parsing all of it again")
+            });
+            let new = match Csv::new(path, config.clone()) {
+                Ok(new) => new,
+                Err(e) => {
+                    warn!("Couldn't reload `{path}`, keeping previous frame. Reason: {e}");
+                    continue;
+                }
+            };
+            let diff = FrameDiff::between(&current, &new);
+            current = new;
+            if callback(&current, &diff).is_break() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
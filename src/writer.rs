@@ -0,0 +1,371 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    str::FromStr,
+};
+
+use miette::{miette, Error};
+
+use crate::{
+    boolean::truth,
+    col_parser::DataValue,
+    csv_parser::Csv,
+    display::markdown_table,
+    dt::format_datetime,
+    epoch::EpochUnit,
+    error::CoalaError,
+    tokenizer::{self, Dialect},
+};
+
+/// Which fields `to_csv` encloses in quotes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Quoting {
+    /// Only fields holding the separator, the quote or a line break
+    #[default]
+    Necessary,
+    /// Every field, the header included
+    All,
+    /// Every field but the nulls, so a null and the text it's written as stay apart
+    NonNull,
+}
+
+/// How `to_csv` writes datetimes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DatetimeFormat {
+    /// As they're printed
+    #[default]
+    Text,
+    /// As Unix timestamps in this unit
+    Epoch(EpochUnit),
+}
+
+/// How `to_csv` writes the values of one column, from the spec given to `WriteConfig::format`.
+/// Values the format doesn't apply to, like text in a number column, are written as usual
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CellFormat {
+    /// `{:.2}`: numbers with that many decimals
+    Decimals(usize),
+    /// A spec holding `%`, like `%d/%m/%Y`: datetimes in UTC through the directives
+    /// `%Y %m %d %H %M %S %j %%`
+    Datetime(String),
+    /// `1/0`, `true/false`, `Y/N`...: the text of true then of false, for boolean columns
+    /// holding 0 and 1 or `true` and `false`
+    Boolean { yes: String, no: String },
+}
+
+impl CellFormat {
+    fn render(&self, value: &DataValue) -> Option<String> {
+        match (self, value) {
+            (_, DataValue::Null) => None,
+            (
+                CellFormat::Decimals(decimals),
+                value @ (DataValue::Float(_) | DataValue::Integer(_) | DataValue::Unsigned(_)),
+            ) => value.as_f64().map(|v| format!("{v:.decimals$}")),
+            #[cfg(feature = "decimal")]
+            (CellFormat::Decimals(decimals), DataValue::Decimal(d)) => {
+                Some(format!("{d:.decimals$}"))
+            }
+            (CellFormat::Datetime(format), DataValue::DateTime(dt)) => {
+                Some(format_datetime(dt, format))
+            }
+            (CellFormat::Boolean { yes, no }, value) => {
+                truth(value).map(|t| if t { yes } else { no }.clone())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for CellFormat {
+    type Err = Error;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        if let Some(decimals) = spec
+            .strip_prefix("{:.")
+            .and_then(|rest| rest.strip_suffix('}'))
+            .and_then(|decimals| decimals.parse().ok())
+        {
+            return Ok(CellFormat::Decimals(decimals));
+        }
+        if spec.contains('%') {
+            return Ok(CellFormat::Datetime(spec.to_string()));
+        }
+        match spec.split_once('/') {
+            Some((yes, no)) if !yes.is_empty() && !no.is_empty() && !spec.contains('{') => {
+                Ok(CellFormat::Boolean {
+                    yes: yes.to_string(),
+                    no: no.to_string(),
+                })
+            }
+            _ => Err(miette!(
+                "Unknown cell format `{spec}`, expected decimals like `{{:.2}}`, a datetime format like `%Y-%m-%d` or booleans like `1/0`"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WriteConfig {
+    pub separator: char,
+    pub quote_char: char,
+    pub quoting: Quoting,
+    pub header: bool,
+    /// Written for nulls. The default, an empty field, reads back as null
+    pub null_value: String,
+    pub datetime_format: DatetimeFormat,
+    /// Spec of the `CellFormat` of a column by name, parsed when writing
+    pub formats: HashMap<String, String>,
+}
+
+impl Default for WriteConfig {
+    fn default() -> Self {
+        Self {
+            separator: ',',
+            quote_char: '"',
+            quoting: Quoting::Necessary,
+            header: true,
+            null_value: String::new(),
+            datetime_format: DatetimeFormat::Text,
+            formats: HashMap::new(),
+        }
+    }
+}
+
+impl WriteConfig {
+    pub fn separator(mut self, separator: char) -> Self {
+        self.separator = separator;
+        self
+    }
+    pub fn quote_char(mut self, quote_char: char) -> Self {
+        self.quote_char = quote_char;
+        self
+    }
+    pub fn quoting(mut self, quoting: Quoting) -> Self {
+        self.quoting = quoting;
+        self
+    }
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+    pub fn null_value(mut self, null_value: &str) -> Self {
+        self.null_value = null_value.to_string();
+        self
+    }
+    pub fn datetime_format(mut self, datetime_format: DatetimeFormat) -> Self {
+        self.datetime_format = datetime_format;
+        self
+    }
+    /// Writes the values of column `name` following `spec`: `{:.2}` for two decimals, a datetime
+    /// format like `%Y-%m-%d`, or the texts of true and false like `1/0`. A spec the writer
+    /// doesn't know fails the write
+    pub fn format(mut self, name: &str, spec: &str) -> Self {
+        self.formats.insert(name.to_string(), spec.to_string());
+        self
+    }
+    // The format of each column of `header`, failing on unknown columns and specs
+    fn cell_formats(&self, header: &[String]) -> Result<Vec<Option<CellFormat>>, Error> {
+        if let Some(name) = self.formats.keys().find(|name| !header.contains(name)) {
+            return Err(CoalaError::MissingColumn { name: name.clone() }.into());
+        }
+        header
+            .iter()
+            .map(|name| self.formats.get(name).map(|spec| spec.parse()).transpose())
+            .collect()
+    }
+    // `None` for a null
+    fn cell(&self, value: DataValue, format: Option<&CellFormat>) -> Option<String> {
+        if let Some(text) = format.and_then(|format| format.render(&value)) {
+            return Some(text);
+        }
+        match value {
+            DataValue::Null => None,
+            DataValue::DateTime(dt) => Some(match self.datetime_format {
+                DatetimeFormat::Text => dt.to_string(),
+                DatetimeFormat::Epoch(unit) => (dt.timestamp() * unit.per_second()).to_string(),
+            }),
+            value => Some(value.to_string()),
+        }
+    }
+    fn write_record(&self, out: &mut impl Write, record: &[Option<String>]) -> Result<(), Error> {
+        let dialect = Dialect {
+            separator: self.separator,
+            quote: self.quote_char,
+            escape: None,
+        };
+        let text =
+            |field: &Option<String>| field.clone().unwrap_or_else(|| self.null_value.clone());
+        let written = match self.quoting {
+            Quoting::Necessary => {
+                let record: Vec<String> = record.iter().map(text).collect();
+                tokenizer::write_record(out, &record, dialect)
+            }
+            Quoting::All | Quoting::NonNull => {
+                let quote = self.quote_char.to_string();
+                let doubled = quote.repeat(2);
+                let fields: Vec<String> = record
+                    .iter()
+                    .map(|field| match field {
+                        None if self.quoting == Quoting::NonNull => self.null_value.clone(),
+                        field => format!("{quote}{}{quote}", text(field).replace(&quote, &doubled)),
+                    })
+                    .collect();
+                let separator = self.separator.to_string();
+                writeln!(out, "{}", fields.join(&separator))
+            }
+        };
+        written.map_err(CoalaError::from)?;
+        Ok(())
+    }
+}
+
+/// How `to_json` lays out the frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JsonLayout {
+    /// An array with an object per row, like `[{"a": 1, "b": "x"}, ...]`
+    #[default]
+    Records,
+    /// An object with an array per column, like `{"a": [1, ...], "b": ["x", ...]}`
+    Columns,
+}
+
+// Numbers that JSON can't hold and the types without a JSON counterpart are written as text
+fn push_json(out: &mut String, value: &DataValue) {
+    let quoted = |s: &str| serde_json::Value::from(s).to_string();
+    match value {
+        DataValue::Null => out.push_str("null"),
+        DataValue::Float(v) if v.is_finite() => out.push_str(&v.to_string()),
+        DataValue::Float(_) => out.push_str("null"),
+        DataValue::Integer(v) => out.push_str(&v.to_string()),
+        DataValue::Unsigned(v) => out.push_str(&v.to_string()),
+        DataValue::String(s) => out.push_str(&quoted(s)),
+        DataValue::List(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                push_json(out, value);
+            }
+            out.push(']');
+        }
+        DataValue::Struct(fields) => {
+            out.push('{');
+            for (i, (key, value)) in fields.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&quoted(key));
+                out.push(':');
+                push_json(out, value);
+            }
+            out.push('}');
+        }
+        value => out.push_str(&quoted(&value.to_string())),
+    }
+}
+
+fn create(path: &str) -> Result<BufWriter<File>, Error> {
+    Ok(BufWriter::new(
+        File::create(path).map_err(CoalaError::from)?,
+    ))
+}
+
+impl Csv {
+    /// Writes the frame as CSV to `path`, which is created or truncated
+    pub fn to_csv(&self, path: &str, config: &WriteConfig) -> Result<(), Error> {
+        self.write_csv(create(path)?, config)
+    }
+    /// Writes the frame as CSV to `out`, e.g. `std::io::stdout()`
+    pub fn write_csv(&self, mut out: impl Write, config: &WriteConfig) -> Result<(), Error> {
+        let csv = self.in_column_order();
+        let formats = config.cell_formats(&csv.header)?;
+        if config.header {
+            let header: Vec<Option<String>> = csv.header.iter().cloned().map(Some).collect();
+            config.write_record(&mut out, &header)?;
+        }
+        for row in 0..csv.n_rows {
+            let record = csv
+                .cols
+                .iter()
+                .zip(&formats)
+                .map(|(col, format)| Ok(config.cell(col.data_as_value(row)?, format.as_ref())))
+                .collect::<Result<Vec<_>, Error>>()?;
+            config.write_record(&mut out, &record)?;
+        }
+        out.flush().map_err(CoalaError::from)?;
+        Ok(())
+    }
+    /// The whole frame as a Markdown table, e.g. for a README or an issue
+    pub fn to_markdown(&self) -> Result<String, Error> {
+        let csv = self.in_column_order();
+        Ok(markdown_table(&csv.header, &csv.cells()?, csv.n_rows))
+    }
+    /// Writes the frame as JSON to `path`, which is created or truncated
+    pub fn to_json(&self, path: &str, layout: JsonLayout) -> Result<(), Error> {
+        self.write_json(create(path)?, layout)
+    }
+    /// Writes the frame as JSON to `out`. Columns keep their order, nulls and floats that
+    /// aren't finite become `null`
+    pub fn write_json(&self, mut out: impl Write, layout: JsonLayout) -> Result<(), Error> {
+        let csv = self.in_column_order();
+        let keys: Vec<String> = csv
+            .header
+            .iter()
+            .map(|name| serde_json::Value::from(name.as_str()).to_string())
+            .collect();
+        let mut text = String::new();
+        // Written as it grows, a row or a column at a time
+        let mut flush = |text: &mut String| {
+            let written = out.write_all(text.as_bytes());
+            text.clear();
+            written.map_err(CoalaError::from)
+        };
+        match layout {
+            JsonLayout::Records => {
+                text.push('[');
+                for row in 0..csv.n_rows {
+                    if row > 0 {
+                        text.push(',');
+                    }
+                    text.push('{');
+                    for (i, (key, col)) in keys.iter().zip(&csv.cols).enumerate() {
+                        if i > 0 {
+                            text.push(',');
+                        }
+                        text.push_str(key);
+                        text.push(':');
+                        push_json(&mut text, &col.data_as_value(row)?);
+                    }
+                    text.push('}');
+                    flush(&mut text)?;
+                }
+                text.push(']');
+            }
+            JsonLayout::Columns => {
+                text.push('{');
+                for (i, (key, col)) in keys.iter().zip(&csv.cols).enumerate() {
+                    if i > 0 {
+                        text.push(',');
+                    }
+                    text.push_str(key);
+                    text.push_str(":[");
+                    for row in 0..csv.n_rows {
+                        if row > 0 {
+                            text.push(',');
+                        }
+                        push_json(&mut text, &col.data_as_value(row)?);
+                    }
+                    text.push(']');
+                    flush(&mut text)?;
+                }
+                text.push('}');
+            }
+        }
+        text.push('\n');
+        flush(&mut text)?;
+        out.flush().map_err(CoalaError::from)?;
+        Ok(())
+    }
+}